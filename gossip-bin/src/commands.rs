@@ -25,7 +25,7 @@ impl Command {
     }
 }
 
-const COMMANDS: [Command; 39] = [
+const COMMANDS: [Command; 40] = [
     Command {
         cmd: "oneshot",
         usage_params: "{depends}",
@@ -56,6 +56,11 @@ const COMMANDS: [Command; 39] = [
         usage_params: "",
         desc: "clear relay avoidance timeouts.",
     },
+    Command {
+        cmd: "daemon",
+        usage_params: "[<passphrase_file>]",
+        desc: "run without the GUI, syncing until stopped. Unlocks the key from the file, or prompts for the passphrase.",
+    },
     Command {
         cmd: "decrypt",
         usage_params: "<pubkeyhex> <ciphertext>",
@@ -245,6 +250,7 @@ pub fn handle_command(mut args: env::Args, runtime: &Runtime) -> Result<bool, Er
         "bech32_decode" => bech32_decode(command, args)?,
         "bech32_encode_naddr" => bech32_encode_naddr(command, args)?,
         "clear_timeouts" => clear_timeouts()?,
+        "daemon" => daemon(command, args, runtime)?,
         "decrypt" => decrypt(command, args)?,
         "delete_spam_by_content" => delete_spam_by_content(command, args, runtime)?,
         "delete_relay" => delete_relay(command, args)?,
@@ -1099,6 +1105,61 @@ pub fn login() -> Result<(), Error> {
     Ok(())
 }
 
+pub fn daemon(cmd: Command, mut args: env::Args, runtime: &Runtime) -> Result<(), Error> {
+    if !GLOBALS.identity.is_unlocked() {
+        let epk = GLOBALS.storage.read_encrypted_private_key()?;
+        if GLOBALS.identity.has_private_key() || epk.is_some() {
+            let mut password = match args.next() {
+                Some(path) => read_passphrase_file(&path)?,
+                None => match rpassword::prompt_password("Password: ") {
+                    Ok(password) => password,
+                    Err(e) => {
+                        return cmd.usage(format!(
+                            "Cannot prompt for the passphrase ({}), give a passphrase file",
+                            e
+                        ))
+                    }
+                },
+            };
+            let result = match epk {
+                Some(epk) if !GLOBALS.identity.has_private_key() => {
+                    GLOBALS.identity.set_encrypted_private_key(epk, &password)
+                }
+                _ => GLOBALS.identity.unlock(&password),
+            };
+            password.zeroize();
+            result?;
+        } else {
+            println!("No private key, running without one");
+        }
+    }
+
+    println!("Running without the GUI. Stop with Ctrl-C or SIGTERM.");
+
+    // This returns once the overlord has shut down
+    runtime.block_on(gossip_lib::run());
+
+    Ok(())
+}
+
+fn read_passphrase_file(path: &str) -> Result<String, Error> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            tracing::warn!("Passphrase file {} can be read by other users", path);
+        }
+    }
+
+    let mut contents = std::fs::read_to_string(path)?;
+    let password = contents
+        .trim_end_matches(|c| c == '\n' || c == '\r')
+        .to_owned();
+    contents.zeroize();
+    Ok(password)
+}
+
 pub fn offline() -> Result<(), Error> {
     GLOBALS.storage.write_setting_offline(&true, None)?;
     Ok(())
@@ -1,5 +1,5 @@
 use gossip_lib::GLOBALS;
-use gossip_lib::{Person, PersonList, PersonTable, Private, Table};
+use gossip_lib::{MediaDescriptor, Person, PersonList, PersonTable, Private, Table};
 use std::collections::HashMap;
 
 use nostr_types::{
@@ -44,6 +44,9 @@ pub(crate) struct NoteData {
     /// Lists the author is on
     pub lists: HashMap<PersonList, Private>,
 
+    /// Matches a muted word, hashtag or thread
+    pub muted_content: bool,
+
     /// Deletion reasons if any
     pub deletions: Vec<String>,
 
@@ -74,6 +77,9 @@ pub(crate) struct NoteData {
     /// The content shattered into renderable elements
     pub shattered_content: ShatteredContent,
 
+    /// Media described by imeta tags, by url
+    pub media: HashMap<String, MediaDescriptor>,
+
     /// error content (gossip-created notations)
     pub error_content: Option<String>,
 
@@ -280,15 +286,20 @@ impl NoteData {
             .get_event_seen_on_relay(event.id)
             .unwrap_or_default();
 
+        let muted_content = GLOBALS.mutes.read().matches(&event);
+
         let bookmarked = GLOBALS.current_bookmarks.read().contains(&event.id);
 
         let volatile = GLOBALS.storage.event_is_volatile(event.id);
 
+        let media = gossip_lib::imeta::media_descriptors(&event);
+
         NoteData {
             event,
             delegation,
             author,
             lists,
+            muted_content,
             deletions,
             annotations,
             repost,
@@ -299,6 +310,7 @@ impl NoteData {
             zaptotal,
             seen_on,
             shattered_content,
+            media,
             error_content,
             direct_message,
             encryption,
@@ -345,7 +357,7 @@ impl NoteData {
     }
 
     pub(super) fn muted(&self) -> bool {
-        self.lists.contains_key(&PersonList::Muted)
+        self.lists.contains_key(&PersonList::Muted) || self.muted_content
     }
 
     pub(super) fn event_reference(&self) -> EventReference {
@@ -0,0 +1,522 @@
+// Just enough markdown for reading articles. We don't need all of CommonMark, only
+// what articles commonly use, and anything we don't understand is shown as text.
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Block {
+    Heading(usize, String),
+    Paragraph(String),
+    Quote(String),
+    ListItem {
+        depth: usize,
+        number: Option<u64>,
+        text: String,
+    },
+    Code(String),
+    Rule,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Inline {
+    Text {
+        text: String,
+        bold: bool,
+        italic: bool,
+        strike: bool,
+    },
+    Code(String),
+    Link {
+        text: String,
+        url: String,
+    },
+    Image {
+        alt: String,
+        url: String,
+    },
+}
+
+pub(super) fn parse(markdown: &str) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+
+    let mut lines = markdown.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            let fence = &trimmed[..3];
+            let mut code: Vec<&str> = Vec::new();
+            for line in lines.by_ref() {
+                if line.trim().starts_with(fence) {
+                    break;
+                }
+                code.push(line);
+            }
+            blocks.push(Block::Code(code.join("\n")));
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix('>') {
+            if !paragraph.is_empty() {
+                flush(&mut blocks, &mut paragraph, &mut quote);
+            }
+            quote.push(rest.trim());
+            continue;
+        }
+        if !quote.is_empty() {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+        }
+
+        // A line of = or - under a paragraph makes it a heading
+        if !paragraph.is_empty() && (all_of(trimmed, '=') || all_of(trimmed, '-')) {
+            let level = if trimmed.starts_with('=') { 1 } else { 2 };
+            blocks.push(Block::Heading(level, paragraph.join(" ")));
+            paragraph.clear();
+            continue;
+        }
+
+        if let Some((level, text)) = heading(trimmed) {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            blocks.push(Block::Heading(level, text.to_owned()));
+            continue;
+        }
+
+        if is_rule(trimmed) {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            blocks.push(Block::Rule);
+            continue;
+        }
+
+        if let Some((number, text)) = list_item(trimmed) {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            let indent = line.len() - line.trim_start().len();
+            blocks.push(Block::ListItem {
+                depth: indent / 2,
+                number,
+                text: text.to_owned(),
+            });
+            continue;
+        }
+
+        // A line carrying on from a list item belongs to it
+        if paragraph.is_empty() && line.starts_with(' ') {
+            if let Some(Block::ListItem { text, .. }) = blocks.last_mut() {
+                text.push(' ');
+                text.push_str(trimmed);
+                continue;
+            }
+        }
+
+        paragraph.push(trimmed);
+    }
+    flush(&mut blocks, &mut paragraph, &mut quote);
+
+    blocks
+}
+
+fn flush<'a>(blocks: &mut Vec<Block>, paragraph: &mut Vec<&'a str>, quote: &mut Vec<&'a str>) {
+    if !paragraph.is_empty() {
+        blocks.push(Block::Paragraph(paragraph.join(" ")));
+        paragraph.clear();
+    }
+    if !quote.is_empty() {
+        blocks.push(Block::Quote(quote.join(" ")));
+        quote.clear();
+    }
+}
+
+fn all_of(line: &str, c: char) -> bool {
+    !line.is_empty() && line.chars().all(|x| x == c)
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+fn is_rule(line: &str) -> bool {
+    let line: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    line.len() >= 3 && (all_of(&line, '-') || all_of(&line, '*') || all_of(&line, '_'))
+}
+
+fn list_item(line: &str) -> Option<(Option<u64>, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(bullet) {
+            return Some((None, text.trim()));
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && digits < 10 {
+        let rest = &line[digits..];
+        if rest.starts_with(". ") || rest.starts_with(") ") {
+            return Some((line[..digits].parse().ok(), rest[2..].trim()));
+        }
+    }
+    None
+}
+
+pub(super) fn parse_inline(text: &str) -> Vec<Inline> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output: Vec<Inline> = Vec::new();
+    let mut current = String::new();
+    let (mut bold, mut italic, mut strike) = (false, false, false);
+
+    // Push the text gathered so far in the current style
+    macro_rules! push_text {
+        () => {
+            if !current.is_empty() {
+                output.push(Inline::Text {
+                    text: std::mem::take(&mut current),
+                    bold,
+                    italic,
+                    strike,
+                });
+            }
+        };
+    }
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let prev = if i > 0 { Some(chars[i - 1]) } else { None };
+        let next = chars.get(i + 1).copied();
+        let word_start = match prev {
+            None => true,
+            Some(p) => p.is_whitespace() || p == '(',
+        };
+
+        match c {
+            '\\' if next.is_some_and(|n| n.is_ascii_punctuation()) => {
+                current.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '`' => {
+                if let Some(end) = find(&chars, i + 1, '`') {
+                    push_text!();
+                    output.push(Inline::Code(chars[i + 1..end].iter().collect()));
+                    i = end + 1;
+                    continue;
+                }
+            }
+            '*' | '_' | '~' => {
+                let double = next == Some(c);
+                let width = if double { 2 } else { 1 };
+                let after = chars.get(i + width).copied();
+                let on = match (c, double) {
+                    ('~', false) => None,
+                    ('~', true) => Some(strike),
+                    (_, true) => Some(bold),
+                    (_, false) => Some(italic),
+                };
+                // Closing needs text before it, opening needs text after it, and
+                // an underscore inside a word is just an underscore
+                let flanked = if on == Some(true) {
+                    prev.is_some_and(|p| !p.is_whitespace())
+                } else {
+                    after.is_some_and(|a| !a.is_whitespace())
+                };
+                let in_word = c == '_'
+                    && prev.is_some_and(|p| p.is_alphanumeric())
+                    && after.is_some_and(|a| a.is_alphanumeric());
+                if let (Some(on), true, false) = (on, flanked, in_word) {
+                    push_text!();
+                    match (c, double) {
+                        ('~', _) => strike = !on,
+                        (_, true) => bold = !on,
+                        _ => italic = !on,
+                    }
+                    i += width;
+                    continue;
+                }
+            }
+            '!' if next == Some('[') => {
+                if let Some((alt, url, end)) = link_at(&chars, i + 1) {
+                    push_text!();
+                    output.push(Inline::Image { alt, url });
+                    i = end;
+                    continue;
+                }
+            }
+            '[' => {
+                if let Some((text, url, end)) = link_at(&chars, i) {
+                    push_text!();
+                    output.push(Inline::Link { text, url });
+                    i = end;
+                    continue;
+                }
+            }
+            'n' if word_start && starts_with(&chars, i, "nostr:") => {
+                let end = (i..chars.len())
+                    .find(|&j| !chars[j].is_ascii_alphanumeric() && chars[j] != ':')
+                    .unwrap_or(chars.len());
+                push_text!();
+                let uri: String = chars[i..end].iter().collect();
+                output.push(Inline::Link {
+                    text: uri.clone(),
+                    url: uri,
+                });
+                i = end;
+                continue;
+            }
+            'h' if word_start
+                && (starts_with(&chars, i, "https://") || starts_with(&chars, i, "http://")) =>
+            {
+                let mut end = (i..chars.len())
+                    .find(|&j| chars[j].is_whitespace())
+                    .unwrap_or(chars.len());
+                // Trailing punctuation ends the sentence, not the URL
+                while end > i && ".,;:!?)".contains(chars[end - 1]) {
+                    end -= 1;
+                }
+                push_text!();
+                let url: String = chars[i..end].iter().collect();
+                output.push(Inline::Link {
+                    text: url.clone(),
+                    url,
+                });
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+
+        current.push(c);
+        i += 1;
+    }
+    push_text!();
+
+    output
+}
+
+fn find(chars: &[char], from: usize, c: char) -> Option<usize> {
+    (from..chars.len()).find(|&i| chars[i] == c)
+}
+
+fn starts_with(chars: &[char], at: usize, s: &str) -> bool {
+    let mut i = at;
+    for c in s.chars() {
+        if chars.get(i) != Some(&c) {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+// A `[text](url)` starting at `at`, with the index just after it
+fn link_at(chars: &[char], at: usize) -> Option<(String, String, usize)> {
+    let close = find(chars, at + 1, ']')?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = find(chars, close + 2, ')')?;
+    let text: String = chars[at + 1..close].iter().collect();
+    let target: String = chars[close + 2..end].iter().collect();
+    // Drop any title after the URL
+    let url = target.split_whitespace().next()?.to_owned();
+    Some((text, url, end + 1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn plain(text: &str) -> Inline {
+        Inline::Text {
+            text: text.to_owned(),
+            bold: false,
+            italic: false,
+            strike: false,
+        }
+    }
+
+    fn item(depth: usize, number: Option<u64>, text: &str) -> Block {
+        Block::ListItem {
+            depth,
+            number,
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_headings() {
+        assert_eq!(
+            parse("# One\n### Three ###\nSub\n---\nTop\n==="),
+            vec![
+                Block::Heading(1, "One".to_owned()),
+                Block::Heading(3, "Three".to_owned()),
+                Block::Heading(2, "Sub".to_owned()),
+                Block::Heading(1, "Top".to_owned()),
+            ]
+        );
+
+        // Too many hashes, or no space after them, is just text
+        assert_eq!(
+            parse("####### seven\n#hashtag"),
+            vec![Block::Paragraph("####### seven #hashtag".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_paragraphs_quotes_and_rules() {
+        assert_eq!(
+            parse("Some text\nmore text\n\n> quoted\n> lines\n\n* * *\nafter"),
+            vec![
+                Block::Paragraph("Some text more text".to_owned()),
+                Block::Quote("quoted lines".to_owned()),
+                Block::Rule,
+                Block::Paragraph("after".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(
+            parse("- one\n- two\n  continued\n  - nested\n1. first\n2) second\n+ plus"),
+            vec![
+                item(0, None, "one"),
+                item(0, None, "two continued"),
+                item(1, None, "nested"),
+                item(0, Some(1), "first"),
+                item(0, Some(2), "second"),
+                item(0, None, "plus"),
+            ]
+        );
+
+        // A number that starts a sentence is not a list
+        assert_eq!(
+            parse("1.5 million"),
+            vec![Block::Paragraph("1.5 million".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        assert_eq!(
+            parse("```rust\nfn main() {\n    # not a heading\n}\n```\nafter"),
+            vec![
+                Block::Code("fn main() {\n    # not a heading\n}".to_owned()),
+                Block::Paragraph("after".to_owned()),
+            ]
+        );
+
+        // Only the opening fence closes it
+        assert_eq!(parse("~~~\n```\n~~~"), vec![Block::Code("```".to_owned())]);
+
+        assert_eq!(
+            parse_inline("use `a*b` here"),
+            vec![
+                plain("use "),
+                Inline::Code("a*b".to_owned()),
+                plain(" here")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_links() {
+        assert_eq!(
+            parse_inline("see [gossip](https://example.com/gossip \"title\")."),
+            vec![
+                plain("see "),
+                Inline::Link {
+                    text: "gossip".to_owned(),
+                    url: "https://example.com/gossip".to_owned(),
+                },
+                plain("."),
+            ]
+        );
+
+        assert_eq!(
+            parse_inline("![a cat](https://example.com/cat.png)"),
+            vec![Inline::Image {
+                alt: "a cat".to_owned(),
+                url: "https://example.com/cat.png".to_owned(),
+            }]
+        );
+
+        // Bare URLs and nostr URIs, without the punctuation that ends a sentence
+        assert_eq!(
+            parse_inline("go to https://example.com/x. by nostr:npub1abc."),
+            vec![
+                plain("go to "),
+                Inline::Link {
+                    text: "https://example.com/x".to_owned(),
+                    url: "https://example.com/x".to_owned(),
+                },
+                plain(". by "),
+                Inline::Link {
+                    text: "nostr:npub1abc".to_owned(),
+                    url: "nostr:npub1abc".to_owned(),
+                },
+                plain("."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_emphasis() {
+        assert_eq!(
+            parse_inline("a **b** ~~c~~"),
+            vec![
+                plain("a "),
+                Inline::Text {
+                    text: "b".to_owned(),
+                    bold: true,
+                    italic: false,
+                    strike: false,
+                },
+                plain(" "),
+                Inline::Text {
+                    text: "c".to_owned(),
+                    bold: false,
+                    italic: false,
+                    strike: true,
+                },
+            ]
+        );
+
+        assert_eq!(
+            parse_inline("snake_case_name and 2 * 3 and \\*escaped\\*"),
+            vec![plain("snake_case_name and 2 * 3 and *escaped*")]
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(parse("").is_empty());
+
+        // An unclosed fence runs to the end
+        assert_eq!(
+            parse("```\ncode\nmore"),
+            vec![Block::Code("code\nmore".to_owned())]
+        );
+
+        // Unclosed links and code are shown as they are
+        assert_eq!(
+            parse_inline("[text](no end and [a]b and `tick"),
+            vec![plain("[text](no end and [a]b and `tick")]
+        );
+
+        // Stray markup doesn't panic, even among multi-byte characters
+        for markdown in ["#", "> ", "-", "1.", "**", "~~~", "é*ü_", "![", "[]()", "h"] {
+            let _ = parse(markdown);
+            let _ = parse_inline(markdown);
+        }
+    }
+}
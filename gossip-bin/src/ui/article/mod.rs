@@ -0,0 +1,431 @@
+use super::{widgets, GossipUi, Page};
+use eframe::egui;
+use egui::{Context, Frame, Image, Margin, RichText, Ui};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{Article, FeedKind, GLOBALS};
+use nostr_types::{Event, EventKind, NAddr, NostrBech32};
+
+mod markdown;
+use markdown::{Block, Inline};
+
+/// An article being written or edited
+#[derive(Debug, Clone, Default)]
+pub(super) struct ArticleEditor {
+    /// Empty for a new article, which gets one made from its title
+    pub d: String,
+    pub title: String,
+    pub summary: String,
+    pub image: String,
+    /// Comma separated
+    pub hashtags: String,
+    pub content: String,
+    pub preview: bool,
+}
+
+impl ArticleEditor {
+    pub fn edit(article: &Article) -> ArticleEditor {
+        ArticleEditor {
+            d: article.d.clone(),
+            title: article.title.clone(),
+            summary: article.summary.clone(),
+            image: article.image.clone().unwrap_or_default(),
+            hashtags: article.hashtags.join(", "),
+            content: article.content.clone(),
+            preview: false,
+        }
+    }
+
+    pub fn to_article(&self) -> Article {
+        Article {
+            d: self.d.clone(),
+            title: self.title.trim().to_owned(),
+            summary: self.summary.trim().to_owned(),
+            image: Some(self.image.trim().to_owned()).filter(|image| !image.is_empty()),
+            // The overlord keeps the original date when editing
+            published_at: None,
+            hashtags: self
+                .hashtags
+                .split(',')
+                .map(|t| t.trim().trim_start_matches('#').to_owned())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            content: self.content.clone(),
+        }
+    }
+}
+
+pub(super) fn enter_page(app: &mut GossipUi, naddr: &NAddr) {
+    app.article_revision = None;
+
+    // Keep its revisions from now on
+    GLOBALS
+        .opened_articles
+        .write()
+        .insert((naddr.author, naddr.d.clone()));
+
+    if let Ok(None) = GLOBALS
+        .storage
+        .get_replaceable_event(naddr.kind, naddr.author, &naddr.d)
+    {
+        let _ = GLOBALS
+            .to_overlord
+            .send(ToOverlordMessage::FetchNAddr(naddr.to_owned()));
+    }
+}
+
+pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    let naddr = match &app.page {
+        Page::Article(naddr) => naddr.clone(),
+        _ => return,
+    };
+
+    let current = match GLOBALS
+        .storage
+        .get_replaceable_event(naddr.kind, naddr.author, &naddr.d)
+    {
+        Ok(Some(event)) => event,
+        Ok(None) => {
+            widgets::page_header(ui, "Article", |_| {});
+            ui.label("Looking for this article on relays...");
+            return;
+        }
+        Err(e) => {
+            widgets::page_header(ui, "Article", |_| {});
+            ui.label(format!("{}", e));
+            return;
+        }
+    };
+
+    let revisions: Vec<Event> = GLOBALS
+        .storage
+        .get_revisions(naddr.kind, naddr.author, &naddr.d)
+        .unwrap_or_default();
+
+    // The version we are showing
+    let event = match app.article_revision {
+        Some(id) => revisions
+            .iter()
+            .find(|e| e.id == id)
+            .unwrap_or(&current)
+            .clone(),
+        None => current.clone(),
+    };
+    let article = match Article::from_event(&event) {
+        Some(article) => article,
+        None => {
+            widgets::page_header(ui, "Article", |_| {});
+            ui.label("This is not an article.");
+            return;
+        }
+    };
+
+    let title = if article.title.is_empty() {
+        "Untitled".to_owned()
+    } else {
+        article.title.clone()
+    };
+    let is_ours = GLOBALS.identity.public_key() == Some(naddr.author);
+
+    widgets::page_header(ui, title, |ui| {
+        if is_ours && ui.button("Edit").clicked() {
+            if let Some(latest) = Article::from_event(&current) {
+                app.article_editor = Some(ArticleEditor::edit(&latest));
+                app.set_page(ui.ctx(), Page::YourArticles);
+            }
+        }
+
+        if !revisions.is_empty() {
+            let selected = match app.article_revision {
+                Some(_) => format!("Revision of {}", format_local(event.created_at)),
+                None => "Latest version".to_owned(),
+            };
+            egui::ComboBox::from_id_source(egui::Id::from("ArticleRevisionCombo"))
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut app.article_revision, None, "Latest version");
+                    for revision in revisions.iter() {
+                        ui.selectable_value(
+                            &mut app.article_revision,
+                            Some(revision.id),
+                            format!("Revision of {}", format_local(revision.created_at)),
+                        );
+                    }
+                });
+        }
+    });
+
+    app.vert_scroll_area().id_source("article").show(ui, |ui| {
+        ui.horizontal_wrapped(|ui| {
+            let name = gossip_lib::names::best_name_from_pubkey_lookup(&naddr.author);
+            if ui.link(RichText::new(name).strong()).clicked() {
+                app.set_page(ui.ctx(), Page::Person(naddr.author));
+            }
+            if let Some(published_at) = article.published_at {
+                ui.label(RichText::new(format!("published {}", format_local(published_at))).weak());
+            }
+            if article.published_at != Some(event.created_at) {
+                ui.label(
+                    RichText::new(format!("updated {}", format_local(event.created_at))).weak(),
+                );
+            }
+        });
+
+        let deletions = GLOBALS.storage.get_deletions(&current).unwrap_or_default();
+        if !deletions.is_empty() {
+            let color = app.theme.warning_marker_text_color();
+            ui.label(RichText::new("DELETED").color(color));
+            for reason in deletions.iter().filter(|r| !r.is_empty()) {
+                ui.label(RichText::new(reason).color(color).italics());
+            }
+        }
+
+        if app.article_revision.is_some() {
+            let color = app.theme.notice_marker_text_color();
+            ui.label(
+                RichText::new("You are reading an earlier version of this article.").color(color),
+            );
+        }
+
+        if !article.hashtags.is_empty() {
+            ui.label(
+                RichText::new(
+                    article
+                        .hashtags
+                        .iter()
+                        .map(|t| format!("#{}", t))
+                        .collect::<Vec<String>>()
+                        .join(" "),
+                )
+                .weak(),
+            );
+        }
+
+        if let Some(image) = &article.image {
+            ui.add_space(8.0);
+            render_image(app, ui, "", image);
+        }
+
+        if !article.summary.is_empty() {
+            ui.add_space(8.0);
+            ui.label(RichText::new(&article.summary).italics().size(16.0));
+        }
+
+        ui.add_space(8.0);
+        ui.separator();
+        ui.add_space(8.0);
+
+        render_markdown(app, ui, &article.content);
+
+        ui.add_space(20.0);
+    });
+}
+
+/// Render an article body
+pub(super) fn render_markdown(app: &mut GossipUi, ui: &mut Ui, content: &str) {
+    for block in markdown::parse(content) {
+        match block {
+            Block::Heading(level, text) => {
+                let size = match level {
+                    1 => 24.0,
+                    2 => 20.0,
+                    3 => 17.0,
+                    _ => 15.0,
+                };
+                ui.add_space(6.0);
+                render_inline(app, ui, &text, Some(size));
+                ui.add_space(4.0);
+            }
+            Block::Paragraph(text) => {
+                render_inline(app, ui, &text, None);
+                ui.add_space(8.0);
+            }
+            Block::Quote(text) => {
+                ui.horizontal(|ui| {
+                    ui.separator();
+                    ui.vertical(|ui| render_inline(app, ui, &text, None));
+                });
+                ui.add_space(8.0);
+            }
+            Block::ListItem {
+                depth,
+                number,
+                text,
+            } => {
+                ui.horizontal(|ui| {
+                    ui.add_space(8.0 + 16.0 * depth as f32);
+                    match number {
+                        Some(n) => ui.label(format!("{}.", n)),
+                        None => ui.label("•"),
+                    };
+                    ui.vertical(|ui| render_inline(app, ui, &text, None));
+                });
+                ui.add_space(2.0);
+            }
+            Block::Code(code) => {
+                Frame::none()
+                    .fill(ui.visuals().extreme_bg_color)
+                    .inner_margin(Margin::same(6.0))
+                    .show(ui, |ui| {
+                        ui.label(RichText::new(code).monospace());
+                    });
+                ui.add_space(8.0);
+            }
+            Block::Rule => {
+                ui.separator();
+            }
+        }
+    }
+}
+
+fn render_inline(app: &mut GossipUi, ui: &mut Ui, text: &str, heading_size: Option<f32>) {
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        for span in markdown::parse_inline(text) {
+            match span {
+                Inline::Text {
+                    text,
+                    bold,
+                    italic,
+                    strike,
+                } => {
+                    let mut rt = RichText::new(text);
+                    if let Some(size) = heading_size {
+                        rt = rt.size(size).strong();
+                    }
+                    if bold {
+                        rt = rt.strong();
+                    }
+                    if italic {
+                        rt = rt.italics();
+                    }
+                    if strike {
+                        rt = rt.strikethrough();
+                    }
+                    ui.label(rt);
+                }
+                Inline::Code(code) => {
+                    ui.label(RichText::new(code).code());
+                }
+                Inline::Link { text, url } => render_link(app, ui, &text, &url),
+                Inline::Image { alt, url } => render_image(app, ui, &alt, &url),
+            }
+        }
+    });
+}
+
+fn render_link(app: &mut GossipUi, ui: &mut Ui, text: &str, url: &str) {
+    let bech32 = match url.strip_prefix("nostr:") {
+        Some(rest) => NostrBech32::try_from_string(rest),
+        None => {
+            ui.hyperlink_to(text, url);
+            return;
+        }
+    };
+
+    // Show a nostr link by what it points to, unless it was given other text
+    let text = if text == url {
+        match &bech32 {
+            Some(NostrBech32::Pubkey(pubkey)) => {
+                format!(
+                    "@{}",
+                    gossip_lib::names::best_name_from_pubkey_lookup(pubkey)
+                )
+            }
+            Some(NostrBech32::Profile(profile)) => format!(
+                "@{}",
+                gossip_lib::names::best_name_from_pubkey_lookup(&profile.pubkey)
+            ),
+            _ => text.to_owned(),
+        }
+    } else {
+        text.to_owned()
+    };
+
+    if !ui.link(text).clicked() {
+        return;
+    }
+    match bech32 {
+        Some(NostrBech32::Pubkey(pubkey)) => app.set_page(ui.ctx(), Page::Person(pubkey)),
+        Some(NostrBech32::Profile(profile)) => app.set_page(ui.ctx(), Page::Person(profile.pubkey)),
+        Some(NostrBech32::NAddr(naddr)) if naddr.kind == EventKind::LongFormContent => {
+            app.set_page(ui.ctx(), Page::Article(naddr))
+        }
+        Some(NostrBech32::NAddr(naddr)) => {
+            if let Ok(Some(event)) =
+                GLOBALS
+                    .storage
+                    .get_replaceable_event(naddr.kind, naddr.author, &naddr.d)
+            {
+                app.set_page(
+                    ui.ctx(),
+                    Page::Feed(FeedKind::Thread {
+                        id: event.id,
+                        referenced_by: event.id,
+                        author: Some(event.pubkey),
+                    }),
+                );
+            } else {
+                GLOBALS
+                    .status_queue
+                    .write()
+                    .write("Parameterized event not found.".to_owned());
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::FetchNAddr(naddr));
+            }
+        }
+        Some(NostrBech32::Id(id)) => app.set_page(
+            ui.ctx(),
+            Page::Feed(FeedKind::Thread {
+                id,
+                referenced_by: id,
+                author: None,
+            }),
+        ),
+        Some(NostrBech32::NEvent(ne)) => app.set_page(
+            ui.ctx(),
+            Page::Feed(FeedKind::Thread {
+                id: ne.id,
+                referenced_by: ne.id,
+                author: ne.author,
+            }),
+        ),
+        _ => {}
+    }
+}
+
+fn render_image(app: &mut GossipUi, ui: &mut Ui, alt: &str, url: &str) {
+    let label = if alt.is_empty() { "[ Image ]" } else { alt };
+    let url = match app.try_check_url(url) {
+        Some(url) => url,
+        None => {
+            ui.hyperlink_to(label, url);
+            return;
+        }
+    };
+
+    if read_setting!(show_media) {
+        if let Some(media) = app.try_get_media(ui.ctx(), url.clone(), false) {
+            let width = ui.available_width().min(media.size_vec2().x);
+            ui.add(
+                Image::new(&media)
+                    .max_width(width)
+                    .maintain_aspect_ratio(true),
+            )
+            .on_hover_text(alt);
+            return;
+        }
+    }
+    ui.hyperlink_to(label, url.as_str());
+}
+
+// Dates as the reader would write them
+fn format_local(when: nostr_types::Unixtime) -> String {
+    match chrono::DateTime::from_timestamp(when.0, 0) {
+        Some(utc) => utc
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        None => format!("{}", when.0),
+    }
+}
@@ -6,11 +6,13 @@ use eframe::{
 use egui::{Button, Color32, Pos2, RichText, Stroke, Ui};
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::FeedKind;
+use gossip_lib::MediaDescriptor;
 use gossip_lib::GLOBALS;
-use nostr_types::{ContentSegment, Id, IdHex, NAddr, NostrBech32, PublicKey, Span, Url};
+use nostr_types::{ContentSegment, EventKind, Id, IdHex, NAddr, NostrBech32, PublicKey, Span, Url};
 use std::{
     cell::{Ref, RefCell},
     rc::Rc,
+    sync::atomic::Ordering,
 };
 
 const MAX_POST_HEIGHT: f32 = 200.0;
@@ -209,10 +211,12 @@ pub(super) fn render_hyperlink(
     let privacy_issue = note.direct_message;
 
     if let (Ok(url), Some(nurl)) = (url::Url::try_from(link), app.try_check_url(link)) {
-        if is_image_url(&url) {
-            show_image_toggle(app, ui, nurl, privacy_issue, note.volatile);
-        } else if is_video_url(&url) {
-            show_video_toggle(app, ui, nurl, privacy_issue, note.volatile);
+        // An imeta tag may say what the media is when the url doesn't
+        let descriptor = note.media.get(link);
+        if is_image_url(&url) || descriptor.map(|d| d.is_image()).unwrap_or(false) {
+            show_image_toggle(app, ui, nurl, privacy_issue, note.volatile, descriptor);
+        } else if is_video_url(&url) || descriptor.map(|d| d.is_video()).unwrap_or(false) {
+            show_video_toggle(app, ui, nurl, privacy_issue, note.volatile, descriptor);
         } else {
             crate::ui::widgets::break_anywhere_hyperlink_to(ui, link, link);
         }
@@ -303,7 +307,10 @@ pub(super) fn render_parameterized_event_link(
     let name = format!("[{:?}: {}]", naddr.kind, naddr.d);
     // let name = format!("nostr:{}", naddr.as_bech32_string());
     if ui.link(&name).clicked() {
-        if let Ok(Some(prevent)) =
+        if naddr.kind == EventKind::LongFormContent {
+            // The article page fetches it if we don't have it
+            app.set_page(ui.ctx(), Page::Article(naddr.to_owned()));
+        } else if let Ok(Some(prevent)) =
             GLOBALS
                 .storage
                 .get_replaceable_event(naddr.kind, naddr.author, &naddr.d)
@@ -372,6 +379,7 @@ fn show_image_toggle(
     url: Url,
     privacy_issue: bool,
     volatile: bool,
+    descriptor: Option<&MediaDescriptor>,
 ) {
     let row_height = ui.cursor().height();
     let url_string = url.to_string();
@@ -383,8 +391,16 @@ fn show_image_toggle(
         && (!privacy_issue || app.media_show_list.contains(&url)))
         || (!read_setting!(show_media) && app.media_show_list.contains(&url));
 
-    if show_image {
-        if let Some(response) = try_render_image(app, ui, url.clone(), volatile) {
+    let alt = descriptor.and_then(|d| d.alt.clone());
+
+    // The image isn't what the author of this note posted
+    let mismatch = descriptor
+        .and_then(|d| d.sha256.as_ref())
+        .map(|sha256| GLOBALS.media.sha256_mismatch(&url, sha256))
+        .unwrap_or(false);
+
+    if show_image && !mismatch {
+        if let Some(response) = try_render_image(app, ui, url.clone(), volatile, alt.as_deref()) {
             show_link = false;
 
             // full-width toggle
@@ -395,13 +411,30 @@ fn show_image_toggle(
                     app.media_full_width_list.insert(url.clone());
                 }
             }
+        } else if let Some(descriptor) = descriptor {
+            // Hold the space while it loads, so the feed doesn't jump
+            if read_setting!(load_media)
+                && !app.has_media_loading_failed(url_string.as_str())
+                && render_placeholder(app, ui, &url, descriptor)
+            {
+                show_link = false;
+            }
         }
     }
 
     if show_link {
         // show media toggle
-        let response = if privacy_issue {
+        let response = if mismatch {
+            ui.link("[ MISMATCHED Image ]").on_hover_text(format!(
+                "This image is not the one the author posted (its hash differs). URL={}",
+                url_string
+            ))
+        } else if privacy_issue {
             ui.link("[ PRIVACY RISK Image ]").on_hover_text(format!("The sender might be trying to associate your nostr pubkey with your IP address. URL={}", url_string))
+        } else if let Some(alt) = &alt {
+            // show alt text and url on hover
+            ui.link("[ Image ]")
+                .on_hover_text(format!("{}\n\n{}", alt, url_string))
         } else {
             // show url on hover
             ui.link("[ Image ]").on_hover_text(url_string.clone())
@@ -444,7 +477,13 @@ fn show_image_toggle(
 
 /// Try to fetch and render a piece of media
 ///  - return: true if successfully rendered, false otherwise
-fn try_render_image(app: &mut GossipUi, ui: &mut Ui, url: Url, volatile: bool) -> Option<Response> {
+fn try_render_image(
+    app: &mut GossipUi,
+    ui: &mut Ui,
+    url: Url,
+    volatile: bool,
+    alt: Option<&str>,
+) -> Option<Response> {
     let mut response_return = None;
     if let Some(media) = app.try_get_media(ui.ctx(), url.clone(), volatile) {
         let size = media_scale(
@@ -476,6 +515,10 @@ fn try_render_image(app: &mut GossipUi, ui: &mut Ui, url: Url, volatile: bool) -
                         .maintain_aspect_ratio(true)
                         .sense(egui::Sense::click()),
                 );
+                let response = match alt {
+                    Some(alt) => response.on_hover_text(alt),
+                    None => response,
+                };
                 if response.hovered() {
                     ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
                 }
@@ -486,12 +529,71 @@ fn try_render_image(app: &mut GossipUi, ui: &mut Ui, url: Url, volatile: bool) -
     response_return
 }
 
+/// Reserve the space of an image whose dimensions an imeta tag gives, drawing
+/// its blurhash if there is one
+///  - return: true if rendered, false if the dimensions are unknown
+fn render_placeholder(
+    app: &mut GossipUi,
+    ui: &mut Ui,
+    url: &Url,
+    descriptor: &MediaDescriptor,
+) -> bool {
+    let (width, height) = match descriptor.dimensions {
+        Some(dimensions) => dimensions,
+        None => return false,
+    };
+
+    // The image will be loaded at no more than this size
+    let max_image_side = GLOBALS.max_image_side.load(Ordering::Relaxed) as f32;
+    let mut media_size = Vec2::new(width as f32, height as f32);
+    if media_size.max_elem() > max_image_side {
+        media_size *= max_image_side / media_size.max_elem();
+    }
+    let size = media_scale(app.media_full_width_list.contains(url), ui, media_size);
+
+    // insert a newline if the current line has text
+    if ui.cursor().min.x > ui.max_rect().min.x {
+        ui.end_row();
+    }
+
+    egui::Frame::none()
+        .inner_margin(egui::Margin::same(0.0))
+        .outer_margin(egui::Margin {
+            top: 10.0,
+            left: 0.0,
+            right: 0.0,
+            bottom: 10.0,
+        })
+        .fill(egui::Color32::TRANSPARENT)
+        .rounding(ui.style().noninteractive().rounding)
+        .show(ui, |ui| {
+            let response = match app.try_get_placeholder(ui.ctx(), descriptor) {
+                Some(texture) => ui.add(Image::new(&texture).fit_to_exact_size(size)),
+                None => {
+                    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+                    ui.painter().rect_filled(
+                        rect,
+                        ui.style().noninteractive().rounding,
+                        ui.visuals().faint_bg_color,
+                    );
+                    response
+                }
+            };
+            if let Some(alt) = &descriptor.alt {
+                response.on_hover_text(alt);
+            }
+        });
+
+    true
+}
+
 fn show_video_toggle(
     app: &mut GossipUi,
     ui: &mut Ui,
     url: Url,
     privacy_issue: bool,
     volatile: bool,
+    descriptor: Option<&MediaDescriptor>,
 ) {
     let row_height = ui.cursor().height();
     let url_string = url.to_string();
@@ -503,7 +605,13 @@ fn show_video_toggle(
         && (!privacy_issue || app.media_show_list.contains(&url)))
         || (!read_setting!(show_media) && app.media_show_list.contains(&url));
 
-    if show_video {
+    // The video isn't what the author of this note posted
+    let mismatch = descriptor
+        .and_then(|d| d.sha256.as_ref())
+        .map(|sha256| GLOBALS.media.sha256_mismatch(&url, sha256))
+        .unwrap_or(false);
+
+    if show_video && !mismatch {
         if let Some(response) = try_render_video(app, ui, url.clone(), volatile) {
             show_link = false;
 
@@ -520,7 +628,12 @@ fn show_video_toggle(
 
     if show_link {
         // show media toggle
-        let response = if privacy_issue {
+        let response = if mismatch {
+            ui.link("[ MISMATCHED Video ]").on_hover_text(format!(
+                "This video is not the one the author posted (its hash differs). URL={}",
+                url_string
+            ))
+        } else if privacy_issue {
             ui.link("[ PRIVACY RISK Video ]").on_hover_text(format!(
                 "The sender might be trying to associate your pubkey with your IP address. URL={}",
                 url_string
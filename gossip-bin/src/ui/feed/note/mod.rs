@@ -18,7 +18,7 @@ use egui::{
     Align, Context, Frame, Label, Layout, RichText, Sense, Separator, Stroke, TextStyle, Ui,
 };
 use gossip_lib::comms::ToOverlordMessage;
-use gossip_lib::{relay, DmChannel, FeedKind, ZapState, GLOBALS};
+use gossip_lib::{relay, CountKey, DmChannel, FeedKind, Mute, Private, ZapState, GLOBALS};
 use nostr_types::{
     Event, EventDelegation, EventKind, EventReference, IdHex, NAddr, NEvent, NostrUrl, UncheckedUrl,
 };
@@ -540,6 +540,21 @@ pub fn render_note_inner(
                             );
                         }
 
+                        if note.event.kind == EventKind::LongFormContent {
+                            let naddr = NAddr {
+                                d: note.event.parameter().unwrap_or_default(),
+                                relays: vec![],
+                                kind: EventKind::LongFormContent,
+                                author: note.event.pubkey,
+                            };
+                            if ui
+                                .link(RichText::new("READ ARTICLE").text_style(TextStyle::Small))
+                                .clicked()
+                            {
+                                app.set_page(ui.ctx(), Page::Article(naddr));
+                            }
+                        }
+
                         if note.repost.is_some() {
                             let color = app.theme.notice_marker_text_color();
                             ui.label(
@@ -779,6 +794,17 @@ pub fn render_note_inner(
 
                                         app.draft_data.replying_to = Some(note.event.id);
                                     }
+
+                                    // Replies counted by relays (NIP-45)
+                                    if let Some(replies) =
+                                        GLOBALS.counts.get(CountKey::Replies(note.event.id))
+                                    {
+                                        ui.add(
+                                            Label::new(widgets::relay_count_text(&replies))
+                                                .sense(Sense::hover()),
+                                        )
+                                        .on_hover_text("Replies, as counted by relays");
+                                    }
                                 };
 
                                 ui.add_space(24.0);
@@ -1002,6 +1028,24 @@ pub fn render_note_inner(
                                     )
                                     .on_hover_ui(hover_ui)
                                     .on_disabled_hover_ui(hover_ui);
+
+                                    // Reactions counted by relays (NIP-45), which may
+                                    // include many we have not loaded
+                                    if let Some(reactions) =
+                                        GLOBALS.counts.get(CountKey::Reactions(note.event.id))
+                                    {
+                                        ui.add(
+                                            Label::new(
+                                                RichText::new(format!(
+                                                    "({})",
+                                                    widgets::relay_count_text(&reactions)
+                                                ))
+                                                .weak(),
+                                            )
+                                            .sense(Sense::hover()),
+                                        )
+                                        .on_hover_text("Reactions, as counted by relays");
+                                    }
                                 }
                             });
 
@@ -1463,6 +1507,51 @@ fn note_actions(
         )));
     } // end Bookmark
 
+    // ---- Mute Thread ----
+    if GLOBALS.mutes.read().thread_muted(&note.event) {
+        items.push(MoreMenuItem::Button(MoreMenuButton::new(
+            "Unmute thread",
+            Box::new(|_, _| {
+                // The thread may have been muted at this note, its parent or its root
+                let mut ids = vec![note.event.id];
+                for eref in [note.event.replies_to(), note.event.replies_to_root()]
+                    .into_iter()
+                    .flatten()
+                {
+                    if let EventReference::Id { id, .. } = eref {
+                        ids.push(id);
+                    }
+                }
+                for id in ids {
+                    let _ = gossip_lib::mutes::unmute(&Mute::Thread(id));
+                }
+            }),
+        )));
+    } else {
+        let thread = match note.event.replies_to_root() {
+            Some(EventReference::Id { id, .. }) => id,
+            _ => note.event.id,
+        };
+        let mut mute_items: Vec<MoreMenuItem> = Vec::new();
+        mute_items.push(MoreMenuItem::Button(MoreMenuButton::new(
+            "Public",
+            Box::new(move |_, _| {
+                let _ = gossip_lib::mutes::mute(Mute::Thread(thread), Private(false));
+            }),
+        )));
+        mute_items.push(MoreMenuItem::Button(MoreMenuButton::new(
+            "Private",
+            Box::new(move |_, _| {
+                let _ = gossip_lib::mutes::mute(Mute::Thread(thread), Private(true));
+            }),
+        )));
+        items.push(MoreMenuItem::SubMenu(MoreMenuSubMenu::new(
+            "Mute thread",
+            mute_items,
+            &menu,
+        )));
+    } // end Mute Thread
+
     // ---- Copy ID SubMenu ----
     {
         // put all copy buttons in a submenu
@@ -1,6 +1,8 @@
 use super::FeedNoteParams;
 use crate::ui::widgets::{InformationPopup, MoreMenuButton, MoreMenuItem};
-use crate::ui::{widgets, you, FeedKind, GossipUi, HighlightType, Page, Theme};
+use crate::ui::{
+    widgets, you, DraftData, DraftSync, FeedKind, GossipUi, HighlightType, Page, Theme,
+};
 use eframe::egui;
 use eframe::epaint::text::LayoutJob;
 use egui::containers::CollapsingHeader;
@@ -9,10 +11,15 @@ use egui_winit::egui::text::{CCursor, CCursorRange};
 use egui_winit::egui::text_edit::TextEditOutput;
 use egui_winit::egui::{vec2, AboveOrBelow, Id};
 use gossip_lib::comms::ToOverlordMessage;
-use gossip_lib::{DmChannel, PersonTable, Relay, Table, GLOBALS};
+use gossip_lib::{DmChannel, Draft, DraftContext, PersonTable, Relay, Table, GLOBALS};
 use memoize::memoize;
-use nostr_types::{ContentSegment, NostrBech32, NostrUrl, ShatteredContent, Tag};
+use nostr_types::{ContentSegment, NostrBech32, NostrUrl, ShatteredContent, Tag, Unixtime};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// How often the draft being composed is saved
+const DRAFT_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(2);
 
 #[memoize]
 pub fn textarea_highlighter(theme: Theme, text: String, interests: Vec<String>) -> LayoutJob {
@@ -143,8 +150,26 @@ pub(in crate::ui) fn posting_area(
                 _ => None,
             };
             match &dm_channel {
-                Some(dmc) => dm_posting_area(app, ctx, frame, ui, dmc),
-                None => real_posting_area(app, ctx, ui),
+                Some(dmc) => {
+                    let context = DraftContext::DmChannel(dmc.keys().to_vec());
+                    sync_draft(
+                        &mut app.dm_draft_data,
+                        &mut app.dm_draft_sync,
+                        Some(context),
+                        false,
+                    );
+                    dm_posting_area(app, ctx, frame, ui, dmc);
+                }
+                None => {
+                    // Reposts have nothing to save
+                    let context = match (app.draft_data.repost, app.draft_data.replying_to) {
+                        (Some(_), _) => None,
+                        (None, Some(id)) => Some(DraftContext::Reply(id)),
+                        (None, None) => Some(DraftContext::NewNote),
+                    };
+                    sync_draft(&mut app.draft_data, &mut app.draft_sync, context, true);
+                    real_posting_area(app, ctx, ui);
+                }
             }
         }
     });
@@ -411,6 +436,28 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
     let screen_rect = ctx.input(|i| i.screen_rect);
     let window_height = screen_rect.max.y - screen_rect.min.y;
 
+    // Files dropped on the window are uploaded for this draft
+    if app.draft_data.repost.is_none() {
+        for file in ctx.input(|i| i.raw.dropped_files.clone()) {
+            if let Some(path) = file.path {
+                app.draft_data.include_media = true;
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::UploadMedia(path));
+            }
+        }
+    }
+
+    // Put the URLs of finished uploads into the draft
+    for media in GLOBALS.uploads.take_finished() {
+        if !app.draft_data.draft.is_empty() && !app.draft_data.draft.ends_with(char::is_whitespace)
+        {
+            app.draft_data.draft.push('\n');
+        }
+        app.draft_data.draft.push_str(&media.url);
+        app.draft_data.uploads.push(media);
+    }
+
     app.vert_scroll_area()
         .max_height(window_height * 0.7)
         .show(ui, |ui| {
@@ -466,6 +513,18 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                     ui.add_space(10.0);
                 }
 
+                if app.draft_data.include_schedule {
+                    ui.horizontal(|ui| {
+                        ui.label("Publish at: ");
+                        ui.add(
+                            text_edit_line!(app, app.draft_data.schedule_at)
+                                .hint_text("YYYY-MM-DD HH:MM (local time)")
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+                    ui.add_space(10.0);
+                }
+
                 if app.draft_data.include_content_warning {
                     ui.horizontal(|ui| {
                         ui.label("Content Warning: ");
@@ -478,6 +537,29 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                     ui.add_space(10.0);
                 }
 
+                if app.draft_data.include_media {
+                    ui.horizontal(|ui| {
+                        ui.label("File: ");
+                        ui.add(
+                            text_edit_line!(app, app.draft_data.media_path)
+                                .hint_text("Path of an image or video, or drop one here")
+                                .desired_width(300.0),
+                        );
+                        if ui.button("Upload").clicked() && !app.draft_data.media_path.is_empty() {
+                            let path = PathBuf::from(app.draft_data.media_path.trim());
+                            let _ = GLOBALS
+                                .to_overlord
+                                .send(ToOverlordMessage::UploadMedia(path));
+                            app.draft_data.media_path = "".to_owned();
+                        }
+                        let uploading = GLOBALS.uploads.in_progress();
+                        if uploading > 0 {
+                            ui.label(format!("Uploading {}...", uploading));
+                        }
+                    });
+                    ui.add_space(10.0);
+                }
+
                 // if we are tagging, we will consume arrow presses and enter key
                 let enter_key;
                 (app.draft_data.tagging_search_selected, enter_key) =
@@ -581,6 +663,8 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
     ui.horizontal(|ui| {
         let send_label = if app.draft_data.repost.is_some() {
             "Repost note"
+        } else if app.draft_data.include_schedule {
+            "Schedule note"
         } else {
             "Send note"
         };
@@ -590,12 +674,29 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
             if app.draft_data.repost.is_none() {
                 let response = widgets::options_menu_button(ui, &app.theme, &app.assets);
                 let menu = widgets::MoreMenu::bubble(ui.next_auto_id())
-                    .with_max_size(vec2(180.0, 80.0))
-                    .with_min_size(vec2(180.0, 80.0))
+                    .with_max_size(vec2(180.0, 130.0))
+                    .with_min_size(vec2(180.0, 130.0))
                     .place_above(!read_setting!(posting_area_at_top));
 
                 let mut items: Vec<MoreMenuItem> = Vec::new();
 
+                if app.draft_data.include_schedule {
+                    items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                        "Post Now Instead",
+                        Box::new(|_, app| {
+                            app.draft_data.include_schedule = false;
+                            app.draft_data.schedule_at = "".to_owned();
+                        }),
+                    )));
+                } else {
+                    items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                        "Schedule for Later",
+                        Box::new(|_, app| {
+                            app.draft_data.include_schedule = true;
+                        }),
+                    )));
+                }
+
                 if app.draft_data.include_subject {
                     items.push(MoreMenuItem::Button(MoreMenuButton::new(
                         "Remove Subject",
@@ -630,6 +731,23 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                     )));
                 }
 
+                if app.draft_data.include_media {
+                    items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                        "Remove Media Row",
+                        Box::new(|_, app| {
+                            app.draft_data.include_media = false;
+                            app.draft_data.media_path = "".to_owned();
+                        }),
+                    )));
+                } else {
+                    items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                        "Attach Media",
+                        Box::new(|_, app| {
+                            app.draft_data.include_media = true;
+                        }),
+                    )));
+                }
+
                 items.push(MoreMenuItem::Button(
                     MoreMenuButton::new(
                         "Show raw preview",
@@ -715,34 +833,59 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
         if app.draft_data.include_subject {
             tags.push(Tag::new_subject(app.draft_data.subject.clone()));
         }
-        match app.draft_data.replying_to {
-            Some(replying_to_id) => {
-                let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Post {
-                    content: replaced,
-                    tags,
-                    in_reply_to: Some(replying_to_id),
-                    annotation: app.draft_data.is_annotate,
-                    dm_channel: None,
-                });
+        // Describe uploaded media that is still in the post (NIP-92)
+        for media in &app.draft_data.uploads {
+            if replaced.contains(&media.url) {
+                tags.push(media.imeta_tag());
             }
-            None => {
-                if let Some(event_id) = app.draft_data.repost {
-                    let _ = GLOBALS
-                        .to_overlord
-                        .send(ToOverlordMessage::Repost(event_id));
-                } else {
+        }
+        if app.draft_data.include_schedule {
+            match parse_schedule_time(&app.draft_data.schedule_at) {
+                Some(publish_at) => {
+                    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::SchedulePost {
+                        content: replaced,
+                        tags,
+                        in_reply_to: app.draft_data.replying_to,
+                        annotation: app.draft_data.is_annotate,
+                        dm_channel: None,
+                        publish_at,
+                    });
+                    app.reset_draft();
+                }
+                None => GLOBALS.status_queue.write().write(
+                    "Enter when to publish as YYYY-MM-DD HH:MM, in your local time".to_owned(),
+                ),
+            }
+        } else {
+            match app.draft_data.replying_to {
+                Some(replying_to_id) => {
                     let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Post {
                         content: replaced,
                         tags,
-                        in_reply_to: None,
+                        in_reply_to: Some(replying_to_id),
                         annotation: app.draft_data.is_annotate,
                         dm_channel: None,
                     });
                 }
+                None => {
+                    if let Some(event_id) = app.draft_data.repost {
+                        let _ = GLOBALS
+                            .to_overlord
+                            .send(ToOverlordMessage::Repost(event_id));
+                    } else {
+                        let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Post {
+                            content: replaced,
+                            tags,
+                            in_reply_to: None,
+                            annotation: app.draft_data.is_annotate,
+                            dm_channel: None,
+                        });
+                    }
+                }
             }
-        }
 
-        app.reset_draft();
+            app.reset_draft();
+        }
     }
 
     // List tags that will be applied
@@ -965,6 +1108,125 @@ fn show_tag_hovers(ui: &mut Ui, app: &mut GossipUi, output: &mut TextEditOutput)
     }
 }
 
+/// Keep the saved draft in step with the composer. Changes are saved every few
+/// seconds. When the composer switches to another context (e.g. to reply to something
+/// else) the current draft is saved and the draft of the new context is loaded. If the
+/// new context has no draft, `carry_over` keeps what was typed for the new context.
+fn sync_draft(
+    data: &mut DraftData,
+    sync: &mut DraftSync,
+    context: Option<DraftContext>,
+    carry_over: bool,
+) {
+    if context == sync.context {
+        save_draft(data, sync, false);
+        return;
+    }
+
+    save_draft(data, sync, true);
+
+    if let Some(new_context) = &context {
+        match GLOBALS.storage.read_draft(new_context, None) {
+            Ok(Some(draft)) => {
+                load_draft(data, &draft);
+                sync.saved = Some(draft);
+            }
+            _ => {
+                if carry_over {
+                    // What was typed moves to the new context
+                    if let Some(old_context) = &sync.context {
+                        let _ = GLOBALS.storage.delete_draft(old_context, None);
+                    }
+                } else {
+                    let replying_to = data.replying_to;
+                    data.clear();
+                    data.replying_to = replying_to;
+                }
+                sync.saved = None;
+            }
+        }
+    }
+
+    sync.context = context;
+}
+
+/// Forget the saved draft of the composer, as it was posted or cancelled
+pub(in crate::ui) fn discard_saved_draft(sync: &mut DraftSync) {
+    if let Some(context) = &sync.context {
+        let _ = GLOBALS.storage.delete_draft(context, None);
+    }
+    sync.saved = None;
+}
+
+fn save_draft(data: &DraftData, sync: &mut DraftSync, now: bool) {
+    let context = match &sync.context {
+        Some(context) => context.clone(),
+        None => return,
+    };
+
+    if !now
+        && sync
+            .saved_at
+            .map(|t| t.elapsed() < DRAFT_AUTOSAVE_INTERVAL)
+            .unwrap_or(false)
+    {
+        return;
+    }
+
+    let draft = Draft {
+        context,
+        content: data.draft.clone(),
+        subject: data.include_subject.then(|| data.subject.clone()),
+        content_warning: data
+            .include_content_warning
+            .then(|| data.content_warning.clone()),
+        annotation: data.is_annotate,
+        updated_at: Unixtime::now(),
+    };
+
+    let unchanged = match &sync.saved {
+        Some(saved) => {
+            saved.content == draft.content
+                && saved.subject == draft.subject
+                && saved.content_warning == draft.content_warning
+                && saved.annotation == draft.annotation
+        }
+        None => draft.content.trim().is_empty(),
+    };
+    if unchanged {
+        return;
+    }
+
+    let result = if draft.content.trim().is_empty() {
+        sync.saved = None;
+        GLOBALS.storage.delete_draft(&draft.context, None)
+    } else {
+        let result = GLOBALS.storage.write_draft(&draft, None);
+        sync.saved = Some(draft);
+        result
+    };
+    if let Err(e) = result {
+        tracing::error!("Could not save draft: {}", e);
+    }
+    sync.saved_at = Some(Instant::now());
+}
+
+fn load_draft(data: &mut DraftData, draft: &Draft) {
+    data.draft = draft.content.clone();
+    data.include_subject = draft.subject.is_some();
+    data.subject = draft.subject.clone().unwrap_or_default();
+    data.include_content_warning = draft.content_warning.is_some();
+    data.content_warning = draft.content_warning.clone().unwrap_or_default();
+    data.is_annotate = draft.annotation;
+}
+
+// Parse a local "YYYY-MM-DD HH:MM" time
+fn parse_schedule_time(text: &str) -> Option<Unixtime> {
+    let naive = chrono::NaiveDateTime::parse_from_str(text.trim(), "%Y-%m-%d %H:%M").ok()?;
+    let local = naive.and_local_timezone(chrono::Local).earliest()?;
+    Some(Unixtime(local.timestamp()))
+}
+
 fn do_replacements(draft: &str, replacements: &HashMap<String, ContentSegment>) -> String {
     let mut output = draft.to_owned();
     for (pat, content) in replacements {
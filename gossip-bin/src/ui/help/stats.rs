@@ -37,6 +37,31 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         ui.separator();
         ui.add_space(6.0);
 
+        let cache = GLOBALS.fetcher.cache_stats();
+        ui.label(format!(
+            "Cache: {} files, {}{}",
+            cache.files,
+            format_size(cache.bytes, DECIMAL),
+            if cache.quota_bytes > 0 {
+                format!(" of {}", format_size(cache.quota_bytes, DECIMAL))
+            } else {
+                "".to_owned()
+            }
+        ));
+        ui.label(format!(
+            "Cache hits: {}, misses: {}",
+            cache.hits, cache.misses
+        ));
+        ui.label(format!(
+            "Cache evictions: {} files, {}",
+            cache.evicted_files,
+            format_size(cache.evicted_bytes, DECIMAL)
+        ));
+
+        ui.add_space(6.0);
+        ui.separator();
+        ui.add_space(6.0);
+
         ui.label(format!(
             "Number of known relays: {}",
             match GLOBALS.storage.filter_relays(|_| true) {
@@ -82,6 +107,12 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         ));
         ui.add_space(6.0);
 
+        ui.label(format!(
+            "Event Index (Search): {} records",
+            GLOBALS.storage.get_event_search_index_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
         ui.label(format!(
             "Event Seen on Relay: {} records",
             GLOBALS.storage.get_event_seen_on_relay_len().unwrap_or(0)
@@ -106,6 +137,12 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         ));
         ui.add_space(6.0);
 
+        ui.label(format!(
+            "Relay Metrics: {} records",
+            GLOBALS.storage.get_relay_metrics_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
         ui.label(format!(
             "People: {} records",
             PersonTable::num_records().unwrap_or(0)
@@ -141,5 +178,65 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
             GLOBALS.storage.get_nip46servers_len().unwrap_or(0)
         ));
         ui.add_space(6.0);
+
+        ui.label(format!(
+            "NWC Connections: {} records",
+            GLOBALS.storage.get_nwc_connections_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
+        ui.label(format!(
+            "Outbox: {} records",
+            GLOBALS.storage.get_outbox_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
+        ui.label(format!(
+            "Offline Queue: {} records",
+            GLOBALS.storage.get_offline_queue_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
+        ui.label(format!(
+            "Scheduled Posts: {} records",
+            GLOBALS.storage.get_scheduled_posts_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
+        ui.label(format!(
+            "Unverified Zaps: {} records",
+            GLOBALS.storage.get_unverified_zaps_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
+        ui.label(format!(
+            "Zap Providers: {} records",
+            GLOBALS.storage.get_zap_providers_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
+        ui.label(format!(
+            "Drafts: {} records",
+            GLOBALS.storage.get_drafts_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
+        ui.label(format!(
+            "Counts: {} records",
+            GLOBALS.storage.get_counts_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
+        ui.label(format!(
+            "Article Revisions: {} records",
+            GLOBALS.storage.get_revisions_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
+        ui.label(format!(
+            "Mutes: {} records",
+            GLOBALS.storage.get_mutes_len().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
     });
 }
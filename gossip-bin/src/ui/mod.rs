@@ -33,6 +33,7 @@ macro_rules! write_setting {
     };
 }
 
+mod article;
 mod assets;
 mod components;
 mod dm_chat_list;
@@ -67,12 +68,12 @@ use egui_winit::egui::Response;
 use egui_winit::egui::ViewportBuilder;
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::{
-    DmChannel, DmChannelData, Error, FeedKind, Person, PersonList, Private, RunState, ZapState,
-    GLOBALS,
+    DmChannel, DmChannelData, Draft, DraftContext, Error, FeedKind, MediaDescriptor, Person,
+    PersonList, Private, RetentionRule, RunState, UploadedMedia, ZapState, GLOBALS,
 };
 use nostr_types::ContentSegment;
 use nostr_types::RelayUrl;
-use nostr_types::{Id, Metadata, MilliSatoshi, Profile, PublicKey, UncheckedUrl, Url};
+use nostr_types::{Id, Metadata, MilliSatoshi, NAddr, Profile, PublicKey, UncheckedUrl, Url};
 use widgets::ModalEntry;
 
 use std::collections::{HashMap, HashSet};
@@ -157,10 +158,16 @@ enum Page {
     YourMetadata,
     YourDelegation,
     YourNostrConnect,
+    YourWallet,
+    YourOutbox,
+    YourDrafts,
+    YourArticles,
+    Article(NAddr),
     RelaysActivityMonitor,
     RelaysCoverage,
     RelaysMine,
     RelaysKnownNetwork(Option<RelayUrl>),
+    RelaysHealth,
     Search,
     Settings,
     HelpHelp,
@@ -205,10 +212,26 @@ impl Page {
             Page::YourMetadata => (SubMenu::Account.as_str(), "Profile".into()),
             Page::YourDelegation => (SubMenu::Account.as_str(), "Delegation".into()),
             Page::YourNostrConnect => (SubMenu::Account.as_str(), "Nostr Connect".into()),
+            Page::YourWallet => (SubMenu::Account.as_str(), "Wallet".into()),
+            Page::YourOutbox => (SubMenu::Account.as_str(), "Outbox".into()),
+            Page::YourDrafts => (SubMenu::Account.as_str(), "Drafts".into()),
+            Page::YourArticles => (SubMenu::Account.as_str(), "Articles".into()),
+            Page::Article(naddr) => {
+                let title = GLOBALS
+                    .storage
+                    .get_replaceable_event(naddr.kind, naddr.author, &naddr.d)
+                    .ok()
+                    .flatten()
+                    .and_then(|event| gossip_lib::Article::from_event(&event))
+                    .map(|article| article.title)
+                    .unwrap_or_default();
+                ("Article", title)
+            }
             Page::RelaysActivityMonitor => (SubMenu::Relays.as_str(), "Active Relays".into()),
             Page::RelaysCoverage => (SubMenu::Relays.as_str(), "Coverage Report".into()),
             Page::RelaysMine => (SubMenu::Relays.as_str(), "My Relays".into()),
             Page::RelaysKnownNetwork(_) => (SubMenu::Relays.as_str(), "Known Network".into()),
+            Page::RelaysHealth => (SubMenu::Relays.as_str(), "Relay Health".into()),
             Page::Search => ("Search", "Search".into()),
             Page::Settings => ("Settings", "Settings".into()),
             Page::HelpHelp => (SubMenu::Help.as_str(), "Troubleshooting".into()),
@@ -244,9 +267,14 @@ impl Page {
             Page::Feed(_) => name_cat(self),
             Page::PeopleLists | Page::PeopleList(_) => cat_name(self),
             Page::Person(_) => name_cat(self),
-            Page::YourKeys | Page::YourMetadata | Page::YourDelegation | Page::YourNostrConnect => {
-                cat_name(self)
-            }
+            Page::YourKeys
+            | Page::YourMetadata
+            | Page::YourDelegation
+            | Page::YourNostrConnect
+            | Page::YourWallet
+            | Page::YourOutbox
+            | Page::YourDrafts
+            | Page::YourArticles => cat_name(self),
             Page::Wizard(_) => name_cat(self),
             _ => name(self),
         }
@@ -339,6 +367,15 @@ pub struct DraftData {
 
     // If this is an annotation
     pub is_annotate: bool,
+
+    // If this is to be published later, and when (local time, as typed)
+    pub include_schedule: bool,
+    pub schedule_at: String,
+
+    // Media attached to this draft: the file to upload next, and what was uploaded
+    pub include_media: bool,
+    pub media_path: String,
+    pub uploads: Vec<UploadedMedia>,
 }
 
 impl Default for DraftData {
@@ -365,6 +402,13 @@ impl Default for DraftData {
             tagging_search_results: Vec::new(),
 
             is_annotate: false,
+
+            include_schedule: false,
+            schedule_at: "".to_owned(),
+
+            include_media: false,
+            media_path: "".to_owned(),
+            uploads: Vec::new(),
         }
     }
 }
@@ -388,9 +432,27 @@ impl DraftData {
         self.tagging_search_searched = None;
         self.tagging_search_results.clear();
         self.is_annotate = false;
+        self.include_schedule = false;
+        self.schedule_at = "".to_owned();
+        self.include_media = false;
+        self.media_path = "".to_owned();
+        self.uploads.clear();
     }
 }
 
+/// Keeps a DraftData in step with the draft saved in storage
+#[derive(Default)]
+pub struct DraftSync {
+    // What the draft in memory is for
+    pub context: Option<DraftContext>,
+
+    // What we last saved (or loaded) for that context
+    pub saved: Option<Draft>,
+
+    // When we last saved
+    pub saved_at: Option<Instant>,
+}
+
 struct GossipUi {
     #[cfg(feature = "video-ffmpeg")]
     audio_device: Option<AudioDevice>,
@@ -463,6 +525,10 @@ struct GossipUi {
     theme: Theme,
     avatars: HashMap<PublicKey, TextureHandle>,
     images: HashMap<Url, TextureHandle>,
+    /// frames of animated images, with how long to show each
+    animations: HashMap<Url, Vec<(TextureHandle, Duration)>>,
+    /// blurhash placeholders for images that are loading, by blurhash
+    placeholders: HashMap<String, Option<TextureHandle>>,
     /// used when settings.show_media=false to explicitly show
     media_show_list: HashSet<Url>,
     /// used when settings.show_media=true to explicitly hide
@@ -476,6 +542,8 @@ struct GossipUi {
     unlock_needs_focus: bool,
     draft_data: DraftData,
     dm_draft_data: DraftData,
+    draft_sync: DraftSync,
+    dm_draft_sync: DraftSync,
 
     // User entry: metadata
     editing_metadata: bool,
@@ -507,6 +575,13 @@ struct GossipUi {
     nostr_connect_name: String,
     nostr_connect_relay1: String,
     nostr_connect_relay2: String,
+    wallet_connect_name: String,
+    wallet_connect_string: String,
+    retention_new_rule: RetentionRule,
+    retention_new_kinds: String,
+    article_editor: Option<article::ArticleEditor>,
+    article_revision: Option<Id>,
+    deleting_article: Option<String>,
 
     // Collapsed threads
     collapsed: Vec<Id>,
@@ -718,6 +793,8 @@ impl GossipUi {
             theme,
             avatars: HashMap::new(),
             images: HashMap::new(),
+            animations: HashMap::new(),
+            placeholders: HashMap::new(),
             media_show_list: HashSet::new(),
             media_hide_list: HashSet::new(),
             media_full_width_list: HashSet::new(),
@@ -726,6 +803,8 @@ impl GossipUi {
             unlock_needs_focus: true,
             draft_data: DraftData::default(),
             dm_draft_data: DraftData::default(),
+            draft_sync: DraftSync::default(),
+            dm_draft_sync: DraftSync::default(),
             editing_metadata: false,
             metadata: Metadata::new(),
             delegatee_tag_str: "".to_owned(),
@@ -751,6 +830,13 @@ impl GossipUi {
             nostr_connect_name: "".to_owned(),
             nostr_connect_relay1: "".to_owned(),
             nostr_connect_relay2: "".to_owned(),
+            wallet_connect_name: "".to_owned(),
+            wallet_connect_string: "".to_owned(),
+            retention_new_rule: RetentionRule::new(""),
+            retention_new_kinds: "".to_owned(),
+            article_editor: None,
+            article_revision: None,
+            deleting_article: None,
             collapsed: vec![],
             opened: HashSet::new(),
             visible_note_ids: vec![],
@@ -870,10 +956,24 @@ impl GossipUi {
                     .to_overlord
                     .send(ToOverlordMessage::UpdateMetadata(*pubkey));
             }
-            Page::YourKeys | Page::YourMetadata | Page::YourDelegation | Page::YourNostrConnect => {
+            Page::YourKeys
+            | Page::YourMetadata
+            | Page::YourDelegation
+            | Page::YourNostrConnect
+            | Page::YourWallet
+            | Page::YourOutbox
+            | Page::YourDrafts
+            | Page::YourArticles => {
                 self.open_menu(ctx, SubMenu::Account);
             }
-            Page::RelaysActivityMonitor | Page::RelaysCoverage | Page::RelaysMine => {
+            Page::Article(naddr) => {
+                article::enter_page(self, naddr);
+                self.close_all_menus_except_feeds(ctx);
+            }
+            Page::RelaysActivityMonitor
+            | Page::RelaysCoverage
+            | Page::RelaysMine
+            | Page::RelaysHealth => {
                 self.relays.enter_page(None);
                 self.open_menu(ctx, SubMenu::Relays);
             }
@@ -1139,6 +1239,7 @@ impl GossipUi {
             self.add_menu_item_page(ui, Page::RelaysActivityMonitor, None, true);
             self.add_menu_item_page(ui, Page::RelaysMine, None, true);
             self.add_menu_item_page(ui, Page::RelaysKnownNetwork(None), None, true);
+            self.add_menu_item_page(ui, Page::RelaysHealth, None, true);
             ui.vertical(|ui| {
                 ui.spacing_mut().button_padding *= 2.0;
                 ui.visuals_mut().widgets.inactive.weak_bg_fill =
@@ -1165,6 +1266,10 @@ impl GossipUi {
             self.add_menu_item_page(ui, Page::YourKeys, None, true);
             self.add_menu_item_page(ui, Page::YourDelegation, None, true);
             self.add_menu_item_page(ui, Page::YourNostrConnect, None, true);
+            self.add_menu_item_page(ui, Page::YourWallet, None, true);
+            self.add_menu_item_page(ui, Page::YourOutbox, None, true);
+            self.add_menu_item_page(ui, Page::YourDrafts, None, true);
+            self.add_menu_item_page(ui, Page::YourArticles, None, true);
         });
         self.after_openable_menu(ui, &cstate);
     }
@@ -1621,6 +1726,9 @@ impl eframe::App for GossipUi {
             ZapState::SeekingAmount(id, _, _, _) => Some(id),
             ZapState::LoadingInvoice(id, _) => Some(id),
             ZapState::ReadyToPay(id, _) => Some(id),
+            ZapState::PayingInvoice(id, _) => Some(id),
+            ZapState::Paid(id) => Some(id),
+            ZapState::PaymentFailed(id, _) => Some(id),
         };
 
         egui::CentralPanel::default()
@@ -1645,11 +1753,17 @@ impl eframe::App for GossipUi {
                     Page::YourKeys
                     | Page::YourMetadata
                     | Page::YourDelegation
-                    | Page::YourNostrConnect => you::update(self, ctx, frame, ui),
+                    | Page::YourNostrConnect
+                    | Page::YourWallet
+                    | Page::YourOutbox
+                    | Page::YourDrafts
+                    | Page::YourArticles => you::update(self, ctx, frame, ui),
+                    Page::Article(_) => article::update(self, ctx, frame, ui),
                     Page::RelaysActivityMonitor
                     | Page::RelaysCoverage
                     | Page::RelaysMine
-                    | Page::RelaysKnownNetwork(_) => relays::update(self, ctx, frame, ui),
+                    | Page::RelaysKnownNetwork(_)
+                    | Page::RelaysHealth => relays::update(self, ctx, frame, ui),
                     Page::Search => search::update(self, ctx, frame, ui),
                     Page::Settings => settings::update(self, ctx, frame, ui),
                     Page::HelpHelp | Page::HelpStats | Page::HelpAbout => {
@@ -1877,21 +1991,69 @@ impl GossipUi {
         if let Some(th) = self.images.get(&url) {
             return Some(th.to_owned());
         }
+        if let Some(frames) = self.animations.get(&url) {
+            return Some(current_frame(ctx, frames));
+        }
+
+        if let Some(frames) = GLOBALS.media.get_frames(&url, volatile) {
+            let mut textures: Vec<(TextureHandle, Duration)> = frames
+                .into_iter()
+                .enumerate()
+                .map(|(i, frame)| {
+                    let current_size =
+                        [frame.image.width() as usize, frame.image.height() as usize];
+                    let pixels = frame.image.as_flat_samples();
+                    let color_image =
+                        ColorImage::from_rgba_unmultiplied(current_size, pixels.as_slice());
+                    let name = if i == 0 {
+                        url.as_str().to_owned()
+                    } else {
+                        format!("{}#{}", url.as_str(), i)
+                    };
+                    let texture_handle =
+                        ctx.load_texture(name, color_image, TextureOptions::default());
+                    (texture_handle, frame.delay)
+                })
+                .collect();
 
-        if let Some(rgba_image) = GLOBALS.media.get_image(&url, volatile) {
+            if textures.len() == 1 {
+                let (texture_handle, _) = textures.remove(0);
+                self.images.insert(url, texture_handle.clone());
+                Some(texture_handle)
+            } else {
+                let texture_handle = current_frame(ctx, &textures);
+                self.animations.insert(url, textures);
+                Some(texture_handle)
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Get the blurhash placeholder for media described by an imeta tag
+    pub fn try_get_placeholder(
+        &mut self,
+        ctx: &Context,
+        descriptor: &MediaDescriptor,
+    ) -> Option<TextureHandle> {
+        let blurhash = descriptor.blurhash.as_ref()?;
+        if let Some(th) = self.placeholders.get(blurhash) {
+            return th.to_owned();
+        }
+
+        let texture_handle = descriptor.placeholder().map(|rgba_image| {
             let current_size = [rgba_image.width() as usize, rgba_image.height() as usize];
             let pixels = rgba_image.as_flat_samples();
             let color_image = ColorImage::from_rgba_unmultiplied(current_size, pixels.as_slice());
-            let texture_handle = ctx.load_texture(
-                url.as_str().to_owned(),
+            ctx.load_texture(
+                format!("blurhash:{}", blurhash),
                 color_image,
-                TextureOptions::default(),
-            );
-            self.images.insert(url, texture_handle.clone());
-            Some(texture_handle)
-        } else {
-            None
-        }
+                TextureOptions::LINEAR,
+            )
+        });
+        self.placeholders
+            .insert(blurhash.to_owned(), texture_handle.clone());
+        texture_handle
     }
 
     #[cfg(feature = "video-ffmpeg")]
@@ -2207,6 +2369,21 @@ impl GossipUi {
                 // we have to copy it and get out of the borrow first
                 qr_string = Some(invoice.to_owned());
             }
+            ZapState::PayingInvoice(_id, _) => {
+                ui.label("Paying with your wallet...");
+            }
+            ZapState::Paid(_id) => {
+                ui.label("Zap paid.");
+                if ui.button("Close").clicked() {
+                    *GLOBALS.current_zap.write() = ZapState::None;
+                }
+            }
+            ZapState::PaymentFailed(_id, ref reason) => {
+                ui.label(format!("Your wallet could not pay the zap: {}", reason));
+                if ui.button("Close").clicked() {
+                    *GLOBALS.current_zap.write() = ZapState::None;
+                }
+            }
         };
 
         if let Some(qr) = qr_string {
@@ -2220,8 +2397,10 @@ impl GossipUi {
 
     fn reset_draft(&mut self) {
         if let Page::Feed(FeedKind::DmChat(_)) = &self.page {
+            feed::post::discard_saved_draft(&mut self.dm_draft_sync);
             self.dm_draft_data.clear();
         } else {
+            feed::post::discard_saved_draft(&mut self.draft_sync);
             self.draft_data.clear();
             self.show_post_area = false;
             self.draft_needs_focus = false;
@@ -2450,3 +2629,28 @@ fn wait_for_data_migration(app: &mut GossipUi, ctx: &Context) {
             });
         });
 }
+
+/// The frame of an animation to show now. Asks for a repaint when the next
+/// frame is due, but no sooner than max_fps allows.
+fn current_frame(ctx: &Context, frames: &[(TextureHandle, Duration)]) -> TextureHandle {
+    if !read_setting!(animate_media) {
+        return frames[0].0.clone();
+    }
+
+    let total: Duration = frames.iter().map(|(_, delay)| *delay).sum();
+    if total.is_zero() {
+        return frames[0].0.clone();
+    }
+
+    let elapsed = Duration::from_secs_f64(ctx.input(|i| i.time));
+    let mut position = Duration::from_nanos((elapsed.as_nanos() % total.as_nanos()) as u64);
+    let min_wait = Duration::from_secs_f32(1.0 / read_setting!(max_fps).max(1) as f32);
+    for (texture_handle, delay) in frames {
+        if position < *delay {
+            ctx.request_repaint_after((*delay - position).max(min_wait));
+            return texture_handle.clone();
+        }
+        position -= *delay;
+    }
+    frames[0].0.clone()
+}
@@ -11,10 +11,10 @@ use egui_winit::egui::text_edit::TextEditOutput;
 use egui_winit::egui::vec2;
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::{
-    FeedKind, Freshness, People, Person, PersonList, PersonListMetadata, PersonTable, Private,
-    Table, GLOBALS,
+    FeedKind, Freshness, Mute, MuteEntry, People, Person, PersonList, PersonListMetadata,
+    PersonTable, Private, Table, GLOBALS,
 };
-use nostr_types::{Profile, PublicKey, Unixtime};
+use nostr_types::{IdHex, Profile, PublicKey, Unixtime};
 
 pub(in crate::ui) struct ListUi {
     // cache
@@ -25,6 +25,11 @@ pub(in crate::ui) struct ListUi {
     cache_remote_tag: String,
     cache_local_hash: u64,
     cache_local_tag: String,
+    cache_mutes: Vec<MuteEntry>,
+
+    // mute words and hashtags
+    mute_text: String,
+    mute_private: bool,
 
     // add contact
     add_contact_search: String,
@@ -48,6 +53,11 @@ impl ListUi {
             cache_remote_tag: String::new(),
             cache_local_hash: 2,
             cache_local_tag: String::new(),
+            cache_mutes: Vec::new(),
+
+            // mute words and hashtags
+            mute_text: String::new(),
+            mute_private: false,
 
             // add contact
             add_contact_search: String::new(),
@@ -215,6 +225,11 @@ pub(super) fn update(
     ui.add_space(10.0);
 
     app.vert_scroll_area().show(ui, |ui| {
+        if list == PersonList::Muted {
+            render_mutes(ui, app, &metadata);
+            ui.add_space(10.0);
+        }
+
         // not nice but needed because of 'app' borrow in closure
         let mut people = app.people_list.cache_people.clone();
         for (person, private) in people.iter_mut() {
@@ -935,6 +950,96 @@ fn render_clear_list_confirm_popup(ui: &mut Ui, app: &mut GossipUi, list: Person
     }
 }
 
+fn render_mutes(ui: &mut Ui, app: &mut GossipUi, metadata: &PersonListMetadata) {
+    ui.heading("Muted words, hashtags and threads");
+    ui.add_space(5.0);
+
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut app.people_list.mute_text)
+                .hint_text("word, or #hashtag")
+                .desired_width(200.0),
+        );
+        let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+        if !*metadata.private {
+            ui.add(Label::new("Private").selectable(false));
+            ui.add(widgets::Switch::small(
+                &app.theme,
+                &mut app.people_list.mute_private,
+            ));
+        }
+
+        if ui.button("Mute").clicked() || entered {
+            let text = app.people_list.mute_text.trim();
+            let mute = if text.starts_with('#') {
+                Mute::hashtag(text)
+            } else {
+                Mute::word(text)
+            };
+            if let Some(mute) = mute {
+                let private = Private(app.people_list.mute_private || *metadata.private);
+                if let Err(e) = gossip_lib::mutes::mute(mute, private) {
+                    GLOBALS.status_queue.write().write(format!("{}", e));
+                }
+                app.people_list.mute_text.clear();
+                mark_refresh(app);
+            }
+        }
+    });
+
+    ui.add_space(5.0);
+
+    let mut mutes = app.people_list.cache_mutes.clone();
+    for entry in mutes.iter_mut() {
+        ui.horizontal(|ui| {
+            match &entry.mute {
+                Mute::Word(word) => {
+                    ui.label(format!("\"{}\"", word));
+                }
+                Mute::Hashtag(hashtag) => {
+                    ui.label(format!("#{}", hashtag));
+                }
+                Mute::Thread(id) => {
+                    let idhex: IdHex = (*id).into();
+                    let name = format!("thread {}", gossip_lib::names::hex_id_short(&idhex));
+                    if ui.link(name).clicked() {
+                        app.set_page(
+                            ui.ctx(),
+                            Page::Feed(FeedKind::Thread {
+                                id: *id,
+                                referenced_by: *id,
+                                author: None,
+                            }),
+                        );
+                    }
+                }
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("Remove").clicked() {
+                    let _ = gossip_lib::mutes::unmute(&entry.mute);
+                    mark_refresh(app);
+                }
+                if !*metadata.private {
+                    ui.add(Label::new("Private").selectable(false));
+                    if ui
+                        .add(widgets::Switch::small(&app.theme, &mut entry.private))
+                        .clicked()
+                    {
+                        let _ = gossip_lib::mutes::mute(entry.mute.clone(), Private(entry.private));
+                        mark_refresh(app);
+                    }
+                }
+            });
+        });
+    }
+
+    if mutes.is_empty() {
+        ui.label("Nothing muted");
+    }
+}
+
 fn mark_refresh(app: &mut GossipUi) {
     app.people_list.cache_next_refresh = Instant::now();
 }
@@ -962,6 +1067,14 @@ fn refresh_list_data(app: &mut GossipUi, list: PersonList) {
         people
     };
 
+    app.people_list.cache_mutes = if list == PersonList::Muted {
+        let mut mutes = GLOBALS.storage.read_all_mutes().unwrap_or_default();
+        mutes.sort_by_key(|entry| entry.mute.key());
+        mutes
+    } else {
+        Vec::new()
+    };
+
     let metadata = GLOBALS
         .storage
         .get_person_list_metadata(list)
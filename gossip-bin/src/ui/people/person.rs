@@ -12,8 +12,8 @@ use egui_winit::egui::Response;
 use egui_winit::egui::Widget;
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::{
-    DmChannel, FeedKind, Freshness, People, Person, PersonList, PersonTable, Private, Table,
-    GLOBALS,
+    CountKey, DmChannel, FeedKind, Freshness, People, Person, PersonList, PersonTable, Private,
+    Table, GLOBALS,
 };
 use nostr_types::PublicKey;
 use serde_json::Value;
@@ -110,6 +110,19 @@ fn content(app: &mut GossipUi, ctx: &Context, ui: &mut Ui, pubkey: PublicKey, pe
                 );
             });
 
+            // Counted by relays (NIP-45), so we need not load every contact list
+            if let Some(followers) = GLOBALS.counts.get(CountKey::Followers(pubkey)) {
+                ui.with_layout(layout, |ui| {
+                    profile_item(
+                        ui,
+                        app,
+                        lwidth,
+                        "followers (as counted by relays)",
+                        widgets::relay_count_text(&followers),
+                    );
+                });
+            }
+
             if !is_self {
                 // Petname and petname editing
                 make_frame().show(ui, |ui| {
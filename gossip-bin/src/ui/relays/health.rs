@@ -0,0 +1,180 @@
+use super::{widgets, GossipUi, Page};
+use eframe::egui;
+use egui::{Context, Ui};
+use egui_winit::egui::{vec2, Color32, Pos2, RichText, Sense, Stroke};
+use gossip_lib::{Relay, RelayMetrics, GLOBALS, RELAY_HISTORY_LEN};
+
+const CHART_WIDTH: f32 = 240.0;
+const CHART_HEIGHT: f32 = 40.0;
+
+pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    widgets::page_header(ui, Page::RelaysHealth.name(), |ui| {
+        widgets::TextEdit::search(&app.theme, &app.assets, &mut app.relays.search)
+            .desired_width(200.0)
+            .show(ui);
+    });
+
+    ui.label(
+        RichText::new(
+            "How relays have performed for us over their last connections. Faster and more \
+             reliable relays are preferred when choosing where to read from.",
+        )
+        .weak(),
+    );
+    ui.add_space(10.0);
+
+    let search = app.relays.search.to_lowercase();
+    let mut relays: Vec<(Relay, RelayMetrics, f32)> = GLOBALS
+        .storage
+        .filter_relays(|relay| {
+            search.is_empty() || relay.url.as_str().to_lowercase().contains(&search)
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|relay| {
+            let metrics = GLOBALS
+                .storage
+                .read_relay_metrics(&relay.url, None)
+                .ok()
+                .flatten()?;
+            if metrics.history.is_empty() {
+                return None;
+            }
+            let score = relay.score();
+            Some((relay, metrics, score))
+        })
+        .collect();
+    relays.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+
+    if relays.is_empty() {
+        ui.label("No relay measurements yet. They are recorded as relays are used.");
+        return;
+    }
+
+    let accent = app.theme.accent_color();
+    app.vert_scroll_area()
+        .id_source("RelayHealthScroll")
+        .show(ui, |ui| {
+            for (relay, metrics, score) in relays.iter() {
+                relay_health(ui, relay, metrics, *score, accent);
+                ui.add_space(8.0);
+                ui.separator();
+                ui.add_space(8.0);
+            }
+        });
+}
+
+fn relay_health(ui: &mut Ui, relay: &Relay, metrics: &RelayMetrics, score: f32, color: Color32) {
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(relay.url.as_str()).strong());
+        ui.label(RichText::new(format!("score {:.2}", score)).weak());
+    });
+
+    ui.horizontal(|ui| {
+        ui.vertical(|ui| {
+            ui.set_min_width(260.0);
+            ui.label(format!(
+                "Connect: {}",
+                metrics
+                    .connect_ms
+                    .map(|ms| format!("{:.0} ms", ms))
+                    .unwrap_or("?".to_owned())
+            ));
+            ui.label(format!(
+                "First EOSE: {}",
+                metrics
+                    .first_eose_ms
+                    .map(|ms| format!("{:.0} ms", ms))
+                    .unwrap_or("?".to_owned())
+            ));
+            ui.label(format!(
+                "Events per second: {}",
+                metrics
+                    .events_per_sec
+                    .map(|eps| format!("{:.2}", eps))
+                    .unwrap_or("?".to_owned())
+            ));
+            ui.label(format!(
+                "Received: {}",
+                humansize::format_size(metrics.bytes, humansize::DECIMAL)
+            ));
+            ui.label(format!(
+                "AUTH: {} ok, {} refused",
+                metrics.auth_successes, metrics.auth_failures
+            ));
+            ui.label(format!(
+                "Connections: {} ok, {} failed",
+                relay.success_count, relay.failure_count
+            ));
+            if !metrics.closed.is_empty() {
+                ui.label(format!("CLOSED: {}", reasons(&metrics.closed)));
+            }
+            if !metrics.notices.is_empty() {
+                ui.label(format!("NOTICE: {}", reasons(&metrics.notices)));
+            }
+        });
+
+        ui.vertical(|ui| {
+            let connect: Vec<f32> = metrics
+                .history
+                .iter()
+                .map(|s| s.connect_ms as f32)
+                .collect();
+            chart(ui, "connect ms", &connect, color);
+
+            let eose: Vec<f32> = metrics
+                .history
+                .iter()
+                .map(|s| s.first_eose_ms.unwrap_or(0) as f32)
+                .collect();
+            chart(ui, "first EOSE ms", &eose, color);
+
+            let eps: Vec<f32> = metrics.history.iter().map(|s| s.events_per_sec()).collect();
+            chart(ui, "events/sec", &eps, color);
+        });
+    });
+}
+
+// A bar chart of one value across recent connections, oldest on the left
+fn chart(ui: &mut Ui, label: &str, values: &[f32], color: Color32) {
+    let max = values.iter().cloned().fold(0.0, f32::max);
+    ui.label(
+        RichText::new(format!("{} (max {:.1})", label, max))
+            .small()
+            .weak(),
+    );
+
+    let (rect, _) = ui.allocate_exact_size(vec2(CHART_WIDTH, CHART_HEIGHT), Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_stroke(rect, 0.0, Stroke::new(1.0, ui.visuals().weak_text_color()));
+
+    if max <= 0.0 {
+        return;
+    }
+    let bar_width = CHART_WIDTH / RELAY_HISTORY_LEN as f32;
+    let offset = RELAY_HISTORY_LEN.saturating_sub(values.len()) as f32 * bar_width;
+    for (i, value) in values.iter().enumerate() {
+        let height = CHART_HEIGHT * value / max;
+        let x = rect.left() + offset + i as f32 * bar_width;
+        painter.rect_filled(
+            egui::Rect::from_min_max(
+                Pos2::new(x + 1.0, rect.bottom() - height),
+                Pos2::new(x + bar_width - 1.0, rect.bottom()),
+            ),
+            0.0,
+            color,
+        );
+    }
+}
+
+// The most frequent reasons, most frequent first
+fn reasons(counts: &std::collections::BTreeMap<String, u64>) -> String {
+    let mut counts: Vec<(&String, &u64)> = counts.iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(a.1));
+    counts
+        .iter()
+        .take(4)
+        .map(|(reason, count)| format!("{} ×{}", reason, count))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -9,6 +9,7 @@ use nostr_types::RelayUrl;
 
 mod active;
 mod coverage;
+mod health;
 mod known;
 mod mine;
 
@@ -107,6 +108,7 @@ pub(super) enum RelayFilter {
     Private,
     Hidden,
     Global,
+    Search,
     AlwaysAllowConnect,
     NeverAllowConnect,
     AlwaysAllowAuthenticate,
@@ -124,6 +126,7 @@ impl RelayFilter {
             RelayFilter::Private => "Private",
             RelayFilter::Hidden => "Hidden",
             RelayFilter::Global => "Global Feed",
+            RelayFilter::Search => "Search",
             RelayFilter::AlwaysAllowConnect => "Always allow connect",
             RelayFilter::NeverAllowConnect => "Never allow connect",
             RelayFilter::AlwaysAllowAuthenticate => "Always allow auth",
@@ -149,6 +152,7 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, frame: &mut eframe::Fram
         Page::RelaysCoverage => coverage::update(app, ctx, frame, ui),
         Page::RelaysMine => mine::update(app, ctx, frame, ui),
         Page::RelaysKnownNetwork(_) => known::update(app, ctx, frame, ui),
+        Page::RelaysHealth => health::update(app, ctx, frame, ui),
         _ => {}
     }
 }
@@ -584,6 +588,11 @@ pub(super) fn relay_filter_combo(app: &mut GossipUi, ui: &mut Ui) {
                 RelayFilter::Global,
                 RelayFilter::Global.get_name(),
             );
+            ui.selectable_value(
+                &mut app.relays.filter,
+                RelayFilter::Search,
+                RelayFilter::Search.get_name(),
+            );
             ui.selectable_value(
                 &mut app.relays.filter,
                 RelayFilter::AlwaysAllowConnect,
@@ -665,6 +674,7 @@ pub(super) fn filter_relay(rui: &RelayUi, ri: &Relay) -> bool {
         }
         RelayFilter::Hidden => ri.hidden,
         RelayFilter::Global => ri.has_usage_bits(Relay::GLOBAL),
+        RelayFilter::Search => ri.has_usage_bits(Relay::SEARCH),
         RelayFilter::AlwaysAllowConnect => ri.allow_connect == Some(true),
         RelayFilter::NeverAllowConnect => ri.allow_connect == Some(false),
         RelayFilter::AlwaysAllowAuthenticate => ri.allow_auth == Some(true),
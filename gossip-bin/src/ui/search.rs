@@ -4,6 +4,7 @@ use egui::widgets::Button;
 use egui::{Context, Label, RichText, Sense, Ui};
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::{FeedKind, PersonTable, Table, GLOBALS};
+use nostr_types::RelayUrl;
 
 pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut Frame, ui: &mut Ui) {
     ui.add_space(10.0);
@@ -33,6 +34,15 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut Frame, ui:
         }
     });
 
+    ui.label(
+        RichText::new(
+            "Notes must contain every word. Use \"quotes\" for phrases, and narrow with \
+             author:<npub> kind:<number> since:<YYYY-MM-DD> until:<YYYY-MM-DD>. \
+             Relays that support searching are asked too, and their results arrive later.",
+        )
+        .weak(),
+    );
+
     if trigger_search {
         let _ = GLOBALS
             .to_overlord
@@ -45,6 +55,7 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut Frame, ui:
 
     let people = GLOBALS.people_search_results.read().clone();
     let notes = GLOBALS.note_search_results.read().clone();
+    let more_notes = GLOBALS.note_search_next_page.read().is_some();
 
     app.vert_scroll_area().show(ui, |ui| {
         if !people.is_empty() {
@@ -71,6 +82,9 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut Frame, ui:
                             RichText::new(gossip_lib::names::pubkey_short(&person.pubkey)).weak(),
                         );
                         GossipUi::render_person_name_line(app, ui, person, false);
+                        if let Some(relays) = GLOBALS.people_search_relays.get(&person.pubkey) {
+                            found_on(ui, &relays);
+                        }
                     });
                 });
             }
@@ -94,6 +108,10 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut Frame, ui:
                     } else {
                         ui.label(event.pubkey.as_bech32_string());
                     }
+
+                    if let Some(relays) = GLOBALS.note_search_relays.get(&event.id) {
+                        found_on(ui, &relays);
+                    }
                 });
 
                 let mut summary = event
@@ -118,6 +136,16 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut Frame, ui:
                     );
                 }
             }
+
+            if more_notes {
+                ui.add_space(8.0);
+                ui.separator();
+                ui.add_space(8.0);
+
+                if ui.add(Button::new("Load more notes")).clicked() {
+                    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::SearchMoreNotes);
+                }
+            }
         }
 
         if people.is_empty() && notes.is_empty() {
@@ -129,3 +157,13 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut Frame, ui:
         }
     });
 }
+
+// Which relays a relay search result came from
+fn found_on(ui: &mut Ui, relays: &[RelayUrl]) {
+    let text = relays
+        .iter()
+        .map(|url| url.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    ui.label(RichText::new(format!("found on {}", text)).weak());
+}
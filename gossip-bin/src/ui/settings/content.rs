@@ -45,6 +45,19 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         "Enable zap receipts",
     );
 
+    ui.horizontal(|ui| {
+        ui.label("One-click zap amount: ").on_hover_text(
+            "Zap this many sats without asking for an amount. Set to 0 to always ask.",
+        );
+        ui.add(Slider::new(&mut app.unsaved_settings.zap_default_sats, 0..=10000).text("sats"));
+    });
+
+    ui.checkbox(
+        &mut app.unsaved_settings.fetch_counts,
+        "Ask relays to count replies, reactions and followers",
+    )
+    .on_hover_text("Only relays that support counting (NIP-45) and that gossip is already connected to are asked.");
+
     ui.checkbox(&mut app.unsaved_settings.reposts, "Enable reposts (show)");
 
     ui.checkbox(
@@ -80,6 +93,8 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
     });
 
     ui.checkbox(&mut app.unsaved_settings.show_media, "Render all media inline automatically").on_hover_text("If off, you have to click to (potentially fetch and) render media inline. If on, all media referenced by posts in your feed will be (potentially fetched and) rendered. However, if Fetch Media is disabled, only cached media can be shown as media will not be fetched.");
+    ui.checkbox(&mut app.unsaved_settings.animate_media, "Animate GIF and WebP images")
+        .on_hover_text("If off, only the first frame of an animated image is shown. Takes effect for images loaded after saving.");
     ui.checkbox(&mut app.unsaved_settings.approve_content_warning, "Approve all content-warning tagged media automatically")
         .on_hover_text("If off, you have to click to show content-warning tagged media. If on, all content-warning tagged media in your feed will be rendered.");
 
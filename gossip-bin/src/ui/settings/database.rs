@@ -1,10 +1,11 @@
 use crate::ui::GossipUi;
 use crate::unsaved_settings::UnsavedSettings;
 use eframe::egui;
-use egui::widgets::Slider;
-use egui::{Context, Ui};
+use egui::widgets::{DragValue, Slider};
+use egui::{Context, RichText, Ui};
 use gossip_lib::comms::ToOverlordMessage;
-use gossip_lib::GLOBALS;
+use gossip_lib::{AuthorClass, RetentionPolicy, RetentionRule, GLOBALS};
+use humansize::{format_size, DECIMAL};
 
 pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
     ui.heading("Storage Settings");
@@ -12,8 +13,9 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
     ui.add_space(20.0);
 
     ui.horizontal(|ui| {
-        ui.label("How long to keep events")
-            .on_hover_text("Events older than this will be deleted");
+        ui.label("How long to keep events").on_hover_text(
+            "Events older than this will be deleted, unless a retention rule below says otherwise",
+        );
         ui.add(Slider::new(&mut app.unsaved_settings.prune_period_days, 7..=720).text("days"));
     });
 
@@ -25,14 +27,47 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         );
     });
 
+    ui.horizontal(|ui| {
+        ui.label("Maximum size of downloaded files")
+            .on_hover_text("When the cache grows past this, the least recently used files are deleted. Avatars of people you follow and media in your bookmarks are kept. Set to 0 for no limit.");
+        ui.add(Slider::new(&mut app.unsaved_settings.cache_quota_mb, 0..=20_000).text("MB"));
+    });
+
+    ui.add_space(20.0);
+    ui.heading("Retention Rules");
+    ui.add_space(10.0);
+    ui.label("When deleting old events, the first rule that matches an event decides how long it is kept. Events no rule matches are kept for the period above.");
+    ui.add_space(10.0);
+
+    retention_rules(app, ui);
+
+    ui.add_space(10.0);
+    new_retention_rule(app, ui);
+
+    ui.add_space(10.0);
+    if ui.button("Reset rules to defaults").clicked() {
+        app.unsaved_settings.retention_policy = RetentionPolicy::default();
+    }
+
     // Only let them prune after they have saved
     let stored_settings = UnsavedSettings::load();
     if stored_settings == app.unsaved_settings {
         ui.add_space(20.0);
-        if ui.button("Delete Old Events Now").on_hover_text("This will delete events older than the period specified above. but the LMDB files will continue consuming disk space. To compact them, copy withem with `mdb_copy -c` when gossip is not running.").clicked() {
+        if ui
+            .button("Preview Deleting Old Events")
+            .on_hover_text("Work out what would be deleted, without deleting anything.")
+            .clicked()
+        {
+            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::PreviewPrune);
+        }
+
+        ui.add_space(20.0);
+        if ui.button("Delete Old Events Now").on_hover_text("This will delete events according to the rules above. but the LMDB files will continue consuming disk space. To compact them, copy withem with `mdb_copy -c` when gossip is not running.").clicked() {
             let _ = GLOBALS.to_overlord.send(ToOverlordMessage::PruneDatabase);
         }
 
+        prune_report(ui);
+
         ui.add_space(20.0);
         if ui.button("Delete Old Downloaded Files").on_hover_text("This will delete cache files with modification times older than the period specified above (unfortunately access times are often unavailable and/or unreliable). Note that this will eventually delete everybody's avatar, even if those are in heavy use.").clicked() {
             let _ = GLOBALS.to_overlord.send(ToOverlordMessage::PruneCache);
@@ -41,3 +76,217 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
 
     ui.add_space(20.0);
 }
+
+fn retention_rules(app: &mut GossipUi, ui: &mut Ui) {
+    let rules = &mut app.unsaved_settings.retention_policy.rules;
+    let mut move_up: Option<usize> = None;
+    let mut remove: Option<usize> = None;
+    let count = rules.len();
+
+    for (index, rule) in rules.iter_mut().enumerate() {
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(&rule.name).strong());
+                ui.label(format!("({})", rule.describe_matches()));
+            });
+            ui.horizontal(|ui| {
+                optional_limit(ui, &mut rule.max_age_days, "Keep for", "days", 30);
+                ui.add_space(10.0);
+                optional_limit(ui, &mut rule.max_events, "Keep at most", "events", 10_000);
+                ui.add_space(10.0);
+                optional_limit(
+                    ui,
+                    &mut rule.max_bytes,
+                    "Keep at most",
+                    "bytes",
+                    100_000_000,
+                );
+            });
+            ui.horizontal(|ui| {
+                if index > 0 && ui.button("Move up").clicked() {
+                    move_up = Some(index);
+                }
+                if index + 1 < count && ui.button("Move down").clicked() {
+                    move_up = Some(index + 1);
+                }
+                if ui.button("Remove").clicked() {
+                    remove = Some(index);
+                }
+            });
+        });
+    }
+
+    if let Some(index) = move_up {
+        rules.swap(index - 1, index);
+    }
+    if let Some(index) = remove {
+        rules.remove(index);
+    }
+}
+
+// A limit that may be turned off
+fn optional_limit(ui: &mut Ui, limit: &mut Option<u64>, label: &str, unit: &str, default: u64) {
+    let mut limited = limit.is_some();
+    if ui.checkbox(&mut limited, label).changed() {
+        *limit = if limited { Some(default) } else { None };
+    }
+    match limit {
+        Some(value) => {
+            ui.add(DragValue::new(value).suffix(format!(" {}", unit)));
+            *value = (*value).max(1);
+        }
+        None => {
+            ui.label(if unit == "days" {
+                "forever"
+            } else {
+                "any number"
+            });
+        }
+    }
+}
+
+fn new_retention_rule(app: &mut GossipUi, ui: &mut Ui) {
+    ui.label(RichText::new("Add a rule").strong());
+
+    ui.horizontal(|ui| {
+        ui.label("Name:");
+        ui.add(text_edit_line!(app, app.retention_new_rule.name).desired_width(200.0));
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Events by");
+        let rule = &mut app.retention_new_rule;
+        let selected = match rule.authors {
+            Some(authors) => authors.to_string(),
+            None => "anyone".to_owned(),
+        };
+        egui::ComboBox::from_id_source("RetentionAuthorsCombo")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut rule.authors, None, "anyone");
+                for authors in [
+                    AuthorClass::Myself,
+                    AuthorClass::Followed,
+                    AuthorClass::ListMember,
+                    AuthorClass::Stranger,
+                ] {
+                    ui.selectable_value(&mut rule.authors, Some(authors), authors.to_string());
+                }
+            });
+
+        ui.label("of kinds");
+        ui.add(
+            text_edit_line!(app, app.retention_new_kinds)
+                .hint_text("any (or e.g. 1, 7)")
+                .desired_width(120.0),
+        );
+    });
+
+    ui.horizontal(|ui| {
+        let rule = &mut app.retention_new_rule;
+        tristate_combo(
+            ui,
+            "RetentionBookmarkedCombo",
+            &mut rule.bookmarked,
+            [
+                "bookmarked or not",
+                "bookmarked or pinned",
+                "not bookmarked or pinned",
+            ],
+        );
+        tristate_combo(
+            ui,
+            "RetentionRelatedCombo",
+            &mut rule.related_to_ours,
+            [
+                "related to mine or not",
+                "related to my events",
+                "unrelated to my events",
+            ],
+        );
+    });
+
+    let kinds: Result<Vec<u32>, _> = app
+        .retention_new_kinds
+        .split(',')
+        .map(|k| k.trim())
+        .filter(|k| !k.is_empty())
+        .map(|k| k.parse::<u32>())
+        .collect();
+
+    ui.horizontal(|ui| match kinds {
+        Ok(kinds) => {
+            if !app.retention_new_rule.name.trim().is_empty() && ui.button("Add").clicked() {
+                let mut rule =
+                    std::mem::replace(&mut app.retention_new_rule, RetentionRule::new(""));
+                rule.name = rule.name.trim().to_owned();
+                rule.kinds = kinds;
+                app.unsaved_settings.retention_policy.rules.push(rule);
+                app.retention_new_kinds = "".to_owned();
+            }
+        }
+        Err(_) => {
+            ui.label("Kinds must be numbers separated by commas");
+        }
+    });
+}
+
+// A choice of either, true or false
+fn tristate_combo(ui: &mut Ui, id: &str, value: &mut Option<bool>, labels: [&str; 3]) {
+    let selected = match value {
+        None => labels[0],
+        Some(true) => labels[1],
+        Some(false) => labels[2],
+    };
+    egui::ComboBox::from_id_source(id)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(value, None, labels[0]);
+            ui.selectable_value(value, Some(true), labels[1]);
+            ui.selectable_value(value, Some(false), labels[2]);
+        });
+}
+
+fn prune_report(ui: &mut Ui) {
+    let report = match &*GLOBALS.prune_report.read() {
+        Some(report) => report.clone(),
+        None => return,
+    };
+
+    ui.add_space(10.0);
+    ui.label(
+        RichText::new(if report.dry_run {
+            "What deleting old events would do:"
+        } else {
+            "What deleting old events did:"
+        })
+        .strong(),
+    );
+    ui.label(format!(
+        "{} of {} events, {}",
+        report.deleted,
+        report.examined,
+        format_size(report.deleted_bytes, DECIMAL)
+    ));
+
+    egui::Grid::new("prune_report")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Rule");
+            ui.label("Matched");
+            ui.label(if report.dry_run {
+                "To delete"
+            } else {
+                "Deleted"
+            });
+            ui.label("Size");
+            ui.end_row();
+            for rule in &report.by_rule {
+                ui.label(&rule.name);
+                ui.label(rule.matched.to_string());
+                ui.label(rule.deleted.to_string());
+                ui.label(format_size(rule.deleted_bytes, DECIMAL));
+                ui.end_row();
+            }
+        });
+}
@@ -1,7 +1,8 @@
 use crate::ui::{GossipUi, Page};
 use eframe::egui;
-use egui::widgets::Slider;
+use egui::widgets::{DragValue, Slider};
 use egui::{Context, Ui};
+use gossip_lib::{LocalRelayPolicy, LocalRelayReadPolicy};
 
 pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
     ui.heading("Network Settings");
@@ -29,6 +30,11 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         "Require user approval before AUTHenticating to a relay for the first time",
     );
 
+    ui.horizontal(|ui| {
+        ui.label("SOCKS5 Proxy (host:port)").on_hover_text("If set, all relay, media and other HTTP connections go through this SOCKS5 proxy, such as Tor at 127.0.0.1:9050. Relays at .onion addresses and relays marked 'proxy only' are never connected to without it. Leave empty to connect directly. Takes effect on restart.");
+        ui.add(text_edit_line!(app, app.unsaved_settings.socks5_proxy).hint_text("127.0.0.1:9050"));
+    });
+
     ui.add_space(10.0);
     ui.heading("Relay Settings");
     ui.add_space(10.0);
@@ -175,6 +181,7 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
             .text("seconds"),
         );
     });
+    ui.checkbox(&mut app.unsaved_settings.use_negentropy, "Use negentropy sync (NIP-77) where supported").on_hover_text("If enabled, feeds are reconciled against relays that advertise NIP-77 so that only events we don't already have are downloaded. Takes effect on new subscriptions.");
 
     ui.add_space(10.0);
     ui.heading("Stale Time Settings");
@@ -233,5 +240,73 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         );
     });
 
+    ui.add_space(10.0);
+    ui.heading("Local API");
+    ui.add_space(10.0);
+
+    ui.checkbox(&mut app.unsaved_settings.api_enabled, "Enable the local API").on_hover_text("If enabled, other programs on this computer can read your feeds and settings and act on your behalf through an HTTP and websocket API on localhost, if they have the token below. Takes effect on restart.");
+
+    ui.horizontal(|ui| {
+        ui.label("Port");
+        ui.add(DragValue::new(&mut app.unsaved_settings.api_port));
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Token").on_hover_text("Programs must send this as 'Authorization: Bearer <token>'. Keep it secret. One is made when the API first starts if this is empty.");
+        let mut token = app.unsaved_settings.api_token.clone();
+        ui.add(egui::TextEdit::singleline(&mut token).interactive(false).desired_width(450.0));
+        if ui.button("New Token").clicked() {
+            app.unsaved_settings.api_token = gossip_lib::api::new_token();
+        }
+    });
+
+    ui.add_space(10.0);
+    ui.heading("Local Relay");
+    ui.add_space(10.0);
+
+    ui.checkbox(&mut app.unsaved_settings.local_relay_enabled, "Run a local relay").on_hover_text("If enabled, other nostr clients on this computer can connect to ws://127.0.0.1:<port> to read the events gossip has stored, and see new ones as they arrive. Takes effect on restart.");
+
+    ui.horizontal(|ui| {
+        ui.label("Port");
+        ui.add(DragValue::new(&mut app.unsaved_settings.local_relay_port));
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Events to accept from clients");
+        let policy = &mut app.unsaved_settings.local_relay_policy;
+        egui::ComboBox::from_id_source("LocalRelayPolicyCombo")
+            .selected_text(policy.to_string())
+            .show_ui(ui, |ui| {
+                for choice in [
+                    LocalRelayPolicy::ReadOnly,
+                    LocalRelayPolicy::AcceptOwn,
+                    LocalRelayPolicy::AcceptAll,
+                ] {
+                    ui.selectable_value(policy, choice, choice.to_string());
+                }
+            });
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Events to serve to clients");
+        let policy = &mut app.unsaved_settings.local_relay_read_policy;
+        egui::ComboBox::from_id_source("LocalRelayReadPolicyCombo")
+            .selected_text(policy.to_string())
+            .show_ui(ui, |ui| {
+                for choice in [
+                    LocalRelayReadPolicy::ServeNone,
+                    LocalRelayReadPolicy::ServeOwn,
+                    LocalRelayReadPolicy::ServeAll,
+                ] {
+                    ui.selectable_value(policy, choice, choice.to_string());
+                }
+            });
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Allowed web origins").on_hover_text("Web pages in your browser can reach the local relay too, and are refused unless their origin (like https://example.com) is listed here. Separate origins with spaces or commas. Native clients are not affected.");
+        ui.add(text_edit_line!(app, app.unsaved_settings.local_relay_allowed_origins).desired_width(450.0));
+    });
+
     ui.add_space(20.0);
 }
@@ -28,5 +28,21 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
     )
     .on_hover_text("Takes effect on next relay connection.");
 
+    ui.add_space(20.0);
+    ui.heading("Media Uploads");
+    ui.add_space(10.0);
+
+    ui.horizontal(|ui| {
+        ui.label("Media server: ")
+            .on_hover_text("Files you attach to posts are uploaded here, e.g. https://nostr.build");
+        ui.add(text_edit_line!(app, app.unsaved_settings.media_server).desired_width(300.0));
+    });
+
+    ui.checkbox(
+        &mut app.unsaved_settings.media_server_blossom,
+        "Media server is a Blossom server",
+    )
+    .on_hover_text("Otherwise it is treated as a NIP-96 server.");
+
     ui.add_space(20.0);
 }
@@ -153,6 +153,15 @@ pub fn clickable_label(ui: &mut Ui, enabled: bool, text: impl Into<WidgetText>)
     ui.add_enabled(enabled, label)
 }
 
+/// Text for a count that relays gave us, marked when some relay said it was approximate
+pub fn relay_count_text(count: &gossip_lib::Count) -> String {
+    if count.approximate {
+        format!("~{}", count.count())
+    } else {
+        format!("{}", count.count())
+    }
+}
+
 pub fn break_anywhere_hyperlink_to(ui: &mut Ui, text: impl Into<WidgetText>, url: impl ToString) {
     let mut job = text.into().into_layout_job(
         ui.style(),
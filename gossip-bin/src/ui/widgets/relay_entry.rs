@@ -19,13 +19,13 @@ const LIST_VIEW_HEIGHT: f32 = 60.0;
 /// Height of the list view (width always max. available)
 const DETAIL_VIEW_HEIGHT: f32 = 80.0;
 /// Height of the edit view (width always max. available)
-const EDIT_VIEW_HEIGHT: f32 = 280.0;
+const EDIT_VIEW_HEIGHT: f32 = 310.0;
 /// Height required for one auth-permission drop-down
 const EDIT_VIEW_AUTH_PERM_HEIGHT: f32 = 25.0;
 /// Y-offset for first separator
 const HLINE_1_Y_OFFSET: f32 = LIST_VIEW_HEIGHT - 12.0;
 /// Y-offset for second separator
-const HLINE_2_Y_OFFSET: f32 = 210.0;
+const HLINE_2_Y_OFFSET: f32 = 240.0;
 /// Y top for the detail section
 const DETAIL_SECTION_TOP: f32 = TEXT_TOP + LIST_VIEW_HEIGHT;
 /// Size of edit button
@@ -49,7 +49,7 @@ const USAGE_LINE_X_END: f32 = -10.0;
 /// Line thickness
 const USAGE_LINE_THICKNESS: f32 = 1.0;
 /// Start of permission section from top
-const PERMISSION_SECTION_TOP: f32 = 230.0;
+const PERMISSION_SECTION_TOP: f32 = 260.0;
 const PERMISSION_SECTION_SIZE: Vec2 = Vec2 { x: 223.0, y: 50.0 };
 /// Spacing between nip11 text rows
 const NIP11_Y_SPACING: f32 = 20.0;
@@ -77,6 +77,8 @@ const OUTBOX_HOVER_TEXT: &str = "Where you tell others you write to. You should
 const SPAMSAFE_HOVER_TEXT: &str = "Relay is trusted to filter spam. If not set, replies and mentions from unfollowed people will not be fetched from the relay (when SpamSafe is enabled in settings).";
 const DM_USE_HOVER_TEXT: &str = "Use Relay to receive and send Direct Messages";
 const GLOBAL_FEED_HOVER_TEXT: &str = "Use Relay for Global feed";
+const SEARCH_HOVER_TEXT: &str = "Send searches to this relay (NIP-50). Relays you use which say they support searching are asked anyways.";
+const PROXY_ONLY_HOVER_TEXT: &str = "Only ever connect to this relay through the SOCKS5 proxy (see Network settings). If no proxy is set, this relay will not be used. Relays at .onion addresses always require the proxy.";
 
 #[derive(Clone, PartialEq)]
 pub enum RelayEntryView {
@@ -133,6 +135,7 @@ struct UsageBits {
     spamsafe: bool,
     dm: bool,
     global_feed: bool,
+    search: bool,
 }
 
 impl UsageBits {
@@ -147,6 +150,7 @@ impl UsageBits {
             spamsafe: usage_bits & Relay::SPAMSAFE == Relay::SPAMSAFE,
             dm: usage_bits & Relay::DM == Relay::DM,
             global_feed: usage_bits & Relay::GLOBAL == Relay::GLOBAL,
+            search: usage_bits & Relay::SEARCH == Relay::SEARCH,
         }
     }
 
@@ -196,11 +200,13 @@ pub struct RelayEntry {
     option_symbol: TextureId,
     auth_require_permission: bool,
     conn_require_permission: bool,
+    proxy_only: bool,
 }
 
 impl RelayEntry {
     pub(in crate::ui) fn new(relay: Relay, app: &mut GossipUi) -> Self {
         let usage = UsageBits::from_usage_bits(relay.get_usage_bits());
+        let proxy_only = relay.requires_proxy();
         let accent = app.theme.accent_color();
         let mut hsva: ecolor::HsvaGamma = accent.into();
         hsva.v *= 0.8;
@@ -221,6 +227,7 @@ impl RelayEntry {
             option_symbol: (&app.assets.options_symbol).into(),
             auth_require_permission: false,
             conn_require_permission: false,
+            proxy_only,
         }
     }
 
@@ -1085,6 +1092,68 @@ impl RelayEntry {
             );
         }
         let pos = pos + vec2(0.0, USAGE_SWITCH_Y_SPACING);
+        {
+            // ---- proxy only ----
+            // (onion relays always require the proxy, so that cannot be switched off)
+            let onion = gossip_lib::proxy::is_onion_url(self.relay.url.as_str());
+            let id = self.make_id("proxy_only_switch");
+            let sw_rect = Rect::from_min_size(pos - vec2(0.0, USAGE_SWITCH_Y_OFFSET), switch_size);
+            let response = widgets::switch_custom_at(
+                ui,
+                !onion,
+                &mut self.proxy_only,
+                sw_rect,
+                id,
+                knob_fill,
+                on_fill,
+                off_fill,
+            );
+            if response.changed() {
+                modify_relay(&self.relay.url, |relay| {
+                    relay.proxy_only = self.proxy_only;
+                });
+            }
+            response.on_hover_text(PROXY_ONLY_HOVER_TEXT);
+            draw_text_at(
+                ui,
+                pos + vec2(ui.spacing().item_spacing.x + switch_size.x, 0.0),
+                "Proxy only".into(),
+                Align::LEFT,
+                Some(ui.visuals().text_color()),
+                None,
+            );
+        }
+        {
+            // ---- search use ----
+            let pos = pos + vec2(USAGE_SWITCH_X_SPACING, 0.0);
+            let id = self.make_id("search_switch");
+            let sw_rect = Rect::from_min_size(pos - vec2(0.0, USAGE_SWITCH_Y_OFFSET), switch_size);
+            let response = widgets::switch_custom_at(
+                ui,
+                true,
+                &mut self.usage.search,
+                sw_rect,
+                id,
+                knob_fill,
+                on_fill,
+                off_fill,
+            );
+            if response.changed() {
+                modify_relay(&self.relay.url, |relay| {
+                    relay.adjust_usage_bit(Relay::SEARCH, self.usage.search)
+                });
+            }
+            response.on_hover_text(SEARCH_HOVER_TEXT);
+            draw_text_at(
+                ui,
+                pos + vec2(ui.spacing().item_spacing.x + switch_size.x, 0.0),
+                "Search".into(),
+                Align::LEFT,
+                Some(ui.visuals().text_color()),
+                None,
+            );
+        }
+        let pos = pos + vec2(0.0, USAGE_SWITCH_Y_SPACING);
         {
             // ---- rank ----
             let r = self.relay.rank;
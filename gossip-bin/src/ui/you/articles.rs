@@ -0,0 +1,222 @@
+use super::{GossipUi, Page};
+use crate::ui::article::{self, ArticleEditor};
+use eframe::egui;
+use egui::{Context, RichText, TextEdit, Ui};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{Article, GLOBALS};
+use nostr_types::{Event, EventKind, Filter};
+
+pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    if app.article_editor.is_some() {
+        compose(app, ctx, ui);
+        return;
+    }
+
+    ui.add_space(10.0);
+    ui.horizontal(|ui| {
+        ui.heading("Articles");
+        if ui.button("New article").clicked() {
+            app.article_editor = Some(ArticleEditor::default());
+        }
+    });
+
+    ui.add_space(10.0);
+    ui.label("Long-form articles you have published. Editing an article publishes a new version in its place.");
+
+    ui.add_space(10.0);
+    ui.separator();
+
+    let public_key = match GLOBALS.identity.public_key() {
+        Some(pk) => pk,
+        None => {
+            ui.add_space(10.0);
+            ui.label("You need to setup your keys to publish articles.");
+            return;
+        }
+    };
+
+    let mut filter = Filter::new();
+    filter.add_event_kind(EventKind::LongFormContent);
+    filter.add_author(&public_key.into());
+    let mut events: Vec<Event> = match GLOBALS.storage.find_events_by_filter(&filter, |_| true) {
+        Ok(events) => events,
+        Err(e) => {
+            ui.label(format!("{}", e));
+            return;
+        }
+    };
+    events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+    if events.is_empty() {
+        ui.add_space(10.0);
+        ui.label("You have no articles.");
+        return;
+    }
+
+    app.vert_scroll_area()
+        .id_source("your_articles")
+        .show(ui, |ui| {
+            for event in events.iter() {
+                let article = match Article::from_event(event) {
+                    Some(article) => article,
+                    None => continue,
+                };
+                let deleted = !GLOBALS
+                    .storage
+                    .get_deletions(event)
+                    .unwrap_or_default()
+                    .is_empty();
+
+                ui.add_space(8.0);
+
+                ui.horizontal(|ui| {
+                    let title = if article.title.is_empty() {
+                        "Untitled"
+                    } else {
+                        &article.title
+                    };
+                    if ui.link(RichText::new(title).strong()).clicked() {
+                        app.set_page(ctx, Page::Article(article.naddr(public_key)));
+                    }
+                    ui.label(
+                        RichText::new(crate::date_ago::date_ago(event.created_at))
+                            .italics()
+                            .weak(),
+                    );
+                    if deleted {
+                        ui.label(
+                            RichText::new("DELETED").color(app.theme.warning_marker_text_color()),
+                        );
+                    }
+                });
+
+                if !article.summary.is_empty() {
+                    ui.label(&article.summary);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Edit").clicked() {
+                        app.article_editor = Some(ArticleEditor::edit(&article));
+                    }
+                    if app.deleting_article.as_ref() == Some(&article.d) {
+                        ui.label("Ask relays to delete every version of this article?");
+                        if ui.button("Delete").clicked() {
+                            let _ = GLOBALS
+                                .to_overlord
+                                .send(ToOverlordMessage::DeleteArticle(article.d.clone()));
+                            app.deleting_article = None;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            app.deleting_article = None;
+                        }
+                    } else if !deleted && ui.button("Delete").clicked() {
+                        app.deleting_article = Some(article.d.clone());
+                    }
+                });
+
+                ui.add_space(8.0);
+                ui.separator();
+            }
+        });
+}
+
+fn compose(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
+    let mut editor = match app.article_editor.take() {
+        Some(editor) => editor,
+        None => return,
+    };
+
+    ui.add_space(10.0);
+    ui.heading(if editor.d.is_empty() {
+        "New article"
+    } else {
+        "Edit article"
+    });
+    ui.add_space(10.0);
+
+    let mut done = false;
+    app.vert_scroll_area()
+        .id_source("article_compose")
+        .show(ui, |ui| {
+            egui::Grid::new("article_fields")
+                .num_columns(2)
+                .spacing([10.0, 6.0])
+                .show(ui, |ui| {
+                    ui.label("Title");
+                    ui.add(TextEdit::singleline(&mut editor.title).desired_width(f32::INFINITY));
+                    ui.end_row();
+
+                    ui.label("Summary");
+                    ui.add(
+                        TextEdit::multiline(&mut editor.summary)
+                            .desired_rows(2)
+                            .desired_width(f32::INFINITY),
+                    );
+                    ui.end_row();
+
+                    ui.label("Image URL");
+                    ui.add(TextEdit::singleline(&mut editor.image).desired_width(f32::INFINITY));
+                    ui.end_row();
+
+                    ui.label("Hashtags");
+                    ui.add(
+                        TextEdit::singleline(&mut editor.hashtags)
+                            .hint_text("comma separated")
+                            .desired_width(f32::INFINITY),
+                    );
+                    ui.end_row();
+                });
+
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut editor.preview, false, "Write");
+                ui.selectable_value(&mut editor.preview, true, "Preview");
+            });
+            ui.add_space(6.0);
+
+            if editor.preview {
+                article::render_markdown(app, ui, &editor.content);
+            } else {
+                ui.add(
+                    TextEdit::multiline(&mut editor.content)
+                        .hint_text("Write in markdown")
+                        .code_editor()
+                        .desired_rows(20)
+                        .desired_width(f32::INFINITY),
+                );
+            }
+
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                if !GLOBALS.identity.is_unlocked() {
+                    if GLOBALS.identity.encrypted_private_key().is_some() {
+                        super::offer_unlock_priv_key(app, ui);
+                    } else {
+                        ui.label("You need to ");
+                        if ui.link("setup your private-key").clicked() {
+                            app.set_page(ctx, Page::YourKeys);
+                        }
+                        ui.label(" to publish.");
+                    }
+                } else if ui
+                    .add_enabled(
+                        !editor.title.trim().is_empty() && !editor.content.trim().is_empty(),
+                        egui::Button::new("Publish"),
+                    )
+                    .clicked()
+                {
+                    let _ = GLOBALS
+                        .to_overlord
+                        .send(ToOverlordMessage::PublishArticle(editor.to_article()));
+                    done = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    done = true;
+                }
+            });
+        });
+
+    if !done {
+        app.article_editor = Some(editor);
+    }
+}
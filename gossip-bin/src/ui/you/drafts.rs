@@ -0,0 +1,112 @@
+use super::GossipUi;
+use crate::ui::{FeedKind, Page};
+use eframe::egui;
+use egui::{Context, RichText, Ui};
+use gossip_lib::{DmChannel, Draft, DraftContext, GLOBALS};
+
+pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    ui.add_space(10.0);
+    ui.heading("Drafts");
+
+    ui.add_space(10.0);
+    ui.label("Unfinished posts are saved as you type. Each new note, reply and DM conversation keeps its own draft.");
+
+    ui.add_space(10.0);
+    ui.separator();
+
+    let drafts = match GLOBALS.storage.read_all_drafts() {
+        Ok(drafts) => drafts,
+        Err(e) => {
+            ui.label(format!("{}", e));
+            return;
+        }
+    };
+
+    if drafts.is_empty() {
+        ui.add_space(10.0);
+        ui.label("You have no drafts.");
+        return;
+    }
+
+    app.vert_scroll_area()
+        .id_source("your_drafts")
+        .show(ui, |ui| {
+            for draft in drafts.iter() {
+                ui.add_space(8.0);
+
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(crate::date_ago::date_ago(draft.updated_at))
+                            .italics()
+                            .weak(),
+                    );
+                    ui.label(describe_context(&draft.context));
+                    if ui.button("Resume").clicked() {
+                        resume_draft(app, ctx, draft);
+                    }
+                    if ui.button("Discard").clicked() {
+                        discard_draft(app, draft);
+                    }
+                });
+
+                if let Some(subject) = &draft.subject {
+                    ui.label(RichText::new(subject).strong());
+                }
+                let summary: String = draft
+                    .content
+                    .chars()
+                    .take(100)
+                    .collect::<String>()
+                    .replace('\n', " ");
+                ui.label(summary);
+
+                ui.add_space(8.0);
+                ui.separator();
+            }
+        });
+}
+
+fn describe_context(context: &DraftContext) -> String {
+    match context {
+        DraftContext::NewNote => "New note".to_owned(),
+        DraftContext::Reply(id) => {
+            format!(
+                "Reply to #{}",
+                gossip_lib::names::hex_id_short(&(*id).into())
+            )
+        }
+        DraftContext::DmChannel(keys) => format!("DM to {}", DmChannel::new(keys).name()),
+    }
+}
+
+fn resume_draft(app: &mut GossipUi, ctx: &Context, draft: &Draft) {
+    match &draft.context {
+        DraftContext::NewNote | DraftContext::Reply(_) => {
+            // The composer loads the saved draft once it sees the new context
+            app.draft_data.repost = None;
+            app.draft_data.replying_to = match draft.context {
+                DraftContext::Reply(id) => Some(id),
+                _ => None,
+            };
+            app.show_post_area = true;
+            app.draft_needs_focus = true;
+        }
+        DraftContext::DmChannel(keys) => {
+            app.set_page(ctx, Page::Feed(FeedKind::DmChat(DmChannel::new(keys))));
+        }
+    }
+}
+
+fn discard_draft(app: &mut GossipUi, draft: &Draft) {
+    let _ = GLOBALS.storage.delete_draft(&draft.context, None);
+
+    // If it is open in the composer, clear it there too
+    if app.draft_sync.context.as_ref() == Some(&draft.context) {
+        app.draft_data.clear();
+        app.draft_sync.saved = None;
+    }
+    if app.dm_draft_sync.context.as_ref() == Some(&draft.context) {
+        app.dm_draft_data.clear();
+        app.dm_draft_sync.saved = None;
+    }
+}
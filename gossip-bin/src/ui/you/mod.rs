@@ -7,9 +7,13 @@ use gossip_lib::{Globals, GLOBALS};
 use nostr_types::{KeySecurity, PublicKeyHex};
 use zeroize::Zeroize;
 
+mod articles;
 mod delegation;
+mod drafts;
 mod metadata;
 mod nostr_connect;
+mod outbox;
+mod wallet;
 
 pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
     if app.page == Page::YourKeys {
@@ -119,6 +123,14 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         delegation::update(app, ctx, _frame, ui);
     } else if app.page == Page::YourNostrConnect {
         nostr_connect::update(app, ctx, _frame, ui);
+    } else if app.page == Page::YourWallet {
+        wallet::update(app, ctx, _frame, ui);
+    } else if app.page == Page::YourOutbox {
+        outbox::update(app, ctx, _frame, ui);
+    } else if app.page == Page::YourDrafts {
+        drafts::update(app, ctx, _frame, ui);
+    } else if app.page == Page::YourArticles {
+        articles::update(app, ctx, _frame, ui);
     }
 }
 
@@ -0,0 +1,207 @@
+use super::GossipUi;
+use eframe::egui;
+use egui::{Context, RichText, Ui};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{OutboxStatus, ScheduledPost, GLOBALS};
+use nostr_types::Unixtime;
+
+// We only show this many of the most recent posts
+const MAX_SHOWN: usize = 100;
+
+pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    ui.add_space(10.0);
+    ui.heading("Outbox");
+
+    ui.add_space(10.0);
+    ui.label("Which relays have accepted each of your recent posts. Failed posts are retried automatically for a while, or you can retry them now.");
+
+    let queued = GLOBALS.storage.get_offline_queue_len().unwrap_or(0);
+    if queued > 0 {
+        ui.add_space(10.0);
+        ui.label(
+            RichText::new(format!(
+                "{} events were made while offline, and will be posted when you go online.",
+                queued
+            ))
+            .color(app.theme.notice_marker_text_color()),
+        );
+    }
+
+    show_scheduled_posts(app, ui);
+
+    ui.add_space(10.0);
+    ui.separator();
+
+    let mut entries = match GLOBALS.storage.filter_outbox_entries(|_| true) {
+        Ok(entries) => entries,
+        Err(e) => {
+            ui.label(format!("{}", e));
+            return;
+        }
+    };
+    entries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    entries.truncate(MAX_SHOWN);
+
+    if entries.is_empty() && queued == 0 {
+        ui.add_space(10.0);
+        ui.label("You haven't posted anything yet.");
+        return;
+    }
+
+    app.vert_scroll_area()
+        .id_source("your_outbox")
+        .show(ui, |ui| {
+            for entry in entries.iter() {
+                ui.add_space(8.0);
+
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(crate::date_ago::date_ago(entry.created_at))
+                            .italics()
+                            .weak(),
+                    );
+                    ui.label(format!("{:?}", entry.event.kind));
+                    ui.label(format!(
+                        "accepted by {} of {}",
+                        entry.count(OutboxStatus::Accepted),
+                        entry.relays.len()
+                    ));
+                    if entry.count(OutboxStatus::Failed) > 0 && ui.button("Retry failed").clicked()
+                    {
+                        let _ = GLOBALS
+                            .to_overlord
+                            .send(ToOverlordMessage::RetryPost(entry.event.id));
+                    }
+                });
+
+                let mut summary: String = entry
+                    .event
+                    .content
+                    .chars()
+                    .take(100)
+                    .collect::<String>()
+                    .replace('\n', " ");
+                if summary.is_empty() {
+                    summary = "[no event summary]".to_owned();
+                }
+                ui.label(summary);
+
+                for relay in entry.relays.iter() {
+                    ui.horizontal(|ui| {
+                        ui.add_space(20.0);
+                        let status = match relay.status {
+                            OutboxStatus::Pending => RichText::new("pending").weak(),
+                            OutboxStatus::Accepted => {
+                                RichText::new("accepted").color(app.theme.accent_color())
+                            }
+                            OutboxStatus::Failed => {
+                                RichText::new("failed").color(app.theme.warning_marker_text_color())
+                            }
+                        };
+                        ui.label(status);
+                        ui.label(relay.url.as_str());
+                        if relay.attempts > 1 {
+                            ui.label(RichText::new(format!("{} attempts", relay.attempts)).weak());
+                        }
+                        if let Some(message) = &relay.message {
+                            ui.label(RichText::new(message).weak());
+                        }
+                        if relay.status == OutboxStatus::Failed {
+                            if let Some(next) = relay.next_attempt {
+                                let secs = (next.0 - Unixtime::now().0).max(0);
+                                ui.label(RichText::new(format!("retry in {}s", secs)).weak());
+                            }
+                        }
+                    });
+                }
+
+                ui.add_space(8.0);
+                ui.separator();
+            }
+        });
+}
+
+fn show_scheduled_posts(app: &mut GossipUi, ui: &mut Ui) {
+    let posts = match GLOBALS.storage.read_all_scheduled_posts() {
+        Ok(posts) => posts,
+        Err(e) => {
+            ui.label(format!("{}", e));
+            return;
+        }
+    };
+    if posts.is_empty() {
+        return;
+    }
+
+    ui.add_space(10.0);
+    ui.separator();
+    ui.add_space(10.0);
+    ui.heading("Scheduled");
+
+    for post in posts.iter() {
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(format_local(post.publish_at)).italics());
+            if post.dm_recipients.is_some() {
+                ui.label(RichText::new("DM").weak());
+            }
+            if post.dm_recipients.is_none() && ui.button("Edit").clicked() {
+                // Take it back into the composer. It is rescheduled when sent again.
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::CancelScheduledPost(post.id));
+                edit_scheduled_post(app, post);
+            }
+            if ui.button("Cancel").clicked() {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::CancelScheduledPost(post.id));
+            }
+        });
+        let summary: String = post
+            .content
+            .chars()
+            .take(100)
+            .collect::<String>()
+            .replace('\n', " ");
+        ui.label(summary);
+    }
+}
+
+fn edit_scheduled_post(app: &mut GossipUi, post: &ScheduledPost) {
+    app.draft_data.clear();
+    app.draft_data.draft = post.content.clone();
+    app.draft_data.replying_to = post.in_reply_to;
+    app.draft_data.is_annotate = post.annotation;
+    app.draft_data.include_schedule = true;
+    app.draft_data.schedule_at = format_local(post.publish_at);
+
+    // The composer makes these tags again from its own fields
+    for tag in post.tags.iter() {
+        match tag.tagname() {
+            "subject" => {
+                app.draft_data.include_subject = true;
+                app.draft_data.subject = tag.value().to_owned();
+            }
+            "content-warning" => {
+                app.draft_data.include_content_warning = true;
+                app.draft_data.content_warning = tag.value().to_owned();
+            }
+            _ => {}
+        }
+    }
+
+    app.show_post_area = true;
+    app.draft_needs_focus = true;
+}
+
+// In the same format the composer takes
+fn format_local(when: Unixtime) -> String {
+    match chrono::DateTime::from_timestamp(when.0, 0) {
+        Some(utc) => utc
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        None => format!("{}", when.0),
+    }
+}
@@ -0,0 +1,110 @@
+use super::GossipUi;
+use eframe::egui;
+use egui::{Context, Ui};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{nwc, GLOBALS};
+
+pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    ui.add_space(10.0);
+    ui.horizontal_wrapped(|ui| {
+        ui.heading("Wallet");
+    });
+
+    ui.add_space(10.0);
+    ui.label("Connect a wallet with Nostr Wallet Connect (NIP-47) and gossip will pay your zaps with it. Otherwise you are shown an invoice to pay elsewhere.");
+
+    ui.separator();
+    ui.add_space(10.0);
+
+    let connection = match nwc::connection() {
+        Ok(connection) => connection,
+        Err(e) => {
+            ui.label(format!("{}", e));
+            return;
+        }
+    };
+
+    match connection {
+        Some(connection) => {
+            ui.heading("Connected Wallet");
+            ui.add_space(10.0);
+            ui.label(format!("Name: {}", connection.name));
+            ui.label(format!(
+                "Wallet service: {}",
+                connection.wallet_pubkey.as_bech32_string()
+            ));
+            if let Some(lud16) = &connection.lud16 {
+                ui.label(format!("Lightning address: {}", lud16));
+            }
+            ui.label("Relays:");
+            for relay in &connection.relays {
+                ui.label(format!("        {}", relay));
+            }
+
+            if !GLOBALS.identity.is_unlocked() {
+                ui.add_space(10.0);
+                ui.label("Unlock your identity to use this wallet.");
+            }
+
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                match *GLOBALS.nwc.balance.read() {
+                    Some((msats, when)) => {
+                        ui.label(format!(
+                            "Balance: {} sats (as of {})",
+                            msats.0 / 1000,
+                            crate::date_ago::date_ago(when)
+                        ));
+                    }
+                    None => {
+                        ui.label("Balance: unknown");
+                    }
+                }
+                if ui.button("Refresh").clicked() {
+                    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::NwcGetBalance);
+                }
+            });
+
+            ui.add_space(10.0);
+            if ui.button("Disconnect").clicked() {
+                if let Err(e) = nwc::disconnect() {
+                    GLOBALS.status_queue.write().write(format!("{}", e));
+                }
+            }
+        }
+        None => {
+            ui.heading("Connect a Wallet");
+
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                ui.label("Name: ");
+                ui.add(text_edit_line!(app, app.wallet_connect_name));
+            });
+
+            ui.add_space(10.0);
+            ui.label("Paste the connection string from your wallet (nostr+walletconnect://...):");
+            ui.add(text_edit_line!(app, app.wallet_connect_string).password(true));
+            ui.label("The secret in it is stored encrypted with your private key, so your identity must be unlocked to connect and to pay.");
+
+            if !app.wallet_connect_string.is_empty() && ui.button("Connect").clicked() {
+                let name = if app.wallet_connect_name.trim().is_empty() {
+                    "Wallet".to_owned()
+                } else {
+                    app.wallet_connect_name.trim().to_owned()
+                };
+                match nwc::connect(name, &app.wallet_connect_string) {
+                    Ok(()) => {
+                        let _ = GLOBALS.to_overlord.send(ToOverlordMessage::NwcGetBalance);
+                    }
+                    Err(e) => {
+                        GLOBALS.status_queue.write().write(format!("{}", e));
+                    }
+                }
+                app.wallet_connect_name = "".to_owned();
+                app.wallet_connect_string = "".to_owned();
+            }
+        }
+    }
+
+    ui.separator();
+}
@@ -1,4 +1,6 @@
-use gossip_lib::{Error, RunState, Storage, GLOBALS};
+use gossip_lib::{
+    Error, LocalRelayPolicy, LocalRelayReadPolicy, RetentionPolicy, RunState, Storage, GLOBALS,
+};
 use nostr_types::PublicKey;
 use paste::paste;
 
@@ -65,7 +67,10 @@ pub struct UnsavedSettings {
     pub hide_mutes_entirely: bool,
     pub reactions: bool,
     pub enable_zap_receipts: bool,
+    pub zap_default_sats: u64,
+    pub fetch_counts: bool,
     pub show_media: bool,
+    pub animate_media: bool,
     pub approve_content_warning: bool,
     pub show_deleted_events: bool,
     pub avoid_spam_on_unsafe_relays: bool,
@@ -75,6 +80,8 @@ pub struct UnsavedSettings {
     pub set_client_tag: bool,
     pub set_user_agent: bool,
     pub delegatee_tag: String,
+    pub media_server: String,
+    pub media_server_blossom: bool,
 
     // UI settings
     pub max_fps: u32,
@@ -108,6 +115,16 @@ pub struct UnsavedSettings {
     pub websocket_accept_unmasked_frames: bool,
     pub websocket_connect_timeout_sec: u64,
     pub websocket_ping_frequency_sec: u64,
+    pub use_negentropy: bool,
+    pub socks5_proxy: String,
+    pub api_enabled: bool,
+    pub api_port: u16,
+    pub api_token: String,
+    pub local_relay_enabled: bool,
+    pub local_relay_port: u16,
+    pub local_relay_policy: LocalRelayPolicy,
+    pub local_relay_read_policy: LocalRelayReadPolicy,
+    pub local_relay_allowed_origins: String,
 
     // HTTP settings
     pub fetcher_connect_timeout_sec: u64,
@@ -120,7 +137,9 @@ pub struct UnsavedSettings {
 
     // Database settings
     pub prune_period_days: u64,
+    pub retention_policy: RetentionPolicy,
     pub cache_prune_period_days: u64,
+    pub cache_quota_mb: u64,
 }
 
 impl Default for UnsavedSettings {
@@ -149,7 +168,10 @@ impl Default for UnsavedSettings {
             hide_mutes_entirely: default_setting!(hide_mutes_entirely),
             reactions: default_setting!(reactions),
             enable_zap_receipts: default_setting!(enable_zap_receipts),
+            zap_default_sats: default_setting!(zap_default_sats),
+            fetch_counts: default_setting!(fetch_counts),
             show_media: default_setting!(show_media),
+            animate_media: default_setting!(animate_media),
             approve_content_warning: default_setting!(approve_content_warning),
             show_deleted_events: default_setting!(show_deleted_events),
             avoid_spam_on_unsafe_relays: default_setting!(avoid_spam_on_unsafe_relays),
@@ -157,6 +179,8 @@ impl Default for UnsavedSettings {
             set_client_tag: default_setting!(set_client_tag),
             set_user_agent: default_setting!(set_user_agent),
             delegatee_tag: default_setting!(delegatee_tag),
+            media_server: default_setting!(media_server),
+            media_server_blossom: default_setting!(media_server_blossom),
             max_fps: default_setting!(max_fps),
             recompute_feed_periodically: default_setting!(recompute_feed_periodically),
             feed_recompute_interval_ms: default_setting!(feed_recompute_interval_ms),
@@ -188,6 +212,16 @@ impl Default for UnsavedSettings {
             websocket_accept_unmasked_frames: default_setting!(websocket_accept_unmasked_frames),
             websocket_connect_timeout_sec: default_setting!(websocket_connect_timeout_sec),
             websocket_ping_frequency_sec: default_setting!(websocket_ping_frequency_sec),
+            use_negentropy: default_setting!(use_negentropy),
+            socks5_proxy: default_setting!(socks5_proxy),
+            api_enabled: default_setting!(api_enabled),
+            api_port: default_setting!(api_port),
+            api_token: default_setting!(api_token),
+            local_relay_enabled: default_setting!(local_relay_enabled),
+            local_relay_port: default_setting!(local_relay_port),
+            local_relay_policy: default_setting!(local_relay_policy),
+            local_relay_read_policy: default_setting!(local_relay_read_policy),
+            local_relay_allowed_origins: default_setting!(local_relay_allowed_origins),
             fetcher_connect_timeout_sec: default_setting!(fetcher_connect_timeout_sec),
             fetcher_timeout_sec: default_setting!(fetcher_timeout_sec),
             fetcher_max_requests_per_host: default_setting!(fetcher_max_requests_per_host),
@@ -202,7 +236,9 @@ impl Default for UnsavedSettings {
             ),
             nip11_lines_to_output_on_error: default_setting!(nip11_lines_to_output_on_error),
             prune_period_days: default_setting!(prune_period_days),
+            retention_policy: default_setting!(retention_policy),
             cache_prune_period_days: default_setting!(prune_period_days),
+            cache_quota_mb: default_setting!(cache_quota_mb),
         }
     }
 }
@@ -231,7 +267,10 @@ impl UnsavedSettings {
             hide_mutes_entirely: load_setting!(hide_mutes_entirely),
             reactions: load_setting!(reactions),
             enable_zap_receipts: load_setting!(enable_zap_receipts),
+            zap_default_sats: load_setting!(zap_default_sats),
+            fetch_counts: load_setting!(fetch_counts),
             show_media: load_setting!(show_media),
+            animate_media: load_setting!(animate_media),
             approve_content_warning: load_setting!(approve_content_warning),
             show_deleted_events: load_setting!(show_deleted_events),
             avoid_spam_on_unsafe_relays: load_setting!(avoid_spam_on_unsafe_relays),
@@ -239,6 +278,8 @@ impl UnsavedSettings {
             set_client_tag: load_setting!(set_client_tag),
             set_user_agent: load_setting!(set_user_agent),
             delegatee_tag: load_setting!(delegatee_tag),
+            media_server: load_setting!(media_server),
+            media_server_blossom: load_setting!(media_server_blossom),
             max_fps: load_setting!(max_fps),
             recompute_feed_periodically: load_setting!(recompute_feed_periodically),
             feed_recompute_interval_ms: load_setting!(feed_recompute_interval_ms),
@@ -268,6 +309,16 @@ impl UnsavedSettings {
             websocket_accept_unmasked_frames: load_setting!(websocket_accept_unmasked_frames),
             websocket_connect_timeout_sec: load_setting!(websocket_connect_timeout_sec),
             websocket_ping_frequency_sec: load_setting!(websocket_ping_frequency_sec),
+            use_negentropy: load_setting!(use_negentropy),
+            socks5_proxy: load_setting!(socks5_proxy),
+            api_enabled: load_setting!(api_enabled),
+            api_port: load_setting!(api_port),
+            api_token: load_setting!(api_token),
+            local_relay_enabled: load_setting!(local_relay_enabled),
+            local_relay_port: load_setting!(local_relay_port),
+            local_relay_policy: load_setting!(local_relay_policy),
+            local_relay_read_policy: load_setting!(local_relay_read_policy),
+            local_relay_allowed_origins: load_setting!(local_relay_allowed_origins),
             fetcher_connect_timeout_sec: load_setting!(fetcher_connect_timeout_sec),
             fetcher_timeout_sec: load_setting!(fetcher_timeout_sec),
             fetcher_max_requests_per_host: load_setting!(fetcher_max_requests_per_host),
@@ -282,7 +333,9 @@ impl UnsavedSettings {
             ),
             nip11_lines_to_output_on_error: load_setting!(nip11_lines_to_output_on_error),
             prune_period_days: load_setting!(prune_period_days),
+            retention_policy: load_setting!(retention_policy),
             cache_prune_period_days: load_setting!(cache_prune_period_days),
+            cache_quota_mb: load_setting!(cache_quota_mb),
         }
     }
 
@@ -309,7 +362,10 @@ impl UnsavedSettings {
         save_setting!(hide_mutes_entirely, self, txn);
         save_setting!(reactions, self, txn);
         save_setting!(enable_zap_receipts, self, txn);
+        save_setting!(zap_default_sats, self, txn);
+        save_setting!(fetch_counts, self, txn);
         save_setting!(show_media, self, txn);
+        save_setting!(animate_media, self, txn);
         save_setting!(approve_content_warning, self, txn);
         save_setting!(show_deleted_events, self, txn);
         save_setting!(avoid_spam_on_unsafe_relays, self, txn);
@@ -317,6 +373,8 @@ impl UnsavedSettings {
         save_setting!(set_client_tag, self, txn);
         save_setting!(set_user_agent, self, txn);
         save_setting!(delegatee_tag, self, txn);
+        save_setting!(media_server, self, txn);
+        save_setting!(media_server_blossom, self, txn);
         save_setting!(max_fps, self, txn);
         save_setting!(recompute_feed_periodically, self, txn);
         save_setting!(feed_recompute_interval_ms, self, txn);
@@ -344,6 +402,16 @@ impl UnsavedSettings {
         save_setting!(websocket_accept_unmasked_frames, self, txn);
         save_setting!(websocket_connect_timeout_sec, self, txn);
         save_setting!(websocket_ping_frequency_sec, self, txn);
+        save_setting!(use_negentropy, self, txn);
+        save_setting!(socks5_proxy, self, txn);
+        save_setting!(api_enabled, self, txn);
+        save_setting!(api_port, self, txn);
+        save_setting!(api_token, self, txn);
+        save_setting!(local_relay_enabled, self, txn);
+        save_setting!(local_relay_port, self, txn);
+        save_setting!(local_relay_policy, self, txn);
+        save_setting!(local_relay_read_policy, self, txn);
+        save_setting!(local_relay_allowed_origins, self, txn);
         save_setting!(fetcher_connect_timeout_sec, self, txn);
         save_setting!(fetcher_timeout_sec, self, txn);
         save_setting!(fetcher_max_requests_per_host, self, txn);
@@ -352,7 +420,9 @@ impl UnsavedSettings {
         save_setting!(fetcher_host_exclusion_on_high_error_secs, self, txn);
         save_setting!(nip11_lines_to_output_on_error, self, txn);
         save_setting!(prune_period_days, self, txn);
+        save_setting!(retention_policy, self, txn);
         save_setting!(cache_prune_period_days, self, txn);
+        save_setting!(cache_quota_mb, self, txn);
         txn.commit()?;
 
         let runstate = *GLOBALS.read_runstate.borrow();
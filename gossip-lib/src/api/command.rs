@@ -0,0 +1,223 @@
+use crate::comms::ToOverlordMessage;
+use crate::error::{Error, ErrorKind};
+use crate::feed::FeedKind;
+use crate::misc::Private;
+use crate::people::PersonList;
+use nostr_types::{Id, PublicKey, RelayUrl, Tag};
+use serde::Deserialize;
+
+/// An operation the API can ask the Overlord to do. Each is sent as the matching
+/// [ToOverlordMessage]. Operations that only make sense in-process (such as
+/// answering NIP-46 approvals or managing minions), and those that read local
+/// files (such as uploading media), are not offered.
+///
+/// In JSON these look like `{"command": "add_relay", "url": "wss://..."}`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ApiCommand {
+    AddRelay {
+        url: RelayUrl,
+    },
+    DeletePost {
+        id: Id,
+    },
+    DropRelay {
+        url: RelayUrl,
+    },
+    FetchEvent {
+        id: Id,
+        #[serde(default)]
+        relays: Vec<RelayUrl>,
+    },
+    FollowNip05 {
+        nip05: String,
+        #[serde(default = "followed_list")]
+        list: u8,
+        #[serde(default)]
+        private: bool,
+    },
+    FollowPubkey {
+        pubkey: PublicKey,
+        #[serde(default = "followed_list")]
+        list: u8,
+        #[serde(default)]
+        private: bool,
+    },
+    HideOrShowRelay {
+        url: RelayUrl,
+        hidden: bool,
+    },
+    LoadMoreCurrentFeed,
+    Post {
+        content: String,
+        #[serde(default)]
+        tags: Vec<Tag>,
+        #[serde(default)]
+        in_reply_to: Option<Id>,
+    },
+    PreviewPrune,
+    PruneCache,
+    PruneDatabase,
+    RankRelay {
+        url: RelayUrl,
+        rank: u8,
+    },
+    React {
+        id: Id,
+        pubkey: PublicKey,
+        reaction: char,
+    },
+    RefreshScoresAndPickRelays,
+    RefreshSubscribedMetadata,
+    Repost {
+        id: Id,
+    },
+    RetryPost {
+        id: Id,
+    },
+    Search {
+        text: String,
+    },
+    SearchMoreNotes,
+    UnlockKey {
+        password: String,
+    },
+    UpdateMetadata {
+        pubkey: PublicKey,
+    },
+}
+
+fn followed_list() -> u8 {
+    PersonList::Followed.as_u8()
+}
+
+fn person_list(number: u8) -> Result<PersonList, Error> {
+    match PersonList::from_number(number) {
+        Some(list) => Ok(list),
+        None => Err(ErrorKind::General(format!("No such person list: {}", number)).into()),
+    }
+}
+
+impl ApiCommand {
+    pub(crate) fn into_message(self) -> Result<ToOverlordMessage, Error> {
+        Ok(match self {
+            ApiCommand::AddRelay { url } => ToOverlordMessage::AddRelay(url),
+            ApiCommand::DeletePost { id } => ToOverlordMessage::DeletePost(id),
+            ApiCommand::DropRelay { url } => ToOverlordMessage::DropRelay(url),
+            ApiCommand::FetchEvent { id, relays } => ToOverlordMessage::FetchEvent(id, relays),
+            ApiCommand::FollowNip05 {
+                nip05,
+                list,
+                private,
+            } => ToOverlordMessage::FollowNip05(nip05, person_list(list)?, Private(private)),
+            ApiCommand::FollowPubkey {
+                pubkey,
+                list,
+                private,
+            } => ToOverlordMessage::FollowPubkey(pubkey, person_list(list)?, Private(private)),
+            ApiCommand::HideOrShowRelay { url, hidden } => {
+                ToOverlordMessage::HideOrShowRelay(url, hidden)
+            }
+            ApiCommand::LoadMoreCurrentFeed => ToOverlordMessage::LoadMoreCurrentFeed,
+            ApiCommand::Post {
+                content,
+                tags,
+                in_reply_to,
+            } => ToOverlordMessage::Post {
+                content,
+                tags,
+                in_reply_to,
+                annotation: false,
+                dm_channel: None,
+            },
+            ApiCommand::PreviewPrune => ToOverlordMessage::PreviewPrune,
+            ApiCommand::PruneCache => ToOverlordMessage::PruneCache,
+            ApiCommand::PruneDatabase => ToOverlordMessage::PruneDatabase,
+            ApiCommand::RankRelay { url, rank } => ToOverlordMessage::RankRelay(url, rank),
+            ApiCommand::React {
+                id,
+                pubkey,
+                reaction,
+            } => ToOverlordMessage::React(id, pubkey, reaction),
+            ApiCommand::RefreshScoresAndPickRelays => ToOverlordMessage::RefreshScoresAndPickRelays,
+            ApiCommand::RefreshSubscribedMetadata => ToOverlordMessage::RefreshSubscribedMetadata,
+            ApiCommand::Repost { id } => ToOverlordMessage::Repost(id),
+            ApiCommand::RetryPost { id } => ToOverlordMessage::RetryPost(id),
+            ApiCommand::Search { text } => ToOverlordMessage::Search(text),
+            ApiCommand::SearchMoreNotes => ToOverlordMessage::SearchMoreNotes,
+            ApiCommand::UnlockKey { password } => ToOverlordMessage::UnlockKey(password),
+            ApiCommand::UpdateMetadata { pubkey } => ToOverlordMessage::UpdateMetadata(pubkey),
+        })
+    }
+}
+
+/// A feed the API can switch to, e.g. `{"feed": "inbox", "indirect": true}`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "feed", rename_all = "snake_case")]
+pub enum ApiFeed {
+    List {
+        #[serde(default = "followed_list")]
+        list: u8,
+        #[serde(default)]
+        with_replies: bool,
+    },
+    Bookmarks,
+    Inbox {
+        #[serde(default)]
+        indirect: bool,
+    },
+    Thread {
+        id: Id,
+        #[serde(default)]
+        author: Option<PublicKey>,
+    },
+    Person {
+        pubkey: PublicKey,
+    },
+    Global,
+}
+
+impl ApiFeed {
+    pub(crate) fn into_feed_kind(self) -> Result<FeedKind, Error> {
+        Ok(match self {
+            ApiFeed::List { list, with_replies } => {
+                FeedKind::List(person_list(list)?, with_replies)
+            }
+            ApiFeed::Bookmarks => FeedKind::Bookmarks,
+            ApiFeed::Inbox { indirect } => FeedKind::Inbox(indirect),
+            ApiFeed::Thread { id, author } => FeedKind::Thread {
+                id,
+                referenced_by: id,
+                author,
+            },
+            ApiFeed::Person { pubkey } => FeedKind::Person(pubkey),
+            ApiFeed::Global => FeedKind::Global,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        let command: ApiCommand =
+            serde_json::from_str(r#"{"command": "search", "text": "gossip"}"#).unwrap();
+        assert!(matches!(
+            command.into_message().unwrap(),
+            ToOverlordMessage::Search(text) if text == "gossip"
+        ));
+
+        let command: ApiCommand =
+            serde_json::from_str(r#"{"command": "post", "content": "hello"}"#).unwrap();
+        assert!(matches!(
+            command.into_message().unwrap(),
+            ToOverlordMessage::Post { content, in_reply_to: None, .. } if content == "hello"
+        ));
+
+        assert!(
+            serde_json::from_str::<ApiCommand>(r#"{"command": "minion_job_complete"}"#).is_err()
+        );
+    }
+}
@@ -0,0 +1,377 @@
+//! A local HTTP and websocket API, so that scripts, dashboards and other front-ends
+//! can drive a running gossip.
+//!
+//! The server only listens on localhost, and only when the `api_enabled` setting
+//! is on. Every request must carry the `api_token` setting as an
+//! `Authorization: Bearer <token>` header. The `/api/events` websocket may give it
+//! as a `token` query parameter instead, as browsers cannot add headers to
+//! websockets. (Query strings end up in logs and histories, so nothing else
+//! accepts them.)
+//!
+//! | Method | Path                  | Does                                               |
+//! |--------|-----------------------|----------------------------------------------------|
+//! | GET    | `/api/feed`           | The current feed's kind and events (`?limit=N`)    |
+//! | POST   | `/api/feed`           | Switch feeds, given an [ApiFeed]                   |
+//! | POST   | `/api/command`        | Send the Overlord an [ApiCommand]                  |
+//! | GET    | `/api/event/<id>`     | An event, by hex or `note1` id                     |
+//! | GET    | `/api/person/<key>`   | A person, by hex or `npub1` public key             |
+//! | GET    | `/api/settings`       | All settings the API exposes                       |
+//! | GET    | `/api/settings/<name>`| One setting                                        |
+//! | PUT    | `/api/settings/<name>`| Change one (writable) setting, given its JSON value|
+//! | GET    | `/api/events`         | A websocket streaming each newly processed event   |
+
+mod command;
+pub use command::{ApiCommand, ApiFeed};
+
+mod settings;
+
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::storage::{PersonTable, Table};
+use crate::RunState;
+use futures::{SinkExt, StreamExt};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use nostr_types::{Event, Id, PublicKey};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::WebSocketStream;
+use tungstenite::protocol::Role;
+use tungstenite::Message;
+
+// Largest request body we accept
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+// Most feed events returned at once
+const MAX_FEED_EVENTS: usize = 1000;
+
+/// Make a new random API token
+pub fn new_token() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+/// Start the API server in the background, if it is enabled
+pub(crate) fn start() {
+    if !GLOBALS.storage.read_setting_api_enabled() {
+        return;
+    }
+
+    std::mem::drop(tokio::spawn(async move {
+        if let Err(e) = run().await {
+            tracing::error!("API server: {}", e);
+        }
+    }));
+}
+
+async fn run() -> Result<(), Error> {
+    if GLOBALS.storage.read_setting_api_token().is_empty() {
+        GLOBALS
+            .storage
+            .write_setting_api_token(&new_token(), None)?;
+    }
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], GLOBALS.storage.read_setting_api_port()));
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("API server listening on http://{}", addr);
+
+    let mut read_runstate = GLOBALS.read_runstate.clone();
+    read_runstate.mark_unchanged();
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!("API server: {}", e);
+                        continue;
+                    }
+                };
+                std::mem::drop(tokio::spawn(async move {
+                    let connection = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service_fn(handle))
+                        .with_upgrades();
+                    if let Err(e) = connection.await {
+                        tracing::debug!("API connection: {}", e);
+                    }
+                }));
+            },
+            _ = read_runstate.wait_for(|runstate| *runstate == RunState::ShuttingDown) => break,
+        }
+    }
+
+    tracing::info!("API server stopped");
+    Ok(())
+}
+
+type ApiResponse = Response<Full<Bytes>>;
+
+async fn handle(req: Request<Incoming>) -> Result<ApiResponse, Infallible> {
+    if !authorized(&req) {
+        return Ok(error_response(
+            StatusCode::UNAUTHORIZED,
+            "bad or missing token",
+        ));
+    }
+
+    let path: Vec<String> = req
+        .uri()
+        .path()
+        .trim_matches('/')
+        .split('/')
+        .map(|s| s.to_owned())
+        .collect();
+    let path: Vec<&str> = path.iter().map(|s| s.as_str()).collect();
+    let method = req.method().clone();
+
+    let result = match (&method, path.as_slice()) {
+        (&Method::GET, ["api", "events"]) => return Ok(stream_events(req)),
+        (&Method::GET, ["api", "feed"]) => get_feed(&req),
+        (&Method::POST, ["api", "feed"]) => switch_feed(req).await,
+        (&Method::POST, ["api", "command"]) => command(req).await,
+        (&Method::GET, ["api", "event", id]) => get_event(id),
+        (&Method::GET, ["api", "person", pubkey]) => get_person(pubkey),
+        (&Method::GET, ["api", "settings"]) => Ok(json_response(settings::read_all())),
+        (&Method::GET, ["api", "settings", name]) => match settings::read(name) {
+            Some(value) => Ok(json_response(value)),
+            None => Ok(error_response(StatusCode::NOT_FOUND, "no such setting")),
+        },
+        (&Method::PUT, ["api", "settings", name]) => put_setting(name, req).await,
+        _ => Ok(error_response(StatusCode::NOT_FOUND, "no such endpoint")),
+    };
+
+    Ok(result.unwrap_or_else(|e| error_response(StatusCode::BAD_REQUEST, &e.to_string())))
+}
+
+fn authorized(req: &Request<Incoming>) -> bool {
+    let expected = GLOBALS.storage.read_setting_api_token();
+    if expected.is_empty() {
+        return false;
+    }
+
+    let from_header = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim().to_owned());
+    let from_query = if is_events_upgrade(req.method(), req.uri().path(), req.headers()) {
+        req.uri().query().and_then(|q| query_param(q, "token"))
+    } else {
+        None
+    };
+
+    match from_header.or(from_query) {
+        Some(token) => constant_time_eq(token.as_bytes(), expected.as_bytes()),
+        None => false,
+    }
+}
+
+// Whether this is the `/api/events` websocket upgrade, the only request that may
+// carry its token in the query
+fn is_events_upgrade(method: &Method, path: &str, headers: &hyper::HeaderMap) -> bool {
+    method == Method::GET
+        && path.trim_matches('/') == "api/events"
+        && headers
+            .get(hyper::header::UPGRADE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.eq_ignore_ascii_case("websocket"))
+            .unwrap_or(false)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+async fn read_json<T: serde::de::DeserializeOwned>(req: Request<Incoming>) -> Result<T, Error> {
+    let body = match Limited::new(req.into_body(), MAX_BODY_BYTES)
+        .collect()
+        .await
+    {
+        Ok(body) => body.to_bytes(),
+        Err(e) => return Err(format!("reading request: {}", e).into()),
+    };
+    Ok(serde_json::from_slice(&body)?)
+}
+
+fn json_response(value: Value) -> ApiResponse {
+    let mut response = Response::new(Full::new(Bytes::from(value.to_string())));
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+fn error_response(status: StatusCode, message: &str) -> ApiResponse {
+    let mut response = json_response(json!({ "error": message }));
+    *response.status_mut() = status;
+    response
+}
+
+fn get_feed(req: &Request<Incoming>) -> Result<ApiResponse, Error> {
+    let limit = match req.uri().query().and_then(|q| query_param(q, "limit")) {
+        Some(limit) => limit.parse::<usize>()?.min(MAX_FEED_EVENTS),
+        None => MAX_FEED_EVENTS,
+    };
+
+    let mut events: Vec<Event> = Vec::new();
+    for id in GLOBALS.feed.get_feed_events().into_iter().take(limit) {
+        if let Some(event) = GLOBALS.storage.read_event(id)? {
+            events.push(event);
+        }
+    }
+
+    Ok(json_response(json!({
+        "feed": GLOBALS.feed.get_feed_kind().to_string(),
+        "events": events,
+    })))
+}
+
+async fn switch_feed(req: Request<Incoming>) -> Result<ApiResponse, Error> {
+    let feed: ApiFeed = read_json(req).await?;
+    GLOBALS.feed.switch_feed(feed.into_feed_kind()?);
+    Ok(json_response(json!({ "ok": true })))
+}
+
+async fn command(req: Request<Incoming>) -> Result<ApiResponse, Error> {
+    let command: ApiCommand = read_json(req).await?;
+    GLOBALS.to_overlord.send(command.into_message()?)?;
+    Ok(json_response(json!({ "ok": true })))
+}
+
+fn get_event(id: &str) -> Result<ApiResponse, Error> {
+    let id = match Id::try_from_hex_string(id) {
+        Ok(id) => id,
+        Err(_) => Id::try_from_bech32_string(id)?,
+    };
+    match GLOBALS.storage.read_event(id)? {
+        Some(event) => Ok(json_response(serde_json::to_value(event)?)),
+        None => Ok(error_response(StatusCode::NOT_FOUND, "no such event")),
+    }
+}
+
+fn get_person(pubkey: &str) -> Result<ApiResponse, Error> {
+    let pubkey = match PublicKey::try_from_hex_string(pubkey, true) {
+        Ok(pubkey) => pubkey,
+        Err(_) => PublicKey::try_from_bech32_string(pubkey, true)?,
+    };
+    match PersonTable::read_record(pubkey, None)? {
+        Some(person) => Ok(json_response(serde_json::to_value(person)?)),
+        None => Ok(error_response(StatusCode::NOT_FOUND, "no such person")),
+    }
+}
+
+async fn put_setting(name: &str, req: Request<Incoming>) -> Result<ApiResponse, Error> {
+    if settings::is_read_only(name) {
+        return Ok(error_response(
+            StatusCode::FORBIDDEN,
+            "setting is read-only through the API",
+        ));
+    }
+    let value: Value = read_json(req).await?;
+    if settings::write(name, value)? {
+        Ok(json_response(json!({ "ok": true })))
+    } else {
+        Ok(error_response(StatusCode::NOT_FOUND, "no such setting"))
+    }
+}
+
+// Upgrade to a websocket, and send each newly processed event down it
+fn stream_events(req: Request<Incoming>) -> ApiResponse {
+    let key = match req.headers().get(hyper::header::SEC_WEBSOCKET_KEY) {
+        Some(key) => tungstenite::handshake::derive_accept_key(key.as_bytes()),
+        None => return error_response(StatusCode::BAD_REQUEST, "expected a websocket"),
+    };
+
+    std::mem::drop(tokio::spawn(async move {
+        let upgraded = match hyper::upgrade::on(req).await {
+            Ok(upgraded) => upgraded,
+            Err(e) => {
+                tracing::warn!("API websocket upgrade: {}", e);
+                return;
+            }
+        };
+        let mut websocket =
+            WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None).await;
+        let mut new_events = GLOBALS.new_events.subscribe();
+        let mut read_runstate = GLOBALS.read_runstate.clone();
+        read_runstate.mark_unchanged();
+
+        loop {
+            tokio::select! {
+                event = new_events.recv() => {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(n)) => {
+                            tracing::warn!("API websocket fell behind, skipped {} events", n);
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+                    let json = match serde_json::to_string(&event) {
+                        Ok(json) => json,
+                        Err(_) => continue,
+                    };
+                    if websocket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                },
+                message = websocket.next() => match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    _ => {}
+                },
+                _ = read_runstate.wait_for(|runstate| *runstate == RunState::ShuttingDown) => break,
+            }
+        }
+
+        let _ = websocket.close(None).await;
+    }));
+
+    let mut response = Response::new(Full::new(Bytes::new()));
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(
+        hyper::header::CONNECTION,
+        hyper::header::HeaderValue::from_static("upgrade"),
+    );
+    headers.insert(
+        hyper::header::UPGRADE,
+        hyper::header::HeaderValue::from_static("websocket"),
+    );
+    if let Ok(accept) = hyper::header::HeaderValue::from_str(&key) {
+        headers.insert(hyper::header::SEC_WEBSOCKET_ACCEPT, accept);
+    }
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::header::{HeaderValue, UPGRADE};
+    use hyper::HeaderMap;
+
+    #[test]
+    fn test_is_events_upgrade() {
+        let mut upgrade = HeaderMap::new();
+        upgrade.insert(UPGRADE, HeaderValue::from_static("websocket"));
+        let plain = HeaderMap::new();
+
+        assert!(is_events_upgrade(&Method::GET, "/api/events", &upgrade));
+        assert!(!is_events_upgrade(&Method::GET, "/api/events", &plain));
+        assert!(!is_events_upgrade(&Method::GET, "/api/feed", &upgrade));
+        assert!(!is_events_upgrade(&Method::PUT, "/api/events", &upgrade));
+    }
+}
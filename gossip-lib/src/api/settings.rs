@@ -0,0 +1,111 @@
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use paste::paste;
+use serde_json::Value;
+
+// Settings the API can read, and of those the ones it can also write, by name.
+// Settings that hold keys or the API token itself are left out. Settings that
+// say where our files get sent (the media servers) are read-only, so that API
+// clients cannot redirect uploads.
+macro_rules! api_settings {
+    (
+        read_only: [$($ro_field:ident),* $(,)?],
+        read_write: [$($field:ident),* $(,)?] $(,)?
+    ) => {
+        paste! {
+            pub(super) const SETTINGS: &[&str] = &[
+                $(stringify!($ro_field),)*
+                $(stringify!($field),)*
+            ];
+
+            pub(super) fn read(name: &str) -> Option<Value> {
+                match name {
+                    $(
+                        stringify!($ro_field) => {
+                            serde_json::to_value(GLOBALS.storage.[<read_setting_ $ro_field>]()).ok()
+                        }
+                    )*
+                    $(
+                        stringify!($field) => {
+                            serde_json::to_value(GLOBALS.storage.[<read_setting_ $field>]()).ok()
+                        }
+                    )*
+                    _ => None,
+                }
+            }
+
+            pub(super) fn is_read_only(name: &str) -> bool {
+                [$(stringify!($ro_field)),*].contains(&name)
+            }
+
+            // Returns false if there is no such setting, or it is read-only
+            pub(super) fn write(name: &str, value: Value) -> Result<bool, Error> {
+                match name {
+                    $(
+                        stringify!($field) => {
+                            let value = serde_json::from_value(value)?;
+                            GLOBALS.storage.[<write_setting_ $field>](&value, None)?;
+                            Ok(true)
+                        }
+                    )*
+                    _ => Ok(false),
+                }
+            }
+        }
+    };
+}
+
+api_settings!(
+    read_only: [media_server, media_server_blossom],
+    read_write: [
+        offline,
+        load_avatars,
+        load_media,
+        check_nip05,
+        automatically_fetch_metadata,
+        relay_connection_requires_approval,
+        relay_auth_requires_approval,
+        num_relays_per_person,
+        max_relays,
+        load_more_count,
+        reposts,
+        show_long_form,
+        show_mentions,
+        direct_messages,
+        future_allowance_secs,
+        hide_mutes_entirely,
+        reactions,
+        enable_zap_receipts,
+        zap_default_sats,
+        fetch_counts,
+        show_media,
+        approve_content_warning,
+        show_deleted_events,
+        pow,
+        set_client_tag,
+        set_user_agent,
+        relay_list_becomes_stale_minutes,
+        metadata_becomes_stale_minutes,
+        nip05_becomes_stale_if_valid_hours,
+        nip05_becomes_stale_if_invalid_minutes,
+        avatar_becomes_stale_hours,
+        media_becomes_stale_hours,
+        use_negentropy,
+        fetcher_connect_timeout_sec,
+        fetcher_timeout_sec,
+        fetcher_max_requests_per_host,
+        prune_period_days,
+        cache_prune_period_days,
+        cache_quota_mb,
+        avoid_spam_on_unsafe_relays,
+    ],
+);
+
+/// All the settings the API exposes, as a JSON object
+pub(super) fn read_all() -> Value {
+    let map: serde_json::Map<String, Value> = SETTINGS
+        .iter()
+        .filter_map(|name| read(name).map(|value| (name.to_string(), value)))
+        .collect();
+    Value::Object(map)
+}
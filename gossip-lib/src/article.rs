@@ -0,0 +1,138 @@
+//! Long-form articles (NIP-23)
+
+use nostr_types::{Event, EventKind, NAddr, PublicKey, Tag, Unixtime};
+
+// Slugs made from titles are cut to this many characters
+const MAX_SLUG_LEN: usize = 48;
+
+/// A long-form article, as carried by a kind 30023 event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Article {
+    /// The `d` tag, which names the article among its author's articles. Edits
+    /// keep it so that they replace the earlier version.
+    pub d: String,
+
+    pub title: String,
+
+    pub summary: String,
+
+    /// The URL of a header image
+    pub image: Option<String>,
+
+    /// When the article was first published. Edits keep this.
+    pub published_at: Option<Unixtime>,
+
+    pub hashtags: Vec<String>,
+
+    /// The markdown body
+    pub content: String,
+}
+
+impl Article {
+    /// Read an article from a long-form content event
+    pub fn from_event(event: &Event) -> Option<Article> {
+        if event.kind != EventKind::LongFormContent {
+            return None;
+        }
+        Some(Self::from_tags(&event.tags, &event.content))
+    }
+
+    fn from_tags(tags: &[Tag], content: &str) -> Article {
+        let mut article = Article {
+            content: content.to_owned(),
+            ..Default::default()
+        };
+        for tag in tags {
+            match tag.tagname() {
+                "d" => article.d = tag.value().to_owned(),
+                "title" => article.title = tag.value().to_owned(),
+                "summary" => article.summary = tag.value().to_owned(),
+                "image" if !tag.value().is_empty() => article.image = Some(tag.value().to_owned()),
+                "published_at" => {
+                    article.published_at = tag.value().trim().parse::<i64>().ok().map(Unixtime)
+                }
+                "t" => {
+                    if let Ok(hashtag) = tag.parse_hashtag() {
+                        article.hashtags.push(hashtag);
+                    }
+                }
+                _ => {}
+            }
+        }
+        article
+    }
+
+    /// The tags for an event carrying this article
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = vec![Tag::new_identifier(self.d.clone())];
+        if !self.title.is_empty() {
+            tags.push(Tag::new_title(self.title.clone()));
+        }
+        if !self.summary.is_empty() {
+            tags.push(Tag::new(&["summary", &self.summary]));
+        }
+        if let Some(image) = &self.image {
+            tags.push(Tag::new(&["image", image]));
+        }
+        if let Some(published_at) = self.published_at {
+            tags.push(Tag::new(&["published_at", &published_at.0.to_string()]));
+        }
+        for hashtag in &self.hashtags {
+            tags.push(Tag::new_hashtag(hashtag.clone()));
+        }
+        tags
+    }
+
+    /// The address of this article by this author
+    pub fn naddr(&self, author: PublicKey) -> NAddr {
+        NAddr {
+            d: self.d.clone(),
+            relays: vec![],
+            kind: EventKind::LongFormContent,
+            author,
+        }
+    }
+}
+
+/// A `d` tag for a new article, made from its title
+pub fn slug(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars().flat_map(|c| c.to_lowercase()) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let mut slug: String = slug.chars().take(MAX_SLUG_LEN).collect();
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    if slug.is_empty() {
+        // Untitled, so just make it unique
+        slug = format!("article-{}", Unixtime::now().0);
+    }
+    slug
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_article_tags() {
+        let article = Article {
+            d: slug("Hello, World: a Test!"),
+            title: "Hello, World: a Test!".to_owned(),
+            summary: "Just testing".to_owned(),
+            image: Some("https://example.com/a.png".to_owned()),
+            published_at: Some(Unixtime(1700000000)),
+            hashtags: vec!["nostr".to_owned()],
+            content: "# Hello\n\nWorld".to_owned(),
+        };
+        assert_eq!(article.d, "hello-world-a-test");
+
+        let tags = article.tags();
+        assert_eq!(Article::from_tags(&tags, &article.content), article);
+    }
+}
@@ -0,0 +1,192 @@
+// Blurhash (https://blurha.sh) is a short string describing a blurred version of
+// an image, used as a placeholder while the image loads.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+
+const BASE83: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// Compute the blurhash of an image, with this many components across and down
+pub(crate) fn encode(image: &DynamicImage, components_x: u32, components_y: u32) -> String {
+    let image = image.to_rgb8();
+    let (width, height) = image.dimensions();
+    let linear: Vec<[f32; 3]> = image
+        .pixels()
+        .map(|p| {
+            [
+                srgb_to_linear(p[0] as u32),
+                srgb_to_linear(p[1] as u32),
+                srgb_to_linear(p[2] as u32),
+            ]
+        })
+        .collect();
+
+    let mut factors: Vec<[f32; 3]> = Vec::new();
+    for j in 0..components_y {
+        for i in 0..components_x {
+            let normalisation = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let mut factor = [0.0f32; 3];
+            for y in 0..height {
+                for x in 0..width {
+                    let basis = normalisation
+                        * (std::f32::consts::PI * i as f32 * x as f32 / width as f32).cos()
+                        * (std::f32::consts::PI * j as f32 * y as f32 / height as f32).cos();
+                    let pixel = linear[(y * width + x) as usize];
+                    for (f, p) in factor.iter_mut().zip(pixel) {
+                        *f += basis * p;
+                    }
+                }
+            }
+            let scale = 1.0 / (width * height).max(1) as f32;
+            factors.push([factor[0] * scale, factor[1] * scale, factor[2] * scale]);
+        }
+    }
+
+    let dc = factors[0];
+    let ac = &factors[1..];
+
+    let mut hash = String::new();
+    let size_flag = (components_x - 1) + (components_y - 1) * 9;
+    base83(&mut hash, size_flag, 1);
+
+    let maximum_value = if ac.is_empty() {
+        base83(&mut hash, 0, 1);
+        1.0
+    } else {
+        let actual_maximum = ac
+            .iter()
+            .flat_map(|f| f.iter())
+            .fold(0.0f32, |max, v| max.max(v.abs()));
+        let quantised_maximum = (actual_maximum * 166.0 - 0.5).floor().clamp(0.0, 82.0) as u32;
+        base83(&mut hash, quantised_maximum, 1);
+        (quantised_maximum + 1) as f32 / 166.0
+    };
+
+    let dc_value =
+        (linear_to_srgb(dc[0]) << 16) + (linear_to_srgb(dc[1]) << 8) + linear_to_srgb(dc[2]);
+    base83(&mut hash, dc_value, 4);
+
+    for factor in ac {
+        let quantise = |v: f32| {
+            let v = v / maximum_value;
+            (v.signum() * v.abs().sqrt() * 9.0 + 9.5)
+                .floor()
+                .clamp(0.0, 18.0) as u32
+        };
+        let value = quantise(factor[0]) * 19 * 19 + quantise(factor[1]) * 19 + quantise(factor[2]);
+        base83(&mut hash, value, 2);
+    }
+
+    hash
+}
+
+/// Render a blurhash as an image of the given size, or None if the hash is invalid
+pub(crate) fn decode(hash: &str, width: u32, height: u32) -> Option<RgbaImage> {
+    if !hash.is_ascii() || hash.len() < 6 || width == 0 || height == 0 {
+        return None;
+    }
+
+    let size_flag = decode_base83(&hash[0..1])?;
+    let components_y = size_flag / 9 + 1;
+    let components_x = size_flag % 9 + 1;
+    if hash.len() != 4 + 2 * (components_x * components_y) as usize {
+        return None;
+    }
+
+    let quantised_maximum = decode_base83(&hash[1..2])?;
+    let maximum_value = (quantised_maximum + 1) as f32 / 166.0;
+
+    let mut colors: Vec<[f32; 3]> = Vec::with_capacity((components_x * components_y) as usize);
+    let dc = decode_base83(&hash[2..6])?;
+    colors.push([
+        srgb_to_linear(dc >> 16),
+        srgb_to_linear((dc >> 8) & 255),
+        srgb_to_linear(dc & 255),
+    ]);
+    for i in 1..(components_x * components_y) as usize {
+        let ac = decode_base83(&hash[4 + i * 2..6 + i * 2])?;
+        let unquantise = |q: u32| {
+            let v = (q as f32 - 9.0) / 9.0;
+            v.signum() * v * v * maximum_value
+        };
+        colors.push([
+            unquantise(ac / (19 * 19)),
+            unquantise((ac / 19) % 19),
+            unquantise(ac % 19),
+        ]);
+    }
+
+    let mut image = RgbaImage::new(width, height);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let mut linear = [0.0f32; 3];
+        for j in 0..components_y {
+            for i in 0..components_x {
+                let basis = (std::f32::consts::PI * x as f32 * i as f32 / width as f32).cos()
+                    * (std::f32::consts::PI * y as f32 * j as f32 / height as f32).cos();
+                let color = colors[(i + j * components_x) as usize];
+                for (l, c) in linear.iter_mut().zip(color) {
+                    *l += c * basis;
+                }
+            }
+        }
+        *pixel = Rgba([
+            linear_to_srgb(linear[0]) as u8,
+            linear_to_srgb(linear[1]) as u8,
+            linear_to_srgb(linear[2]) as u8,
+            255,
+        ]);
+    }
+    Some(image)
+}
+
+fn base83(output: &mut String, value: u32, length: u32) {
+    for i in 1..=length {
+        let digit = (value / 83u32.pow(length - i)) % 83;
+        output.push(BASE83[digit as usize] as char);
+    }
+}
+
+fn decode_base83(input: &str) -> Option<u32> {
+    input.bytes().try_fold(0u32, |value, byte| {
+        let digit = BASE83.iter().position(|b| *b == byte)?;
+        Some(value * 83 + digit as u32)
+    })
+}
+
+fn srgb_to_linear(value: u32) -> f32 {
+    let v = value as f32 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u32 {
+    let v = value.clamp(0.0, 1.0);
+    if v <= 0.0031308 {
+        (v * 12.92 * 255.0 + 0.5) as u32
+    } else {
+        ((1.055 * v.powf(1.0 / 2.4) - 0.055) * 255.0 + 0.5) as u32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_blurhash() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(16, 16, Rgb([255, 255, 255])));
+        let hash = encode(&image, 4, 3);
+        assert_eq!(hash, "LKTSUA~qfQ~q~qoffQoffQfQfQfQ");
+
+        // Decoding gives back an image of the requested size, white at the corner
+        let decoded = decode(&hash, 8, 6).unwrap();
+        assert_eq!(decoded.dimensions(), (8, 6));
+        assert!(decoded.get_pixel(0, 0)[0] > 240);
+
+        assert!(decode("LKTSUA~q", 8, 6).is_none());
+    }
+}
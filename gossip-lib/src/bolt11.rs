@@ -0,0 +1,140 @@
+//! Just enough BOLT-11 lightning invoice parsing to check zaps: the amount and
+//! the description hash.
+
+use nostr_types::MilliSatoshi;
+
+/// The parts of a BOLT-11 lightning invoice that we check zaps against
+#[derive(Debug)]
+pub(crate) struct Bolt11 {
+    /// The amount, if the invoice has one
+    pub amount: Option<MilliSatoshi>,
+
+    /// The hash of the description (for zaps, the zap request), if it has one
+    pub description_hash: Option<[u8; 32]>,
+}
+
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+impl Bolt11 {
+    /// Parse an invoice, or None if it is invalid
+    pub(crate) fn parse(invoice: &str) -> Option<Bolt11> {
+        let invoice = invoice.trim().to_lowercase();
+        let separator = invoice.rfind('1')?;
+        let hrp = &invoice[..separator];
+        let data: Vec<u8> = invoice[separator + 1..]
+            .bytes()
+            .map(|c| BECH32_CHARSET.iter().position(|&x| x == c).map(|p| p as u8))
+            .collect::<Option<Vec<u8>>>()?;
+
+        if !hrp.starts_with("ln") || !bech32_checksum_ok(hrp, &data) {
+            return None;
+        }
+
+        // Skip the 35-bit timestamp, and leave off the signature and the checksum
+        if data.len() < 7 + 104 + 6 {
+            return None;
+        }
+        let fields = &data[7..data.len() - 104 - 6];
+
+        let mut description_hash: Option<[u8; 32]> = None;
+        let mut pos = 0;
+        while pos + 3 <= fields.len() {
+            let tag = fields[pos];
+            let len = (fields[pos + 1] as usize) * 32 + fields[pos + 2] as usize;
+            let value = fields.get(pos + 3..pos + 3 + len)?;
+            // 'h', the hash of the description, as 52 groups of 5 bits
+            if tag == 23 && len == 52 {
+                description_hash = five_to_eight(value).get(..32)?.try_into().ok();
+            }
+            pos += 3 + len;
+        }
+
+        Some(Bolt11 {
+            amount: bolt11_amount(hrp),
+            description_hash,
+        })
+    }
+}
+
+// Like "lnbc2500u", after the currency prefix comes an optional amount in bitcoin
+// with an optional multiplier
+fn bolt11_amount(hrp: &str) -> Option<MilliSatoshi> {
+    let rest = hrp
+        .strip_prefix("ln")?
+        .trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let digits_end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let number: u64 = rest[..digits_end].parse().ok()?;
+    let msats = match &rest[digits_end..] {
+        "" => number.checked_mul(100_000_000_000)?,
+        "m" => number.checked_mul(100_000_000)?,
+        "u" => number.checked_mul(100_000)?,
+        "n" => number.checked_mul(100)?,
+        "p" if number % 10 == 0 => number / 10,
+        _ => return None,
+    };
+    Some(MilliSatoshi(msats))
+}
+
+fn bech32_checksum_ok(hrp: &str, data: &[u8]) -> bool {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let values = hrp
+        .bytes()
+        .map(|b| b >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.bytes().map(|b| b & 31))
+        .chain(data.iter().copied());
+    let mut checksum: u32 = 1;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum == 1
+}
+
+fn five_to_eight(groups: &[u8]) -> Vec<u8> {
+    let mut output: Vec<u8> = Vec::with_capacity(groups.len() * 5 / 8);
+    let mut accumulator: u32 = 0;
+    let mut bits = 0;
+    for group in groups {
+        accumulator = (accumulator << 5) | *group as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((accumulator >> bits) as u8);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_bolt11() {
+        // From the BOLT-11 examples
+        let invoice = "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqs9qrsgq7ea976txfraylvgzuxs8kgcw23ezlrszfnh8r6qtfpr6cxga50aj6txm9rxrydzd06dfeawfk6swupvz4erwnyutnjq7x39ymw6j38gp7ynn44";
+        let bolt11 = Bolt11::parse(invoice).unwrap();
+        assert_eq!(bolt11.amount, Some(MilliSatoshi(2_000_000_000)));
+        let description = "One piece of chocolate cake, one icecream cone, one pickle, one slice of swiss cheese, one slice of salami, one lollypop, one piece of cherry pie, one sausage, one cupcake, and one slice of watermelon";
+        let hash: [u8; 32] = Sha256::digest(description.as_bytes()).into();
+        assert_eq!(bolt11.description_hash, Some(hash));
+
+        // A corrupted invoice fails its checksum
+        assert!(Bolt11::parse(&invoice.replace("lnbc20m", "lnbc30m")).is_none());
+
+        assert_eq!(bolt11_amount("lnbc2500u"), Some(MilliSatoshi(250_000_000)));
+        assert_eq!(bolt11_amount("lntb10n"), Some(MilliSatoshi(1_000)));
+        assert_eq!(bolt11_amount("lnbc10p"), Some(MilliSatoshi(1)));
+        assert_eq!(bolt11_amount("lnbc1p"), None);
+        assert_eq!(bolt11_amount("lnbc"), None);
+    }
+}
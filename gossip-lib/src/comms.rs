@@ -1,14 +1,19 @@
+use crate::article::Article;
+use crate::counts::CountKey;
 use crate::dm_channel::DmChannel;
 use crate::misc::Private;
 use crate::nip46::{Approval, ParsedCommand};
 use crate::people::PersonList;
+use crate::post::ScheduledPost;
 use crate::relay::Relay;
+use crate::search::SearchQuery;
 use nostr_types::{
     Event, EventReference, Id, IdHex, Metadata, MilliSatoshi, NAddr, Profile, PublicKey, RelayUrl,
     Tag, UncheckedUrl, Unixtime,
 };
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
 
 /// This is a message sent to the Overlord. Tasks which take any amount of time,
 /// especially involving relays, are handled by the Overlord in this way. There is
@@ -42,6 +47,9 @@ pub enum ToOverlordMessage {
     /// Removess a bookmark, and publishes new bookmarks list
     BookmarkRm(EventReference),
 
+    /// Calls [cancel_scheduled_post](crate::Overlord::cancel_scheduled_post)
+    CancelScheduledPost(u64),
+
     /// Calls [change_passphrase](crate::Overlord::change_passphrase)
     ChangePassphrase { old: String, new: String },
 
@@ -59,6 +67,9 @@ pub enum ToOverlordMessage {
     /// Calls [delegation_reset](crate::Overlord::delegation_reset)
     DelegationReset,
 
+    /// Calls [delete_article](crate::Overlord::delete_article)
+    DeleteArticle(String),
+
     /// Calls [delete_person_list](crate::Overlord::delete_person_list)
     DeletePersonList(PersonList),
 
@@ -117,6 +128,9 @@ pub enum ToOverlordMessage {
     /// Calls [nip46_server_op_approval_response](crate::Overlord::nip46_server_op_approval_response)
     Nip46ServerOpApprovalResponse(PublicKey, ParsedCommand, Approval),
 
+    /// Calls [nwc_get_balance](crate::Overlord::nwc_get_balance)
+    NwcGetBalance,
+
     /// Calls [post](crate::Overlord::post)
     Post {
         content: String,
@@ -132,12 +146,21 @@ pub enum ToOverlordMessage {
     /// Calls [post_nip46_event](crate::Overlord::post_nip46_event)
     PostNip46Event(Event, Vec<RelayUrl>),
 
+    /// Calls [post_scheduled](crate::Overlord::post_scheduled)
+    PostScheduled(u64),
+
+    /// Calls [preview_prune](crate::Overlord::preview_prune)
+    PreviewPrune,
+
     /// Calls [prune_cache](crate::Overlord::prune_cache)
     PruneCache,
 
     /// Calls [prune_database](crate::Overlord::prune_database)
     PruneDatabase,
 
+    /// Calls [publish_article](crate::Overlord::publish_article)
+    PublishArticle(Article),
+
     /// Calls [push_person_list](crate::Overlord::push_person_list)
     PushPersonList(PersonList),
 
@@ -162,9 +185,25 @@ pub enum ToOverlordMessage {
     /// Calls [repost](crate::Overlord::repost)
     Repost(Id),
 
+    /// Calls [retry_post](crate::Overlord::retry_post)
+    RetryPost(Id),
+
+    /// Calls [schedule_post](crate::Overlord::schedule_post)
+    SchedulePost {
+        content: String,
+        tags: Vec<Tag>,
+        in_reply_to: Option<Id>,
+        annotation: bool,
+        dm_channel: Option<DmChannel>,
+        publish_at: Unixtime,
+    },
+
     /// Calls [search](crate::Overlord::search)
     Search(String),
 
+    /// Calls [search_more_notes](crate::Overlord::search_more_notes)
+    SearchMoreNotes,
+
     /// Calls [set_active_person](crate::Overlord::set_active_person)
     SetActivePerson(PublicKey),
 
@@ -214,9 +253,15 @@ pub enum ToOverlordMessage {
         merge: bool,
     },
 
+    /// Calls [update_scheduled_post](crate::Overlord::update_scheduled_post)
+    UpdateScheduledPost(ScheduledPost),
+
     /// Calls [update_relay](crate::Overlord::update_relay)
     UpdateRelay(Relay, Relay),
 
+    /// Calls [upload_media](crate::Overlord::upload_media)
+    UploadMedia(PathBuf),
+
     /// Calls [visible_notes_changed](crate::Overlord::visible_notes_changed)
     VisibleNotesChanged(Vec<Id>),
 
@@ -258,9 +303,11 @@ pub(crate) enum ToMinionPayloadDetail {
     AdvertiseRelayList(Box<Event>, Box<Event>),
     AuthApproved,
     AuthDeclined,
+    Count(Vec<CountKey>),
     FetchEvent(Id),
     FetchNAddr(NAddr),
     PostEvents(Vec<Event>),
+    Search(SearchQuery),
     Shutdown,
     SubscribeAugments(Vec<IdHex>),
     SubscribeConfig,
@@ -274,6 +321,7 @@ pub(crate) enum ToMinionPayloadDetail {
     SubscribeRootReplies(EventReference),
     SubscribeDmChannel(DmChannel),
     SubscribeNip46,
+    SubscribeNwc,
     TempSubscribeGeneralFeedChunk(Unixtime),
     TempSubscribePersonFeedChunk { pubkey: PublicKey, anchor: Unixtime },
     TempSubscribeInboxFeedChunk(Unixtime),
@@ -289,6 +337,7 @@ pub enum RelayConnectionReason {
     Config,
     Discovery,
     FetchAugments,
+    FetchCounts,
     FetchDirectMessages,
     FetchContacts,
     FetchEvent,
@@ -303,9 +352,12 @@ pub enum RelayConnectionReason {
     PostMetadata,
     PostMuteList,
     PostNostrConnect,
+    PostWalletConnect,
     ReadThread,
+    Search,
     SubscribePerson,
     SubscribeGlobal,
+    WalletConnect,
 }
 
 impl fmt::Display for RelayConnectionReason {
@@ -323,6 +375,7 @@ impl RelayConnectionReason {
             Config => "Reading our client configuration",
             FetchInbox => "Searching for inbox of us",
             FetchAugments => "Fetching events that augment other events (likes, zaps, deletions)",
+            FetchCounts => "Counting followers, replies or reactions",
             FetchDirectMessages => "Fetching direct messages",
             FetchEvent => "Fetching a particular event",
             FetchMetadata => "Fetching metadata for a person",
@@ -338,8 +391,11 @@ impl RelayConnectionReason {
             PostMetadata => "Posting our metadata",
             PostNostrConnect => "Posting nostrconnect",
             ReadThread => "Reading ancestors to build a thread",
+            Search => "Searching for notes and people",
             SubscribePerson => "Subscribe to the events of a person",
             SubscribeGlobal => "Subscribe to the global feed on a relay",
+            WalletConnect => "Nostr wallet connect",
+            PostWalletConnect => "Posting a wallet connect request",
         }
     }
 
@@ -350,6 +406,7 @@ impl RelayConnectionReason {
             Config => false,
             FetchInbox => true,
             FetchAugments => false,
+            FetchCounts => false,
             FetchDirectMessages => true,
             FetchEvent => false,
             FetchMetadata => false,
@@ -365,8 +422,11 @@ impl RelayConnectionReason {
             PostMetadata => false,
             PostNostrConnect => false,
             ReadThread => true,
+            Search => false,
             SubscribePerson => false,
             SubscribeGlobal => false,
+            WalletConnect => true,
+            PostWalletConnect => false,
         }
     }
 }
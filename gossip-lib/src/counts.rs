@@ -0,0 +1,227 @@
+use crate::comms::{RelayConnectionReason, RelayJob, ToMinionPayload, ToMinionPayloadDetail};
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::manager;
+use crate::relay::{self, Relay};
+use dashmap::{DashMap, DashSet};
+use nostr_types::{EventKind, Filter, PublicKeyHex, RelayUrl, Unixtime};
+use std::collections::HashMap;
+
+/// Something we ask relays to count
+pub type CountKey = crate::storage::types::CountKey1;
+
+/// The counts relays have given us for something
+pub type Count = crate::storage::types::Count1;
+
+/// How long a count is good for before we ask again
+pub const COUNT_FRESH_SECS: i64 = 60 * 30;
+
+// If no relay answered, don't ask again for this long
+const REQUEST_RETRY_SECS: i64 = 60 * 5;
+
+/// Counts of followers, replies and reactions, as reported by relays (NIP-45).
+///
+/// This lets us show those numbers without downloading every event.
+#[derive(Debug, Default)]
+pub struct Counts {
+    cache: DashMap<CountKey, Count>,
+    requested: DashMap<CountKey, Unixtime>,
+
+    // Counts wanted since we last asked relays (see maybe_fetch_counts)
+    wanted: DashSet<CountKey>,
+}
+
+impl Counts {
+    pub(crate) fn new() -> Counts {
+        Counts {
+            ..Default::default()
+        }
+    }
+
+    /// The count we have, if any. If it is missing or stale (and the `fetch_counts`
+    /// setting is on), relays are asked for a fresh one in the background.
+    ///
+    /// This is cheap enough to call every frame.
+    pub fn get(&self, key: CountKey) -> Option<Count> {
+        let now = Unixtime::now();
+
+        let cached = match self.cache.get(&key) {
+            Some(count) => Some(count.clone()),
+            None => {
+                // Only look in storage the first time
+                if self.requested.contains_key(&key) {
+                    None
+                } else {
+                    let stored = GLOBALS.storage.read_count(&key, None).ok().flatten();
+                    if let Some(count) = &stored {
+                        self.cache.insert(key, count.clone());
+                    }
+                    stored
+                }
+            }
+        };
+
+        let fresh = cached
+            .as_ref()
+            .map(|c| c.updated_at.0 + COUNT_FRESH_SECS > now.0)
+            .unwrap_or(false);
+        let recently_requested = self
+            .requested
+            .get(&key)
+            .map(|t| t.0 + REQUEST_RETRY_SECS > now.0)
+            .unwrap_or(false);
+        if !fresh && !recently_requested && GLOBALS.storage.read_setting_fetch_counts() {
+            self.wanted.insert(key);
+        }
+
+        cached
+    }
+
+    /// Ask relays for the counts wanted since the last time, in one job per relay.
+    /// Only relays we are already connected to are asked; counts they can't be
+    /// asked for are wanted again the next time they are shown.
+    ///
+    /// This is run periodically while online.
+    pub(crate) fn maybe_fetch_counts(&self) -> Result<(), Error> {
+        let now = Unixtime::now();
+        self.requested
+            .retain(|_, asked| asked.0 + REQUEST_RETRY_SECS > now.0);
+
+        let wanted: Vec<CountKey> = self.wanted.iter().map(|k| *k.key()).collect();
+        self.wanted.clear();
+        if wanted.is_empty() {
+            return Ok(());
+        }
+
+        let connected = connected_counting_relays()?;
+        if connected.is_empty() {
+            return Ok(());
+        }
+
+        let mut per_relay: HashMap<RelayUrl, Vec<CountKey>> = HashMap::new();
+        for key in wanted {
+            let relays = counting_relays(&key, &connected)?;
+            if relays.is_empty() {
+                continue;
+            }
+            self.requested.insert(key, now);
+            for url in relays {
+                per_relay.entry(url).or_default().push(key);
+            }
+        }
+
+        for (url, keys) in per_relay.drain() {
+            manager::engage_minion(
+                url,
+                vec![RelayJob {
+                    reason: RelayConnectionReason::FetchCounts,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Count(keys),
+                    },
+                }],
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Record a count given by a relay
+    pub(crate) fn record(
+        &self,
+        key: CountKey,
+        url: &RelayUrl,
+        count: u64,
+        approximate: bool,
+    ) -> Result<(), Error> {
+        let mut record = match self.cache.get(&key) {
+            Some(c) => c.clone(),
+            None => GLOBALS
+                .storage
+                .read_count(&key, None)?
+                .unwrap_or_else(|| Count::new(key)),
+        };
+
+        match record.relays.iter_mut().find(|(u, _)| u == url) {
+            Some(entry) => entry.1 = count,
+            None => record.relays.push((url.to_owned(), count)),
+        }
+        record.approximate |= approximate;
+        record.updated_at = Unixtime::now();
+
+        GLOBALS.storage.write_count(&record, None)?;
+        self.cache.insert(key, record);
+        Ok(())
+    }
+}
+
+/// The filter whose matches are counted
+pub(crate) fn count_filter(key: &CountKey) -> Filter {
+    match key {
+        CountKey::Followers(pubkey) => {
+            let pkh: PublicKeyHex = pubkey.into();
+            let mut filter = Filter {
+                kinds: vec![EventKind::ContactList],
+                ..Default::default()
+            };
+            filter.set_tag_values('p', vec![pkh.to_string()]);
+            filter
+        }
+        CountKey::Replies(id) => {
+            let mut filter = Filter {
+                kinds: vec![EventKind::TextNote],
+                ..Default::default()
+            };
+            filter.set_tag_values('e', vec![id.as_hex_string()]);
+            filter
+        }
+        CountKey::Reactions(id) => {
+            let mut filter = Filter {
+                kinds: vec![EventKind::Reaction],
+                ..Default::default()
+            };
+            filter.set_tag_values('e', vec![id.as_hex_string()]);
+            filter
+        }
+    }
+}
+
+// The relays we are connected to that say they support NIP-45
+fn connected_counting_relays() -> Result<Vec<RelayUrl>, Error> {
+    let mut relays: Vec<RelayUrl> = Vec::new();
+    let connected: Vec<RelayUrl> = GLOBALS
+        .connected_relays
+        .iter()
+        .map(|r| r.key().clone())
+        .collect();
+    for url in connected {
+        if let Some(relay) = GLOBALS.storage.read_relay(&url, None)? {
+            if relay.supports_nip(45) && !relay.should_avoid() {
+                relays.push(url);
+            }
+        }
+    }
+    Ok(relays)
+}
+
+// The relays to ask, of those we are connected to that can count
+fn counting_relays(key: &CountKey, connected: &[RelayUrl]) -> Result<Vec<RelayUrl>, Error> {
+    let mut candidates: Vec<RelayUrl> = Relay::choose_relay_urls(Relay::READ, |_| true)?;
+
+    match key {
+        // Contact lists that include someone tend to be sent to their inboxes
+        CountKey::Followers(pubkey) => {
+            candidates.extend(relay::get_all_pubkey_inboxes(*pubkey)?);
+        }
+        CountKey::Replies(id) | CountKey::Reactions(id) => {
+            if let Some(event) = GLOBALS.storage.read_event(*id)? {
+                candidates.extend(relay::relays_for_seeking_replies(&event)?);
+            }
+        }
+    }
+
+    candidates.sort();
+    candidates.dedup();
+    candidates.retain(|url| connected.contains(url));
+    Ok(candidates)
+}
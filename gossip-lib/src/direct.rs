@@ -2,7 +2,10 @@ use crate::{Error, ErrorKind};
 use base64::Engine;
 use http::Uri;
 use nostr_types::{ClientMessage, Event, Filter, RelayMessage, SubscriptionId};
+use std::net::TcpStream;
 use tungstenite::protocol::Message;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::WebSocket;
 
 pub fn fetch(url: &str, filters: Vec<Filter>) -> Result<Vec<Event>, Error> {
     tracing::info!("Fetching from {}", url);
@@ -30,7 +33,7 @@ pub fn fetch(url: &str, filters: Vec<Filter>) -> Result<Vec<Event>, Error> {
         .uri(uri)
         .body(())?;
 
-    let (mut websocket, _response) = tungstenite::connect(request)?;
+    let (mut websocket, _response) = connect(request)?;
 
     websocket.send(Message::Text(wire))?;
 
@@ -122,7 +125,7 @@ pub fn post(url: &str, event: Event) -> Result<(), Error> {
         .uri(uri)
         .body(())?;
 
-    let (mut websocket, _response) = tungstenite::connect(request)?;
+    let (mut websocket, _response) = connect(request)?;
 
     websocket.send(Message::Text(wire))?;
 
@@ -170,6 +173,30 @@ pub fn post(url: &str, event: Event) -> Result<(), Error> {
     Ok(())
 }
 
+// Connect the websocket, through the SOCKS5 proxy if one is set
+fn connect(
+    request: http::Request<()>,
+) -> Result<
+    (
+        WebSocket<MaybeTlsStream<TcpStream>>,
+        tungstenite::handshake::client::Response,
+    ),
+    Error,
+> {
+    let uri = request.uri().clone();
+    let host = match uri.host() {
+        Some(host) => host,
+        None => return Err(ErrorKind::UrlHasNoHostname.into()),
+    };
+    let stream = crate::proxy::connect_tcp_blocking(host, crate::proxy::websocket_port(&uri))?;
+    tungstenite::client_tls_with_config(request, stream, None, None).map_err(|e| match e {
+        tungstenite::HandshakeError::Failure(e) => e.into(),
+        tungstenite::HandshakeError::Interrupted(_) => {
+            ErrorKind::General("Websocket handshake interrupted".to_owned()).into()
+        }
+    })
+}
+
 fn url_to_host_and_uri(url: &str) -> Result<(String, Uri), Error> {
     let uri: http::Uri = url.parse::<http::Uri>()?;
     let authority = match uri.authority() {
@@ -6,6 +6,7 @@ use nostr_types::RelayUrl;
 #[derive(Debug)]
 pub enum ErrorKind {
     BadNostrConnectString,
+    BadWalletConnectString,
     BroadcastSend(String),
     BroadcastReceive(tokio::sync::broadcast::error::RecvError),
     CannotUpdateRelayUrl,
@@ -23,7 +24,9 @@ pub enum ErrorKind {
     KeySizeWrong,
     Lmdb(heed::Error),
     MaxRelaysReached,
+    MediaUploadFailed(String),
     MpscSend(tokio::sync::mpsc::error::SendError<ToOverlordMessage>),
+    Negentropy(negentropy::Error),
     Nip05KeyNotFound,
     Nip46CommandMissingId,
     Nip46CommandNotJsonObject,
@@ -32,6 +35,7 @@ pub enum ErrorKind {
     Nip46ParsingError(String, String),
     Nip46RelayNeeded,
     Nostr(nostr_types::Error),
+    NoMediaServer,
     NoPublicKey,
     NoPrivateKey,
     NoPrivateKeyForAuth(RelayUrl),
@@ -61,6 +65,7 @@ pub enum ErrorKind {
     Offline,
     ParseInt(std::num::ParseIntError),
     ParseBool(std::str::ParseBoolError),
+    ProxyRequired(String),
     RecordIsNotNewable,
     Regex(regex::Error),
     RelayRejectedUs,
@@ -68,6 +73,7 @@ pub enum ErrorKind {
     SerdeJson(serde_json::Error),
     ShuttingDown,
     SliceError(std::array::TryFromSliceError),
+    Socks(tokio_socks::Error),
     Speedy(speedy::Error),
     Svg(usvg::Error),
     TagNotIndexed(String),
@@ -80,6 +86,7 @@ pub enum ErrorKind {
     Usage(String, String), // error, usage line
     UsersCantUseNip17,
     Utf8Error(std::str::Utf8Error),
+    WalletNotConnected,
     Websocket(tungstenite::Error),
     WrongEventKind,
 }
@@ -104,6 +111,7 @@ impl std::fmt::Display for Error {
         }
         match &self.kind {
             BadNostrConnectString => write!(f, "Bad nostrconnect string"),
+            BadWalletConnectString => write!(f, "Bad wallet connect string"),
             BroadcastSend(s) => write!(f, "Error broadcasting: {s}"),
             BroadcastReceive(e) => write!(f, "Error receiving broadcast: {e}"),
             CannotUpdateRelayUrl => {
@@ -126,7 +134,9 @@ impl std::fmt::Display for Error {
                 f,
                 "Maximum relay connections reached, will not connect to another"
             ),
+            MediaUploadFailed(s) => write!(f, "Media upload failed: {s}"),
             MpscSend(e) => write!(f, "Error sending mpsc: {e}"),
+            Negentropy(e) => write!(f, "Negentropy: {e}"),
             Nip05KeyNotFound => write!(f, "NIP-05 public key not found"),
             Nip46CommandMissingId => write!(f, "NIP-46 command missing ID"),
             Nip46CommandNotJsonObject => write!(f, "NIP-46 command not a json object"),
@@ -135,6 +145,7 @@ impl std::fmt::Display for Error {
             Nip46ParsingError(_id, e) => write!(f, "NIP-46 parse error: {e}"),
             Nip46RelayNeeded => write!(f, "NIP-46 relay needed to respond."),
             Nostr(e) => write!(f, "Nostr: {e}"),
+            NoMediaServer => write!(f, "No media server is configured"),
             NoPublicKey => write!(f, "No public key identity available."),
             NoPrivateKey => write!(f, "No private key available."),
             NoPrivateKeyForAuth(u) => {
@@ -166,6 +177,7 @@ impl std::fmt::Display for Error {
             Offline => write!(f, "Offline"),
             ParseInt(e) => write!(f, "Bad integer: {e}"),
             ParseBool(e) => write!(f, "Bad bool: {e}"),
+            ProxyRequired(host) => write!(f, "{host} requires a SOCKS5 proxy, but none is set"),
             RecordIsNotNewable => write!(f, "Record is not newable"),
             Regex(e) => write!(f, "Regex: {e}"),
             RelayRejectedUs => write!(f, "Relay rejected us."),
@@ -173,6 +185,7 @@ impl std::fmt::Display for Error {
             SerdeJson(e) => write!(f, "SerdeJson Error: {e}"),
             ShuttingDown => write!(f, "Shutting down"),
             SliceError(e) => write!(f, "Slice: {e}"),
+            Socks(e) => write!(f, "SOCKS5 proxy: {e}"),
             Speedy(e) => write!(f, "Speedy: {e}"),
             Svg(e) => write!(f, "SVG: {e}"),
             TagNotIndexed(s) => write!(f, "Tag not indexed: {s}"),
@@ -185,6 +198,7 @@ impl std::fmt::Display for Error {
             Usage(e, u) => write!(f, "{}\n\nUsage: {}", e, u),
             UsersCantUseNip17 => write!(f, "User(s) can't use NIP-17 DMs"),
             Utf8Error(e) => write!(f, "UTF-8 error: {e}"),
+            WalletNotConnected => write!(f, "No wallet is connected"),
             Websocket(e) => write!(f, "Websocket: {e}"),
             WrongEventKind => write!(f, "Wrong event kind"),
         }
@@ -259,6 +273,12 @@ impl From<tokio::sync::mpsc::error::SendError<ToOverlordMessage>> for ErrorKind
     }
 }
 
+impl From<negentropy::Error> for ErrorKind {
+    fn from(e: negentropy::Error) -> ErrorKind {
+        ErrorKind::Negentropy(e)
+    }
+}
+
 impl From<nostr_types::Error> for ErrorKind {
     fn from(e: nostr_types::Error) -> ErrorKind {
         ErrorKind::Nostr(e)
@@ -331,6 +351,12 @@ impl From<std::array::TryFromSliceError> for ErrorKind {
     }
 }
 
+impl From<tokio_socks::Error> for ErrorKind {
+    fn from(e: tokio_socks::Error) -> ErrorKind {
+        ErrorKind::Socks(e)
+    }
+}
+
 impl From<speedy::Error> for ErrorKind {
     fn from(e: speedy::Error) -> ErrorKind {
         ErrorKind::Speedy(e)
@@ -4,6 +4,7 @@ pub use feed_kind::FeedKind;
 use crate::comms::{ToMinionMessage, ToMinionPayload, ToMinionPayloadDetail, ToOverlordMessage};
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
+use crate::mutes::MuteSet;
 use crate::people::PersonList;
 use dashmap::DashMap;
 use nostr_types::{Event, EventKind, EventReference, Filter, Id, NAddr, Unixtime};
@@ -388,7 +389,8 @@ impl Feed {
             }
             FeedKind::Global => {
                 let dismissed = GLOBALS.dismissed.read().await.clone();
-                let screen = |e: &Event| basic_screen(e, true, false, &dismissed);
+                let mutes = screened_mutes();
+                let screen = |e: &Event| basic_screen(e, true, false, &dismissed, &mutes);
                 let events = GLOBALS.storage.load_volatile_events(screen);
                 *self.current_feed_events.write() = events.iter().map(|e| e.id).collect();
             }
@@ -414,9 +416,11 @@ impl Feed {
         let now = Unixtime::now();
         let limit = GLOBALS.storage.read_setting_load_more_count() as usize;
         let dismissed = GLOBALS.dismissed.read().await.clone();
+        let mutes = screened_mutes();
 
-        let outer_screen =
-            |e: &Event| basic_screen(e, include_replies, include_dms, &dismissed) && screen(e);
+        let outer_screen = |e: &Event| {
+            basic_screen(e, include_replies, include_dms, &dismissed, &mutes) && screen(e)
+        };
 
         let mut before_filter = filter;
         let mut after_filter = before_filter.clone();
@@ -443,8 +447,24 @@ impl Feed {
     }
 }
 
+// Muted words, hashtags and threads are left out of feeds (and so notifications) only
+// if mutes are hidden entirely. Otherwise the UI shows them as muted.
+fn screened_mutes() -> MuteSet {
+    if GLOBALS.storage.read_setting_hide_mutes_entirely() {
+        GLOBALS.mutes.read().clone()
+    } else {
+        MuteSet::default()
+    }
+}
+
 #[inline]
-fn basic_screen(e: &Event, include_replies: bool, include_dms: bool, dismissed: &[Id]) -> bool {
+fn basic_screen(
+    e: &Event,
+    include_replies: bool,
+    include_dms: bool,
+    dismissed: &[Id],
+    mutes: &MuteSet,
+) -> bool {
     let now = Unixtime::now();
 
     e.created_at <= now
@@ -455,6 +475,7 @@ fn basic_screen(e: &Event, include_replies: bool, include_dms: bool, dismissed:
                 && e.kind != EventKind::GiftWrap))
         && !dismissed.contains(&e.id)
         && !e.is_annotation()
+        && !mutes.matches(e)
 }
 
 pub fn enabled_event_kinds() -> Vec<EventKind> {
@@ -1,17 +1,19 @@
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
+use crate::people::PersonList;
 use crate::profile::Profile;
+use crate::storage::{PersonTable, Table};
 use crate::USER_AGENT;
 use futures::stream::{FuturesUnordered, StreamExt};
-use nostr_types::{Unixtime, Url};
+use nostr_types::{UncheckedUrl, Unixtime, Url};
 use reqwest::header::ETAG;
 use reqwest::Client;
 use reqwest::StatusCode;
 use sha2::Digest;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
-use std::sync::atomic::Ordering;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::RwLock;
 use std::time::{Duration, SystemTime};
 
@@ -24,6 +26,32 @@ enum FetchState {
     // If it succeeds, it's fetch state is removed entirely from our url map
 }
 
+// A file in the cache
+#[derive(Copy, Clone, Debug)]
+struct CacheEntry {
+    size: u64,
+    last_access: SystemTime,
+}
+
+/// Statistics about the cache of fetched files
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CacheStats {
+    /// Number of files in the cache, and their total size
+    pub files: usize,
+    pub bytes: u64,
+
+    /// The size the cache is kept under (0 if unlimited)
+    pub quota_bytes: u64,
+
+    /// Requests answered from the cache, and those that were not
+    pub hits: usize,
+    pub misses: usize,
+
+    /// Files evicted to stay under the quota, and their total size
+    pub evicted_files: usize,
+    pub evicted_bytes: u64,
+}
+
 /// System that fetches HTTP resources
 #[derive(Debug, Default)]
 pub struct Fetcher {
@@ -40,6 +68,21 @@ pub struct Fetcher {
 
     // Here is where we put hosts into a penalty box to time them out
     penalty_box: RwLock<HashMap<String, Unixtime>>,
+
+    // Size and last access time of every cache file. Filesystems often do not
+    // track access times, so we do.
+    cache_index: RwLock<HashMap<PathBuf, CacheEntry>>,
+
+    cache_hits: AtomicUsize,
+    cache_misses: AtomicUsize,
+    evicted_files: AtomicUsize,
+    evicted_bytes: AtomicU64,
+
+    // Whether we are evicting files right now
+    evicting: AtomicBool,
+
+    // The quota setting (in MB) we last checked the cache against
+    checked_quota_mb: AtomicU64,
 }
 
 impl Fetcher {
@@ -55,6 +98,11 @@ impl Fetcher {
         *GLOBALS.fetcher.cache_dir.write().unwrap() = Profile::cache_dir(false)?;
         *GLOBALS.fetcher.tmp_cache_dir.write().unwrap() = Profile::cache_dir(true)?;
 
+        // Index what is already in the cache
+        if let Err(e) = GLOBALS.fetcher.index_cache() {
+            tracing::error!("Fetcher failed to index the cache: {e}");
+        }
+
         // Create client
         let connect_timeout = std::time::Duration::new(
             GLOBALS.storage.read_setting_fetcher_connect_timeout_sec(),
@@ -65,7 +113,7 @@ impl Fetcher {
             std::time::Duration::new(GLOBALS.storage.read_setting_fetcher_timeout_sec(), 0);
 
         *GLOBALS.fetcher.client.write().unwrap() = Some(
-            Client::builder()
+            crate::proxy::http_client_builder()?
                 .gzip(true)
                 .brotli(true)
                 .deflate(true)
@@ -99,6 +147,23 @@ impl Fetcher {
             .count()
     }
 
+    /// Statistics about the cache
+    pub fn cache_stats(&self) -> CacheStats {
+        let index = self.cache_index.read().unwrap();
+        CacheStats {
+            files: index.len(),
+            bytes: index.values().map(|e| e.size).sum(),
+            quota_bytes: GLOBALS
+                .storage
+                .read_setting_cache_quota_mb()
+                .saturating_mul(1_000_000),
+            hits: self.cache_hits.load(Ordering::Relaxed),
+            misses: self.cache_misses.load(Ordering::Relaxed),
+            evicted_files: self.evicted_files.load(Ordering::Relaxed),
+            evicted_bytes: self.evicted_bytes.load(Ordering::Relaxed),
+        }
+    }
+
     pub(crate) async fn process_queue(&self) {
         // Initialize if not already
         if GLOBALS.fetcher.client.read().unwrap().is_none() {
@@ -242,6 +307,8 @@ impl Fetcher {
                                         "FETCH {url}: Cache Hit age={}s",
                                         dur.as_secs()
                                     );
+                                    self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                                    self.touch_cache_file(&cache_file, contents.len() as u64);
                                     return Ok(Some(contents));
                                 }
                                 Err(e) => return Err(e.into()),
@@ -263,6 +330,7 @@ impl Fetcher {
             FetchState::Queued(use_temp_cache)
         };
         self.urls.write().unwrap().insert(url.to_owned(), state);
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
 
         tracing::debug!("FETCH {url}: Queued");
 
@@ -505,6 +573,7 @@ impl Fetcher {
         GLOBALS.bytes_read.fetch_add(bytes.len(), Ordering::Relaxed);
 
         // Write to the file
+        let size = bytes.len() as u64;
         if let Err(e) = tokio::fs::write(cache_file.as_path(), bytes).await {
             finish(
                 FailOutcome::Fail,
@@ -516,6 +585,7 @@ impl Fetcher {
         }
 
         tracing::debug!("FETCH {url}: Cached");
+        self.touch_cache_file(&cache_file, size);
 
         // Remove from host load
         self.decrement_host_load(&host);
@@ -526,6 +596,8 @@ impl Fetcher {
         }
 
         self.urls.write().unwrap().remove(&url);
+
+        self.enforce_quota().await;
     }
 
     fn cache_file(&self, url: &Url, tmp: bool) -> PathBuf {
@@ -607,6 +679,186 @@ impl Fetcher {
         }
     }
 
+    // Index the files in both caches, taking their access (or modification)
+    // times as the last time we used them
+    fn index_cache(&self) -> Result<(), Error> {
+        let mut index: HashMap<PathBuf, CacheEntry> = HashMap::new();
+        let dirs = [
+            self.cache_dir.read().unwrap().clone(),
+            self.tmp_cache_dir.read().unwrap().clone(),
+        ];
+        for dir in dirs {
+            for entry in fs::read_dir(dir.as_path())? {
+                let entry = entry?;
+                let path = entry.path();
+                // etag files go with their cache file
+                if path.extension().is_some() {
+                    continue;
+                }
+                if let Ok(metadata) = entry.metadata() {
+                    if metadata.is_dir() {
+                        continue;
+                    }
+                    let last_access = match metadata.accessed() {
+                        Ok(st) => st,
+                        Err(_) => metadata.modified()?,
+                    };
+                    index.insert(
+                        path,
+                        CacheEntry {
+                            size: metadata.len(),
+                            last_access,
+                        },
+                    );
+                }
+            }
+        }
+        *self.cache_index.write().unwrap() = index;
+        Ok(())
+    }
+
+    fn touch_cache_file(&self, path: &Path, size: u64) {
+        self.cache_index.write().unwrap().insert(
+            path.to_owned(),
+            CacheEntry {
+                size,
+                last_access: SystemTime::now(),
+            },
+        );
+    }
+
+    /// Check the cache against its quota if the quota setting has changed since
+    /// we last did, so that lowering it takes effect without waiting on a fetch
+    pub(crate) async fn enforce_changed_quota(&self) {
+        let quota_mb = GLOBALS.storage.read_setting_cache_quota_mb();
+        if self.checked_quota_mb.swap(quota_mb, Ordering::Relaxed) != quota_mb {
+            self.enforce_quota().await;
+        }
+    }
+
+    // If the cache is over quota, evict the least recently used files until it is
+    // comfortably under, sparing the ones we want to keep
+    async fn enforce_quota(&self) {
+        let quota = GLOBALS
+            .storage
+            .read_setting_cache_quota_mb()
+            .saturating_mul(1_000_000);
+        if quota == 0 {
+            return;
+        }
+        let bytes: u64 = self
+            .cache_index
+            .read()
+            .unwrap()
+            .values()
+            .map(|e| e.size)
+            .sum();
+        if bytes <= quota {
+            return;
+        }
+
+        // Only one eviction at a time
+        if self.evicting.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        // Go 10% under, so we don't do this on every fetch
+        let target = quota - quota / 10;
+
+        let protected =
+            match tokio::task::spawn_blocking(|| GLOBALS.fetcher.protected_cache_files()).await {
+                Ok(protected) => protected,
+                Err(e) => {
+                    tracing::error!("Cache eviction failed: {}", e);
+                    self.evicting.store(false, Ordering::Relaxed);
+                    return;
+                }
+            };
+
+        let mut candidates: Vec<(PathBuf, CacheEntry)> = self
+            .cache_index
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| !protected.contains(*path))
+            .map(|(path, entry)| (path.clone(), *entry))
+            .collect();
+        candidates.sort_by_key(|(_, entry)| entry.last_access);
+
+        let mut remaining = bytes;
+        let mut count: usize = 0;
+        for (path, entry) in candidates {
+            if remaining <= target {
+                break;
+            }
+            if let Err(e) = tokio::fs::remove_file(path.as_path()).await {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    tracing::warn!("Cache eviction of {}: {}", path.display(), e);
+                    continue;
+                }
+            }
+            let _ = tokio::fs::remove_file(path.with_extension("etag")).await;
+            self.cache_index.write().unwrap().remove(&path);
+            remaining = remaining.saturating_sub(entry.size);
+            count += 1;
+            self.evicted_files.fetch_add(1, Ordering::Relaxed);
+            self.evicted_bytes.fetch_add(entry.size, Ordering::Relaxed);
+        }
+
+        tracing::info!(
+            "Evicted {} files from the cache, now {} bytes (quota {})",
+            count,
+            remaining,
+            quota
+        );
+
+        self.evicting.store(false, Ordering::Relaxed);
+    }
+
+    // Cache files we never evict: avatars of people we follow, and media in
+    // notes we bookmarked
+    fn protected_cache_files(&self) -> HashSet<PathBuf> {
+        let mut urls: Vec<Url> = Vec::new();
+
+        match GLOBALS.storage.get_people_in_list(PersonList::Followed) {
+            Ok(followed) => {
+                for (pubkey, _) in followed {
+                    if let Ok(Some(person)) = PersonTable::read_record(pubkey, None) {
+                        if let Some(picture) = person.picture() {
+                            let url = UncheckedUrl(picture.to_string());
+                            if let Ok(url) = Url::try_from_unchecked_url(&url) {
+                                urls.push(url);
+                            }
+                        }
+                    }
+                }
+            }
+            Err(e) => tracing::error!("{}", e),
+        }
+
+        let bookmarks = GLOBALS.current_bookmarks.read().clone();
+        for id in bookmarks {
+            let event = match GLOBALS.storage.read_event(id) {
+                Ok(Some(event)) => event,
+                _ => continue,
+            };
+            urls.extend(
+                crate::imeta::media_descriptors(&event)
+                    .into_keys()
+                    .filter_map(|u| Url::try_from_unchecked_url(&UncheckedUrl(u)).ok()),
+            );
+            let mut finder = linkify::LinkFinder::new();
+            finder.kinds(&[linkify::LinkKind::Url]);
+            urls.extend(finder.links(&event.content).filter_map(|link| {
+                Url::try_from_unchecked_url(&UncheckedUrl(link.as_str().to_owned())).ok()
+            }));
+        }
+
+        urls.iter()
+            .flat_map(|url| [self.cache_file(url, false), self.cache_file(url, true)])
+            .collect()
+    }
+
     pub(crate) async fn prune(&self, age: Duration) -> Result<usize, Error> {
         let mut count: usize = 0;
         let cache_path = self.cache_dir.read().unwrap().to_owned();
@@ -617,13 +869,17 @@ impl Fetcher {
                     continue;
                 }
 
-                // FIXME - many filesystems do not track access times. We may want
-                //         to track these in LMDB (along with etags)
-                let file_time = match metadata.accessed() {
-                    Ok(st) => st,
-                    Err(_) => match metadata.modified() {
+                // Many filesystems do not track access times, so prefer our own
+                // record of when we last used it
+                let path = entry.path();
+                let file_time = match self.cache_index.read().unwrap().get(&path) {
+                    Some(cache_entry) => cache_entry.last_access,
+                    None => match metadata.accessed() {
                         Ok(st) => st,
-                        Err(_) => metadata.created()?,
+                        Err(_) => match metadata.modified() {
+                            Ok(st) => st,
+                            Err(_) => metadata.created()?,
+                        },
                     },
                 };
                 let file_age = match SystemTime::now().duration_since(file_time) {
@@ -631,7 +887,8 @@ impl Fetcher {
                     Err(_) => continue,
                 };
                 if file_age > age {
-                    tokio::fs::remove_file(entry.path().as_path()).await?;
+                    tokio::fs::remove_file(path.as_path()).await?;
+                    self.cache_index.write().unwrap().remove(&path);
                     count += 1;
                 }
             }
@@ -1,5 +1,6 @@
 use crate::bookmarks::BookmarkList;
 use crate::comms::{RelayJob, ToMinionMessage, ToOverlordMessage};
+use crate::counts::Counts;
 use crate::delegation::Delegation;
 use crate::error::Error;
 use crate::feed::Feed;
@@ -8,13 +9,18 @@ use crate::gossip_identity::GossipIdentity;
 use crate::media::Media;
 use crate::minion::MinionExitReason;
 use crate::misc::ZapState;
+use crate::mutes::MuteSet;
+use crate::nwc::Nwc;
 use crate::pending::Pending;
 use crate::people::{People, Person};
 use crate::relay::Relay;
 use crate::relay_picker::RelayPicker;
+use crate::retention::PruneReport;
+use crate::search::SearchQuery;
 use crate::seeker::Seeker;
 use crate::status::StatusQueue;
 use crate::storage::Storage;
+use crate::upload::Uploads;
 use crate::RunState;
 use dashmap::DashMap;
 use nostr_types::{Event, Id, Profile, PublicKey, RelayUrl};
@@ -22,7 +28,7 @@ use parking_lot::RwLock as PRwLock;
 use regex::Regex;
 use rhai::{Engine, AST};
 use std::collections::HashSet;
-use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize};
 use std::sync::Arc;
 use tokio::sync::watch::Receiver as WatchReceiver;
 use tokio::sync::watch::Sender as WatchSender;
@@ -38,6 +44,10 @@ pub struct Globals {
     /// To create a sender, just clone() it.
     pub to_overlord: mpsc::UnboundedSender<ToOverlordMessage>,
 
+    /// This is a broadcast channel of newly processed events.
+    /// To create a receiver, just run .subscribe() on it.
+    pub new_events: broadcast::Sender<Event>,
+
     /// Current minion tasks
     pub minions: PRwLock<tokio::task::JoinSet<Result<MinionExitReason, Error>>>,
 
@@ -87,11 +97,22 @@ pub struct Globals {
     /// Seeker
     pub seeker: Seeker,
 
+    /// Counts from relays (NIP-45)
+    pub counts: Counts,
+
     /// Failed Avatars
     /// If in this map, the avatar failed to load or process and is unrecoverable
     /// (but we will take them out and try again if new metadata flows in)
     pub failed_avatars: PRwLock<HashSet<PublicKey>>,
 
+    /// Our events re-mined with proof-of-work for relays that asked for it, by
+    /// the id we first posted them as
+    pub(crate) mined_events: DashMap<Id, Event>,
+
+    /// Articles (by author and `d` tag) the user has opened, whose superseded
+    /// versions we keep as revisions
+    pub opened_articles: PRwLock<HashSet<(PublicKey, String)>>,
+
     pub pixels_per_point_times_100: AtomicU32,
 
     /// UI status messages
@@ -117,6 +138,13 @@ pub struct Globals {
     //pub naddrs_being_searched_for: PRwLock<Vec<NAddr>>, // being searched for
     pub people_search_results: PRwLock<Vec<Person>>,
     pub note_search_results: PRwLock<Vec<Event>>,
+    /// The query and offset of the next page of note search results, if there is one
+    pub note_search_next_page: PRwLock<Option<(SearchQuery, usize)>>,
+    /// The relays each relay search result came from (NIP-50). Local results are not here.
+    pub note_search_relays: DashMap<Id, Vec<RelayUrl>>,
+    pub people_search_relays: DashMap<PublicKey, Vec<RelayUrl>>,
+    /// The job id of the current relay search
+    pub relay_search_job: AtomicU64,
 
     /// UI note cache invalidation per note
     // when we update an augment (deletion/reaction/zap) the UI must recompute
@@ -173,6 +201,18 @@ pub struct Globals {
     /// Current bookmarks, resolved into a Vec<Id> (updated by tasks)
     pub current_bookmarks: PRwLock<Vec<Id>>,
     pub recompute_current_bookmarks: Arc<Notify>,
+
+    /// Muted words, hashtags and threads (a copy of storage for screening)
+    pub mutes: PRwLock<MuteSet>,
+
+    /// Nostr Wallet Connect requests in flight, and the wallet balance
+    pub nwc: Nwc,
+
+    /// Media uploads in progress, and finished ones for the composer
+    pub uploads: Uploads,
+
+    /// What the last database prune (or dry run) deleted
+    pub prune_report: PRwLock<Option<PruneReport>>,
 }
 
 lazy_static! {
@@ -185,6 +225,9 @@ lazy_static! {
         // Setup a communications channel from the Minions to the Overlord.
         let (to_overlord, tmp_overlord_receiver) = mpsc::unbounded_channel();
 
+        // Setup a channel announcing newly processed events
+        let (new_events, _) = broadcast::channel(1024);
+
         // Setup a watch channel for going offline state change
         // We start in the Offline state
         let (write_runstate, read_runstate) = tokio::sync::watch::channel(RunState::Initializing);
@@ -197,6 +240,7 @@ lazy_static! {
         Globals {
             to_minions,
             to_overlord,
+            new_events,
             minions: PRwLock::new(tokio::task::JoinSet::new()),
             minions_task_url: DashMap::new(),
             write_runstate,
@@ -210,7 +254,10 @@ lazy_static! {
             feed: Feed::new(),
             fetcher: Fetcher::new(),
             seeker: Seeker::new(),
+            counts: Counts::new(),
             failed_avatars: PRwLock::new(HashSet::new()),
+            mined_events: DashMap::new(),
+            opened_articles: PRwLock::new(HashSet::new()),
             pixels_per_point_times_100: AtomicU32::new(139), // 100 dpi, 1/72th inch => 1.38888
             status_queue: PRwLock::new(StatusQueue::new(
                 "Welcome to Gossip. Status messages will appear here. Click them to dismiss them.".to_owned()
@@ -224,6 +271,10 @@ lazy_static! {
             //naddrs_being_searched_for: PRwLock::new(Vec::new()),
             people_search_results: PRwLock::new(Vec::new()),
             note_search_results: PRwLock::new(Vec::new()),
+            note_search_next_page: PRwLock::new(None),
+            note_search_relays: DashMap::new(),
+            people_search_relays: DashMap::new(),
+            relay_search_job: AtomicU64::new(0),
             ui_notes_to_invalidate: PRwLock::new(Vec::new()),
             ui_people_to_invalidate: PRwLock::new(Vec::new()),
             ui_invalidate_all: AtomicBool::new(false),
@@ -244,6 +295,10 @@ lazy_static! {
             bookmarks: PRwLock::new(BookmarkList::empty()),
             current_bookmarks: PRwLock::new(Vec::new()),
             recompute_current_bookmarks: Arc::new(Notify::new()),
+            mutes: PRwLock::new(MuteSet::default()),
+            nwc: Nwc::default(),
+            uploads: Uploads::default(),
+            prune_report: PRwLock::new(None),
         }
     };
 }
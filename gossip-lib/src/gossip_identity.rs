@@ -93,6 +93,11 @@ impl GossipIdentity {
         // Index any waiting GiftWraps
         GLOBALS.storage.index_unindexed_giftwraps()?;
 
+        // Import private mutes we have not been able to read until now
+        if let Err(e) = crate::mutes::import_private_mutes() {
+            tracing::warn!("Could not import private mutes: {}", e);
+        }
+
         // Update wait for login condition
         GLOBALS
             .wait_for_login
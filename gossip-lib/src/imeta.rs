@@ -0,0 +1,150 @@
+use image::RgbaImage;
+use nostr_types::{Event, Tag};
+use std::collections::HashMap;
+
+/// What an `imeta` tag (NIP-92) says about a piece of media linked in a note
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaDescriptor {
+    pub url: String,
+    pub mime: Option<String>,
+
+    /// SHA-256 of the file, in lowercase hex
+    pub sha256: Option<String>,
+
+    /// Width and height in pixels
+    pub dimensions: Option<(u32, u32)>,
+
+    pub blurhash: Option<String>,
+
+    /// A description of the media for those who cannot see it
+    pub alt: Option<String>,
+}
+
+impl MediaDescriptor {
+    /// Parse an `imeta` tag. Returns None if it is not one, or has no url.
+    pub fn from_tag(tag: &Tag) -> Option<MediaDescriptor> {
+        if tag.tagname() != "imeta" {
+            return None;
+        }
+
+        let mut descriptor = MediaDescriptor::default();
+        let mut index = 1;
+        loop {
+            let field = tag.get_index(index);
+            if field.is_empty() {
+                break;
+            }
+            index += 1;
+
+            let (key, value) = match field.split_once(' ') {
+                Some((key, value)) => (key, value.trim()),
+                None => continue,
+            };
+            match key {
+                "url" => descriptor.url = value.to_owned(),
+                "m" => descriptor.mime = Some(value.to_lowercase()),
+                "x" => {
+                    if value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit()) {
+                        descriptor.sha256 = Some(value.to_lowercase());
+                    }
+                }
+                "dim" => {
+                    descriptor.dimensions = value.split_once('x').and_then(|(w, h)| {
+                        match (w.parse::<u32>(), h.parse::<u32>()) {
+                            (Ok(w), Ok(h)) if w > 0 && h > 0 => Some((w, h)),
+                            _ => None,
+                        }
+                    })
+                }
+                "blurhash" => descriptor.blurhash = Some(value.to_owned()),
+                "alt" if !value.is_empty() => descriptor.alt = Some(value.to_owned()),
+                _ => {}
+            }
+        }
+
+        if descriptor.url.is_empty() {
+            None
+        } else {
+            Some(descriptor)
+        }
+    }
+
+    /// Whether the media is an image, going by its mime type
+    pub fn is_image(&self) -> bool {
+        self.mime
+            .as_ref()
+            .map(|m| m.starts_with("image/"))
+            .unwrap_or(false)
+    }
+
+    /// Whether the media is a video, going by its mime type
+    pub fn is_video(&self) -> bool {
+        self.mime
+            .as_ref()
+            .map(|m| m.starts_with("video/"))
+            .unwrap_or(false)
+    }
+
+    /// A placeholder image from the blurhash, to show while the media loads.
+    /// It is rendered small, in the media's aspect ratio, to be scaled up.
+    pub fn placeholder(&self) -> Option<RgbaImage> {
+        let blurhash = self.blurhash.as_ref()?;
+        // (in u64, as the dimensions are whatever the tag said)
+        let scaled = |a: u32, b: u32| (32 * a as u64 / b as u64).max(1) as u32;
+        let (width, height) = match self.dimensions {
+            Some((w, h)) if w >= h => (32, scaled(h, w)),
+            Some((w, h)) => (scaled(w, h), 32),
+            None => (32, 32),
+        };
+        crate::blurhash::decode(blurhash, width, height)
+    }
+}
+
+/// The media descriptors of an event, by url
+pub fn media_descriptors(event: &Event) -> HashMap<String, MediaDescriptor> {
+    event
+        .tags
+        .iter()
+        .filter_map(MediaDescriptor::from_tag)
+        .map(|descriptor| (descriptor.url.clone(), descriptor))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_tag() {
+        let tag = Tag::new(&[
+            "imeta",
+            "url https://nostr.build/i/my-image.jpg",
+            "m image/jpeg",
+            "blurhash eVF$^OI:${M{o#*0-nNFxakD-?xVM}WEWB%iNKxvR-oetmo#R-aen$",
+            "dim 3024x4032",
+            "alt A scenic photo overlooking the coast of Costa Rica",
+            "x 1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2b",
+            "fallback https://nostrcheck.me/alt1.jpg",
+        ]);
+        let descriptor = MediaDescriptor::from_tag(&tag).unwrap();
+        assert_eq!(descriptor.url, "https://nostr.build/i/my-image.jpg");
+        assert!(descriptor.is_image());
+        assert_eq!(descriptor.dimensions, Some((3024, 4032)));
+        assert_eq!(
+            descriptor.alt.as_deref(),
+            Some("A scenic photo overlooking the coast of Costa Rica")
+        );
+        assert!(descriptor.sha256.is_some());
+
+        let placeholder = descriptor.placeholder().unwrap();
+        assert_eq!(placeholder.dimensions(), (24, 32));
+
+        let huge = MediaDescriptor {
+            dimensions: Some((u32::MAX, u32::MAX / 2)),
+            ..descriptor
+        };
+        assert_eq!(huge.placeholder().unwrap().dimensions(), (32, 15));
+
+        assert!(MediaDescriptor::from_tag(&Tag::new(&["imeta", "m image/png"])).is_none());
+    }
+}
@@ -64,12 +64,26 @@
 //! with the storage engine. In some cases, the `Overlord` has more complex code for doing this,
 //! but in many cases, you can interact with `GLOBALS.storage` directly.
 
+/// A local HTTP and websocket API for other programs
+pub mod api;
+
+pub mod article;
+pub use article::Article;
+
 pub mod bookmarks;
 pub use bookmarks::BookmarkList;
 
+mod blurhash;
+
+mod bolt11;
+
 /// Defines messages sent to the overlord
 pub mod comms;
 
+/// Counts of followers, replies and reactions (NIP-45)
+pub mod counts;
+pub use counts::{Count, CountKey, Counts};
+
 mod delegation;
 pub use delegation::Delegation;
 
@@ -86,7 +100,7 @@ mod feed;
 pub use feed::{Feed, FeedKind};
 
 mod fetcher;
-pub use fetcher::Fetcher;
+pub use fetcher::{CacheStats, Fetcher};
 
 mod filter;
 
@@ -96,16 +110,28 @@ pub use globals::{Globals, GLOBALS};
 mod gossip_identity;
 pub use gossip_identity::GossipIdentity;
 
+/// Media descriptions from imeta tags (NIP-92)
+pub mod imeta;
+pub use imeta::MediaDescriptor;
+
+/// A local nostr relay serving our database
+pub mod local_relay;
+pub use local_relay::{LocalRelayPolicy, LocalRelayReadPolicy};
+
 pub mod manager;
 
 mod media;
-pub use media::Media;
+pub use media::{ImageFrame, Media};
 
 mod minion;
 
 mod misc;
 pub use misc::{Freshness, Private, ZapState};
 
+/// Muted words, hashtags and threads (NIP-51)
+pub mod mutes;
+pub use mutes::{Mute, MuteEntry, MuteSet};
+
 /// Rendering various names of users
 pub mod names;
 
@@ -116,6 +142,14 @@ pub mod nip05;
 pub mod nip46;
 pub use nip46::{Nip46Server, Nip46UnconnectedServer};
 
+/// Paying zaps through a Nostr Wallet Connect wallet (NIP-47)
+pub mod nwc;
+pub use nwc::NwcConnection;
+
+/// Tracking the publishing of our own events
+pub mod outbox;
+pub use outbox::{OutboxEntry, OutboxRelay, OutboxStatus, QueuedEvent};
+
 mod overlord;
 pub use overlord::Overlord;
 
@@ -130,6 +164,7 @@ mod person_relay;
 pub use person_relay::PersonRelay;
 
 mod post;
+pub use post::{Draft, DraftContext, ScheduledPost};
 
 /// Processing incoming events
 pub mod process;
@@ -137,14 +172,24 @@ pub mod process;
 mod profile;
 pub use profile::Profile;
 
+/// SOCKS5 proxy support for outbound connections
+pub mod proxy;
+
 mod relationship;
 
+/// Local full-text search
+pub mod search;
+
 pub mod relay;
-pub use relay::Relay;
+pub use relay::{Relay, RelayMetrics};
 
 pub mod relay_picker;
 pub use relay_picker::RelayPicker;
 
+/// Retention policies for pruning the database
+pub mod retention;
+pub use retention::{AuthorClass, PruneReport, RetentionPolicy, RetentionRule};
+
 mod seeker;
 pub use seeker::Seeker;
 
@@ -157,6 +202,14 @@ pub use storage::{PersonTable, Storage, Table};
 
 mod tasks;
 
+/// Uploading media to NIP-96 and Blossom servers
+pub mod upload;
+pub use upload::UploadedMedia;
+
+/// Checking zap receipts (NIP-57)
+pub mod zaps;
+pub use zaps::{ZapProvider, ZapReceiptCheck};
+
 #[macro_use]
 extern crate lazy_static;
 
@@ -229,6 +282,9 @@ pub fn init(rapid: bool) -> Result<(), Error> {
         }
     }
 
+    // Load word, hashtag and thread mutes
+    *GLOBALS.mutes.write() = MuteSet::load()?;
+
     // Populate global bookmarks
     if let Some(pubkey) = GLOBALS.identity.public_key() {
         if let Some(event) =
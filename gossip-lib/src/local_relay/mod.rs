@@ -0,0 +1,306 @@
+//! A nostr relay (NIP-01) on localhost serving the events in our database, so that
+//! other nostr clients on this computer can use gossip as a personal caching relay.
+//!
+//! It runs when the `local_relay_enabled` setting is on, at
+//! `ws://127.0.0.1:<local_relay_port>`. It answers REQ from storage, keeps
+//! subscriptions open for newly processed events, and takes EVENT writes as the
+//! `local_relay_policy` setting allows. Which stored events it serves is up to the
+//! `local_relay_read_policy` setting. Filters that our indexes cannot answer (see
+//! [Storage::filter_is_indexed](crate::Storage::filter_is_indexed)) are refused.
+//! Plain HTTP requests get the NIP-11 relay information document.
+//!
+//! Web pages open in a browser could reach it too, so requests that carry an
+//! `Origin` header (which browsers add, and native clients don't) are refused
+//! unless that origin is listed in the `local_relay_allowed_origins` setting.
+
+mod session;
+
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::RunState;
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use nostr_types::PublicKey;
+use serde_json::json;
+use speedy::{Readable, Writable};
+use std::convert::Infallible;
+use std::fmt;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio_tungstenite::WebSocketStream;
+use tungstenite::protocol::Role;
+
+// Most subscriptions one connection may hold open
+const MAX_SUBSCRIPTIONS: usize = 32;
+
+// Most filters in one REQ
+const MAX_FILTERS: usize = 16;
+
+// Most events returned for one filter
+const MAX_LIMIT: usize = 500;
+
+/// Which events the local relay accepts from clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Readable, Writable)]
+pub enum LocalRelayPolicy {
+    /// Accept no events, only serve them
+    ReadOnly,
+
+    /// Accept only events we authored
+    AcceptOwn,
+
+    /// Accept any valid event
+    AcceptAll,
+}
+
+impl LocalRelayPolicy {
+    /// Whether to accept an event by `author`, where `ours` is our public key
+    pub fn accepts(&self, author: PublicKey, ours: Option<PublicKey>) -> bool {
+        match self {
+            LocalRelayPolicy::ReadOnly => false,
+            LocalRelayPolicy::AcceptOwn => Some(author) == ours,
+            LocalRelayPolicy::AcceptAll => true,
+        }
+    }
+}
+
+impl fmt::Display for LocalRelayPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocalRelayPolicy::ReadOnly => write!(f, "Read only"),
+            LocalRelayPolicy::AcceptOwn => write!(f, "Accept my own events"),
+            LocalRelayPolicy::AcceptAll => write!(f, "Accept any valid event"),
+        }
+    }
+}
+
+/// Which stored events the local relay serves to clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Readable, Writable)]
+pub enum LocalRelayReadPolicy {
+    /// Serve no events, only accept them
+    ServeNone,
+
+    /// Serve only events we authored
+    ServeOwn,
+
+    /// Serve every event we have
+    ServeAll,
+}
+
+impl LocalRelayReadPolicy {
+    /// Whether to serve an event by `author`, where `ours` is our public key
+    pub fn serves(&self, author: PublicKey, ours: Option<PublicKey>) -> bool {
+        match self {
+            LocalRelayReadPolicy::ServeNone => false,
+            LocalRelayReadPolicy::ServeOwn => Some(author) == ours,
+            LocalRelayReadPolicy::ServeAll => true,
+        }
+    }
+}
+
+impl fmt::Display for LocalRelayReadPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocalRelayReadPolicy::ServeNone => write!(f, "Serve nothing"),
+            LocalRelayReadPolicy::ServeOwn => write!(f, "Serve my own events"),
+            LocalRelayReadPolicy::ServeAll => write!(f, "Serve all stored events"),
+        }
+    }
+}
+
+/// Start the local relay in the background, if it is enabled
+pub(crate) fn start() {
+    if !GLOBALS.storage.read_setting_local_relay_enabled() {
+        return;
+    }
+
+    std::mem::drop(tokio::spawn(async move {
+        if let Err(e) = run().await {
+            tracing::error!("Local relay: {}", e);
+        }
+    }));
+}
+
+async fn run() -> Result<(), Error> {
+    let addr = SocketAddr::from((
+        [127, 0, 0, 1],
+        GLOBALS.storage.read_setting_local_relay_port(),
+    ));
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("Local relay listening on ws://{}", addr);
+
+    let mut read_runstate = GLOBALS.read_runstate.clone();
+    read_runstate.mark_unchanged();
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!("Local relay: {}", e);
+                        continue;
+                    }
+                };
+                std::mem::drop(tokio::spawn(async move {
+                    let connection = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service_fn(handle))
+                        .with_upgrades();
+                    if let Err(e) = connection.await {
+                        tracing::debug!("Local relay connection: {}", e);
+                    }
+                }));
+            },
+            _ = read_runstate.wait_for(|runstate| *runstate == RunState::ShuttingDown) => break,
+        }
+    }
+
+    tracing::info!("Local relay stopped");
+    Ok(())
+}
+
+async fn handle(req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    if !origin_allowed(&req) {
+        let mut response = Response::new(Full::new(Bytes::from("origin not allowed")));
+        *response.status_mut() = StatusCode::FORBIDDEN;
+        return Ok(response);
+    }
+
+    let key = match req.headers().get(header::SEC_WEBSOCKET_KEY) {
+        Some(key) => tungstenite::handshake::derive_accept_key(key.as_bytes()),
+        None => return Ok(relay_information(&req)),
+    };
+
+    std::mem::drop(tokio::spawn(async move {
+        match hyper::upgrade::on(req).await {
+            Ok(upgraded) => {
+                let websocket =
+                    WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None)
+                        .await;
+                session::serve(websocket).await;
+            }
+            Err(e) => tracing::warn!("Local relay upgrade: {}", e),
+        }
+    }));
+
+    let mut response = Response::new(Full::new(Bytes::new()));
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    if let Ok(accept) = HeaderValue::from_str(&key) {
+        headers.insert(header::SEC_WEBSOCKET_ACCEPT, accept);
+    }
+    Ok(response)
+}
+
+// The NIP-11 relay information document, or a hint for people in web browsers
+fn relay_information(req: &Request<Incoming>) -> Response<Full<Bytes>> {
+    let wants_nip11 = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("application/nostr+json"))
+        .unwrap_or(false);
+
+    let (content_type, body) = if wants_nip11 {
+        let policy = GLOBALS.storage.read_setting_local_relay_policy();
+        let document = json!({
+            "name": "gossip local relay",
+            "description": "Events from this computer's gossip database",
+            "pubkey": GLOBALS.identity.public_key().map(|pk| pk.as_hex_string()),
+            "supported_nips": [1, 11],
+            "software": "https://github.com/mikedilger/gossip",
+            "version": env!("CARGO_PKG_VERSION"),
+            "limitation": {
+                "max_subscriptions": MAX_SUBSCRIPTIONS,
+                "max_filters": MAX_FILTERS,
+                "max_limit": MAX_LIMIT,
+                "auth_required": false,
+                "restricted_writes": policy != LocalRelayPolicy::AcceptAll,
+            },
+        });
+        ("application/nostr+json", document.to_string())
+    } else {
+        (
+            "text/plain",
+            "This is gossip's local nostr relay. Connect to it with a nostr client.".to_owned(),
+        )
+    };
+
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    // Only origins that got past origin_allowed() reach here
+    if let Some(origin) = req.headers().get(header::ORIGIN) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+        headers.insert(header::VARY, HeaderValue::from_static("origin"));
+    }
+    response
+}
+
+// Requests without an Origin header don't come from web pages. Those with one
+// must be from an origin we allow.
+fn origin_allowed(req: &Request<Incoming>) -> bool {
+    match req.headers().get(header::ORIGIN) {
+        None => true,
+        Some(origin) => match origin.to_str() {
+            Ok(origin) => is_allowed_origin(
+                origin,
+                &GLOBALS.storage.read_setting_local_relay_allowed_origins(),
+            ),
+            Err(_) => false,
+        },
+    }
+}
+
+// Whether `origin` is in `allowed`, a list separated by whitespace or commas
+fn is_allowed_origin(origin: &str, allowed: &str) -> bool {
+    allowed
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|a| !a.is_empty())
+        .any(|a| a.trim_end_matches('/').eq_ignore_ascii_case(origin))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nostr_types::PrivateKey;
+
+    #[test]
+    fn test_policy_accepts() {
+        let ours = PrivateKey::generate().public_key();
+        let theirs = PrivateKey::generate().public_key();
+
+        assert!(!LocalRelayPolicy::ReadOnly.accepts(ours, Some(ours)));
+        assert!(LocalRelayPolicy::AcceptOwn.accepts(ours, Some(ours)));
+        assert!(!LocalRelayPolicy::AcceptOwn.accepts(theirs, Some(ours)));
+        assert!(!LocalRelayPolicy::AcceptOwn.accepts(theirs, None));
+        assert!(LocalRelayPolicy::AcceptAll.accepts(theirs, None));
+    }
+
+    #[test]
+    fn test_read_policy_serves() {
+        let ours = PrivateKey::generate().public_key();
+        let theirs = PrivateKey::generate().public_key();
+
+        assert!(!LocalRelayReadPolicy::ServeNone.serves(ours, Some(ours)));
+        assert!(LocalRelayReadPolicy::ServeOwn.serves(ours, Some(ours)));
+        assert!(!LocalRelayReadPolicy::ServeOwn.serves(theirs, Some(ours)));
+        assert!(!LocalRelayReadPolicy::ServeOwn.serves(theirs, None));
+        assert!(LocalRelayReadPolicy::ServeAll.serves(theirs, None));
+    }
+
+    #[test]
+    fn test_is_allowed_origin() {
+        let allowed = "http://localhost:3000, https://example.com/";
+        assert!(is_allowed_origin("http://localhost:3000", allowed));
+        assert!(is_allowed_origin("https://example.com", allowed));
+        assert!(!is_allowed_origin("https://evil.example", allowed));
+        assert!(!is_allowed_origin("null", allowed));
+        assert!(!is_allowed_origin("http://localhost:3000", ""));
+    }
+}
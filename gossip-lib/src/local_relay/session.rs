@@ -0,0 +1,213 @@
+use super::{LocalRelayReadPolicy, MAX_FILTERS, MAX_LIMIT, MAX_SUBSCRIPTIONS};
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::storage::Storage;
+use crate::RunState;
+use futures::{SinkExt, StreamExt};
+use hyper::upgrade::Upgraded;
+use hyper_util::rt::TokioIo;
+use nostr_types::{ClientMessage, Event, Filter, Id, RelayMessage, SubscriptionId};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::WebSocketStream;
+use tungstenite::Message;
+
+type Socket = WebSocketStream<TokioIo<Upgraded>>;
+
+// One client connection, and the subscriptions it holds open
+struct Session {
+    websocket: Socket,
+    subscriptions: HashMap<String, Vec<Filter>>,
+}
+
+pub(super) async fn serve(websocket: Socket) {
+    let mut session = Session {
+        websocket,
+        subscriptions: HashMap::new(),
+    };
+    let mut new_events = GLOBALS.new_events.subscribe();
+    let mut read_runstate = GLOBALS.read_runstate.clone();
+    read_runstate.mark_unchanged();
+
+    loop {
+        let result = tokio::select! {
+            message = session.websocket.next() => match message {
+                Some(Ok(Message::Text(text))) => session.handle_message(&text).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                _ => Ok(()),
+            },
+            event = new_events.recv() => match event {
+                Ok(event) => session.send_live_event(event).await,
+                Err(RecvError::Lagged(n)) => {
+                    tracing::warn!("Local relay client fell behind, skipped {} events", n);
+                    Ok(())
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = read_runstate.wait_for(|runstate| *runstate == RunState::ShuttingDown) => break,
+        };
+        if let Err(e) = result {
+            tracing::debug!("Local relay client: {}", e);
+            break;
+        }
+    }
+
+    let _ = session.websocket.close(None).await;
+}
+
+impl Session {
+    async fn send(&mut self, message: RelayMessage) -> Result<(), Error> {
+        let wire = serde_json::to_string(&message)?;
+        self.websocket.send(Message::Text(wire)).await?;
+        Ok(())
+    }
+
+    async fn handle_message(&mut self, text: &str) -> Result<(), Error> {
+        let message: ClientMessage = match serde_json::from_str(text) {
+            Ok(message) => message,
+            Err(e) => {
+                return self
+                    .send(RelayMessage::Notice(format!(
+                        "error: could not parse: {}",
+                        e
+                    )))
+                    .await
+            }
+        };
+
+        match message {
+            ClientMessage::Req(subid, filters) => self.req(subid, filters).await,
+            ClientMessage::Close(subid) => {
+                self.subscriptions.remove(&subid.0);
+                Ok(())
+            }
+            ClientMessage::Event(event) => self.event(*event).await,
+            _ => {
+                self.send(RelayMessage::Notice(
+                    "error: this relay only supports REQ, CLOSE and EVENT".to_owned(),
+                ))
+                .await
+            }
+        }
+    }
+
+    async fn req(&mut self, subid: SubscriptionId, mut filters: Vec<Filter>) -> Result<(), Error> {
+        if !self.subscriptions.contains_key(&subid.0)
+            && self.subscriptions.len() >= MAX_SUBSCRIPTIONS
+        {
+            let reason = format!("error: no more than {} subscriptions", MAX_SUBSCRIPTIONS);
+            return self.send(RelayMessage::Closed(subid, reason)).await;
+        }
+        if filters.len() > MAX_FILTERS {
+            let reason = format!("error: no more than {} filters", MAX_FILTERS);
+            return self.send(RelayMessage::Closed(subid, reason)).await;
+        }
+
+        if GLOBALS.storage.read_setting_local_relay_read_policy() == LocalRelayReadPolicy::ServeNone
+        {
+            let reason = "restricted: this relay does not serve events".to_owned();
+            return self.send(RelayMessage::Closed(subid, reason)).await;
+        }
+
+        // Scraping all of storage ignores the limit, so we don't do it for clients
+        if !filters.iter().all(Storage::filter_is_indexed) {
+            let reason = "error: unsupported filter".to_owned();
+            return self.send(RelayMessage::Closed(subid, reason)).await;
+        }
+
+        for filter in filters.iter_mut() {
+            filter.limit = Some(filter.limit.unwrap_or(MAX_LIMIT).min(MAX_LIMIT));
+        }
+
+        // Look them up away from the async runtime, it can take a while
+        let query = filters.clone();
+        let events = tokio::task::spawn_blocking(move || stored_events(&query)).await??;
+
+        for event in events {
+            self.send(RelayMessage::Event(subid.clone(), Box::new(event)))
+                .await?;
+        }
+        self.send(RelayMessage::Eose(subid.clone())).await?;
+
+        self.subscriptions.insert(subid.0, filters);
+        Ok(())
+    }
+
+    async fn event(&mut self, event: Event) -> Result<(), Error> {
+        let id = event.id;
+
+        let policy = GLOBALS.storage.read_setting_local_relay_policy();
+        if !policy.accepts(event.pubkey, GLOBALS.identity.public_key()) {
+            return self
+                .send(RelayMessage::Ok(
+                    id,
+                    false,
+                    "restricted: this relay does not accept that event".to_owned(),
+                ))
+                .await;
+        }
+
+        if let Err(e) = event.verify(None) {
+            return self
+                .send(RelayMessage::Ok(id, false, format!("invalid: {}", e)))
+                .await;
+        }
+
+        if GLOBALS.storage.has_event(id)? {
+            return self
+                .send(RelayMessage::Ok(id, true, "duplicate:".to_owned()))
+                .await;
+        }
+
+        // Processing announces it to live subscriptions, ours included
+        match crate::process::process_new_event(&event, None, None, false, false) {
+            Ok(()) => self.send(RelayMessage::Ok(id, true, "".to_owned())).await,
+            Err(e) => {
+                self.send(RelayMessage::Ok(id, false, format!("error: {}", e)))
+                    .await
+            }
+        }
+    }
+
+    async fn send_live_event(&mut self, event: Event) -> Result<(), Error> {
+        let policy = GLOBALS.storage.read_setting_local_relay_read_policy();
+        if !policy.serves(event.pubkey, GLOBALS.identity.public_key()) {
+            return Ok(());
+        }
+
+        let subids: Vec<String> = self
+            .subscriptions
+            .iter()
+            .filter(|(_, filters)| filters.iter().any(|f| f.event_matches(&event)))
+            .map(|(subid, _)| subid.clone())
+            .collect();
+        for subid in subids {
+            self.send(RelayMessage::Event(
+                SubscriptionId(subid),
+                Box::new(event.clone()),
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+// Stored events matching any of the filters that we may serve, newest first
+fn stored_events(filters: &[Filter]) -> Result<Vec<Event>, Error> {
+    let policy = GLOBALS.storage.read_setting_local_relay_read_policy();
+    let ours = GLOBALS.identity.public_key();
+    let mut seen: HashSet<Id> = HashSet::new();
+    let mut events: Vec<Event> = Vec::new();
+    for filter in filters {
+        for event in GLOBALS
+            .storage
+            .find_events_by_filter(filter, |e| policy.serves(e.pubkey, ours))?
+        {
+            if seen.insert(event.id) {
+                events.push(event);
+            }
+        }
+    }
+    events.sort_by(|a, b| b.created_at.0.cmp(&a.created_at.0));
+    Ok(events)
+}
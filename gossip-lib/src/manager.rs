@@ -120,10 +120,11 @@ async fn engage_minion_inner(url: RelayUrl, mut jobs: Vec<RelayJob>) -> Result<(
         // Start up the minion
         let mut minion = Minion::new(url.clone()).await?;
         let payloads = jobs.iter().map(|job| job.payload.clone()).collect();
-        let abort_handle = GLOBALS
-            .minions
-            .write()
-            .spawn(async move { minion.handle(payloads).await });
+        let abort_handle = GLOBALS.minions.write().spawn(async move {
+            let result = minion.handle(payloads).await;
+            minion.save_measurements();
+            result
+        });
         let id = abort_handle.id();
         GLOBALS.minions_task_url.insert(id, url.clone());
 
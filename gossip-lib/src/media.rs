@@ -1,25 +1,51 @@
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
 use dashmap::{DashMap, DashSet};
+use image::codecs::gif::GifDecoder;
+use image::codecs::webp::WebPDecoder;
 use image::imageops;
 use image::imageops::FilterType;
-use image::{DynamicImage, Rgba, RgbaImage};
+use image::{AnimationDecoder, DynamicImage, Frames, ImageFormat, Rgba, RgbaImage};
 use nostr_types::{UncheckedUrl, Url};
+use sha2::{Digest, Sha256};
 use std::collections::HashSet;
+use std::io::Cursor;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
 use tokio::sync::RwLock;
 
+// Animations are cut short once their frames take this much memory
+const MAX_ANIMATION_BYTES: usize = 128 * 1024 * 1024;
+
+// Most media hashes we remember, for checking against imeta tags
+const MAX_MEDIA_HASHES: usize = 4096;
+
+// Like browsers, treat very short frame delays as the classic GIF default
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// One frame of an image. Still images have a single frame.
+#[derive(Debug, Clone)]
+pub struct ImageFrame {
+    pub image: RgbaImage,
+
+    /// How long to show this frame before the next one
+    pub delay: Duration,
+}
+
 /// System that processes media fetched from the internet
 pub struct Media {
     // We fetch (with Fetcher), process, and temporarily hold media
     // until the UI next asks for them, at which point we remove them
     // and hand them over. This way we can do the work that takes
     // longer and the UI can do as little work as possible.
-    image_temp: DashMap<Url, RgbaImage>,
+    image_temp: DashMap<Url, Vec<ImageFrame>>,
     data_temp: DashMap<Url, Vec<u8>>,
     media_pending_processing: DashSet<Url>,
     failed_media: RwLock<HashSet<UncheckedUrl>>,
+
+    // SHA-256 (hex) of media we have fetched
+    media_sha256: DashMap<Url, String>,
 }
 
 impl Default for Media {
@@ -35,6 +61,7 @@ impl Media {
             data_temp: DashMap::new(),
             media_pending_processing: DashSet::new(),
             failed_media: RwLock::new(HashSet::new()),
+            media_sha256: DashMap::new(),
         }
     }
 
@@ -62,13 +89,36 @@ impl Media {
         self.failed_media.blocking_write().remove(unchecked_url);
     }
 
-    /// Get an image by Url
+    /// Whether the media at this Url is known to differ from this SHA-256 hash (in
+    /// hex), as given by a note's imeta tag. This is false until it has been fetched.
+    ///
+    /// Other notes may use the same Url with another hash, so a mismatch only
+    /// concerns the note that gave the hash.
+    pub fn sha256_mismatch(&self, url: &Url, sha256: &str) -> bool {
+        match self.media_sha256.get(url) {
+            Some(actual) => !actual.eq_ignore_ascii_case(sha256),
+            None => false,
+        }
+    }
+
+    /// Get an image by Url. Of an animated image, this is the first frame.
     ///
     /// This returns immediately, usually with None if never called on that Url before.
     /// Call it again later to try to pick up the result.
     ///
     /// FIXME: this API doesn't serve async clients well.
     pub fn get_image(&self, url: &Url, use_temp_cache: bool) -> Option<RgbaImage> {
+        self.get_frames(url, use_temp_cache)
+            .and_then(|frames| frames.into_iter().next())
+            .map(|frame| frame.image)
+    }
+
+    /// Get the frames of an image by Url. Still images (and all images, if
+    /// the `animate_media` setting is off) have just one frame.
+    ///
+    /// This returns immediately, usually with None if never called on that Url before.
+    /// Call it again later to try to pick up the result.
+    pub fn get_frames(&self, url: &Url, use_temp_cache: bool) -> Option<Vec<ImageFrame>> {
         // If we have it, hand it over (we won't need a copy anymore)
         if let Some(th) = self.image_temp.remove(url) {
             return Some(th.1);
@@ -90,14 +140,10 @@ impl Media {
                             .load(Ordering::Relaxed)
                         / 100;
 
-                    match load_image_bytes(
-                        &bytes, false, // don't crop square
-                        size,  // default size,
-                        false, // don't force that size
-                        false, // don't round
-                    ) {
-                        Ok(color_image) => {
-                            GLOBALS.media.image_temp.insert(aurl, color_image);
+                    let animate = GLOBALS.storage.read_setting_animate_media();
+                    match load_image_frames(&bytes, size, animate) {
+                        Ok(frames) => {
+                            GLOBALS.media.image_temp.insert(aurl, frames);
                         }
                         Err(_) => {
                             GLOBALS
@@ -149,6 +195,11 @@ impl Media {
         ) {
             Ok(None) => None,
             Ok(Some(bytes)) => {
+                if self.media_sha256.len() >= MAX_MEDIA_HASHES {
+                    self.media_sha256.clear();
+                }
+                self.media_sha256
+                    .insert(url.clone(), hex::encode(Sha256::digest(&bytes)));
                 self.data_temp.insert(url.clone(), bytes);
                 None
             }
@@ -182,18 +233,7 @@ pub(crate) fn load_image_bytes(
             image = crop_square(image);
         }
         if force_resize || image.width() > max_image_side || image.height() > max_image_side {
-            // https://docs.rs/image/latest/image/imageops/enum.FilterType.html
-            let algo = match &*GLOBALS.storage.read_setting_image_resize_algorithm() {
-                "Nearest" => FilterType::Nearest,
-                "Triangle" => FilterType::Triangle,
-                "CatmullRom" => FilterType::CatmullRom,
-                "Gaussian" => FilterType::Gaussian,
-                "Lanczos3" => FilterType::Lanczos3,
-                _ => FilterType::Triangle,
-            };
-
-            // This preserves aspect ratio. The sizes represent bounds.
-            image = image.resize(default_size, default_size, algo);
+            image = resize_image(image, default_size);
         }
         let mut image = image.into_rgba8();
         if round {
@@ -221,7 +261,98 @@ pub(crate) fn load_image_bytes(
     }
 }
 
-fn adjust_orientation(image_bytes: &[u8], image: DynamicImage) -> DynamicImage {
+// Load all the frames of an image (just the first, unless animate is set)
+pub(crate) fn load_image_frames(
+    image_bytes: &[u8],
+    default_size: u32,
+    animate: bool,
+) -> Result<Vec<ImageFrame>, Error> {
+    if animate {
+        if let Some(frames) = load_animation(image_bytes, default_size)? {
+            return Ok(frames);
+        }
+    }
+
+    let image = load_image_bytes(
+        image_bytes,
+        false, // don't crop square
+        default_size,
+        false, // don't force that size
+        false, // don't round
+    )?;
+    Ok(vec![ImageFrame {
+        image,
+        delay: Duration::ZERO,
+    }])
+}
+
+// Load the frames of an animated GIF or WebP. Returns None if the image is not
+// animated (or not one of these).
+fn load_animation(image_bytes: &[u8], default_size: u32) -> Result<Option<Vec<ImageFrame>>, Error> {
+    let max_image_side = GLOBALS.max_image_side.load(Ordering::Relaxed) as u32;
+    let default_size = default_size.min(max_image_side);
+
+    let cursor = Cursor::new(image_bytes);
+    let frames: Frames = match image::guess_format(image_bytes) {
+        Ok(ImageFormat::Gif) => GifDecoder::new(cursor)?.into_frames(),
+        Ok(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(cursor)?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder.into_frames()
+        }
+        _ => return Ok(None),
+    };
+
+    let mut output: Vec<ImageFrame> = Vec::new();
+    let mut bytes: usize = 0;
+    for frame in frames {
+        let frame = frame?;
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let mut delay = Duration::from_millis((numer / denom.max(1)) as u64);
+        if delay < MIN_FRAME_DELAY {
+            delay = DEFAULT_FRAME_DELAY;
+        }
+
+        let mut image = DynamicImage::ImageRgba8(frame.into_buffer());
+        if image.width() > default_size || image.height() > default_size {
+            image = resize_image(image, default_size);
+        }
+        let image = image.into_rgba8();
+
+        bytes += image.as_raw().len();
+        if bytes > MAX_ANIMATION_BYTES && !output.is_empty() {
+            tracing::debug!("Animation cut short at {} frames", output.len());
+            break;
+        }
+        output.push(ImageFrame { image, delay });
+    }
+
+    // A single frame is not an animation
+    if output.len() < 2 {
+        return Ok(None);
+    }
+    Ok(Some(output))
+}
+
+// Resize to fit within size x size, preserving aspect ratio
+fn resize_image(image: DynamicImage, size: u32) -> DynamicImage {
+    // https://docs.rs/image/latest/image/imageops/enum.FilterType.html
+    let algo = match &*GLOBALS.storage.read_setting_image_resize_algorithm() {
+        "Nearest" => FilterType::Nearest,
+        "Triangle" => FilterType::Triangle,
+        "CatmullRom" => FilterType::CatmullRom,
+        "Gaussian" => FilterType::Gaussian,
+        "Lanczos3" => FilterType::Lanczos3,
+        _ => FilterType::Triangle,
+    };
+
+    // This preserves aspect ratio. The sizes represent bounds.
+    image.resize(size, size, algo)
+}
+
+pub(crate) fn adjust_orientation(image_bytes: &[u8], image: DynamicImage) -> DynamicImage {
     match get_orientation(image_bytes) {
         1 => image,
         2 => DynamicImage::ImageRgba8(imageops::flip_horizontal(&image)),
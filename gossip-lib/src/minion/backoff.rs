@@ -0,0 +1,458 @@
+use super::{AuthState, Minion};
+use crate::comms::ToOverlordMessage;
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::misc::reason_prefix;
+use futures_util::sink::SinkExt;
+use nostr_types::{ClientMessage, Event, Id, PreEvent, Unixtime};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tungstenite::protocol::Message as WsMessage;
+
+// The first pause after a relay says we are rate-limited, doubling each time after
+const BACKOFF_START_SECS: u64 = 2;
+
+// But we never pause longer than this
+const BACKOFF_MAX_SECS: u64 = 300;
+
+// Once a pause is over, how many held requests we send each tick
+const PACED_PER_TICK: usize = 2;
+
+// How many times we resend an event that a relay refused for a reason we can fix
+const MAX_RESENDS: u8 = 3;
+
+// The most proof-of-work we are willing to do when a relay asks for it
+const MAX_POW: u8 = 28;
+
+// How long we avoid a relay that has blocked us
+const BLOCKED_AVOID_SECS: u64 = 60 * 60 * 24;
+
+// How long we avoid a relay that is restricted to us
+const RESTRICTED_AVOID_SECS: u64 = 60 * 60;
+
+/// How long a relay has asked us to wait before sending more
+#[derive(Debug, Default)]
+pub(super) struct Backoff {
+    until: Option<Instant>,
+    delay: Option<Duration>,
+}
+
+/// One of our events posted to the relay, kept until the relay answers with an OK
+/// so that we can send it again if the relay asks us to
+#[derive(Debug)]
+pub(super) struct PostingEvent {
+    event: Event,
+
+    /// The id of the event we were asked to post. Re-mining changes the id, but
+    /// the outbox knows it by this one.
+    original: Id,
+
+    resends: u8,
+}
+
+// Re-mined copies of our events, by the id we first posted them as, so that every
+// relay asking for proof-of-work on an event gets the same copy
+const MAX_MINED_EVENTS: usize = 64;
+
+// Only one event is mined at a time, so that relays asking for the same event at
+// once share the work
+static MINING_LOCK: Mutex<()> = Mutex::new(());
+
+// Sign our event again with proof-of-work of at least this difficulty, or reuse
+// the copy we already mined for another relay. The copy is not stored as an event;
+// the outbox knows it by the original.
+fn mine(event: Event, original: Id, difficulty: u8) -> Result<Event, Error> {
+    let _lock = MINING_LOCK.lock();
+
+    if let Some(mined) = GLOBALS.mined_events.get(&original) {
+        if mined.pow() >= difficulty {
+            return Ok(mined.clone());
+        }
+    }
+
+    let pre_event = PreEvent {
+        pubkey: event.pubkey,
+        created_at: Unixtime::now(),
+        kind: event.kind,
+        tags: event
+            .tags
+            .iter()
+            .filter(|t| t.tagname() != "nonce")
+            .cloned()
+            .collect(),
+        content: event.content.clone(),
+    };
+    let mined = GLOBALS
+        .identity
+        .sign_event_with_pow(pre_event, difficulty, None)?;
+
+    if GLOBALS.mined_events.len() >= MAX_MINED_EVENTS {
+        GLOBALS.mined_events.clear();
+    }
+    GLOBALS.mined_events.insert(original, mined.clone());
+    Ok(mined)
+}
+
+// The difficulty a `pow:` message asks for. Relays word these differently
+// ("pow: difficulty 26 is less than 30") so we take the largest number.
+fn required_pow(message: &str) -> Option<u8> {
+    message
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|s| s.parse::<u8>().ok())
+        .max()
+}
+
+impl Minion {
+    /// If the relay has asked us to wait and the wait is not over
+    pub(super) fn backing_off(&self) -> bool {
+        match self.backoff.until {
+            Some(until) => Instant::now() < until,
+            None => false,
+        }
+    }
+
+    /// Pause our requests if a relay says (in a CLOSED, NOTICE or OK message)
+    /// that we are sending them too fast
+    pub(super) fn heed_rate_limit(&mut self, message: &str) {
+        if reason_prefix(message) == Some("rate-limited") {
+            self.slow_down();
+        }
+    }
+
+    /// Stay away from the relay if it refuses us as a whole, which it says in a
+    /// NOTICE or in answer to our AUTH. Refusing one of our events or one of our
+    /// subscriptions only concerns that event or subscription.
+    pub(super) fn heed_refusal(&mut self, message: &str) {
+        match reason_prefix(message) {
+            Some("blocked") => self.avoid_relay(BLOCKED_AVOID_SECS),
+            Some("restricted") => self.avoid_relay(RESTRICTED_AVOID_SECS),
+            _ => {}
+        }
+    }
+
+    /// The relay answered normally, so the next rate limit starts with a short
+    /// pause again
+    pub(super) fn ease_backoff(&mut self) {
+        if !self.backing_off() {
+            self.backoff = Backoff::default();
+        }
+    }
+
+    // Pause requests, for twice as long as last time
+    fn slow_down(&mut self) {
+        let delay = match self.backoff.delay {
+            Some(delay) => (delay * 2).min(Duration::from_secs(BACKOFF_MAX_SECS)),
+            None => Duration::from_secs(BACKOFF_START_SECS),
+        };
+        tracing::info!(
+            "{}: Rate limited, pausing requests for {} seconds",
+            &self.url,
+            delay.as_secs()
+        );
+        self.backoff.delay = Some(delay);
+        self.backoff.until = Some(Instant::now() + delay);
+    }
+
+    // Have the overlord stay away from this relay for a while
+    fn avoid_relay(&mut self, secs: u64) {
+        let until = Unixtime::now() + Duration::from_secs(secs);
+        tracing::warn!(
+            "{}: Refusing us, avoiding it for {} seconds",
+            &self.url,
+            secs
+        );
+        self.dbrelay.avoid_until = Some(until);
+        if let Err(e) =
+            GLOBALS
+                .storage
+                .modify_relay(&self.url, |relay| relay.avoid_until = Some(until), None)
+        {
+            tracing::error!("{}: ERROR setting avoid_until: {}", &self.url, e);
+        }
+    }
+
+    /// Post one of our events to the relay, or hold it if the relay has asked us
+    /// to wait
+    pub(super) async fn post_event(&mut self, event: Event, job_id: u64) -> Result<(), Error> {
+        let id = event.id;
+        self.posting_ids.insert(id, job_id);
+        self.posting_events.insert(
+            id,
+            PostingEvent {
+                event,
+                original: id,
+                resends: 0,
+            },
+        );
+
+        if self.backing_off() {
+            self.events_rate_limited.push(id);
+            return Ok(());
+        }
+        self.send_posting_event(id).await
+    }
+
+    async fn send_posting_event(&mut self, id: Id) -> Result<(), Error> {
+        let event = match self.posting_events.get(&id) {
+            Some(posting) => posting.event.clone(),
+            None => return Ok(()),
+        };
+        let msg = ClientMessage::Event(Box::new(event));
+        let wire = serde_json::to_string(&msg)?;
+        let ws_stream = self.stream.as_mut().unwrap();
+        self.last_message_sent = wire.clone();
+        ws_stream.send(WsMessage::Text(wire)).await?;
+        Ok(())
+    }
+
+    /// The relay refused one of our events. If it is for a reason we can fix, we
+    /// arrange to send it again and return true. Otherwise we return false and it
+    /// has failed.
+    pub(super) async fn resend_refused_event(
+        &mut self,
+        id: Id,
+        message: &str,
+    ) -> Result<bool, Error> {
+        let resends = match self.posting_events.get_mut(&id) {
+            Some(posting) if posting.resends < MAX_RESENDS => {
+                posting.resends += 1;
+                posting.resends
+            }
+            _ => return Ok(false),
+        };
+
+        match reason_prefix(message) {
+            Some("rate-limited") => {
+                tracing::info!(
+                    "{}: Will resend rate-limited event (try {})",
+                    &self.url,
+                    resends
+                );
+                self.events_rate_limited.push(id);
+                Ok(true)
+            }
+            Some("auth-required") => {
+                if self.dbrelay.allow_auth == Some(false) {
+                    return Ok(false);
+                }
+                match self.auth_state {
+                    AuthState::None => {
+                        if let Err(e) = self.authenticate().await {
+                            tracing::warn!("{}: Cannot AUTH to resend event: {}", &self.url, e);
+                            return Ok(false);
+                        }
+                    }
+                    AuthState::Waiting(_) => {}
+                    // It still refuses, or we cannot satisfy it
+                    AuthState::Authenticated | AuthState::Failed => return Ok(false),
+                }
+                self.events_waiting_for_auth.push(id);
+                Ok(true)
+            }
+            Some("pow") => {
+                let difficulty = match required_pow(message) {
+                    Some(d) if d <= MAX_POW => d,
+                    _ => {
+                        tracing::info!(
+                            "{}: Not doing the proof-of-work asked for: {}",
+                            &self.url,
+                            message
+                        );
+                        return Ok(false);
+                    }
+                };
+                Ok(self.start_remining(id, difficulty))
+            }
+            _ => Ok(false),
+        }
+    }
+
+    // Have the event signed again with proof-of-work of this difficulty, to send
+    // in place of the old one once it is ready. Only our own events can be re-mined.
+    fn start_remining(&mut self, id: Id, difficulty: u8) -> bool {
+        let posting = match self.posting_events.get(&id) {
+            Some(posting) => posting,
+            None => return false,
+        };
+        if Some(posting.event.pubkey) != GLOBALS.identity.public_key()
+            || !GLOBALS.identity.is_unlocked()
+        {
+            return false;
+        }
+
+        tracing::info!(
+            "{}: Re-mining event {} at difficulty {}",
+            &self.url,
+            posting.original.as_hex_string(),
+            difficulty
+        );
+        let event = posting.event.clone();
+        let original = posting.original;
+
+        // Mining can take a while, so it happens off the async threads and we
+        // check on it each tick rather than wait for it here
+        let mining = tokio::task::spawn_blocking(move || mine(event, original, difficulty));
+        self.events_being_mined.insert(id, mining);
+        true
+    }
+
+    // Send the re-mined events that are ready
+    async fn send_mined(&mut self) -> Result<(), Error> {
+        let ready: Vec<Id> = self
+            .events_being_mined
+            .iter()
+            .filter(|(_, mining)| mining.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in ready {
+            let mining = match self.events_being_mined.remove(&id) {
+                Some(mining) => mining,
+                None => continue,
+            };
+            let mined: Result<Event, Error> = match mining.await {
+                Ok(result) => result,
+                Err(e) => Err(e.into()),
+            };
+            let event = match mined {
+                Ok(event) => event,
+                Err(e) => {
+                    tracing::warn!("{}: Could not re-mine event: {}", &self.url, e);
+                    self.finish_posting(id, false, "pow: could not do the proof-of-work")
+                        .await?;
+                    continue;
+                }
+            };
+
+            let posting = match self.posting_events.remove(&id) {
+                Some(posting) => posting,
+                None => continue,
+            };
+
+            // Track the new event in place of the old one
+            let new_id = event.id;
+            if let Some(job_id) = self.posting_ids.remove(&id) {
+                self.posting_ids.insert(new_id, job_id);
+                if let Some(ids) = self.posting_jobs.get_mut(&job_id) {
+                    for job_event_id in ids.iter_mut() {
+                        if *job_event_id == id {
+                            *job_event_id = new_id;
+                        }
+                    }
+                }
+            }
+            self.posting_events.insert(
+                new_id,
+                PostingEvent {
+                    event,
+                    original: posting.original,
+                    resends: posting.resends,
+                },
+            );
+
+            self.send_posting_event(new_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The relay has given its final answer on one of our events
+    pub(super) async fn finish_posting(
+        &mut self,
+        id: Id,
+        ok: bool,
+        message: &str,
+    ) -> Result<(), Error> {
+        let job_id = match self.posting_ids.remove(&id) {
+            Some(job_id) => job_id,
+            None => return Ok(()),
+        };
+        let original = self
+            .posting_events
+            .remove(&id)
+            .map(|posting| posting.original)
+            .unwrap_or(id);
+
+        crate::outbox::record_result(original, &self.url, ok, message)?;
+
+        if ok {
+            // Save seen_on data
+            // (it was already processed by the overlord before the minion got it,
+            //  but with None for seen_on.) A re-mined copy is not stored, and the
+            //  relay does not have the original.
+            if id == original {
+                GLOBALS
+                    .storage
+                    .add_event_seen_on_relay(id, &self.url, Unixtime::now(), None)?;
+            }
+        } else {
+            // demerit the relay
+            self.bump_failure_count().await;
+        }
+
+        let mut job_is_done: bool = false;
+        {
+            // Take it out of the posting_jobs
+            if let Some(job_ids) = self.posting_jobs.get_mut(&job_id) {
+                job_ids.retain(|id_in_vec| *id_in_vec != id);
+                job_is_done = job_ids.is_empty();
+            } // else is not expected, but if it happens we ignore it.
+        }
+
+        if job_is_done {
+            self.posting_jobs.remove(&job_id);
+
+            // Tell overlord
+            self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
+                self.url.clone(),
+                job_id,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// AUTH failed, so the events waiting on it have failed too
+    pub(super) async fn fail_events_waiting_for_auth(&mut self) -> Result<(), Error> {
+        let ids = std::mem::take(&mut self.events_waiting_for_auth);
+        for id in ids {
+            self.finish_posting(id, false, "auth-required: AUTH failed")
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Send requests and events that were held back, a few at a time once the
+    /// relay's pause is over. This is run every tick.
+    pub(super) async fn send_held(&mut self) -> Result<(), Error> {
+        self.send_mined().await?;
+
+        if self.auth_state == AuthState::Authenticated && !self.events_waiting_for_auth.is_empty() {
+            let ids = std::mem::take(&mut self.events_waiting_for_auth);
+            for id in ids {
+                tracing::info!("{}: Resending event after AUTH", &self.url);
+                self.send_posting_event(id).await?;
+            }
+        }
+
+        let mut budget = PACED_PER_TICK;
+        while budget > 0 && !self.backing_off() && !self.subscriptions_rate_limited.is_empty() {
+            let handle = self.subscriptions_rate_limited.remove(0);
+            tracing::info!(
+                "Sending previously rate-limited subscription {} to {}",
+                handle,
+                &self.url
+            );
+            self.send_subscription(&handle).await?;
+            budget -= 1;
+        }
+        while budget > 0 && !self.backing_off() && !self.events_rate_limited.is_empty() {
+            let id = self.events_rate_limited.remove(0);
+            tracing::info!("{}: Resending previously rate-limited event", &self.url);
+            self.send_posting_event(id).await?;
+            budget -= 1;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use super::Minion;
+use crate::comms::ToOverlordMessage;
+use crate::counts::CountKey;
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use futures_util::sink::SinkExt;
+use nostr_types::Unixtime;
+use tungstenite::protocol::Message as WsMessage;
+
+// If the relay has not answered a COUNT in this time, we give up on it
+const COUNT_TIMEOUT_SECS: i64 = 30;
+
+/// A COUNT we sent and are awaiting the answer to
+pub(super) struct CountQuery {
+    job_id: u64,
+    key: CountKey,
+    asked: Unixtime,
+}
+
+impl Minion {
+    /// Ask the relay to count (NIP-45)
+    pub(super) async fn count(&mut self, job_id: u64, key: CountKey) -> Result<(), Error> {
+        let subid = self.limits().subid("count_", self.next_count_id);
+        self.next_count_id += 1;
+
+        let filter = crate::counts::count_filter(&key);
+        self.count_queries.insert(
+            subid.clone(),
+            CountQuery {
+                job_id,
+                key,
+                asked: Unixtime::now(),
+            },
+        );
+
+        let wire = serde_json::to_string(&serde_json::json!(["COUNT", subid, filter]))?;
+        let websocket_stream = self.stream.as_mut().unwrap();
+        tracing::trace!("{}: Sending {}", &self.url, &wire);
+        self.last_message_sent = wire.clone();
+        websocket_stream.send(WsMessage::Text(wire)).await?;
+        Ok(())
+    }
+
+    /// Handle a COUNT answer from the relay
+    pub(super) async fn handle_count_message(&mut self, ws_message: &str) -> Result<(), Error> {
+        let parts: Vec<serde_json::Value> = serde_json::from_str(ws_message)?;
+        let subid = parts.get(1).and_then(|v| v.as_str()).unwrap_or("");
+
+        let query = match self.count_queries.remove(subid) {
+            Some(query) => query,
+            None => {
+                tracing::debug!("{}: COUNT for unknown query {}", &self.url, subid);
+                return Ok(());
+            }
+        };
+
+        let answer = parts.get(2);
+        match answer.and_then(|a| a.get("count")).and_then(|c| c.as_u64()) {
+            Some(count) => {
+                let approximate = answer
+                    .and_then(|a| a.get("approximate"))
+                    .and_then(|a| a.as_bool())
+                    .unwrap_or(false);
+                GLOBALS
+                    .counts
+                    .record(query.key, &self.url, count, approximate)?;
+            }
+            None => {
+                tracing::debug!("{}: Malformed COUNT: {}", &self.url, ws_message);
+            }
+        }
+
+        self.count_done(query)
+    }
+
+    /// If this subscription id is one of our COUNTs, the relay refused it
+    pub(super) fn count_closed(&mut self, subid: &str, message: &str) -> Result<bool, Error> {
+        match self.count_queries.remove(subid) {
+            Some(query) => {
+                tracing::debug!("{}: COUNT refused: {}", &self.url, message);
+                self.count_done(query)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Give up on COUNTs the relay is not answering
+    pub(super) fn check_count_timeouts(&mut self) -> Result<(), Error> {
+        let now = Unixtime::now();
+        let expired: Vec<String> = self
+            .count_queries
+            .iter()
+            .filter(|(_, query)| query.asked.0 + COUNT_TIMEOUT_SECS < now.0)
+            .map(|(subid, _)| subid.clone())
+            .collect();
+        for subid in expired {
+            if let Some(query) = self.count_queries.remove(&subid) {
+                tracing::debug!("{}: COUNT timed out", &self.url);
+                self.count_done(query)?;
+            }
+        }
+        Ok(())
+    }
+
+    // The job is complete once all of its COUNTs are done
+    fn count_done(&mut self, query: CountQuery) -> Result<(), Error> {
+        if self
+            .count_queries
+            .values()
+            .any(|other| other.job_id == query.job_id)
+        {
+            return Ok(());
+        }
+        self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
+            self.url.clone(),
+            query.job_id,
+        ))?;
+        Ok(())
+    }
+}
@@ -341,6 +341,23 @@ pub fn nip46() -> Vec<Filter> {
     vec![filter]
 }
 
+pub fn nwc() -> Vec<Filter> {
+    let connection = match crate::nwc::connection() {
+        Ok(Some(connection)) => connection,
+        _ => return vec![],
+    };
+    let pkh: PublicKeyHex = connection.client_pubkey.into();
+
+    let mut filter = Filter {
+        authors: vec![connection.wallet_pubkey.into()],
+        kinds: vec![EventKind::from(crate::nwc::NWC_RESPONSE_KIND)],
+        ..Default::default()
+    };
+    filter.set_tag_values('p', vec![pkh.to_string()]);
+
+    vec![filter]
+}
+
 pub fn metadata(pubkeys: &[PublicKey]) -> Vec<Filter> {
     let pkhp: Vec<PublicKeyHex> = pubkeys.iter().map(|pk| pk.into()).collect();
 
@@ -9,6 +9,16 @@ impl Minion {
         // TODO: pull out the raw event without any deserialization to be sure we don't mangle
         //       it.
 
+        // NIP-77 messages are not part of RelayMessage
+        if ws_message.starts_with("[\"NEG-") {
+            return self.handle_negentropy_message(&ws_message).await;
+        }
+
+        // Nor are NIP-45 COUNT answers
+        if ws_message.starts_with("[\"COUNT\"") {
+            return self.handle_count_message(&ws_message).await;
+        }
+
         let relay_message: RelayMessage = match serde_json::from_str(&ws_message) {
             Ok(rm) => rm,
             Err(e) => {
@@ -24,56 +34,80 @@ impl Minion {
 
         match relay_message {
             RelayMessage::Event(subid, event) => {
-                let handle = self
-                    .subscription_map
-                    .get_handle_by_id(&subid.0)
-                    .unwrap_or_else(|| "_".to_owned());
-
-                if let Some(sub) = self.subscription_map.get_mut_by_id(&subid.0) {
-                    // Check if the event matches one of our filters
-                    //
+                self.measure_event();
+
+                // Relay search (NIP-50) results
+                if let Some(search) = self.search_queries.get(&subid.0) {
+                    if !crate::search::is_current_relay_search(search.job_id) {
+                        return Ok(());
+                    }
+                    crate::process::process_new_event(
+                        &event,
+                        Some(self.url.clone()),
+                        Some("search".to_owned()),
+                        true,
+                        false,
+                    )?;
+                    crate::search::add_relay_search_result(&event, &self.url)?;
+                    return Ok(());
+                }
+
+                // The subscription may carry several of our handles. Find the ones
+                // this event is for.
+                let handles = self.subscription_map.get_handles_by_id(&subid.0);
+                let mut matching: Vec<String> = Vec::new();
+                for handle in handles.iter() {
+                    let sub = match self.subscription_map.get(handle) {
+                        Some(sub) => sub,
+                        None => continue,
+                    };
+
                     // exclude temp_ feeds that get rewritten as we are likely to get data
                     // from the previous version of said filter.
-                    if !handle.starts_with("temp_") {
-                        let mut it_matches = false;
-                        for filter in sub.get_filters().iter() {
-                            if filter.event_matches(&event) {
-                                it_matches = true;
-                                break;
-                            }
-                        }
-                        if !it_matches {
-                            tracing::debug!(
-                                "{} sent event that does not match filters on subscription {}: {}",
-                                self.url,
-                                handle,
-                                event.id.as_hex_string()
-                            );
-
-                            // ignore it
-                            return Ok(());
-                        }
+                    if (handle.starts_with("temp_") && handles.len() == 1)
+                        || sub.get_filters().iter().any(|f| f.event_matches(&event))
+                    {
+                        matching.push(handle.clone());
                     }
+                }
+                if !handles.is_empty() && matching.is_empty() {
+                    tracing::debug!(
+                        "{} sent event that does not match filters on subscription {}: {}",
+                        self.url,
+                        handles.join(", "),
+                        event.id.as_hex_string()
+                    );
 
-                    // Events that come in after EOSE on the general feed bump the last_general_eose
-                    // timestamp for that relay, so we don't query before them next time we run.
-                    if handle == "general_feed" && sub.eose() {
-                        // Update last general EOSE
-                        self.dbrelay.last_general_eose_at =
-                            Some(match self.dbrelay.last_general_eose_at {
-                                Some(old) => old.max(event.created_at.0 as u64),
-                                None => event.created_at.0 as u64,
-                            });
-                        GLOBALS.storage.modify_relay(
-                            &self.dbrelay.url,
-                            |relay| {
-                                relay.last_general_eose_at = self.dbrelay.last_general_eose_at;
-                            },
-                            None,
-                        )?;
-                    }
+                    // ignore it
+                    return Ok(());
+                }
+
+                // Events that come in after EOSE on the general feed bump the last_general_eose
+                // timestamp for that relay, so we don't query before them next time we run.
+                if matching.iter().any(|h| h == "general_feed")
+                    && self
+                        .subscription_map
+                        .get("general_feed")
+                        .map(|sub| sub.eose())
+                        .unwrap_or(false)
+                {
+                    // Update last general EOSE
+                    self.dbrelay.last_general_eose_at =
+                        Some(match self.dbrelay.last_general_eose_at {
+                            Some(old) => old.max(event.created_at.0 as u64),
+                            None => event.created_at.0 as u64,
+                        });
+                    GLOBALS.storage.modify_relay(
+                        &self.dbrelay.url,
+                        |relay| {
+                            relay.last_general_eose_at = self.dbrelay.last_general_eose_at;
+                        },
+                        None,
+                    )?;
                 }
 
+                let handle = matching.first().cloned().unwrap_or_else(|| "_".to_owned());
+
                 // Remove from sought set
                 if let Some(ess) = self.sought_events.remove(&event.id) {
                     // and notify the overlord of the completed job
@@ -96,49 +130,66 @@ impl Minion {
             }
             RelayMessage::Notice(msg) => {
                 tracing::warn!("{}: NOTICE: {}", &self.url, msg);
+                self.measure_notice(&msg);
+                self.heed_rate_limit(&msg);
+                self.heed_refusal(&msg);
             }
             RelayMessage::Eose(subid) => {
-                let handle = self
-                    .subscription_map
-                    .get_handle_by_id(&subid.0)
-                    .unwrap_or_else(|| "_".to_owned());
-
-                // If this is a temporary subscription, we should close it after an EOSE
-                let close: bool = handle.starts_with("temp_");
-
-                // Update the matching subscription
-                match self.subscription_map.get_mut_by_id(&subid.0) {
-                    Some(sub) => {
-                        tracing::debug!("{}: {}: EOSE: {:?}", &self.url, handle, subid);
-                        if close {
-                            self.unsubscribe(&handle).await?;
-                        } else {
-                            sub.set_eose();
-                        }
-                        if handle == "general_feed" {
-                            // Update last general EOSE
-                            let now = Unixtime::now().0 as u64;
-                            self.dbrelay.last_general_eose_at =
-                                Some(match self.dbrelay.last_general_eose_at {
-                                    Some(old) => old.max(now),
-                                    None => now,
-                                });
-                            GLOBALS.storage.modify_relay(
-                                &self.dbrelay.url,
-                                |relay| {
-                                    relay.last_general_eose_at = self.dbrelay.last_general_eose_at;
-                                },
-                                None,
-                            )?;
+                self.measure_eose();
+                self.ease_backoff();
+
+                if self.search_done(&subid.0).await? {
+                    return Ok(());
+                }
+
+                // The handles on this subscription which have now had EOSE on
+                // every subscription carrying them
+                let handles = match self.subscription_map.eose_by_id(&subid.0) {
+                    Some(handles) => handles,
+                    None => {
+                        tracing::debug!("{}: EOSE for unknown subscription {:?}", &self.url, subid);
+                        return Ok(());
+                    }
+                };
+
+                for handle in handles {
+                    // If some of it has not been sent yet, it is not at EOSE. A
+                    // temporary subscription frees up its slots for the rest.
+                    if self.has_groups_waiting_for_slot(&handle) {
+                        if handle.starts_with("temp_") {
+                            let (emptied, shared) = self.subscription_map.detach(&handle);
+                            for id in emptied {
+                                self.close_subscription(&id).await?;
+                            }
+                            self.send_shared_reqs(shared).await?;
                         }
+                        continue;
                     }
-                    None => {
-                        tracing::debug!(
-                            "{}: {} EOSE for unknown subscription {:?}",
-                            &self.url,
-                            handle,
-                            subid
-                        );
+
+                    tracing::debug!("{}: {}: EOSE: {:?}", &self.url, handle, subid);
+
+                    // If this is a temporary subscription, we should close it after an EOSE
+                    if handle.starts_with("temp_") {
+                        self.unsubscribe(&handle).await?;
+                    } else if let Some(sub) = self.subscription_map.get_mut(&handle) {
+                        sub.set_eose();
+                    }
+
+                    if handle == "general_feed" {
+                        // Update last general EOSE
+                        let now = Unixtime::now().0 as u64;
+                        self.dbrelay.last_general_eose_at =
+                            Some(match self.dbrelay.last_general_eose_at {
+                                Some(old) => old.max(now),
+                                None => now,
+                            });
+                        GLOBALS.storage.modify_relay(
+                            &self.dbrelay.url,
+                            |relay| {
+                                relay.last_general_eose_at = self.dbrelay.last_general_eose_at;
+                            },
+                            None,
+                        )?;
                     }
                 }
             }
@@ -154,10 +205,13 @@ impl Minion {
                 // If we are waiting for a response for this id, process
                 if let AuthState::Waiting(waiting_id) = self.auth_state {
                     if waiting_id == id {
+                        self.measure_auth(ok);
                         if !ok {
                             self.auth_state = AuthState::Failed;
                             // Auth failed.
                             tracing::warn!("AUTH failed to {}: {}", &self.url, ok_message);
+                            self.heed_refusal(&ok_message);
+                            self.fail_events_waiting_for_auth().await?;
                         } else {
                             tracing::info!("Authenticated to {}", &self.url);
                             self.auth_state = AuthState::Authenticated;
@@ -166,43 +220,17 @@ impl Minion {
                     }
                 }
 
-                if let Some(job_id) = self.posting_ids.get(&id).copied() {
+                if self.posting_ids.contains_key(&id) {
                     if ok {
-                        // Save seen_on data
-                        // (it was already processed by the overlord before the minion got it,
-                        //  but with None for seen_on.)
-                        GLOBALS.storage.add_event_seen_on_relay(
-                            id,
-                            &self.url,
-                            Unixtime::now(),
-                            None,
-                        )?;
+                        self.ease_backoff();
                     } else {
-                        // demerit the relay
-                        self.bump_failure_count().await;
+                        self.heed_rate_limit(&ok_message);
                     }
 
-                    let mut job_is_done: bool = false;
-                    {
-                        // Take it out of the posting_jobs
-                        if let Some(job_ids) = self.posting_jobs.get_mut(&job_id) {
-                            job_ids.retain(|id_in_vec| *id_in_vec != id);
-                            job_is_done = job_ids.is_empty();
-                        } // else is not expected, but if it happens we ignore it.
+                    // Resend it if the relay refused it for a reason we can fix
+                    if ok || !self.resend_refused_event(id, &ok_message).await? {
+                        self.finish_posting(id, ok, &ok_message).await?;
                     }
-
-                    if job_is_done {
-                        self.posting_jobs.remove(&job_id);
-
-                        // Tell overlord
-                        self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
-                            self.url.clone(),
-                            job_id,
-                        ))?;
-                    }
-
-                    // Take it out of the posting_ids whether or not job is done
-                    self.posting_ids.remove(&id);
                 }
 
                 match ok {
@@ -240,116 +268,129 @@ impl Minion {
                 }
             }
             RelayMessage::Closed(subid, message) => {
-                let handle = self
-                    .subscription_map
-                    .get_handle_by_id(&subid.0)
-                    .unwrap_or_else(|| "_".to_owned());
+                self.measure_closed(&message);
+                self.heed_rate_limit(&message);
+
+                if self.count_closed(&subid.0, &message)? {
+                    return Ok(());
+                }
+                if self.search_queries.contains_key(&subid.0) {
+                    tracing::debug!("{}: Search refused: {}", &self.url, message);
+                    self.search_done(&subid.0).await?;
+                    return Ok(());
+                }
 
-                if handle != "_" {
+                // The relay has closed it, and everything it carried
+                let handles = self.subscription_map.remove_id(&subid.0);
+                if handles.is_empty() {
+                    tracing::debug!("{}: Closed unknown subscription {:?}", &self.url, subid);
+                }
+                for handle in handles {
                     tracing::info!("{}: Closed: {}: {}", &self.url, handle, message);
+                    self.subscription_closed(handle, &message).await?;
                 }
+            }
+        }
 
-                // Check the machine-readable prefix
-                if let Some(prefix) = message.split(':').next() {
-                    match prefix {
-                        "duplicate" => {
-                            // not much we can do; it SHOULD replace dup REQ subs, not complain.
-                            tracing::warn!(
-                                "{} not accepting {} due to duplicate is strange.",
-                                &self.url,
-                                handle
-                            );
-                        }
-                        "pow" => {
-                            tracing::warn!(
-                                "{} wants POW for {} but we do not do POW on demand.",
-                                &self.url,
-                                handle
-                            );
-                        }
-                        "rate-limited" => {
-                            // Wait to retry later
-                            self.subscriptions_rate_limited.push(handle);
+        Ok(())
+    }
 
-                            // return now, don't remove sub from map
-                            return Ok(());
-                        }
-                        "invalid" => {
-                            tracing::warn!(
-                                "{} won't serve our {} sub (says invalid)",
-                                &self.url,
-                                &handle
-                            );
-                            self.failed_subs.insert(handle.clone());
-                        }
-                        "error" => {
-                            tracing::warn!(
-                                "{} won't serve our {} sub (says error)",
-                                &self.url,
-                                &handle
-                            );
-                            self.failed_subs.insert(handle.clone());
-                        }
-                        "auth-required" => {
-                            if self.dbrelay.allow_auth == Some(false) {
-                                // we don't allow auth to this relay.
+    // The relay closed a subscription carrying this handle
+    async fn subscription_closed(&mut self, handle: String, message: &str) -> Result<(), Error> {
+        // Check the machine-readable prefix
+        if let Some(prefix) = message.split(':').next() {
+            match prefix {
+                "duplicate" => {
+                    // not much we can do; it SHOULD replace dup REQ subs, not complain.
+                    tracing::warn!(
+                        "{} not accepting {} due to duplicate is strange.",
+                        &self.url,
+                        handle
+                    );
+                }
+                "pow" => {
+                    tracing::warn!(
+                        "{} wants POW for {} but we do not do POW on demand.",
+                        &self.url,
+                        handle
+                    );
+                }
+                "rate-limited" => {
+                    // Wait to retry later
+                    self.subscriptions_rate_limited.push(handle);
+
+                    // return now, don't remove sub from map
+                    return Ok(());
+                }
+                "invalid" => {
+                    tracing::warn!(
+                        "{} won't serve our {} sub (says invalid)",
+                        &self.url,
+                        &handle
+                    );
+                    self.failed_subs.insert(handle.clone());
+                }
+                "error" => {
+                    tracing::warn!("{} won't serve our {} sub (says error)", &self.url, &handle);
+                    self.failed_subs.insert(handle.clone());
+                }
+                "auth-required" => {
+                    if self.dbrelay.allow_auth == Some(false) {
+                        // we don't allow auth to this relay.
+                        // fail this subscription handle
+                        self.failed_subs.insert(handle.clone());
+                    } else {
+                        match self.auth_state {
+                            AuthState::None => {
+                                // authenticate
+                                self.authenticate().await?;
+
+                                // cork and retry once auth completes
+                                self.subscriptions_waiting_for_auth
+                                    .insert(handle, Unixtime::now());
+
+                                // return now, don't remove sub from map
+                                return Ok(());
+                            }
+                            AuthState::Waiting(_) => {
+                                // cork and retry once auth completes
+                                self.subscriptions_waiting_for_auth
+                                    .insert(handle, Unixtime::now());
+
+                                // return now, don't remove sub from map
+                                return Ok(());
+                            }
+                            AuthState::Authenticated => {
+                                // We are authenticated, but it doesn't think so.
+                                // The relay is broken. Fail this sub.
+                                self.failed_subs.insert(handle.clone());
+                            }
+                            AuthState::Failed => {
                                 // fail this subscription handle
                                 self.failed_subs.insert(handle.clone());
-                            } else {
-                                match self.auth_state {
-                                    AuthState::None => {
-                                        // authenticate
-                                        self.authenticate().await?;
-
-                                        // cork and retry once auth completes
-                                        self.subscriptions_waiting_for_auth
-                                            .insert(handle, Unixtime::now());
-
-                                        // return now, don't remove sub from map
-                                        return Ok(());
-                                    }
-                                    AuthState::Waiting(_) => {
-                                        // cork and retry once auth completes
-                                        self.subscriptions_waiting_for_auth
-                                            .insert(handle, Unixtime::now());
-
-                                        // return now, don't remove sub from map
-                                        return Ok(());
-                                    }
-                                    AuthState::Authenticated => {
-                                        // We are authenticated, but it doesn't think so.
-                                        // The relay is broken. Fail this sub.
-                                        self.failed_subs.insert(handle.clone());
-                                    }
-                                    AuthState::Failed => {
-                                        // fail this subscription handle
-                                        self.failed_subs.insert(handle.clone());
-                                    }
-                                }
                             }
                         }
-                        "restricted" => {
-                            tracing::warn!(
-                                "{} won't serve our {} sub (says restricted)",
-                                &self.url,
-                                &handle
-                            );
-                            self.failed_subs.insert(handle.clone());
-                        }
-                        _ => {
-                            tracing::debug!("{} closed with unknown prefix {}", &self.url, prefix);
-                            // Presume any other kind of Closed is an failed subscription
-                            self.failed_subs.insert(handle.clone());
-                        }
                     }
                 }
-
-                // Remove the subscription
-                tracing::debug!("{}: removed subscription {}", &self.url, handle);
-                let _ = self.subscription_map.remove(&handle);
+                "restricted" => {
+                    tracing::warn!(
+                        "{} won't serve our {} sub (says restricted)",
+                        &self.url,
+                        &handle
+                    );
+                    self.failed_subs.insert(handle.clone());
+                }
+                _ => {
+                    tracing::debug!("{} closed with unknown prefix {}", &self.url, prefix);
+                    // Presume any other kind of Closed is an failed subscription
+                    self.failed_subs.insert(handle.clone());
+                }
             }
         }
 
+        // Remove the subscription
+        tracing::debug!("{}: removed subscription {}", &self.url, handle);
+        self.drop_subscription(&handle).await?;
         Ok(())
     }
 }
@@ -0,0 +1,97 @@
+use super::Minion;
+use crate::globals::GLOBALS;
+use crate::storage::types::RelaySession1;
+use nostr_types::Unixtime;
+use std::time::Instant;
+
+/// What we measure about the relay while connected. This is folded into the
+/// relay record when the minion finishes.
+#[derive(Debug, Default)]
+pub(super) struct Measurements {
+    connected: Option<(Unixtime, Instant)>,
+    connect_ms: u32,
+    first_eose_ms: Option<u32>,
+    events: u64,
+    bytes: u64,
+    auth_successes: u64,
+    auth_failures: u64,
+    closed: Vec<String>,
+    notices: Vec<String>,
+}
+
+impl Minion {
+    /// We connected, having started to at `started`
+    pub(super) fn measure_connected(&mut self, started: Instant) {
+        self.measurements.connect_ms = started.elapsed().as_millis() as u32;
+        self.measurements.connected = Some((Unixtime::now(), Instant::now()));
+    }
+
+    pub(super) fn measure_message(&mut self, bytes: usize) {
+        self.measurements.bytes += bytes as u64;
+    }
+
+    pub(super) fn measure_event(&mut self) {
+        self.measurements.events += 1;
+    }
+
+    pub(super) fn measure_eose(&mut self) {
+        if self.measurements.first_eose_ms.is_none() {
+            if let Some((_, at)) = self.measurements.connected {
+                self.measurements.first_eose_ms = Some(at.elapsed().as_millis() as u32);
+            }
+        }
+    }
+
+    pub(super) fn measure_auth(&mut self, ok: bool) {
+        if ok {
+            self.measurements.auth_successes += 1;
+        } else {
+            self.measurements.auth_failures += 1;
+        }
+    }
+
+    pub(super) fn measure_closed(&mut self, message: &str) {
+        self.measurements.closed.push(message.to_owned());
+    }
+
+    pub(super) fn measure_notice(&mut self, message: &str) {
+        self.measurements.notices.push(message.to_owned());
+    }
+
+    /// Save what we measured into the relay metrics. This does nothing if we never
+    /// connected.
+    pub(crate) fn save_measurements(&mut self) {
+        let m = std::mem::take(&mut self.measurements);
+        let (at, connected) = match m.connected {
+            Some(c) => c,
+            None => return,
+        };
+
+        let session = RelaySession1 {
+            at,
+            connect_ms: m.connect_ms,
+            first_eose_ms: m.first_eose_ms,
+            seconds: connected.elapsed().as_secs() as u32,
+            events: m.events,
+            bytes: m.bytes,
+        };
+
+        if let Err(e) = GLOBALS.storage.modify_relay_metrics(
+            &self.url,
+            |metrics| {
+                metrics.add_session(session.clone());
+                metrics.auth_successes += m.auth_successes;
+                metrics.auth_failures += m.auth_failures;
+                for message in &m.closed {
+                    metrics.count_closed(message);
+                }
+                for message in &m.notices {
+                    metrics.count_notice(message);
+                }
+            },
+            None,
+        ) {
+            tracing::error!("{}: ERROR saving relay metrics: {}", &self.url, e);
+        }
+    }
+}
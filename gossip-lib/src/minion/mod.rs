@@ -1,7 +1,19 @@
+mod backoff;
+use backoff::{Backoff, PostingEvent};
+mod count;
+use count::CountQuery;
 mod filter_fns;
 use filter_fns::FeedRange;
 
 mod handle_websocket;
+mod metrics;
+use metrics::Measurements;
+mod planner;
+use planner::Limits;
+mod reconcile;
+use reconcile::{NegentropyPending, NegentropySession};
+mod search;
+use search::RelaySearch;
 mod subscription;
 mod subscription_map;
 
@@ -19,8 +31,8 @@ use http::uri::{Parts, Scheme};
 use http::Uri;
 use mime::Mime;
 use nostr_types::{
-    ClientMessage, EventKind, EventReference, Filter, Id, IdHex, NAddr, PreEvent, PublicKey,
-    PublicKeyHex, RelayInformationDocument, RelayUrl, Tag, Unixtime,
+    ClientMessage, Event, EventKind, EventReference, Filter, Id, IdHex, NAddr, PreEvent, PublicKey,
+    PublicKeyHex, RelayInformationDocument, RelayUrl, SubscriptionId, Tag, Unixtime,
 };
 use reqwest::Response;
 use std::borrow::Cow;
@@ -32,6 +44,7 @@ use tokio::net::TcpStream;
 use tokio::sync::broadcast::Receiver;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::watch::Receiver as WatchReceiver;
+use tokio::task::JoinHandle;
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use tungstenite::protocol::{Message as WsMessage, WebSocketConfig};
 
@@ -80,12 +93,19 @@ pub struct Minion {
     next_events_subscription_id: u32,
     posting_jobs: HashMap<u64, Vec<Id>>,
     posting_ids: HashMap<Id, u64>,
+    posting_events: HashMap<Id, PostingEvent>,
+    events_being_mined: HashMap<Id, JoinHandle<Result<Event, Error>>>,
     sought_events: HashMap<Id, EventSeekState>,
     last_message_sent: String,
     auth_challenge: String,
     subscriptions_waiting_for_auth: HashMap<String, Unixtime>,
     subscriptions_waiting_for_metadata: Vec<(u64, Vec<PublicKey>)>,
     subscriptions_rate_limited: Vec<String>,
+    subscriptions_waiting_for_slot: Vec<String>,
+    groups_waiting_for_slot: Vec<(String, Vec<Filter>)>,
+    events_rate_limited: Vec<Id>,
+    events_waiting_for_auth: Vec<Id>,
+    backoff: Backoff,
     general_feed_keys: Vec<PublicKey>,
     read_runstate: WatchReceiver<RunState>,
     exiting: Option<MinionExitReason>,
@@ -94,6 +114,15 @@ pub struct Minion {
     initial_handling: bool,
     loading_more: usize,
     subscriptions_empty_asof: Option<Unixtime>,
+    negentropy_sessions: HashMap<String, NegentropySession>,
+    negentropy_pending: HashMap<String, NegentropyPending>,
+    next_negentropy_id: u32,
+    negentropy_failed: bool,
+    count_queries: HashMap<String, CountQuery>,
+    next_count_id: u32,
+    search_queries: HashMap<String, RelaySearch>,
+    next_search_id: u32,
+    measurements: Measurements,
 }
 
 impl Drop for Minion {
@@ -126,12 +155,19 @@ impl Minion {
             next_events_subscription_id: 0,
             posting_jobs: HashMap::new(),
             posting_ids: HashMap::new(),
+            posting_events: HashMap::new(),
+            events_being_mined: HashMap::new(),
             sought_events: HashMap::new(),
             last_message_sent: String::new(),
             auth_challenge: "".to_string(),
             subscriptions_waiting_for_auth: HashMap::new(),
             subscriptions_waiting_for_metadata: Vec::new(),
             subscriptions_rate_limited: Vec::new(),
+            subscriptions_waiting_for_slot: Vec::new(),
+            groups_waiting_for_slot: Vec::new(),
+            events_rate_limited: Vec::new(),
+            events_waiting_for_auth: Vec::new(),
+            backoff: Backoff::default(),
             general_feed_keys: Vec::new(),
             read_runstate,
             exiting: None,
@@ -140,6 +176,15 @@ impl Minion {
             initial_handling: true,
             loading_more: 0,
             subscriptions_empty_asof: None,
+            negentropy_sessions: HashMap::new(),
+            negentropy_pending: HashMap::new(),
+            next_negentropy_id: 0,
+            negentropy_failed: false,
+            count_queries: HashMap::new(),
+            next_count_id: 0,
+            search_queries: HashMap::new(),
+            next_search_id: 0,
+            measurements: Measurements::default(),
         })
     }
 }
@@ -188,15 +233,18 @@ impl Minion {
 
         // Connect to the relay
         let websocket_stream = {
-            // Fetch NIP-11 data (if not fetched recently)
-            if let Some(last_nip11) = self.dbrelay.last_attempt_nip11 {
-                if (last_nip11 as i64) + 3600 < Unixtime::now().0 {
-                    if let Err(e) = self.fetch_nip11(fetcher_timeout).await {
-                        if matches!(e.kind, ErrorKind::ShuttingDown) {
-                            return Ok(MinionExitReason::GotShutdownMessage);
-                        } else {
-                            return Err(e);
-                        }
+            // Fetch NIP-11 data (if never fetched, or not fetched recently)
+            let nip11_due = match self.dbrelay.last_attempt_nip11 {
+                Some(last_nip11) => (last_nip11 as i64) + 3600 < Unixtime::now().0,
+                None => true,
+            };
+            if nip11_due {
+                // A relay without working NIP-11 may still be a fine relay
+                if let Err(e) = self.fetch_nip11(fetcher_timeout).await {
+                    if matches!(e.kind, ErrorKind::ShuttingDown) {
+                        return Ok(MinionExitReason::GotShutdownMessage);
+                    } else {
+                        tracing::warn!("{}: Unable to fetch NIP-11: {}", &self.url, e);
                     }
                 }
             }
@@ -225,6 +273,8 @@ impl Minion {
 
             let uri: http::Uri = self.url.as_str().parse::<Uri>()?;
             let host = uri.host().unwrap(); // fixme
+            let port = crate::proxy::websocket_port(&uri);
+            let tcp_host = host.to_owned();
             let req = req
                 .header("Host", host)
                 .header("Connection", "Upgrade")
@@ -260,9 +310,26 @@ impl Minion {
                 GLOBALS.storage.read_setting_websocket_connect_timeout_sec()
             };
 
+            let connect_started = std::time::Instant::now();
+
+            // Connect via the SOCKS5 proxy if configured. Relays that require the
+            // proxy will fail here if it is not.
+            let requires_proxy = self.dbrelay.requires_proxy();
             let connect_future = tokio::time::timeout(
                 std::time::Duration::new(connect_timeout_secs, 0),
-                tokio_tungstenite::connect_async_with_config(req, Some(config), false),
+                async move {
+                    let tcp_stream =
+                        crate::proxy::connect_tcp(&tcp_host, port, requires_proxy).await?;
+                    Ok::<_, Error>(
+                        tokio_tungstenite::client_async_tls_with_config(
+                            req,
+                            tcp_stream,
+                            Some(config),
+                            None,
+                        )
+                        .await?,
+                    )
+                },
             );
 
             let websocket_stream;
@@ -282,6 +349,7 @@ impl Minion {
             }
 
             tracing::debug!("{}: Connected", &self.url);
+            self.measure_connected(connect_started);
 
             websocket_stream
         };
@@ -359,6 +427,11 @@ impl Minion {
     }
 
     async fn fetch_nip11(&mut self, fetcher_timeout: std::time::Duration) -> Result<(), Error> {
+        // Never contact a relay that requires the proxy directly
+        if self.dbrelay.requires_proxy() && crate::proxy::socks5_proxy().is_none() {
+            return Err(ErrorKind::ProxyRequired(self.url.as_str().to_owned()).into());
+        }
+
         // Parse the URI
         let uri: http::Uri = self.url.as_str().parse::<Uri>()?;
         let mut parts: Parts = uri.into_parts();
@@ -372,7 +445,7 @@ impl Minion {
         };
         let uri = http::Uri::from_parts(parts)?;
 
-        let request_nip11_future = reqwest::Client::builder()
+        let request_nip11_future = crate::proxy::http_client_builder()?
             .timeout(fetcher_timeout)
             .redirect(reqwest::redirect::Policy::none())
             .gzip(true)
@@ -383,17 +456,25 @@ impl Minion {
             .header("Accept", "application/nostr+json")
             .send();
 
+        // Count the attempt even if it fails, so that we do not retry every connection
+        self.dbrelay.last_attempt_nip11 = Some(Unixtime::now().0 as u64);
+
         let response;
         tokio::select! {
             _ = self.read_runstate.wait_for(|runstate| !runstate.going_online()) => {
                 return Err(ErrorKind::ShuttingDown.into());
             },
             response_result = request_nip11_future => {
-                response = response_result?;
+                match response_result {
+                    Ok(r) => response = r,
+                    Err(e) => {
+                        GLOBALS.storage.write_relay(&self.dbrelay, None)?;
+                        return Err(e.into());
+                    }
+                }
             }
         }
 
-        self.dbrelay.last_attempt_nip11 = Some(Unixtime::now().0 as u64);
         let status = response.status();
         match Self::text_with_charset(response, "utf-8").await {
             Ok(text) => {
@@ -459,6 +540,15 @@ impl Minion {
 
                 // Try to subscribe to subscriptions waiting for something
                 self.try_subscribe_waiting().await?;
+
+                // Give up on slow negentropy reconciliation
+                self.check_negentropy_timeouts().await?;
+
+                // Give up on COUNTs the relay is not answering
+                self.check_count_timeouts()?;
+
+                // Give up on slow searches
+                self.check_search_timeouts().await?;
             },
             to_minion_message = self.from_overlord.recv() => {
                 let to_minion_message = match to_minion_message {
@@ -487,6 +577,7 @@ impl Minion {
                 }?;
 
                 GLOBALS.bytes_read.fetch_add(ws_message.len(), Ordering::Relaxed);
+                self.measure_message(ws_message.len());
 
                 tracing::trace!("{}: Handling message", &self.url);
                 match ws_message {
@@ -513,6 +604,8 @@ impl Minion {
             && self.subscriptions_waiting_for_auth.is_empty()
             && self.subscriptions_waiting_for_metadata.is_empty()
             && self.posting_jobs.is_empty()
+            && self.count_queries.is_empty()
+            && self.search_queries.is_empty()
         {
             let now = Unixtime::now();
             if let Some(when) = self.subscriptions_empty_asof {
@@ -541,21 +634,8 @@ impl Minion {
                 self.posting_jobs
                     .insert(message.job_id, vec![event.id, dmevent.id]);
 
-                let id = event.id;
-                self.posting_ids.insert(id, message.job_id);
-                let msg = ClientMessage::Event(event);
-                let wire = serde_json::to_string(&msg)?;
-                let ws_stream = self.stream.as_mut().unwrap();
-                self.last_message_sent = wire.clone();
-                ws_stream.send(WsMessage::Text(wire)).await?;
-
-                let id = dmevent.id;
-                self.posting_ids.insert(id, message.job_id);
-                let msg = ClientMessage::Event(dmevent);
-                let wire = serde_json::to_string(&msg)?;
-                let ws_stream = self.stream.as_mut().unwrap();
-                self.last_message_sent = wire.clone();
-                ws_stream.send(WsMessage::Text(wire)).await?;
+                self.post_event(*event, message.job_id).await?;
+                self.post_event(*dmevent, message.job_id).await?;
 
                 tracing::info!("Advertised relay lists to {}", &self.url)
             }
@@ -582,6 +662,11 @@ impl Minion {
                     );
                 }
             }
+            ToMinionPayloadDetail::Count(keys) => {
+                for key in keys {
+                    self.count(message.job_id, key).await?;
+                }
+            }
             ToMinionPayloadDetail::FetchEvent(id) => {
                 self.sought_events
                     .entry(id)
@@ -604,16 +689,13 @@ impl Minion {
                 );
 
                 for event in events.drain(..) {
-                    let id = event.id;
-                    self.posting_ids.insert(id, message.job_id);
-                    let msg = ClientMessage::Event(Box::new(event));
-                    let wire = serde_json::to_string(&msg)?;
-                    let ws_stream = self.stream.as_mut().unwrap();
-                    self.last_message_sent = wire.clone();
-                    ws_stream.send(WsMessage::Text(wire)).await?;
+                    self.post_event(event, message.job_id).await?;
                     tracing::info!("Posted event to {}", &self.url);
                 }
             }
+            ToMinionPayloadDetail::Search(query) => {
+                self.search(message.job_id, query).await?;
+            }
             ToMinionPayloadDetail::Shutdown => {
                 tracing::debug!("{}: Websocket listener shutting down", &self.url);
                 self.exiting = Some(MinionExitReason::GotShutdownMessage);
@@ -662,6 +744,9 @@ impl Minion {
             ToMinionPayloadDetail::SubscribeNip46 => {
                 self.subscribe_nip46(message.job_id).await?;
             }
+            ToMinionPayloadDetail::SubscribeNwc => {
+                self.subscribe_nwc(message.job_id).await?;
+            }
             ToMinionPayloadDetail::TempSubscribeGeneralFeedChunk(anchor) => {
                 self.temp_subscribe_general_feed_chunk(message.job_id, anchor)
                     .await?;
@@ -1057,6 +1142,16 @@ impl Minion {
         Ok(())
     }
 
+    async fn subscribe_nwc(&mut self, job_id: u64) -> Result<(), Error> {
+        let filters = filter_fns::nwc();
+
+        if !filters.is_empty() {
+            self.subscribe(filters, "nwc", job_id).await?;
+        }
+
+        Ok(())
+    }
+
     async fn get_events(&mut self) -> Result<(), Error> {
         // Collect all the sought events we have not yet asked for, and
         // presumptively mark them as having been asked for.
@@ -1138,18 +1233,11 @@ impl Minion {
             }
         }
 
-        // Retry rate-limited subscriptions
-        if !self.subscriptions_rate_limited.is_empty() {
-            let mut handles = std::mem::take(&mut self.subscriptions_rate_limited);
-            for handle in handles.drain(..) {
-                tracing::info!(
-                    "Sending previously rate-limited subscription {} to {}",
-                    handle,
-                    &self.url
-                );
-                self.send_subscription(&handle).await?;
-            }
-        }
+        // Retry rate-limited subscriptions and events, and events waiting for auth
+        self.send_held().await?;
+
+        // Send subscriptions that were waiting for the relay to have room
+        self.send_waiting_for_slot().await?;
 
         Ok(())
     }
@@ -1258,26 +1346,15 @@ impl Minion {
 
             sub.set_filters(filters);
             let old_job_id = sub.change_job_id(job_id);
-            let id = sub.get_id();
-            tracing::debug!(
-                "UPDATED SUBSCRIPTION on {} handle={}, id={}",
-                &self.url,
-                handle,
-                id
-            );
+            tracing::debug!("UPDATED SUBSCRIPTION on {} handle={}", &self.url, handle);
             self.to_overlord.send(ToOverlordMessage::MinionJobUpdated(
                 self.url.clone(),
                 old_job_id,
                 job_id,
             ))?;
         } else {
-            let id = self.subscription_map.add(handle, job_id, filters);
-            tracing::debug!(
-                "NEW SUBSCRIPTION on {} handle={}, id={}",
-                &self.url,
-                handle,
-                &id
-            );
+            self.subscription_map.add(handle, job_id, filters);
+            tracing::debug!("NEW SUBSCRIPTION on {} handle={}", &self.url, handle);
         }
 
         if matches!(self.auth_state, AuthState::Waiting(_)) {
@@ -1287,15 +1364,164 @@ impl Minion {
             return Ok(());
         }
 
-        self.send_subscription(handle).await?;
+        // Reconcile first if the relay supports it, otherwise just REQ
+        if !self.try_negentropy(handle).await? {
+            self.send_subscription(handle).await?;
+        }
         Ok(())
     }
 
     async fn send_subscription(&mut self, handle: &str) -> Result<(), Error> {
-        let req_message = match self.subscription_map.get(handle) {
-            Some(sub) => sub.req_message(),
+        // Hold it until the relay is ready for more
+        if self.backing_off() {
+            if !self.subscriptions_rate_limited.iter().any(|h| h == handle) {
+                self.subscriptions_rate_limited.push(handle.to_owned());
+            }
+            return Ok(());
+        }
+
+        let filters = match self.subscription_map.get(handle) {
+            Some(sub) => sub.get_filters().to_vec(),
             None => return Ok(()), // Not much we can do. It is not there.
         };
+        let limits = self.limits();
+        let mut groups = planner::plan_filters(filters, &limits);
+
+        // Ids that carried only this handle are reused, replacing their REQ. Ids
+        // it shared with other handles are asked for again without it.
+        let (mut ids, shared) = self.subscription_map.detach(handle);
+        self.subscriptions_waiting_for_slot.retain(|h| h != handle);
+        self.groups_waiting_for_slot.retain(|(h, _)| h != handle);
+        self.send_shared_reqs(shared).await?;
+
+        // If the relay has no room, wait for some (see send_waiting_for_slot)
+        let room = limits
+            .max_subscriptions
+            .saturating_sub(self.subscription_map.open_ids());
+        if groups.len() > room && (room == 0 || groups.len() <= limits.max_subscriptions) {
+            for id in ids.drain(..) {
+                self.close_subscription(&id).await?;
+            }
+            tracing::debug!(
+                "{}: Subscription {} waiting for the relay to have room",
+                &self.url,
+                handle
+            );
+            self.subscriptions_waiting_for_slot.push(handle.to_owned());
+            return Ok(());
+        }
+
+        // It needs more REQs than the relay allows at once, so send what fits now
+        // and the rest as the relay makes room
+        if groups.len() > room {
+            tracing::debug!(
+                "{}: Subscription {} needs {} REQs, sending {} now",
+                &self.url,
+                handle,
+                groups.len(),
+                room
+            );
+            for filters in groups.split_off(room) {
+                self.groups_waiting_for_slot
+                    .push((handle.to_owned(), filters));
+            }
+        }
+
+        for filters in groups {
+            let id = if ids.is_empty() {
+                self.subscription_map.next_id()
+            } else {
+                ids.remove(0)
+            };
+            self.send_req(&id, vec![handle.to_owned()], filters).await?;
+        }
+        for id in ids.drain(..) {
+            self.close_subscription(&id).await?;
+        }
+        Ok(())
+    }
+
+    // Send subscriptions that were waiting for the relay to have room, coalescing as
+    // many of them into each REQ as the relay's limits allow
+    async fn send_waiting_for_slot(&mut self) -> Result<(), Error> {
+        let limits = self.limits();
+
+        // First the rest of subscriptions that were too big to send at once
+        while !self.groups_waiting_for_slot.is_empty()
+            && self.subscription_map.open_ids() < limits.max_subscriptions
+            && !self.backing_off()
+        {
+            let (handle, filters) = self.groups_waiting_for_slot.remove(0);
+            if self.subscription_map.get(&handle).is_none() {
+                continue;
+            }
+            let id = self.subscription_map.next_id();
+            self.send_req(&id, vec![handle], filters).await?;
+        }
+
+        while !self.subscriptions_waiting_for_slot.is_empty()
+            && self.subscription_map.open_ids() < limits.max_subscriptions
+            && !self.backing_off()
+        {
+            let mut handles: Vec<String> = Vec::new();
+            let mut filters: Vec<Filter> = Vec::new();
+            while let Some(handle) = self.subscriptions_waiting_for_slot.first().cloned() {
+                let more = match self.subscription_map.get(&handle) {
+                    Some(sub) => sub.get_filters().to_vec(),
+                    None => {
+                        self.subscriptions_waiting_for_slot.remove(0);
+                        continue;
+                    }
+                };
+                let mut combined = filters.clone();
+                combined.extend(more);
+                let combined = planner::merge_filters(combined);
+                if !handles.is_empty()
+                    && limits
+                        .max_filters
+                        .map(|max| combined.len() > max)
+                        .unwrap_or(false)
+                {
+                    break;
+                }
+                self.subscriptions_waiting_for_slot.remove(0);
+                handles.push(handle);
+                filters = combined;
+            }
+
+            match handles.len() {
+                0 => break,
+                1 => {
+                    // On its own it may need splitting
+                    self.send_subscription(&handles[0]).await?;
+                    if self.subscriptions_waiting_for_slot.contains(&handles[0]) {
+                        break;
+                    }
+                }
+                _ => {
+                    tracing::debug!(
+                        "{}: Coalescing subscriptions {} into one",
+                        &self.url,
+                        handles.join(", ")
+                    );
+                    let id = self.subscription_map.next_id();
+                    let filters = planner::plan_filters(filters, &limits).concat();
+                    self.send_req(&id, handles, filters).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // REQ on this id, carrying these handles
+    async fn send_req(
+        &mut self,
+        id: &str,
+        handles: Vec<String>,
+        filters: Vec<Filter>,
+    ) -> Result<(), Error> {
+        self.subscription_map.attach(id, handles);
+        let req_message = ClientMessage::Req(SubscriptionId(id.to_owned()), filters);
         let wire = serde_json::to_string(&req_message)?;
         let websocket_stream = self.stream.as_mut().unwrap();
         tracing::trace!("{}: Sending {}", &self.url, &wire);
@@ -1304,36 +1530,60 @@ impl Minion {
         Ok(())
     }
 
-    async fn unsubscribe(&mut self, handle: &str) -> Result<(), Error> {
-        if !self.subscription_map.has(handle) {
-            return Ok(());
-        }
-        // If it was a chunk, update loading_more
-        if handle.contains("_feed_chunk") {
-            self.loading_more -= 1;
-            let _ = GLOBALS.loading_more.fetch_sub(1, Ordering::SeqCst);
+    /// REQ again on ids that a handle was taken off, with the filters of the
+    /// handles they still carry, so that the relay stops sending what the handle
+    /// asked for
+    pub(super) async fn send_shared_reqs(&mut self, ids: Vec<String>) -> Result<(), Error> {
+        let limits = self.limits();
+        for id in ids {
+            let handles = self.subscription_map.get_handles_by_id(&id);
+            let mut filters: Vec<Filter> = Vec::new();
+            for handle in handles.iter() {
+                if let Some(sub) = self.subscription_map.get(handle) {
+                    filters.extend(sub.get_filters().iter().cloned());
+                }
+            }
+            if filters.is_empty() {
+                self.subscription_map.remove_id(&id);
+                self.close_subscription(&id).await?;
+                continue;
+            }
+            let filters = planner::plan_filters(filters, &limits).concat();
+            self.send_req(&id, handles, filters).await?;
         }
-        let subscription = self.subscription_map.get(handle).unwrap();
-        let wire = serde_json::to_string(&subscription.close_message())?;
+        Ok(())
+    }
+
+    /// Whether part of a subscription is still waiting for the relay to have room
+    fn has_groups_waiting_for_slot(&self, handle: &str) -> bool {
+        self.groups_waiting_for_slot
+            .iter()
+            .any(|(h, _)| h == handle)
+    }
+
+    async fn close_subscription(&mut self, id: &str) -> Result<(), Error> {
+        let close_message = ClientMessage::Close(SubscriptionId(id.to_owned()));
+        let wire = serde_json::to_string(&close_message)?;
         let websocket_stream = self.stream.as_mut().unwrap();
         tracing::trace!("{}: Sending {}", &self.url, &wire);
         self.last_message_sent = wire.clone();
         websocket_stream.send(WsMessage::Text(wire.clone())).await?;
-        let id = self.subscription_map.remove(handle);
-        if let Some(id) = id {
-            tracing::debug!(
-                "END SUBSCRIPTION on {} handle={}, id={}",
-                &self.url,
-                handle,
-                &id
-            );
-        } else {
-            tracing::debug!(
-                "END SUBSCRIPTION on {} handle={} NOT FOUND",
-                &self.url,
-                handle
-            );
+        Ok(())
+    }
+
+    async fn unsubscribe(&mut self, handle: &str) -> Result<(), Error> {
+        let subscription = match self.subscription_map.get(handle) {
+            Some(sub) => sub,
+            None => return Ok(()),
+        };
+        self.cancel_negentropy(handle).await?;
+        // If it was a chunk, update loading_more
+        if handle.contains("_feed_chunk") {
+            self.loading_more -= 1;
+            let _ = GLOBALS.loading_more.fetch_sub(1, Ordering::SeqCst);
         }
+        self.drop_subscription(handle).await?;
+        tracing::debug!("END SUBSCRIPTION on {} handle={}", &self.url, handle);
         self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
             self.url.clone(),
             subscription.get_job_id(),
@@ -1341,6 +1591,22 @@ impl Minion {
         Ok(())
     }
 
+    /// Forget a subscription, closing what it alone was using on the wire
+    pub(super) async fn drop_subscription(&mut self, handle: &str) -> Result<(), Error> {
+        self.subscriptions_waiting_for_slot.retain(|h| h != handle);
+        self.groups_waiting_for_slot.retain(|(h, _)| h != handle);
+        let (emptied, shared) = self.subscription_map.remove(handle);
+        for id in emptied {
+            self.close_subscription(&id).await?;
+        }
+        self.send_shared_reqs(shared).await
+    }
+
+    /// What the relay lets us ask of it at once
+    pub(super) fn limits(&self) -> Limits {
+        Limits::new(self.nip11.as_ref().or(self.dbrelay.nip11.as_ref()))
+    }
+
     async fn authenticate(&mut self) -> Result<(), Error> {
         match self.auth_state {
             AuthState::Authenticated => return Ok(()),
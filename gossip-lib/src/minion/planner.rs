@@ -0,0 +1,184 @@
+use nostr_types::{Filter, RelayInformationDocument};
+
+// When a relay does not say how many subscriptions it allows, we hold at most this
+// many open on it. Relays that do not advertise a limit commonly enforce one
+// around this size.
+const DEFAULT_MAX_SUBSCRIPTIONS: usize = 20;
+
+/// How much a relay lets us ask of it at once, from its NIP-11 `limitation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Limits {
+    pub max_subscriptions: usize,
+    pub max_filters: Option<usize>,
+    pub max_limit: Option<usize>,
+    pub max_subid_length: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_subscriptions: DEFAULT_MAX_SUBSCRIPTIONS,
+            max_filters: None,
+            max_limit: None,
+            max_subid_length: None,
+        }
+    }
+}
+
+impl Limits {
+    pub(super) fn new(nip11: Option<&RelayInformationDocument>) -> Limits {
+        let limitation = match nip11.and_then(|nip11| nip11.limitation.as_ref()) {
+            Some(limitation) => limitation,
+            None => return Limits::default(),
+        };
+
+        // Zero would stop us entirely, which is surely not what the relay meant
+        let positive = |n: Option<usize>| n.filter(|n| *n > 0);
+        Limits {
+            max_subscriptions: positive(limitation.max_subscriptions)
+                .unwrap_or(DEFAULT_MAX_SUBSCRIPTIONS),
+            max_filters: positive(limitation.max_filters),
+            max_limit: positive(limitation.max_limit),
+            max_subid_length: positive(limitation.max_subid_length),
+        }
+    }
+
+    /// A subscription id made from a prefix and a number, shortening the prefix to
+    /// its first letter if the relay wants ids shorter than that. (Our REQ ids are
+    /// bare numbers, so the letter keeps these apart from them.)
+    pub(super) fn subid(&self, prefix: &str, n: u32) -> String {
+        let subid = format!("{}{}", prefix, n);
+        match (self.max_subid_length, prefix.chars().next()) {
+            (Some(max), Some(first)) if subid.len() > max => format!("{}{}", first, n),
+            _ => subid,
+        }
+    }
+}
+
+/// Merge filters that differ only in their authors, or only in their ids, so that
+/// we ask with fewer of them. Filters with a limit are left alone, as the limit
+/// would then apply to the merged whole.
+pub(super) fn merge_filters(filters: Vec<Filter>) -> Vec<Filter> {
+    let mut output: Vec<Filter> = Vec::new();
+    'next: for filter in filters {
+        if filter.limit.is_none() {
+            for merged in output.iter_mut() {
+                if merged.limit.is_some() {
+                    continue;
+                }
+                if !merged.authors.is_empty()
+                    && !filter.authors.is_empty()
+                    && same_except(merged, &filter, |f| f.authors.clear())
+                {
+                    for author in filter.authors {
+                        if !merged.authors.contains(&author) {
+                            merged.authors.push(author);
+                        }
+                    }
+                    continue 'next;
+                }
+                if !merged.ids.is_empty()
+                    && !filter.ids.is_empty()
+                    && same_except(merged, &filter, |f| f.ids.clear())
+                {
+                    for id in filter.ids {
+                        if !merged.ids.contains(&id) {
+                            merged.ids.push(id);
+                        }
+                    }
+                    continue 'next;
+                }
+            }
+        }
+        output.push(filter);
+    }
+    output
+}
+
+// Whether two filters are the same once `clear` has been applied to both
+fn same_except(a: &Filter, b: &Filter, clear: impl Fn(&mut Filter)) -> bool {
+    let mut a = a.clone();
+    let mut b = b.clone();
+    clear(&mut a);
+    clear(&mut b);
+    serde_json::to_value(&a).ok() == serde_json::to_value(&b).ok()
+}
+
+/// Plan the REQs for a set of filters: merge what we can, hold limits to the
+/// relay's `max_limit`, and group them by its `max_filters`
+pub(super) fn plan_filters(filters: Vec<Filter>, limits: &Limits) -> Vec<Vec<Filter>> {
+    let mut filters = merge_filters(filters);
+    if let Some(max_limit) = limits.max_limit {
+        for filter in filters.iter_mut() {
+            if let Some(limit) = filter.limit {
+                filter.limit = Some(limit.min(max_limit));
+            }
+        }
+    }
+
+    match limits.max_filters {
+        Some(max) if filters.len() > max => filters.chunks(max).map(|c| c.to_vec()).collect(),
+        _ => vec![filters],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nostr_types::{EventKind, PublicKey};
+
+    fn author_filter(pubkey: PublicKey, limit: Option<usize>) -> Filter {
+        Filter {
+            authors: vec![(&pubkey).into()],
+            kinds: vec![EventKind::Metadata],
+            limit,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_filters() {
+        let keys: Vec<PublicKey> = (0..4)
+            .map(|_| nostr_types::PrivateKey::generate().public_key())
+            .collect();
+
+        // Filters that differ only by author merge into one
+        let merged = merge_filters(vec![
+            author_filter(keys[0], None),
+            author_filter(keys[1], None),
+            author_filter(keys[0], None),
+        ]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].authors.len(), 2);
+
+        // But not if they are limited
+        let limited = merge_filters(vec![
+            author_filter(keys[0], Some(10)),
+            author_filter(keys[1], Some(10)),
+        ]);
+        assert_eq!(limited.len(), 2);
+
+        // Limits are held to max_limit and filters grouped by max_filters
+        let limits = Limits {
+            max_filters: Some(2),
+            max_limit: Some(5),
+            ..Default::default()
+        };
+        let groups = plan_filters(
+            keys.iter().map(|k| author_filter(*k, Some(10))).collect(),
+            &limits,
+        );
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().all(|g| g.len() == 2));
+        assert!(groups.iter().flatten().all(|f| f.limit == Some(5)));
+
+        assert_eq!(limits.subid("search_", 12), "search_12");
+        let short = Limits {
+            max_subid_length: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(short.subid("search_", 12), "s12");
+        assert_eq!(short.subid("é_search_", 12), "é12");
+        assert_eq!(short.subid("", 123456), "123456");
+    }
+}
@@ -0,0 +1,343 @@
+use super::Minion;
+use crate::comms::ToOverlordMessage;
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use futures_util::sink::SinkExt;
+use negentropy::{Negentropy, NegentropyStorageVector};
+use nostr_types::{Filter, IdHex, Unixtime};
+use std::sync::atomic::Ordering;
+use tungstenite::protocol::Message as WsMessage;
+
+// Maximum size of each negentropy message we send (hex encoding doubles this)
+const FRAME_SIZE_LIMIT: u64 = 60_000;
+
+// If reconciliation has not completed in this time, we give up and REQ instead
+const RECONCILE_TIMEOUT_SECS: i64 = 30;
+
+// How many ids we ask for in a single filter after reconciliation
+const IDS_PER_FILTER: usize = 500;
+
+/// A single NEG-OPEN session, one per filter of a subscription
+pub(super) struct NegentropySession {
+    handle: String,
+    negentropy: Negentropy<'static, NegentropyStorageVector>,
+}
+
+/// A subscription whose REQ is being held back while we reconcile
+pub(super) struct NegentropyPending {
+    original_filters: Vec<Filter>,
+    live_filters: Vec<Filter>,
+    need_ids: Vec<IdHex>,
+    sessions_remaining: usize,
+    started: Unixtime,
+}
+
+impl Minion {
+    fn supports_negentropy(&self) -> bool {
+        if self.negentropy_failed || !GLOBALS.storage.read_setting_use_negentropy() {
+            return false;
+        }
+        match self.nip11.as_ref().or(self.dbrelay.nip11.as_ref()) {
+            Some(nip11) => nip11.supported_nips.contains(&77),
+            None => false,
+        }
+    }
+
+    /// Try to replace the REQ of this subscription with NIP-77 reconciliation.
+    ///
+    /// Each filter is reconciled against our local events, and only once all of them
+    /// complete is a REQ sent, asking for just the ids we are missing (plus the live
+    /// part of the subscription). Returns false if this subscription cannot be handled
+    /// this way, in which case the caller should send the REQ as usual.
+    pub(super) async fn try_negentropy(&mut self, handle: &str) -> Result<bool, Error> {
+        self.cancel_negentropy(handle).await?;
+
+        if !self.supports_negentropy() {
+            return Ok(false);
+        }
+
+        let original_filters = match self.subscription_map.get(handle) {
+            Some(sub) => sub.get_filters().to_vec(),
+            None => return Ok(false),
+        };
+
+        let now = Unixtime::now();
+        let mut reconcile_filters: Vec<Filter> = Vec::new();
+        let mut live_filters: Vec<Filter> = Vec::new();
+
+        for filter in original_filters.iter() {
+            let mut reconcile_filter = filter.clone();
+            reconcile_filter.limit = None;
+            reconcile_filter.until = Some(filter.until.unwrap_or(now));
+
+            if let Some(limit) = filter.limit {
+                // We can only reconcile a limited filter if we already have at least
+                // `limit` events, in which case the relay's answer to the REQ would
+                // lie within the time range of the ones we have.
+                let mut items = match GLOBALS.storage.find_event_index_items(&reconcile_filter)? {
+                    Some(items) => items,
+                    None => return Ok(false),
+                };
+                if limit == 0 || items.len() < limit {
+                    return Ok(false);
+                }
+                items.sort_by(|a, b| b.cmp(a));
+                let oldest = items[limit - 1].0;
+                reconcile_filter.since = Some(match filter.since {
+                    Some(since) => since.max(oldest),
+                    None => oldest,
+                });
+            }
+
+            if filter.until.is_none() {
+                // Keep the subscription open for new events
+                let mut live_filter = filter.clone();
+                live_filter.since = Some(now);
+                live_filter.limit = None;
+                live_filters.push(live_filter);
+            }
+
+            reconcile_filters.push(reconcile_filter);
+        }
+
+        // Build all of the sessions before sending anything, so that we can still
+        // fall back cleanly
+        let mut sessions: Vec<(Filter, Negentropy<'static, NegentropyStorageVector>)> = Vec::new();
+        for filter in reconcile_filters.drain(..) {
+            let items = match GLOBALS.storage.find_event_index_items(&filter)? {
+                Some(items) => items,
+                None => return Ok(false),
+            };
+            let mut storage = NegentropyStorageVector::with_capacity(items.len());
+            for (created_at, id) in items.iter() {
+                storage.insert(created_at.0 as u64, negentropy::Id::new(id.0))?;
+            }
+            storage.seal()?;
+            let negentropy = Negentropy::owned(storage, FRAME_SIZE_LIMIT)?;
+            sessions.push((filter, negentropy));
+        }
+
+        self.negentropy_pending.insert(
+            handle.to_owned(),
+            NegentropyPending {
+                original_filters,
+                live_filters,
+                need_ids: Vec::new(),
+                sessions_remaining: sessions.len(),
+                started: now,
+            },
+        );
+
+        for (filter, mut negentropy) in sessions.drain(..) {
+            let subid = self.limits().subid("neg_", self.next_negentropy_id);
+            self.next_negentropy_id += 1;
+            let initial = negentropy.initiate()?;
+            self.negentropy_sessions.insert(
+                subid.clone(),
+                NegentropySession {
+                    handle: handle.to_owned(),
+                    negentropy,
+                },
+            );
+            let msg = serde_json::json!(["NEG-OPEN", subid, filter, hex::encode(initial)]);
+            self.send_negentropy_message(msg).await?;
+        }
+
+        tracing::debug!("{}: NEGENTROPY reconciling handle={}", &self.url, handle);
+
+        Ok(true)
+    }
+
+    /// Handle a NEG-MSG or NEG-ERR from the relay
+    pub(super) async fn handle_negentropy_message(
+        &mut self,
+        ws_message: &str,
+    ) -> Result<(), Error> {
+        let parts: Vec<serde_json::Value> = serde_json::from_str(ws_message)?;
+        let command = parts.first().and_then(|v| v.as_str()).unwrap_or("");
+        let subid = parts.get(1).and_then(|v| v.as_str()).unwrap_or("");
+        let payload = parts.get(2).and_then(|v| v.as_str()).unwrap_or("");
+
+        let handle = match self.negentropy_sessions.get(subid) {
+            Some(session) => session.handle.clone(),
+            None => {
+                tracing::debug!(
+                    "{}: {} for unknown negentropy session {}",
+                    &self.url,
+                    command,
+                    subid
+                );
+                return Ok(());
+            }
+        };
+
+        match command {
+            "NEG-MSG" => match self.reconcile_step(subid, payload) {
+                Ok(Some(next)) => {
+                    let msg = serde_json::json!(["NEG-MSG", subid, hex::encode(next)]);
+                    self.send_negentropy_message(msg).await?;
+                }
+                Ok(None) => {
+                    self.negentropy_sessions.remove(subid);
+                    let msg = serde_json::json!(["NEG-CLOSE", subid]);
+                    self.send_negentropy_message(msg).await?;
+                    self.negentropy_session_done(&handle).await?;
+                }
+                Err(e) => {
+                    tracing::warn!("{}: negentropy failed: {}", &self.url, e);
+                    self.negentropy_failed = true;
+                    self.negentropy_fallback(&handle).await?;
+                }
+            },
+            "NEG-ERR" => {
+                tracing::info!("{}: NEG-ERR: {}", &self.url, payload);
+                self.negentropy_sessions.remove(subid);
+                self.negentropy_failed = true;
+                self.negentropy_fallback(&handle).await?;
+            }
+            _ => {
+                tracing::debug!("{}: Unexpected negentropy message {}", &self.url, command);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Process one relay message, collecting the ids we need. Returns the next
+    // message to send, or None if reconciliation of this session is complete.
+    fn reconcile_step(&mut self, subid: &str, payload: &str) -> Result<Option<Vec<u8>>, Error> {
+        let query = hex::decode(payload).map_err(|e| format!("Bad negentropy hex: {e}"))?;
+
+        let session = match self.negentropy_sessions.get_mut(subid) {
+            Some(session) => session,
+            None => return Ok(None),
+        };
+
+        // We do not upload the events that we have and they don't; that is the job
+        // of whoever authored them.
+        let mut have_ids: Vec<negentropy::Id> = Vec::new();
+        let mut need_ids: Vec<negentropy::Id> = Vec::new();
+        let next = session
+            .negentropy
+            .reconcile_with_ids(&query, &mut have_ids, &mut need_ids)?;
+
+        if let Some(pending) = self.negentropy_pending.get_mut(&session.handle) {
+            pending.need_ids.extend(
+                need_ids
+                    .drain(..)
+                    .map(|id| nostr_types::Id(id.to_bytes()).into()),
+            );
+        }
+
+        Ok(next)
+    }
+
+    // One session of a subscription finished. If it was the last, send the REQ.
+    async fn negentropy_session_done(&mut self, handle: &str) -> Result<(), Error> {
+        let pending = match self.negentropy_pending.get_mut(handle) {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        pending.sessions_remaining = pending.sessions_remaining.saturating_sub(1);
+        if pending.sessions_remaining > 0 {
+            return Ok(());
+        }
+        let pending = self.negentropy_pending.remove(handle).unwrap();
+
+        tracing::debug!(
+            "{}: NEGENTROPY complete handle={}, missing {} events",
+            &self.url,
+            handle,
+            pending.need_ids.len()
+        );
+
+        let mut filters = pending.live_filters;
+        for chunk in pending.need_ids.chunks(IDS_PER_FILTER) {
+            filters.push(Filter {
+                ids: chunk.to_vec(),
+                ..Default::default()
+            });
+        }
+
+        if filters.is_empty() {
+            // Nothing to fetch and nothing to listen for, so the job is done
+            if handle.contains("_feed_chunk") {
+                self.loading_more -= 1;
+                let _ = GLOBALS.loading_more.fetch_sub(1, Ordering::SeqCst);
+            }
+            if let Some(sub) = self.subscription_map.get(handle) {
+                self.drop_subscription(handle).await?;
+                self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
+                    self.url.clone(),
+                    sub.get_job_id(),
+                ))?;
+            }
+            return Ok(());
+        }
+
+        if let Some(sub) = self.subscription_map.get_mut(handle) {
+            sub.set_filters(filters);
+        }
+        self.send_subscription(handle).await
+    }
+
+    // Abandon reconciliation for this subscription and send the original REQ
+    async fn negentropy_fallback(&mut self, handle: &str) -> Result<(), Error> {
+        let pending = match self.negentropy_pending.get(handle) {
+            Some(pending) => pending.original_filters.clone(),
+            None => return Ok(()),
+        };
+        self.cancel_negentropy(handle).await?;
+        if let Some(sub) = self.subscription_map.get_mut(handle) {
+            sub.set_filters(pending);
+        }
+        self.send_subscription(handle).await
+    }
+
+    /// Close any negentropy sessions of this subscription
+    pub(super) async fn cancel_negentropy(&mut self, handle: &str) -> Result<(), Error> {
+        self.negentropy_pending.remove(handle);
+        let subids: Vec<String> = self
+            .negentropy_sessions
+            .iter()
+            .filter(|(_, session)| session.handle == handle)
+            .map(|(subid, _)| subid.clone())
+            .collect();
+        for subid in subids {
+            self.negentropy_sessions.remove(&subid);
+            let msg = serde_json::json!(["NEG-CLOSE", subid]);
+            self.send_negentropy_message(msg).await?;
+        }
+        Ok(())
+    }
+
+    /// Fall back to REQ for any reconciliation that is taking too long
+    pub(super) async fn check_negentropy_timeouts(&mut self) -> Result<(), Error> {
+        let now = Unixtime::now();
+        let expired: Vec<String> = self
+            .negentropy_pending
+            .iter()
+            .filter(|(_, pending)| pending.started.0 + RECONCILE_TIMEOUT_SECS < now.0)
+            .map(|(handle, _)| handle.clone())
+            .collect();
+        for handle in expired {
+            tracing::info!(
+                "{}: negentropy timed out on {}, falling back to REQ",
+                &self.url,
+                handle
+            );
+            self.negentropy_failed = true;
+            self.negentropy_fallback(&handle).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_negentropy_message(&mut self, msg: serde_json::Value) -> Result<(), Error> {
+        let wire = serde_json::to_string(&msg)?;
+        let websocket_stream = self.stream.as_mut().unwrap();
+        tracing::trace!("{}: Sending {}", &self.url, &wire);
+        self.last_message_sent = wire.clone();
+        websocket_stream.send(WsMessage::Text(wire)).await?;
+        Ok(())
+    }
+}
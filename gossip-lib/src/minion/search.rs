@@ -0,0 +1,78 @@
+use super::Minion;
+use crate::comms::ToOverlordMessage;
+use crate::error::Error;
+use crate::search::SearchQuery;
+use futures_util::sink::SinkExt;
+use nostr_types::Unixtime;
+use tungstenite::protocol::Message as WsMessage;
+
+// If the relay has not finished a search in this time, we give up on it
+const SEARCH_TIMEOUT_SECS: i64 = 30;
+
+/// A search (NIP-50) we sent and are receiving results for
+pub(super) struct RelaySearch {
+    pub(super) job_id: u64,
+    asked: Unixtime,
+}
+
+impl Minion {
+    /// Ask the relay to search (NIP-50)
+    pub(super) async fn search(&mut self, job_id: u64, query: SearchQuery) -> Result<(), Error> {
+        let subid = self.limits().subid("search_", self.next_search_id);
+        self.next_search_id += 1;
+
+        let mut req = vec![serde_json::json!("REQ"), serde_json::json!(subid)];
+        req.extend(query.relay_filters()?);
+        self.search_queries.insert(
+            subid,
+            RelaySearch {
+                job_id,
+                asked: Unixtime::now(),
+            },
+        );
+
+        let wire = serde_json::to_string(&req)?;
+        let websocket_stream = self.stream.as_mut().unwrap();
+        tracing::trace!("{}: Sending {}", &self.url, &wire);
+        self.last_message_sent = wire.clone();
+        websocket_stream.send(WsMessage::Text(wire)).await?;
+        Ok(())
+    }
+
+    /// Finish a search, if this subscription id is one of ours. Returns false if
+    /// it is not.
+    pub(super) async fn search_done(&mut self, subid: &str) -> Result<bool, Error> {
+        let search = match self.search_queries.remove(subid) {
+            Some(search) => search,
+            None => return Ok(false),
+        };
+
+        let wire = serde_json::to_string(&serde_json::json!(["CLOSE", subid]))?;
+        if let Some(websocket_stream) = self.stream.as_mut() {
+            self.last_message_sent = wire.clone();
+            websocket_stream.send(WsMessage::Text(wire)).await?;
+        }
+
+        self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
+            self.url.clone(),
+            search.job_id,
+        ))?;
+        Ok(true)
+    }
+
+    /// Give up on searches the relay is not finishing
+    pub(super) async fn check_search_timeouts(&mut self) -> Result<(), Error> {
+        let now = Unixtime::now();
+        let expired: Vec<String> = self
+            .search_queries
+            .iter()
+            .filter(|(_, search)| search.asked.0 + SEARCH_TIMEOUT_SECS < now.0)
+            .map(|(subid, _)| subid.clone())
+            .collect();
+        for subid in expired {
+            tracing::debug!("{}: Search timed out", &self.url);
+            self.search_done(&subid).await?;
+        }
+        Ok(())
+    }
+}
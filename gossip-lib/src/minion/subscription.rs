@@ -1,10 +1,9 @@
 use crate::globals::GLOBALS;
-use nostr_types::{ClientMessage, Filter, SubscriptionId};
+use nostr_types::Filter;
 use std::sync::atomic::Ordering;
 
 #[derive(Debug)]
 pub struct Subscription {
-    id: String,
     job_id: u64,
     filters: Vec<Filter>,
     eose: bool,
@@ -12,10 +11,9 @@ pub struct Subscription {
 }
 
 impl Subscription {
-    pub fn new(id: &str, job_id: u64) -> Subscription {
+    pub fn new(job_id: u64) -> Subscription {
         GLOBALS.open_subscriptions.fetch_add(1, Ordering::SeqCst);
         Subscription {
-            id: id.to_owned(),
             job_id,
             filters: vec![],
             eose: false,
@@ -31,10 +29,6 @@ impl Subscription {
         &self.filters
     }
 
-    pub fn get_id(&self) -> String {
-        self.id.clone()
-    }
-
     pub fn get_job_id(&self) -> u64 {
         self.job_id
     }
@@ -55,20 +49,11 @@ impl Subscription {
     pub fn eose(&self) -> bool {
         self.eose
     }
-
-    pub fn req_message(&self) -> ClientMessage {
-        ClientMessage::Req(SubscriptionId(self.get_id()), self.filters.clone())
-    }
-
-    pub fn close_message(&self) -> ClientMessage {
-        ClientMessage::Close(SubscriptionId(self.get_id()))
-    }
 }
 
 impl Clone for Subscription {
     fn clone(&self) -> Self {
         Subscription {
-            id: self.id.clone(),
             job_id: self.job_id,
             filters: self.filters.clone(),
             eose: self.eose,
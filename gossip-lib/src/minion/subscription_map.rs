@@ -2,50 +2,49 @@ use super::subscription::Subscription;
 use nostr_types::Filter;
 use std::collections::HashMap;
 
-// handle is a coder-friendly string like "general_feed"
-// id is a short numeric string like "0", counting up from 0.
+// handle is a coder-friendly string like "general_feed", one per job.
+// id is a short numeric string like "0", counting up from 0. It names a
+// subscription on the wire. A handle may be spread over several ids when the relay
+// limits the filters in a REQ, and one id may carry several handles when their
+// REQs were coalesced to stay within the relay's subscription limit.
 pub struct SubscriptionMap {
-    handle_to_id: HashMap<String, String>,
-    by_id: HashMap<String, Subscription>,
+    by_handle: HashMap<String, Subscription>,
+    wires: HashMap<String, Wire>,
     count: usize,
 }
 
+// A subscription on the wire
+struct Wire {
+    handles: Vec<String>,
+    eose: bool,
+}
+
 impl SubscriptionMap {
     pub fn new() -> SubscriptionMap {
         SubscriptionMap {
-            handle_to_id: HashMap::new(),
-            by_id: HashMap::new(),
+            by_handle: HashMap::new(),
+            wires: HashMap::new(),
             count: 0,
         }
     }
 
-    pub fn add(&mut self, handle: &str, job_id: u64, filters: Vec<Filter>) -> String {
-        let id = format!("{}", self.count);
-        let mut sub = Subscription::new(&id, job_id);
-        self.count += 1;
+    pub fn add(&mut self, handle: &str, job_id: u64, filters: Vec<Filter>) {
+        let mut sub = Subscription::new(job_id);
         sub.set_filters(filters);
-        self.handle_to_id.insert(handle.to_owned(), id.clone());
-        self.by_id.insert(id.clone(), sub);
-        id
+        self.by_handle.insert(handle.to_owned(), sub);
     }
 
     pub fn has(&self, handle: &str) -> bool {
-        match self.handle_to_id.get(handle) {
-            None => false,
-            Some(id) => self.by_id.contains_key(id),
-        }
+        self.by_handle.contains_key(handle)
     }
 
     pub fn get(&self, handle: &str) -> Option<Subscription> {
-        match self.handle_to_id.get(handle) {
-            None => None,
-            Some(id) => self.by_id.get(id).cloned(),
-        }
+        self.by_handle.get(handle).cloned()
     }
 
     pub fn get_all_handles_matching(&self, substr: &str) -> Vec<String> {
         let mut output: Vec<String> = Vec::new();
-        for handle in self.handle_to_id.keys() {
+        for handle in self.by_handle.keys() {
             if handle.contains(substr) {
                 output.push(handle.clone());
             }
@@ -53,50 +52,140 @@ impl SubscriptionMap {
         output
     }
 
-    /*
-    pub fn get_by_id(&self, id: &str) -> Option<Subscription> {
-        self.by_id.get(id).cloned()
+    pub fn get_mut(&mut self, handle: &str) -> Option<&mut Subscription> {
+        self.by_handle.get_mut(handle)
+    }
+
+    /// Remove a handle. Returns the ids that no longer carry anything, which
+    /// should be closed, and the ids that still carry other handles, which should
+    /// be asked for again without this one.
+    pub fn remove(&mut self, handle: &str) -> (Vec<String>, Vec<String>) {
+        self.by_handle.remove(handle);
+        self.detach(handle)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_handle.is_empty()
+    }
+
+    /// A new id for a subscription on the wire
+    pub fn next_id(&mut self) -> String {
+        let id = format!("{}", self.count);
+        self.count += 1;
+        id
+    }
+
+    /// Record that this id carries these handles
+    pub fn attach(&mut self, id: &str, handles: Vec<String>) {
+        self.wires.insert(
+            id.to_owned(),
+            Wire {
+                handles,
+                eose: false,
+            },
+        );
     }
-     */
 
-    pub fn get_handle_by_id(&self, id: &str) -> Option<String> {
-        for (handle, xid) in self.handle_to_id.iter() {
-            if id == xid {
-                return Some(handle.to_string());
+    /// Take a handle off the ids carrying it. Returns the ids that no longer carry
+    /// anything, which should be closed or reused, and the ids that still carry
+    /// other handles, which should be asked for again without this one.
+    pub fn detach(&mut self, handle: &str) -> (Vec<String>, Vec<String>) {
+        let mut emptied: Vec<String> = Vec::new();
+        let mut shared: Vec<String> = Vec::new();
+        for (id, wire) in self.wires.iter_mut() {
+            if let Some(pos) = wire.handles.iter().position(|h| h == handle) {
+                wire.handles.remove(pos);
+                if wire.handles.is_empty() {
+                    emptied.push(id.clone());
+                } else {
+                    shared.push(id.clone());
+                }
             }
         }
-        None
+        for id in emptied.iter() {
+            self.wires.remove(id);
+        }
+        emptied.sort();
+        shared.sort();
+        (emptied, shared)
     }
 
-    pub fn get_mut(&mut self, handle: &str) -> Option<&mut Subscription> {
-        match self.handle_to_id.get(handle) {
-            None => None,
-            Some(id) => self.by_id.get_mut(id),
+    /// The handles carried by an id
+    pub fn get_handles_by_id(&self, id: &str) -> Vec<String> {
+        match self.wires.get(id) {
+            Some(wire) => wire.handles.clone(),
+            None => vec![],
         }
     }
 
-    pub fn get_mut_by_id(&mut self, id: &str) -> Option<&mut Subscription> {
-        self.by_id.get_mut(id)
+    /// Record EOSE on an id. Returns the handles carried by it which now have had
+    /// EOSE on all of their ids, or None if we do not know this id.
+    pub fn eose_by_id(&mut self, id: &str) -> Option<Vec<String>> {
+        let handles = match self.wires.get_mut(id) {
+            Some(wire) => {
+                wire.eose = true;
+                wire.handles.clone()
+            }
+            None => return None,
+        };
+        Some(
+            handles
+                .into_iter()
+                .filter(|handle| {
+                    self.wires
+                        .values()
+                        .all(|wire| wire.eose || !wire.handles.contains(handle))
+                })
+                .collect(),
+        )
     }
 
-    pub fn remove(&mut self, handle: &str) -> Option<String> {
-        if let Some(id) = self.handle_to_id.get(handle) {
-            let id = id.to_owned();
-            self.by_id.remove(&id);
-            self.handle_to_id.remove(handle);
-            Some(id)
-        } else {
-            None
+    /// Forget an id the relay has closed. Returns the handles it carried.
+    pub fn remove_id(&mut self, id: &str) -> Vec<String> {
+        match self.wires.remove(id) {
+            Some(wire) => wire.handles,
+            None => vec![],
         }
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.by_id.is_empty()
+    /// How many subscriptions we have open on the wire
+    pub fn open_ids(&self) -> usize {
+        self.wires.len()
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detach_and_eose() {
+        let mut map = SubscriptionMap::new();
+        map.add("a", 1, vec![Filter::default()]);
+        map.add("b", 2, vec![Filter::default()]);
+
+        // "a" is split over two ids, the second of which also carries "b"
+        map.attach("0", vec!["a".to_owned()]);
+        map.attach("1", vec!["a".to_owned(), "b".to_owned()]);
+
+        // "a" is not at EOSE until both of its ids are
+        assert_eq!(map.eose_by_id("0"), Some(vec![]));
+        assert_eq!(
+            map.eose_by_id("1"),
+            Some(vec!["a".to_owned(), "b".to_owned()])
+        );
+        assert_eq!(map.eose_by_id("2"), None);
+
+        // Taking "a" off empties one id and leaves the other carrying "b"
+        assert_eq!(
+            map.remove("a"),
+            (vec!["0".to_owned()], vec!["1".to_owned()])
+        );
+        assert_eq!(map.get_handles_by_id("1"), vec!["b".to_owned()]);
+        assert_eq!(map.open_ids(), 1);
 
-    /*
-        pub fn remove_by_id(&mut self, id: &str) {
-            self.by_id.remove(id);
+        assert_eq!(map.remove("b"), (vec!["1".to_owned()], vec![]));
+        assert!(map.is_empty());
+        assert_eq!(map.open_ids(), 0);
     }
-        */
 }
@@ -3,7 +3,8 @@ use crate::globals::GLOBALS;
 use nostr_types::{Event, EventReference, Id, PayRequestData, PublicKey, UncheckedUrl};
 use std::ops::Deref;
 
-/// The state that a Zap is in (it moves through 5 states before it is complete)
+/// The state that a Zap is in (it moves through 5 states before it is complete, or
+/// more if a connected wallet pays it)
 #[derive(Debug, Clone)]
 pub enum ZapState {
     None,
@@ -11,6 +12,9 @@ pub enum ZapState {
     SeekingAmount(Id, PublicKey, PayRequestData, UncheckedUrl),
     LoadingInvoice(Id, PublicKey),
     ReadyToPay(Id, String), // String is the Zap Invoice as a string, to be shown as a QR code
+    PayingInvoice(Id, String), // A connected wallet (NWC) is paying the invoice
+    Paid(Id),
+    PaymentFailed(Id, String), // String is the reason
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -107,3 +111,18 @@ pub fn exponential_decay(base: f32, halflife_seconds: u64, elapsed_seconds: u64)
     let decay_constant = 2.0_f32.ln() / halflife_seconds as f32;
     base * E.powf(-decay_constant * elapsed_seconds as f32)
 }
+
+/// The machine-readable prefix of a relay's CLOSED, NOTICE or OK message (e.g.
+/// "rate-limited" in "rate-limited: slow down"), if it has one
+pub(crate) fn reason_prefix(message: &str) -> Option<&str> {
+    match message.split_once(':') {
+        Some((prefix, _))
+            if !prefix.is_empty()
+                && prefix.len() <= 20
+                && prefix.chars().all(|c| c.is_ascii_lowercase() || c == '-') =>
+        {
+            Some(prefix)
+        }
+        _ => None,
+    }
+}
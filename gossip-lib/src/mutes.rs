@@ -0,0 +1,275 @@
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::misc::Private;
+use crate::people::PersonList;
+use heed::RwTxn;
+use nostr_types::{Event, EventKind, EventReference, Id, Tag, Unixtime};
+use std::collections::{BTreeMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::Ordering;
+
+/// Something other than a person on the mute list (NIP-51)
+pub type Mute = crate::storage::types::Mute1;
+
+/// A mute list entry, and whether it is kept private
+pub type MuteEntry = crate::storage::types::MuteEntry1;
+
+impl Mute {
+    /// A muted word, or None if it is blank
+    pub fn word(word: &str) -> Option<Mute> {
+        let word = word.trim().to_lowercase();
+        if word.is_empty() {
+            None
+        } else {
+            Some(Mute::Word(word))
+        }
+    }
+
+    /// A muted hashtag, with or without its '#', or None if it is blank
+    pub fn hashtag(hashtag: &str) -> Option<Mute> {
+        let hashtag = hashtag.trim().trim_start_matches('#').to_lowercase();
+        if hashtag.is_empty() {
+            None
+        } else {
+            Some(Mute::Hashtag(hashtag))
+        }
+    }
+
+    /// Parse a mute list tag. Person tags are not handled here.
+    pub fn from_tag(tag: &Tag) -> Option<Mute> {
+        match tag.tagname() {
+            "word" => Mute::word(tag.value()),
+            "t" => Mute::hashtag(tag.value()),
+            "e" => tag.parse_event().ok().map(|(id, _, _)| Mute::Thread(id)),
+            _ => None,
+        }
+    }
+
+    /// The mute list tag
+    pub fn to_tag(&self) -> Tag {
+        match self {
+            Mute::Word(word) => Tag::new(&["word", word]),
+            Mute::Hashtag(hashtag) => Tag::new_hashtag(hashtag.to_owned()),
+            Mute::Thread(id) => Tag::new_event(*id, None, None),
+        }
+    }
+}
+
+/// The word, hashtag and thread mutes in effect, for screening events
+#[derive(Debug, Clone, Default)]
+pub struct MuteSet {
+    words: Vec<String>,
+    hashtags: HashSet<String>,
+    threads: HashSet<Id>,
+}
+
+impl MuteSet {
+    /// Load from storage
+    pub fn load() -> Result<MuteSet, Error> {
+        let mut set = MuteSet::default();
+        for entry in GLOBALS.storage.read_all_mutes()? {
+            match entry.mute {
+                Mute::Word(word) => set.words.push(word),
+                Mute::Hashtag(hashtag) => {
+                    let _ = set.hashtags.insert(hashtag);
+                }
+                Mute::Thread(id) => {
+                    let _ = set.threads.insert(id);
+                }
+            }
+        }
+        Ok(set)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty() && self.hashtags.is_empty() && self.threads.is_empty()
+    }
+
+    /// Whether the thread this event belongs to (or starts) is muted
+    pub fn thread_muted(&self, event: &Event) -> bool {
+        if self.threads.is_empty() {
+            return false;
+        }
+        if self.threads.contains(&event.id) {
+            return true;
+        }
+        [event.replies_to_root(), event.replies_to()]
+            .into_iter()
+            .flatten()
+            .any(|eref| match eref {
+                EventReference::Id { id, .. } => self.threads.contains(&id),
+                EventReference::Addr(_) => false,
+            })
+    }
+
+    /// Whether the event matches any word, hashtag or thread mute
+    pub fn matches(&self, event: &Event) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+
+        if self.thread_muted(event) {
+            return true;
+        }
+
+        if !self.hashtags.is_empty()
+            && event
+                .hashtags()
+                .iter()
+                .any(|h| self.hashtags.contains(&h.to_lowercase()))
+        {
+            return true;
+        }
+
+        // The content of DMs and giftwraps is encrypted, so words cannot match
+        if !self.words.is_empty()
+            && !matches!(
+                event.kind,
+                EventKind::EncryptedDirectMessage | EventKind::GiftWrap
+            )
+        {
+            let content = event.content.to_lowercase();
+            if self.words.iter().any(|w| content.contains(w.as_str())) {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// Mute a word, hashtag or thread, or change whether that mute is private
+pub fn mute(mute: Mute, private: Private) -> Result<(), Error> {
+    let entry = MuteEntry {
+        mute,
+        private: private.0,
+    };
+    let mut txn = GLOBALS.storage.get_write_txn()?;
+    GLOBALS.storage.write_mute(&entry, Some(&mut txn))?;
+    touch_mute_list(&mut txn)?;
+    txn.commit()?;
+    reload()
+}
+
+/// Stop muting a word, hashtag or thread
+pub fn unmute(mute: &Mute) -> Result<(), Error> {
+    let mut txn = GLOBALS.storage.get_write_txn()?;
+    GLOBALS.storage.delete_mute(mute, Some(&mut txn))?;
+    touch_mute_list(&mut txn)?;
+    txn.commit()?;
+    reload()
+}
+
+/// Reload `GLOBALS.mutes` from storage and redraw the notes
+pub(crate) fn reload() -> Result<(), Error> {
+    *GLOBALS.mutes.write() = MuteSet::load()?;
+    GLOBALS.ui_invalidate_all.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Import the word, hashtag and thread mutes from the encrypted part of our mute
+/// list event, if that is still needed since upgrading (see migration 43). This
+/// needs the key to be unlocked, and only happens once.
+pub(crate) fn import_private_mutes() -> Result<(), Error> {
+    if !GLOBALS.storage.get_flag_import_private_mutes_needed() || !GLOBALS.identity.is_unlocked() {
+        return Ok(());
+    }
+
+    let my_pubkey = match GLOBALS.identity.public_key() {
+        Some(pk) => pk,
+        None => return Ok(()),
+    };
+
+    let mut mutes: Vec<Mute> = Vec::new();
+    if let Some(event) =
+        GLOBALS
+            .storage
+            .get_replaceable_event(EventKind::MuteList, my_pubkey, "")?
+    {
+        if !event.content.is_empty() {
+            let decrypted_content = GLOBALS.identity.decrypt(&my_pubkey, &event.content)?;
+            let tags: Vec<Tag> = serde_json::from_str(&decrypted_content)?;
+            mutes.extend(tags.iter().filter_map(Mute::from_tag));
+        }
+    }
+
+    // Mutes we already have keep their privacy
+    let existing: HashSet<Vec<u8>> = GLOBALS
+        .storage
+        .read_all_mutes()?
+        .iter()
+        .map(|entry| entry.mute.key())
+        .collect();
+
+    let mut txn = GLOBALS.storage.get_write_txn()?;
+    for mute in mutes.drain(..) {
+        if !existing.contains(&mute.key()) {
+            let entry = MuteEntry {
+                mute,
+                private: true,
+            };
+            GLOBALS.storage.write_mute(&entry, Some(&mut txn))?;
+        }
+    }
+    GLOBALS
+        .storage
+        .set_flag_import_private_mutes_needed(false, Some(&mut txn))?;
+    txn.commit()?;
+    reload()
+}
+
+/// Fold word, hashtag and thread mutes (by key) into a person list hash, so that
+/// the mute list is seen to differ when only these have changed
+pub(crate) fn hash_with_mutes(hash: u64, mutes: &BTreeMap<Vec<u8>, Private>) -> u64 {
+    if mutes.is_empty() {
+        return hash;
+    }
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    hash.hash(&mut hasher);
+    for (key, private) in mutes.iter() {
+        key.hash(&mut hasher);
+        private.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+// The mute list was edited locally, so it needs publishing
+fn touch_mute_list(txn: &mut RwTxn) -> Result<(), Error> {
+    if let Some(mut metadata) = GLOBALS
+        .storage
+        .get_person_list_metadata(PersonList::Muted)?
+    {
+        metadata.last_edit_time = Unixtime::now();
+        GLOBALS
+            .storage
+            .set_person_list_metadata(PersonList::Muted, &metadata, Some(txn))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mute_tags() {
+        for mute in [
+            Mute::Word("spoiler".to_owned()),
+            Mute::Hashtag("politics".to_owned()),
+            Mute::Thread(
+                Id::try_from_hex_string(
+                    "77f7653c67147a125cc624f695029d0557e3ab402e714680eb23dd2499f439a0",
+                )
+                .unwrap(),
+            ),
+        ] {
+            assert_eq!(Mute::from_tag(&mute.to_tag()), Some(mute));
+        }
+
+        assert_eq!(
+            Mute::from_tag(&Tag::new(&["t", "#Politics"])),
+            Some(Mute::Hashtag("politics".to_owned()))
+        );
+        assert_eq!(Mute::from_tag(&Tag::new(&["word", "  "])), None);
+    }
+}
@@ -185,7 +185,7 @@ pub fn parse_nip05(nip05: &str) -> Result<(String, String), Error> {
 async fn fetch_nip05(user: &str, domain: &str) -> Result<Nip05, Error> {
     // FIXME add user-agent if configured
 
-    let nip05_future = reqwest::Client::builder()
+    let nip05_future = crate::proxy::http_client_builder()?
         .timeout(std::time::Duration::new(60, 0))
         .redirect(reqwest::redirect::Policy::none()) // see NIP-05
         .gzip(true)
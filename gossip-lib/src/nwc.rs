@@ -0,0 +1,415 @@
+use crate::comms::{RelayConnectionReason, RelayJob, ToMinionPayload, ToMinionPayloadDetail};
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use crate::manager;
+use crate::misc::ZapState;
+use dashmap::DashMap;
+use nostr_types::{
+    ContentEncryptionAlgorithm, Event, EventKind, Id, MilliSatoshi, PreEvent, PrivateKey,
+    PublicKey, RelayUrl, Tag, Unixtime,
+};
+use parking_lot::RwLock;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use speedy::{Readable, Writable};
+use std::time::Duration;
+
+/// The event kind of requests to a wallet service
+pub const NWC_REQUEST_KIND: u32 = 23194;
+
+/// The event kind of responses from a wallet service
+pub const NWC_RESPONSE_KIND: u32 = 23195;
+
+// How long to wait for the wallet service to answer before giving up
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A wallet service that pays invoices for us (NIP-47)
+#[derive(Debug, Clone, Readable, Writable)]
+pub struct NwcConnection {
+    pub name: String,
+    pub wallet_pubkey: PublicKey,
+    pub relays: Vec<RelayUrl>,
+
+    /// The public key our requests come from
+    pub client_pubkey: PublicKey,
+
+    // The secret from the connection string, which our requests are signed and
+    // encrypted with. Anyone holding it can spend from the wallet, so we store it
+    // encrypted to ourselves with our identity key, the way our own private key is
+    // kept encrypted. This means the identity must be unlocked to use the wallet,
+    // and a different identity cannot use it at all.
+    encrypted_secret: String,
+
+    pub lud16: Option<String>,
+}
+
+// The parts of a `nostr+walletconnect://` connection string
+#[derive(Debug)]
+struct ConnectionString {
+    wallet_pubkey: PublicKey,
+    relays: Vec<RelayUrl>,
+    secret: String,
+    lud16: Option<String>,
+}
+
+impl ConnectionString {
+    fn parse(uri: &str) -> Result<ConnectionString, Error> {
+        let uri = uri.trim();
+        let rest = match uri
+            .strip_prefix("nostr+walletconnect://")
+            .or_else(|| uri.strip_prefix("nostr+walletconnect:"))
+        {
+            Some(rest) => rest,
+            None => return Err(ErrorKind::BadWalletConnectString.into()),
+        };
+        let (pubkey, query) = match rest.split_once('?') {
+            Some(parts) => parts,
+            None => return Err(ErrorKind::BadWalletConnectString.into()),
+        };
+
+        let wallet_pubkey = PublicKey::try_from_hex_string(pubkey.trim_end_matches('/'), true)?;
+
+        let mut relays: Vec<RelayUrl> = Vec::new();
+        let mut secret: Option<String> = None;
+        let mut lud16: Option<String> = None;
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            match &*key {
+                "relay" => relays.push(RelayUrl::try_from_str(&value)?),
+                "secret" => secret = Some(value.into_owned()),
+                "lud16" => lud16 = Some(value.into_owned()),
+                _ => {}
+            }
+        }
+
+        let secret = match secret {
+            Some(secret) => secret,
+            None => return Err(ErrorKind::BadWalletConnectString.into()),
+        };
+        let _ = PrivateKey::try_from_hex_string(&secret)?;
+
+        if relays.is_empty() {
+            return Err(ErrorKind::BadWalletConnectString.into());
+        }
+
+        Ok(ConnectionString {
+            wallet_pubkey,
+            relays,
+            secret,
+            lud16,
+        })
+    }
+}
+
+impl NwcConnection {
+    /// Parse a `nostr+walletconnect://` connection string, encrypting its secret
+    /// with our identity key. The identity must be unlocked.
+    pub fn from_uri(name: String, uri: &str) -> Result<NwcConnection, Error> {
+        let parsed = ConnectionString::parse(uri)?;
+
+        let our_pubkey = our_pubkey()?;
+        let encrypted_secret = GLOBALS.identity.encrypt(
+            &our_pubkey,
+            &parsed.secret,
+            ContentEncryptionAlgorithm::Nip44v2,
+        )?;
+
+        Ok(NwcConnection {
+            name,
+            wallet_pubkey: parsed.wallet_pubkey,
+            relays: parsed.relays,
+            client_pubkey: PrivateKey::try_from_hex_string(&parsed.secret)?.public_key(),
+            encrypted_secret,
+            lud16: parsed.lud16,
+        })
+    }
+
+    fn private_key(&self) -> Result<PrivateKey, Error> {
+        let our_pubkey = our_pubkey()?;
+        let secret = GLOBALS
+            .identity
+            .decrypt(&our_pubkey, &self.encrypted_secret)?;
+        Ok(PrivateKey::try_from_hex_string(&secret)?)
+    }
+
+    // Sign a request with the connection secret (not with the user's key)
+    fn request_event(&self, method: &str, params: Value) -> Result<Event, Error> {
+        let private_key = self.private_key()?;
+
+        let plaintext = json!({
+            "method": method,
+            "params": params,
+        })
+        .to_string();
+        let content = private_key.encrypt(
+            &self.wallet_pubkey,
+            &plaintext,
+            ContentEncryptionAlgorithm::Nip04,
+        )?;
+
+        let pre_event = PreEvent {
+            pubkey: private_key.public_key(),
+            created_at: Unixtime::now(),
+            kind: EventKind::from(NWC_REQUEST_KIND),
+            tags: vec![Tag::new_pubkey(self.wallet_pubkey, None, None)],
+            content,
+        };
+
+        let id = pre_event.hash()?;
+        let sig = private_key.sign_id(id)?;
+
+        Ok(Event {
+            id,
+            pubkey: pre_event.pubkey,
+            created_at: pre_event.created_at,
+            kind: pre_event.kind,
+            sig,
+            content: pre_event.content,
+            tags: pre_event.tags,
+        })
+    }
+}
+
+/// What we asked the wallet service for
+#[derive(Debug, Clone, PartialEq)]
+pub enum NwcRequest {
+    /// Pay the zap invoice (String) for the note (Id)
+    PayZap(Id, String),
+
+    /// Report the wallet balance
+    GetBalance,
+}
+
+/// Requests waiting on the wallet service, and what it last told us
+#[derive(Debug, Default)]
+pub struct Nwc {
+    pending: DashMap<Id, NwcRequest>,
+
+    /// The wallet balance, and when the wallet reported it
+    pub balance: RwLock<Option<(MilliSatoshi, Unixtime)>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NwcResponse {
+    #[serde(default)]
+    error: Option<NwcError>,
+
+    #[serde(default)]
+    result: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NwcError {
+    code: String,
+
+    #[serde(default)]
+    message: String,
+}
+
+// Our public key, if our identity is unlocked (to encrypt to or decrypt from)
+fn our_pubkey() -> Result<PublicKey, Error> {
+    match GLOBALS.identity.public_key() {
+        Some(pubkey) if GLOBALS.identity.is_unlocked() => Ok(pubkey),
+        _ => Err(ErrorKind::NoPrivateKey.into()),
+    }
+}
+
+/// The connected wallet, if any
+pub fn connection() -> Result<Option<NwcConnection>, Error> {
+    Ok(GLOBALS
+        .storage
+        .read_all_nwc_connections()?
+        .into_iter()
+        .next())
+}
+
+/// Connect to the wallet service in the connection string, replacing any other
+pub fn connect(name: String, uri: &str) -> Result<(), Error> {
+    let connection = NwcConnection::from_uri(name, uri)?;
+
+    let olds = GLOBALS.storage.read_all_nwc_connections()?;
+    let mut txn = GLOBALS.storage.get_write_txn()?;
+    for old in olds {
+        GLOBALS
+            .storage
+            .delete_nwc_connection(old.wallet_pubkey, Some(&mut txn))?;
+    }
+    GLOBALS
+        .storage
+        .write_nwc_connection(&connection, Some(&mut txn))?;
+    txn.commit()?;
+
+    *GLOBALS.nwc.balance.write() = None;
+    Ok(())
+}
+
+/// Forget the connected wallet
+pub fn disconnect() -> Result<(), Error> {
+    let olds = GLOBALS.storage.read_all_nwc_connections()?;
+    let mut txn = GLOBALS.storage.get_write_txn()?;
+    for old in olds {
+        GLOBALS
+            .storage
+            .delete_nwc_connection(old.wallet_pubkey, Some(&mut txn))?;
+    }
+    txn.commit()?;
+
+    *GLOBALS.nwc.balance.write() = None;
+    Ok(())
+}
+
+/// Ask the wallet service to pay a zap invoice
+pub(crate) fn pay_zap(id: Id, invoice: String) -> Result<(), Error> {
+    *GLOBALS.current_zap.write() = ZapState::PayingInvoice(id, invoice.clone());
+    let params = json!({ "invoice": invoice });
+    send_request("pay_invoice", params, NwcRequest::PayZap(id, invoice))
+}
+
+/// Ask the wallet service for our balance
+pub(crate) fn get_balance() -> Result<(), Error> {
+    send_request("get_balance", json!({}), NwcRequest::GetBalance)
+}
+
+fn send_request(method: &str, params: Value, request: NwcRequest) -> Result<(), Error> {
+    let connection = match connection()? {
+        Some(connection) => connection,
+        None => return Err(ErrorKind::WalletNotConnected.into()),
+    };
+
+    let event = connection.request_event(method, params)?;
+    let id = event.id;
+    GLOBALS.nwc.pending.insert(id, request);
+
+    // Responses are ephemeral, so listen before asking
+    manager::run_jobs_on_all_relays(
+        connection.relays.clone(),
+        vec![
+            RelayJob {
+                reason: RelayConnectionReason::WalletConnect,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::SubscribeNwc,
+                },
+            },
+            RelayJob {
+                reason: RelayConnectionReason::PostWalletConnect,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::PostEvents(vec![event]),
+                },
+            },
+        ],
+    );
+
+    // Give up if the wallet service never answers
+    std::mem::drop(tokio::spawn(async move {
+        tokio::time::sleep(RESPONSE_TIMEOUT).await;
+        if let Some((_, request)) = GLOBALS.nwc.pending.remove(&id) {
+            fail(request, "the wallet did not respond".to_owned());
+        }
+    }));
+
+    Ok(())
+}
+
+/// Handle a response from the wallet service
+pub(crate) fn handle_response(event: &Event) -> Result<(), Error> {
+    let connection = match connection()? {
+        Some(connection) => connection,
+        None => return Ok(()),
+    };
+    if event.pubkey != connection.wallet_pubkey {
+        return Ok(());
+    }
+
+    // Find the request this responds to. We may have seen the response already
+    // from another relay.
+    let request_id = event.tags.iter().find_map(|tag| {
+        if tag.tagname() == "e" {
+            tag.parse_event().ok().map(|(id, _, _)| id)
+        } else {
+            None
+        }
+    });
+    let request = match request_id.and_then(|id| GLOBALS.nwc.pending.remove(&id)) {
+        Some((_, request)) => request,
+        None => return Ok(()),
+    };
+
+    let plaintext = connection
+        .private_key()?
+        .decrypt(&connection.wallet_pubkey, &event.content)?;
+    let response: NwcResponse = serde_json::from_str(&plaintext)?;
+
+    if let Some(error) = response.error {
+        fail(request, format!("{}: {}", error.code, error.message));
+        return Ok(());
+    }
+
+    match request {
+        NwcRequest::PayZap(id, _) => {
+            let mut zap = GLOBALS.current_zap.write();
+            if matches!(*zap, ZapState::PayingInvoice(zap_id, _) if zap_id == id) {
+                *zap = ZapState::Paid(id);
+            }
+            GLOBALS
+                .status_queue
+                .write()
+                .write("Zap paid by your wallet.".to_owned());
+        }
+        NwcRequest::GetBalance => {
+            let balance = response
+                .result
+                .as_ref()
+                .and_then(|result| result.get("balance"))
+                .and_then(|balance| balance.as_u64());
+            if let Some(msats) = balance {
+                *GLOBALS.nwc.balance.write() = Some((MilliSatoshi(msats), Unixtime::now()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn fail(request: NwcRequest, reason: String) {
+    match request {
+        NwcRequest::PayZap(id, _) => {
+            let mut zap = GLOBALS.current_zap.write();
+            if matches!(*zap, ZapState::PayingInvoice(zap_id, _) if zap_id == id) {
+                *zap = ZapState::PaymentFailed(id, reason.clone());
+            }
+            GLOBALS
+                .status_queue
+                .write()
+                .write(format!("Wallet payment failed: {}", reason));
+        }
+        NwcRequest::GetBalance => {
+            GLOBALS
+                .status_queue
+                .write()
+                .write(format!("Could not get the wallet balance: {}", reason));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_connection_string() {
+        let uri = "nostr+walletconnect://b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4?relay=wss%3A%2F%2Frelay.damus.io&secret=71a8c14c1407c113601079c4302dab36460f0ccd0ad506f1f2dc73b5100e4f3c&lud16=someone%40example.com";
+        let connection = ConnectionString::parse(uri).unwrap();
+        assert_eq!(
+            connection.wallet_pubkey.as_hex_string(),
+            "b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4"
+        );
+        assert_eq!(connection.relays.len(), 1);
+        assert_eq!(connection.relays[0].as_str(), "wss://relay.damus.io/");
+        assert_eq!(connection.lud16.as_deref(), Some("someone@example.com"));
+
+        // A secret is required
+        let uri = "nostr+walletconnect://b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4?relay=wss%3A%2F%2Frelay.damus.io";
+        assert!(ConnectionString::parse(uri).is_err());
+    }
+}
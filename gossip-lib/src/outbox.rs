@@ -0,0 +1,277 @@
+use crate::comms::{RelayConnectionReason, RelayJob, ToMinionPayload, ToMinionPayloadDetail};
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::manager;
+use heed::RwTxn;
+use nostr_types::{Event, Id, RelayUrl, Unixtime};
+use std::collections::HashMap;
+
+/// An outbox entry, aliased to the latest version
+pub type OutboxEntry = crate::storage::types::OutboxEntry1;
+
+/// The publishing record of an event on one relay, aliased to the latest version
+pub type OutboxRelay = crate::storage::types::OutboxRelay1;
+
+/// The publishing status of an event on one relay, aliased to the latest version
+pub type OutboxStatus = crate::storage::types::OutboxStatus1;
+
+/// An event queued while offline, aliased to the latest version
+pub type QueuedEvent = crate::storage::types::QueuedEvent1;
+
+/// After this many attempts at a relay we stop retrying automatically. The user
+/// can still retry manually.
+pub const MAX_ATTEMPTS: u32 = 6;
+
+// If a relay has not answered with an OK in this time, we count it as failed
+const OK_TIMEOUT_SECS: i64 = 90;
+
+// The first retry is this long after a failure, doubling for each attempt after
+const RETRY_BASE_SECS: i64 = 30;
+
+// But we never wait longer than this between retries
+const RETRY_MAX_SECS: i64 = 3600;
+
+/// Record one of our events in the outbox and post it to these relays.
+///
+/// If we are offline, the event is queued instead, to be posted when we go online.
+pub(crate) fn post_event(
+    event: &Event,
+    relay_urls: Vec<RelayUrl>,
+    reason: RelayConnectionReason,
+) -> Result<(), Error> {
+    if GLOBALS.storage.read_setting_offline() {
+        tracing::info!(
+            "Offline: queueing event {} to post later",
+            event.id.as_hex_string()
+        );
+        let queued = QueuedEvent::new(event.clone(), relay_urls);
+        GLOBALS.storage.queue_offline_event(&queued, None)?;
+        return Ok(());
+    }
+
+    let mut txn = GLOBALS.storage.get_write_txn()?;
+    record_attempt(event, &relay_urls, &mut txn)?;
+    txn.commit()?;
+
+    manager::run_jobs_on_all_relays(relay_urls, post_jobs(vec![event.clone()], reason));
+
+    Ok(())
+}
+
+/// Post the events that were queued while we were offline.
+///
+/// Each relay is given its events in a single job, in the order they were made, so
+/// that (for example) a deletion does not arrive before what it deletes.
+pub(crate) fn flush_offline_queue() -> Result<(), Error> {
+    if GLOBALS.storage.read_setting_offline() {
+        return Ok(());
+    }
+
+    let mut txn = GLOBALS.storage.get_write_txn()?;
+    let queued = GLOBALS.storage.read_offline_queue(Some(&*txn))?;
+    if queued.is_empty() {
+        return Ok(());
+    }
+
+    // Move them into the outbox, which takes over from here (including retries)
+    let mut per_relay: HashMap<RelayUrl, Vec<Event>> = HashMap::new();
+    for q in queued.iter() {
+        record_attempt(&q.event, &q.relay_urls, &mut txn)?;
+        for url in q.relay_urls.iter() {
+            per_relay
+                .entry(url.to_owned())
+                .or_default()
+                .push(q.event.clone());
+        }
+    }
+    GLOBALS.storage.clear_offline_queue(Some(&mut txn))?;
+    txn.commit()?;
+
+    tracing::info!("Posting {} events queued while offline", queued.len());
+    for (url, events) in per_relay.drain() {
+        manager::engage_minion(url, post_jobs(events, RelayConnectionReason::PostEvent));
+    }
+
+    Ok(())
+}
+
+// Record in the outbox that we are posting this event to these relays
+fn record_attempt(event: &Event, relay_urls: &[RelayUrl], txn: &mut RwTxn) -> Result<(), Error> {
+    let now = Unixtime::now();
+    let mut entry = match GLOBALS.storage.read_outbox_entry(event.id, Some(&**txn))? {
+        Some(entry) => entry,
+        None => OutboxEntry::new(event.clone()),
+    };
+    for url in relay_urls {
+        start_attempt(entry.relay_mut(url), now);
+    }
+    GLOBALS.storage.write_outbox_entry(&entry, Some(txn))
+}
+
+/// Record the OK (or not) of a relay for one of our events.
+///
+/// This does nothing if the event is not in the outbox.
+pub(crate) fn record_result(id: Id, url: &RelayUrl, ok: bool, message: &str) -> Result<(), Error> {
+    // If the relay already had it, it has it, which is all we wanted
+    let accepted = ok || message.starts_with("duplicate:");
+    let now = Unixtime::now();
+
+    GLOBALS.storage.modify_outbox_entry(
+        id,
+        |entry| {
+            let relay = entry.relay_mut(url);
+            if accepted {
+                relay.status = OutboxStatus::Accepted;
+                relay.next_attempt = None;
+            } else {
+                relay.status = OutboxStatus::Failed;
+                relay.next_attempt = next_attempt(relay.attempts, now);
+            }
+            relay.message = if message.is_empty() {
+                None
+            } else {
+                Some(message.to_owned())
+            };
+        },
+        None,
+    )
+}
+
+/// Fail relays that never answered, and retry failed relays whose time has come.
+///
+/// This is run periodically while online.
+pub(crate) fn retry_due() -> Result<(), Error> {
+    let now = Unixtime::now();
+
+    let candidates = GLOBALS
+        .storage
+        .filter_outbox_entries(|e| e.relays.iter().any(|r| timed_out(r, now) || due(r, now)))?;
+
+    let mut retries: HashMap<RelayUrl, Vec<Event>> = HashMap::new();
+    for candidate in candidates.iter() {
+        let mut urls: Vec<RelayUrl> = Vec::new();
+        GLOBALS.storage.modify_outbox_entry(
+            candidate.event.id,
+            |entry| {
+                urls.clear();
+                for relay in entry.relays.iter_mut() {
+                    if timed_out(relay, now) {
+                        relay.status = OutboxStatus::Failed;
+                        relay.next_attempt = next_attempt(relay.attempts, now);
+                        relay.message = Some("no response".to_owned());
+                    }
+                    if due(relay, now) {
+                        start_attempt(relay, now);
+                        urls.push(relay.url.clone());
+                    }
+                }
+            },
+            None,
+        )?;
+        for url in urls.drain(..) {
+            retries
+                .entry(url)
+                .or_default()
+                .push(candidate.event.clone());
+        }
+    }
+
+    for (url, events) in retries.drain() {
+        tracing::info!("Retrying {} posts to {}", events.len(), &url);
+        manager::engage_minion(url, post_jobs(events, RelayConnectionReason::PostEvent));
+    }
+
+    Ok(())
+}
+
+/// Post one of our events again to every relay that has failed to accept it,
+/// starting the retry schedule over.
+pub(crate) fn retry_failed(id: Id) -> Result<(), Error> {
+    let entry = match GLOBALS.storage.read_outbox_entry(id, None)? {
+        Some(entry) => entry,
+        None => return Ok(()),
+    };
+
+    let now = Unixtime::now();
+    let mut urls: Vec<RelayUrl> = Vec::new();
+    GLOBALS.storage.modify_outbox_entry(
+        id,
+        |entry| {
+            urls.clear();
+            for relay in entry.relays.iter_mut() {
+                if relay.status == OutboxStatus::Failed {
+                    relay.attempts = 0;
+                    start_attempt(relay, now);
+                    urls.push(relay.url.clone());
+                }
+            }
+        },
+        None,
+    )?;
+
+    if !urls.is_empty() {
+        manager::run_jobs_on_all_relays(
+            urls,
+            post_jobs(vec![entry.event], RelayConnectionReason::PostEvent),
+        );
+    }
+
+    Ok(())
+}
+
+fn post_jobs(events: Vec<Event>, reason: RelayConnectionReason) -> Vec<RelayJob> {
+    vec![RelayJob {
+        reason,
+        payload: ToMinionPayload {
+            job_id: rand::random::<u64>(),
+            detail: ToMinionPayloadDetail::PostEvents(events),
+        },
+    }]
+}
+
+fn start_attempt(relay: &mut OutboxRelay, now: Unixtime) {
+    relay.status = OutboxStatus::Pending;
+    relay.attempts += 1;
+    relay.last_attempt = Some(now);
+    relay.next_attempt = None;
+    relay.message = None;
+}
+
+fn timed_out(relay: &OutboxRelay, now: Unixtime) -> bool {
+    relay.status == OutboxStatus::Pending
+        && relay
+            .last_attempt
+            .map(|t| t.0 + OK_TIMEOUT_SECS < now.0)
+            .unwrap_or(true)
+}
+
+fn due(relay: &OutboxRelay, now: Unixtime) -> bool {
+    relay.status == OutboxStatus::Failed && relay.next_attempt.map(|t| t <= now).unwrap_or(false)
+}
+
+// When to retry after this many attempts have failed, if at all
+fn next_attempt(attempts: u32, now: Unixtime) -> Option<Unixtime> {
+    if attempts >= MAX_ATTEMPTS {
+        return None;
+    }
+    Some(Unixtime(now.0 + retry_delay(attempts)))
+}
+
+fn retry_delay(attempts: u32) -> i64 {
+    let doublings = attempts.saturating_sub(1).min(16);
+    (RETRY_BASE_SECS << doublings).min(RETRY_MAX_SECS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), 30);
+        assert_eq!(retry_delay(2), 60);
+        assert_eq!(retry_delay(4), 240);
+        assert_eq!(retry_delay(10), 3600);
+        assert_eq!(next_attempt(MAX_ATTEMPTS, Unixtime(0)), None);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::article::{self, Article};
+use crate::bolt11::Bolt11;
 use crate::comms::{
     RelayConnectionReason, RelayJob, ToMinionMessage, ToMinionPayload, ToMinionPayloadDetail,
     ToOverlordMessage,
@@ -9,11 +11,14 @@ use crate::globals::GLOBALS;
 use crate::manager;
 use crate::minion::MinionExitReason;
 use crate::misc::{Private, ZapState};
+use crate::mutes::{Mute, MuteEntry};
 use crate::nip46::{Approval, ParsedCommand};
 use crate::people::{Person, PersonList};
+use crate::post::ScheduledPost;
 use crate::relay;
 use crate::relay::Relay;
 use crate::relay_picker::RelayAssignment;
+use crate::search::{SearchQuery, SEARCH_PAGE_SIZE};
 use crate::storage::{PersonTable, Table};
 use crate::RunState;
 use heed::RwTxn;
@@ -23,7 +28,9 @@ use nostr_types::{
     MilliSatoshi, NAddr, NostrBech32, PayRequestData, PreEvent, PrivateKey, Profile, PublicKey,
     RelayUrl, Tag, UncheckedUrl, Unixtime,
 };
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc;
 use std::time::Duration;
@@ -199,6 +206,10 @@ impl Overlord {
         // Start background tasks
         crate::tasks::start_background_tasks();
 
+        // Start the local API and local relay, if enabled
+        crate::api::start();
+        crate::local_relay::start();
+
         // Every 500 milliseconds we check if a minion task has completed
         let minion_task_interval = tokio::time::interval(Duration::from_millis(500));
         tokio::pin!(minion_task_interval);
@@ -573,6 +584,9 @@ impl Overlord {
             ToOverlordMessage::BookmarkRm(er) => {
                 self.bookmark_rm(er).await?;
             }
+            ToOverlordMessage::CancelScheduledPost(id) => {
+                Self::cancel_scheduled_post(id)?;
+            }
             ToOverlordMessage::ChangePassphrase { old, new } => {
                 Self::change_passphrase(old, new).await?;
             }
@@ -588,6 +602,9 @@ impl Overlord {
             ToOverlordMessage::DelegationReset => {
                 Self::delegation_reset().await?;
             }
+            ToOverlordMessage::DeleteArticle(d) => {
+                self.delete_article(d).await?;
+            }
             ToOverlordMessage::DeletePersonList(list) => {
                 self.delete_person_list(list).await?;
             }
@@ -657,6 +674,9 @@ impl Overlord {
                 self.nip46_server_op_approval_response(pubkey, parsed_command, approval)
                     .await?;
             }
+            ToOverlordMessage::NwcGetBalance => {
+                self.nwc_get_balance()?;
+            }
             ToOverlordMessage::RefreshScoresAndPickRelays => {
                 self.refresh_scores_and_pick_relays().await?;
             }
@@ -676,12 +696,21 @@ impl Overlord {
             ToOverlordMessage::PostNip46Event(event, relays) => {
                 self.post_nip46_event(event, relays).await?;
             }
+            ToOverlordMessage::PostScheduled(id) => {
+                self.post_scheduled(id).await?;
+            }
+            ToOverlordMessage::PreviewPrune => {
+                Self::preview_prune()?;
+            }
             ToOverlordMessage::PruneCache => {
                 Self::prune_cache().await?;
             }
             ToOverlordMessage::PruneDatabase => {
                 Self::prune_database()?;
             }
+            ToOverlordMessage::PublishArticle(article) => {
+                self.publish_article(article).await?;
+            }
             ToOverlordMessage::PushPersonList(person_list) => {
                 self.push_person_list(person_list).await?;
             }
@@ -703,9 +732,32 @@ impl Overlord {
             ToOverlordMessage::Repost(id) => {
                 self.repost(id).await?;
             }
+            ToOverlordMessage::RetryPost(id) => {
+                Self::retry_post(id)?;
+            }
+            ToOverlordMessage::SchedulePost {
+                content,
+                tags,
+                in_reply_to,
+                annotation,
+                dm_channel,
+                publish_at,
+            } => {
+                Self::schedule_post(
+                    content,
+                    tags,
+                    in_reply_to,
+                    annotation,
+                    dm_channel,
+                    publish_at,
+                )?;
+            }
             ToOverlordMessage::Search(text) => {
                 Self::search(text).await?;
             }
+            ToOverlordMessage::SearchMoreNotes => {
+                Self::search_more_notes().await?;
+            }
             ToOverlordMessage::SetActivePerson(pubkey) => {
                 Self::set_active_person(pubkey).await?;
             }
@@ -752,9 +804,15 @@ impl Overlord {
             ToOverlordMessage::UpdatePersonList { person_list, merge } => {
                 self.update_person_list(person_list, merge).await?;
             }
+            ToOverlordMessage::UpdateScheduledPost(post) => {
+                Self::update_scheduled_post(post)?;
+            }
             ToOverlordMessage::UpdateRelay(old, new) => {
                 self.update_relay(old, new).await?;
             }
+            ToOverlordMessage::UploadMedia(path) => {
+                self.upload_media(path);
+            }
             ToOverlordMessage::VisibleNotesChanged(visible) => {
                 self.visible_notes_changed(visible).await?;
             }
@@ -971,16 +1029,7 @@ impl Overlord {
 
         let config_relays: Vec<RelayUrl> = Relay::choose_relay_urls(Relay::WRITE, |_| true)?;
 
-        manager::run_jobs_on_all_relays(
-            config_relays,
-            vec![RelayJob {
-                reason: RelayConnectionReason::PostEvent,
-                payload: ToMinionPayload {
-                    job_id: rand::random::<u64>(),
-                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
-                },
-            }],
-        );
+        crate::outbox::post_event(&event, config_relays, RelayConnectionReason::PostEvent)?;
 
         Ok(())
     }
@@ -1089,6 +1138,77 @@ impl Overlord {
         Ok(())
     }
 
+    /// Delete one of our articles by its `d` tag. The deletion names the article's
+    /// address, so that it covers every version of it, as well as the current id.
+    pub async fn delete_article(&mut self, d: String) -> Result<(), Error> {
+        let public_key = match GLOBALS.identity.public_key() {
+            Some(pk) => pk,
+            None => {
+                tracing::warn!("No public key! Not posting");
+                return Ok(());
+            }
+        };
+
+        let naddr = NAddr {
+            d: d.clone(),
+            relays: vec![],
+            kind: EventKind::LongFormContent,
+            author: public_key,
+        };
+        let mut tags: Vec<Tag> = vec![Tag::new_address(&naddr, None)];
+
+        let current =
+            GLOBALS
+                .storage
+                .get_replaceable_event(EventKind::LongFormContent, public_key, &d)?;
+        if let Some(current) = &current {
+            tags.push(Tag::new_event(current.id, None, None));
+        }
+        tags.push(Tag::new_kind(EventKind::LongFormContent));
+
+        let event = {
+            let pre_event = PreEvent {
+                pubkey: public_key,
+                created_at: Unixtime::now(),
+                kind: EventKind::EventDeletion,
+                tags,
+                content: "".to_owned(),
+            };
+
+            // Should we add a pow? Maybe the relay needs it.
+            GLOBALS.identity.sign_event(pre_event)?
+        };
+
+        // Process this event locally
+        crate::process::process_new_event(&event, None, None, false, false)?;
+
+        // Determine which relays to post this to
+        let mut relay_urls: Vec<RelayUrl> = Vec::new();
+        {
+            // Get all of the relays that we write to
+            let write_relays = relay::relays_to_post_to(&event)?;
+            relay_urls.extend(write_relays);
+
+            // Get all of the relays the article was seen on
+            if let Some(current) = &current {
+                let seen_on: Vec<RelayUrl> = GLOBALS
+                    .storage
+                    .get_event_seen_on_relay(current.id)?
+                    .iter()
+                    .map(|(url, _time)| url.to_owned())
+                    .collect();
+                relay_urls.extend(seen_on);
+            }
+
+            relay_urls.sort();
+            relay_urls.dedup();
+        }
+
+        crate::outbox::post_event(&event, relay_urls, RelayConnectionReason::PostEvent)?;
+
+        Ok(())
+    }
+
     /// Delete a person list
     pub async fn delete_person_list(&mut self, list: PersonList) -> Result<(), Error> {
         // Get the metadata first, we need it to delete events
@@ -1206,16 +1326,7 @@ impl Overlord {
         }
 
         // Send event to all these relays
-        manager::run_jobs_on_all_relays(
-            relay_urls,
-            vec![RelayJob {
-                reason: RelayConnectionReason::PostEvent,
-                payload: ToMinionPayload {
-                    job_id: rand::random::<u64>(),
-                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
-                },
-            }],
-        );
+        crate::outbox::post_event(&event, relay_urls, RelayConnectionReason::PostEvent)?;
 
         Ok(())
     }
@@ -1272,16 +1383,7 @@ impl Overlord {
             relay_urls.dedup();
         }
 
-        manager::run_jobs_on_all_relays(
-            relay_urls,
-            vec![RelayJob {
-                reason: RelayConnectionReason::PostEvent,
-                payload: ToMinionPayload {
-                    job_id: rand::random::<u64>(),
-                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
-                },
-            }],
-        );
+        crate::outbox::post_event(&event, relay_urls, RelayConnectionReason::PostEvent)?;
 
         Ok(())
     }
@@ -1580,6 +1682,12 @@ impl Overlord {
         Ok(())
     }
 
+    /// Ask the connected wallet (NWC) for its balance. The answer lands in
+    /// `GLOBALS.nwc.balance`.
+    pub fn nwc_get_balance(&mut self) -> Result<(), Error> {
+        crate::nwc::get_balance()
+    }
+
     /// Process approved nip46 server operation
     pub async fn nip46_server_op_approval_response(
         &mut self,
@@ -1704,16 +1812,7 @@ impl Overlord {
             tracing::debug!("Asking {} to post", url);
         }
 
-        manager::run_jobs_on_all_relays(
-            relay_urls,
-            vec![RelayJob {
-                reason: RelayConnectionReason::PostLike,
-                payload: ToMinionPayload {
-                    job_id: rand::random::<u64>(),
-                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
-                },
-            }],
-        );
+        crate::outbox::post_event(&event, relay_urls, RelayConnectionReason::PostLike)?;
 
         // Process the message for ourself
         crate::process::process_new_event(&event, None, None, false, false)?;
@@ -1761,21 +1860,103 @@ impl Overlord {
                 tracing::debug!("Asking {} to post", url);
             }
 
-            manager::run_jobs_on_all_relays(
-                relay_urls,
-                vec![RelayJob {
-                    reason: RelayConnectionReason::PostEvent,
-                    payload: ToMinionPayload {
-                        job_id: rand::random::<u64>(),
-                        detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
-                    },
-                }],
-            );
+            crate::outbox::post_event(&event, relay_urls, RelayConnectionReason::PostEvent)?;
+        }
+
+        Ok(())
+    }
+
+    /// Publish a scheduled post whose time has come. It is taken off the schedule
+    /// as it is handed to [post](Self::post), and put back if that fails.
+    pub async fn post_scheduled(&mut self, id: u64) -> Result<(), Error> {
+        if GLOBALS.identity.public_key().is_none() || !GLOBALS.identity.is_unlocked() {
+            return Ok(());
         }
 
+        // Read and delete in the same transaction so we cannot race an edit
+        let post = {
+            let mut txn = GLOBALS.storage.get_write_txn()?;
+            let post = match GLOBALS.storage.read_scheduled_post(id, Some(&*txn))? {
+                Some(post) => post,
+                None => return Ok(()), // already published, or cancelled
+            };
+            if post.publish_at > Unixtime::now() {
+                return Ok(()); // rescheduled for later
+            }
+            GLOBALS.storage.delete_scheduled_post(id, Some(&mut txn))?;
+            txn.commit()?;
+            post
+        };
+
+        tracing::info!("Publishing scheduled post {}", post.id);
+        let result = self
+            .post(
+                post.content.clone(),
+                post.tags.clone(),
+                post.in_reply_to,
+                post.annotation,
+                post.dm_recipients.as_deref().map(DmChannel::new),
+            )
+            .await;
+
+        if let Err(e) = result {
+            // Put it back so that it is tried again
+            GLOBALS.storage.write_scheduled_post(&post, None)?;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Schedule a post to be published at `publish_at`. It will be prepared and signed
+    /// then, as if [post](Self::post) were called at that time.
+    pub fn schedule_post(
+        content: String,
+        tags: Vec<Tag>,
+        in_reply_to: Option<Id>,
+        annotation: bool,
+        dm_channel: Option<DmChannel>,
+        publish_at: Unixtime,
+    ) -> Result<(), Error> {
+        let post = ScheduledPost {
+            id: rand::random::<u64>(),
+            publish_at,
+            content,
+            tags,
+            in_reply_to,
+            annotation,
+            dm_recipients: dm_channel.map(|c| c.keys().to_vec()),
+            scheduled_at: Unixtime::now(),
+        };
+        GLOBALS.storage.write_scheduled_post(&post, None)?;
         Ok(())
     }
 
+    /// Replace a scheduled post (matched by its id). This fails if it has already
+    /// been published or was cancelled.
+    pub fn update_scheduled_post(mut post: ScheduledPost) -> Result<(), Error> {
+        // Read and write in the same transaction so we cannot race the publisher
+        let mut txn = GLOBALS.storage.get_write_txn()?;
+        if GLOBALS
+            .storage
+            .read_scheduled_post(post.id, Some(&*txn))?
+            .is_none()
+        {
+            return Err(ErrorKind::General("That post is no longer scheduled".to_owned()).into());
+        }
+        post.scheduled_at = Unixtime::now();
+        GLOBALS
+            .storage
+            .write_scheduled_post(&post, Some(&mut txn))?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Cancel a scheduled post
+    pub fn cancel_scheduled_post(id: u64) -> Result<(), Error> {
+        GLOBALS.storage.delete_scheduled_post(id, None)
+    }
+
     pub async fn post_again(&mut self, event: Event) -> Result<(), Error> {
         let relay_urls = relay::relays_to_post_to(&event)?;
 
@@ -1783,16 +1964,7 @@ impl Overlord {
             tracing::debug!("Asking {} to post", url);
         }
 
-        manager::run_jobs_on_all_relays(
-            relay_urls,
-            vec![RelayJob {
-                reason: RelayConnectionReason::PostEvent,
-                payload: ToMinionPayload {
-                    job_id: rand::random::<u64>(),
-                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
-                },
-            }],
-        );
+        crate::outbox::post_event(&event, relay_urls, RelayConnectionReason::PostEvent)?;
 
         Ok(())
     }
@@ -1842,25 +2014,90 @@ impl Overlord {
         Ok(())
     }
 
-    /// Prune the database (events and more)
+    /// Prune the database (events and more) according to the retention policy.
+    /// The report is left in `GLOBALS.prune_report`.
     pub fn prune_database() -> Result<(), Error> {
         GLOBALS
             .status_queue
             .write()
             .write("Pruning database, please be patient..".to_owned());
 
-        let now = Unixtime::now();
-        let then = now
-            - Duration::new(
-                GLOBALS.storage.read_setting_prune_period_days() * 60 * 60 * 24,
-                0,
-            );
-        let count = GLOBALS.storage.prune(then)?;
+        let policy = GLOBALS.storage.read_setting_retention_policy();
+        let report = GLOBALS.storage.prune(&policy, false)?;
 
         GLOBALS.status_queue.write().write(format!(
             "Database has been pruned. {} events removed.",
-            count
+            report.deleted
         ));
+        *GLOBALS.prune_report.write() = Some(report);
+
+        Ok(())
+    }
+
+    /// Work out what pruning the database would delete, without deleting anything.
+    /// The report is left in `GLOBALS.prune_report`.
+    pub fn preview_prune() -> Result<(), Error> {
+        let policy = GLOBALS.storage.read_setting_retention_policy();
+        let report = GLOBALS.storage.prune(&policy, true)?;
+        *GLOBALS.prune_report.write() = Some(report);
+        Ok(())
+    }
+
+    /// Publish an article, or a new version of one if it has the `d` tag of an
+    /// existing article of ours. A new article with no `d` tag gets one made
+    /// from its title.
+    pub async fn publish_article(&mut self, mut article: Article) -> Result<(), Error> {
+        let public_key = match GLOBALS.identity.public_key() {
+            Some(pk) => pk,
+            None => {
+                tracing::warn!("No public key! Not posting");
+                return Ok(());
+            }
+        };
+
+        if article.d.is_empty() {
+            let slug = article::slug(&article.title);
+            article.d = slug.clone();
+            // Don't replace a different article that happens to share the title
+            let mut n = 2;
+            while GLOBALS
+                .storage
+                .get_replaceable_event(EventKind::LongFormContent, public_key, &article.d)?
+                .is_some()
+            {
+                article.d = format!("{}-{}", slug, n);
+                n += 1;
+            }
+        }
+
+        // An edit keeps the original publication time
+        if article.published_at.is_none() {
+            article.published_at = GLOBALS
+                .storage
+                .get_replaceable_event(EventKind::LongFormContent, public_key, &article.d)?
+                .and_then(|event| Article::from_event(&event))
+                .and_then(|previous| previous.published_at)
+                .or(Some(Unixtime::now()));
+        }
+
+        let event = {
+            let pre_event = PreEvent {
+                pubkey: public_key,
+                created_at: Unixtime::now(),
+                kind: EventKind::LongFormContent,
+                tags: article.tags(),
+                content: article.content.clone(),
+            };
+
+            // Should we add a pow? Maybe the relay needs it.
+            GLOBALS.identity.sign_event(pre_event)?
+        };
+
+        // Process this event locally, which keeps the previous version as a revision
+        crate::process::process_new_event(&event, None, None, false, false)?;
+
+        let relay_urls = relay::relays_to_post_to(&event)?;
+        crate::outbox::post_event(&event, relay_urls, RelayConnectionReason::PostEvent)?;
 
         Ok(())
     }
@@ -1883,16 +2120,7 @@ impl Overlord {
         for url in &relay_urls {
             tracing::debug!("Pushing PersonList={} to {}", metadata.title, url);
         }
-        manager::run_jobs_on_all_relays(
-            relay_urls,
-            vec![RelayJob {
-                reason: RelayConnectionReason::PostContacts,
-                payload: ToMinionPayload {
-                    job_id: rand::random::<u64>(),
-                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
-                },
-            }],
-        );
+        crate::outbox::post_event(&event, relay_urls, RelayConnectionReason::PostContacts)?;
 
         Ok(())
     }
@@ -1920,16 +2148,7 @@ impl Overlord {
         for url in &relay_urls {
             tracing::debug!("Pushing Metadata to {}", url);
         }
-        manager::run_jobs_on_all_relays(
-            relay_urls,
-            vec![RelayJob {
-                reason: RelayConnectionReason::PostMetadata,
-                payload: ToMinionPayload {
-                    job_id: rand::random::<u64>(),
-                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
-                },
-            }],
-        );
+        crate::outbox::post_event(&event, relay_urls, RelayConnectionReason::PostMetadata)?;
 
         Ok(())
     }
@@ -2086,22 +2305,21 @@ impl Overlord {
         for url in &relay_urls {
             tracing::debug!("Asking {} to (re)post", url);
         }
-        manager::run_jobs_on_all_relays(
-            relay_urls,
-            vec![RelayJob {
-                reason: RelayConnectionReason::PostEvent,
-                payload: ToMinionPayload {
-                    job_id: rand::random::<u64>(),
-                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
-                },
-            }],
-        );
+        crate::outbox::post_event(&event, relay_urls, RelayConnectionReason::PostEvent)?;
 
         Ok(())
     }
 
-    /// Search people and notes in the local database.
-    /// Search results eventually arrive in `GLOBALS.people_search_results` and `GLOBALS.note_search_results`
+    /// Post one of our events again to the relays which have failed to accept it.
+    /// See [outbox](crate::outbox) for how this is tracked.
+    pub fn retry_post(id: Id) -> Result<(), Error> {
+        crate::outbox::retry_failed(id)
+    }
+
+    /// Search people and notes in the local database, and on relays that support
+    /// searching (NIP-50).
+    /// Search results eventually arrive in `GLOBALS.people_search_results` and `GLOBALS.note_search_results`,
+    /// with relay results streaming in after local ones.
     pub async fn search(mut text: String) -> Result<(), Error> {
         if text.len() < 2 {
             GLOBALS
@@ -2112,6 +2330,12 @@ impl Overlord {
         }
         text = text.to_lowercase();
 
+        // Results from earlier relay searches are no longer wanted
+        let job_id = rand::random::<u64>();
+        GLOBALS.relay_search_job.store(job_id, Ordering::Relaxed);
+        GLOBALS.note_search_relays.clear();
+        GLOBALS.people_search_relays.clear();
+
         let mut people_search_results: Vec<Person> = Vec::new();
         let mut note_search_results: Vec<Event> = Vec::new();
 
@@ -2120,6 +2344,7 @@ impl Overlord {
             text = text.split_off(6);
         }
 
+        let is_bech32 = NostrBech32::try_from_string(&text).is_some();
         if let Some(nb32) = NostrBech32::try_from_string(&text) {
             match nb32 {
                 NostrBech32::CryptSec(_) => {
@@ -2222,11 +2447,63 @@ impl Overlord {
             None,
         )?);
 
-        note_search_results.extend(GLOBALS.storage.search_events(&text)?);
+        let query = SearchQuery::parse(&text);
+        let (events, more) = GLOBALS.storage.search_events(&query, 0, SEARCH_PAGE_SIZE)?;
+        note_search_results.extend(events);
 
+        *GLOBALS.note_search_next_page.write() = if more {
+            Some((query, SEARCH_PAGE_SIZE))
+        } else {
+            None
+        };
         *GLOBALS.people_search_results.write() = people_search_results;
         *GLOBALS.note_search_results.write() = note_search_results;
 
+        // Ask relays too, unless looking up something specific
+        if !is_bech32 && !query.terms.is_empty() {
+            let relays = crate::search::search_relays()?;
+            if !relays.is_empty() {
+                manager::run_jobs_on_all_relays(
+                    relays,
+                    vec![RelayJob {
+                        reason: RelayConnectionReason::Search,
+                        payload: ToMinionPayload {
+                            job_id,
+                            detail: ToMinionPayloadDetail::Search(query),
+                        },
+                    }],
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Load the next page of local note search results, appending them to
+    /// `GLOBALS.note_search_results`
+    pub async fn search_more_notes() -> Result<(), Error> {
+        let (query, offset) = match GLOBALS.note_search_next_page.write().take() {
+            Some(page) => page,
+            None => return Ok(()),
+        };
+
+        let (events, more) = GLOBALS
+            .storage
+            .search_events(&query, offset, SEARCH_PAGE_SIZE)?;
+        {
+            // Relays may have already given us some of these
+            let mut results = GLOBALS.note_search_results.write();
+            for event in events {
+                if !results.iter().any(|e| e.id == event.id) {
+                    results.push(event);
+                }
+            }
+        }
+
+        if more {
+            *GLOBALS.note_search_next_page.write() = Some((query, offset + SEARCH_PAGE_SIZE));
+        }
+
         Ok(())
     }
 
@@ -2506,6 +2783,9 @@ impl Overlord {
         // Pick Relays and start Minions
         if !GLOBALS.storage.read_setting_offline() {
             self.pick_relays().await;
+
+            // Post whatever we made while we were offline
+            crate::outbox::flush_offline_queue()?;
         }
 
         // Separately subscribe to our outbox events on our write relays
@@ -2792,9 +3072,19 @@ impl Overlord {
         let mut txn = GLOBALS.storage.get_write_txn()?;
 
         let mut entries: Vec<(PublicKey, Private)> = Vec::new();
+        let mut mutes: Vec<MuteEntry> = Vec::new();
 
         // Public entries
         for tag in &event.tags {
+            if list == PersonList::Muted {
+                if let Some(mute) = Mute::from_tag(tag) {
+                    mutes.push(MuteEntry {
+                        mute,
+                        private: *metadata.private,
+                    });
+                }
+            }
+
             if let Ok((pubkey, rurl, petname)) = tag.parse_pubkey() {
                 // If our list is marked private, move these public entries to private ones
                 let private = metadata.private;
@@ -2827,6 +3117,14 @@ impl Overlord {
                         // Save the pubkey
                         entries.push((pubkey.to_owned(), Private(true)));
                     }
+                    if list == PersonList::Muted {
+                        if let Some(mute) = Mute::from_tag(tag) {
+                            mutes.push(MuteEntry {
+                                mute,
+                                private: true,
+                            });
+                        }
+                    }
                     if let Ok(title) = tag.parse_title() {
                         metadata.title = title.to_owned();
                     }
@@ -2841,6 +3139,13 @@ impl Overlord {
 
         if !merge {
             GLOBALS.storage.clear_person_list(list, Some(&mut txn))?;
+            if list == PersonList::Muted {
+                GLOBALS.storage.clear_mutes(Some(&mut txn))?;
+            }
+        }
+
+        for entry in &mutes {
+            GLOBALS.storage.write_mute(entry, Some(&mut txn))?;
         }
 
         for (pubkey, private) in &entries {
@@ -2865,6 +3170,10 @@ impl Overlord {
 
         txn.commit()?;
 
+        if list == PersonList::Muted {
+            crate::mutes::reload()?;
+        }
+
         // Pick relays again
         if list.subscribe() {
             // Refresh person-relay scores
@@ -3040,6 +3349,20 @@ impl Overlord {
         Ok(())
     }
 
+    /// Upload a file to the configured media server. When it finishes, it can be
+    /// taken from `GLOBALS.uploads` and attached to a post.
+    pub fn upload_media(&mut self, path: PathBuf) {
+        std::mem::drop(tokio::spawn(async move {
+            if let Err(e) = crate::upload::upload_file(path).await {
+                tracing::error!("{}", e);
+                GLOBALS
+                    .status_queue
+                    .write()
+                    .write(format!("Upload failed: {}", e));
+            }
+        }));
+    }
+
     /// Set which notes are currently visible to the user. This is used to modify subscriptions
     /// that query for likes, zaps, and deletions. Such subscriptions only query for that data
     /// for events currently in view, to keep them small.
@@ -3104,7 +3427,7 @@ impl Overlord {
 
         *GLOBALS.current_zap.write() = ZapState::CheckingLnurl(id, target_pubkey, lnurl.clone());
 
-        let client = reqwest::Client::builder()
+        let client = crate::proxy::http_client_builder()?
             .timeout(std::time::Duration::new(15, 0))
             .gzip(true)
             .brotli(true)
@@ -3141,6 +3464,18 @@ impl Overlord {
 
         *GLOBALS.current_zap.write() = ZapState::SeekingAmount(id, target_pubkey, prd, lnurl);
 
+        // One-click zaps skip asking for the amount
+        let default_sats = GLOBALS.storage.read_setting_zap_default_sats();
+        if default_sats > 0 {
+            self.zap(
+                id,
+                target_pubkey,
+                MilliSatoshi(default_sats * 1000),
+                "".to_owned(),
+            )
+            .await?;
+        }
+
         Ok(())
     }
 
@@ -3276,7 +3611,7 @@ impl Overlord {
 
         let serialized_event = serde_json::to_string(&event)?;
 
-        let client = reqwest::Client::builder()
+        let client = crate::proxy::http_client_builder()?
             .timeout(std::time::Duration::new(15, 0))
             .gzip(true)
             .brotli(true)
@@ -3304,7 +3639,24 @@ impl Overlord {
         if let Value::Object(map) = value {
             if let Some(Value::String(s)) = map.get("pr") {
                 tracing::debug!("Zap Invoice = {}", s);
-                *GLOBALS.current_zap.write() = ZapState::ReadyToPay(id, s.to_owned());
+
+                // The LNURL server is not to be trusted to bill us what we asked
+                if let Err(reason) = Self::check_zap_invoice(s, msats, &serialized_event) {
+                    *GLOBALS.current_zap.write() = ZapState::None;
+                    tracing::warn!("Refusing zap invoice: {}: {}", reason, s);
+                    GLOBALS
+                        .status_queue
+                        .write()
+                        .write(format!("Refusing zap invoice: {}", reason));
+                    return Ok(());
+                }
+
+                if crate::nwc::connection()?.is_some() {
+                    // Have the connected wallet pay it
+                    crate::nwc::pay_zap(id, s.to_owned())?;
+                } else {
+                    *GLOBALS.current_zap.write() = ZapState::ReadyToPay(id, s.to_owned());
+                }
                 return Ok(());
             }
         }
@@ -3318,6 +3670,26 @@ impl Overlord {
 
         Ok(())
     }
+
+    // Check that a zap invoice is for the amount we asked, and (if it says) for our
+    // zap request
+    fn check_zap_invoice(
+        invoice: &str,
+        msats: MilliSatoshi,
+        zap_request: &str,
+    ) -> Result<(), &'static str> {
+        let invoice = Bolt11::parse(invoice).ok_or("it is invalid")?;
+        if invoice.amount != Some(msats) {
+            return Err("its amount is not what we asked for");
+        }
+        if let Some(hash) = invoice.description_hash {
+            let expected: [u8; 32] = Sha256::digest(zap_request.as_bytes()).into();
+            if hash != expected {
+                return Err("it is not for our zap request");
+            }
+        }
+        Ok(())
+    }
 }
 
 fn work_logger(work_receiver: mpsc::Receiver<u8>, powint: u8) {
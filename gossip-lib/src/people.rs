@@ -2,6 +2,7 @@ use crate::comms::ToOverlordMessage;
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
 use crate::misc::{Freshness, Private};
+use crate::mutes::Mute;
 use crate::relay;
 use crate::storage::{PersonTable, Table};
 use dashmap::{DashMap, DashSet};
@@ -562,9 +563,8 @@ impl People {
 
         // Load most recent existing event, if any
         let existing_event: Option<Event> = match kind {
-            EventKind::ContactList | EventKind::MuteList => {
+            EventKind::ContactList => {
                 // We fetch for ContactList to preserve the contents
-                // We fetch for MuteList to preserve 't', 'e', and "word" tags
                 GLOBALS.storage.get_replaceable_event(kind, my_pubkey, "")?
             }
             EventKind::FollowSets => {
@@ -623,26 +623,15 @@ impl People {
             }
         }
 
-        // If MuteList
+        // If MuteList, add the muted words, hashtags and threads
+        // (first importing the private ones from before we kept them, if not yet done)
         if person_list == PersonList::Muted {
-            // Preserve existing tags that we don't operate on yet
-            for t in &old_tags {
-                match t.tagname() {
-                    "t" | "e" => {
-                        if *metadata.private {
-                            private_tags.push(t.clone());
-                        } else {
-                            public_tags.push(t.clone());
-                        }
-                    }
-                    "word" => {
-                        if *metadata.private {
-                            private_tags.push(t.clone());
-                        } else {
-                            public_tags.push(t.clone());
-                        }
-                    }
-                    _ => (),
+            crate::mutes::import_private_mutes()?;
+            for entry in GLOBALS.storage.read_all_mutes()? {
+                if *metadata.private || entry.private {
+                    private_tags.push(entry.mute.to_tag());
+                } else {
+                    public_tags.push(entry.mute.to_tag());
                 }
             }
         }
@@ -986,10 +975,17 @@ pub fn hash_person_list_event(list: PersonList) -> Result<u64, Error> {
         // Collect the data in an ordered map
         let mut map: BTreeMap<PublicKey, Private> = BTreeMap::new();
 
+        // Collect word, hashtag and thread mutes in the same way
+        let mut mutes: BTreeMap<Vec<u8>, Private> = BTreeMap::new();
+
         // Collect public entries
         for tag in &event.tags {
             if let Ok((pubkey, _, _)) = tag.parse_pubkey() {
                 map.insert(pubkey, metadata.private);
+            } else if list == PersonList::Muted {
+                if let Some(mute) = Mute::from_tag(tag) {
+                    mutes.insert(mute.key(), metadata.private);
+                }
             }
         }
 
@@ -1001,6 +997,10 @@ pub fn hash_person_list_event(list: PersonList) -> Result<u64, Error> {
                 for tag in &tags {
                     if let Ok((pubkey, _, _)) = tag.parse_pubkey() {
                         map.insert(pubkey, Private(true));
+                    } else if list == PersonList::Muted {
+                        if let Some(mute) = Mute::from_tag(tag) {
+                            mutes.insert(mute.key(), Private(true));
+                        }
                     }
                 }
             } else {
@@ -1020,7 +1020,9 @@ pub fn hash_person_list_event(list: PersonList) -> Result<u64, Error> {
             private.hash(&mut hasher);
         }
 
-        Ok(hasher.finish())
+        // (an empty list hashes to 0, as it does in storage)
+        let hash = if map.is_empty() { 0 } else { hasher.finish() };
+        Ok(crate::mutes::hash_with_mutes(hash, &mutes))
     } else {
         Ok(0)
     }
@@ -1,3 +1,4 @@
+use crate::comms::ToOverlordMessage;
 use crate::dm_channel::DmChannel;
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
@@ -9,6 +10,15 @@ use nostr_types::{
 };
 use std::sync::mpsc;
 
+/// A saved composer draft, aliased to the latest version
+pub type Draft = crate::storage::types::Draft1;
+
+/// What a draft is for, aliased to the latest version
+pub type DraftContext = crate::storage::types::DraftContext1;
+
+/// A post to be published later, aliased to the latest version
+pub type ScheduledPost = crate::storage::types::ScheduledPost1;
+
 pub fn prepare_post_normal(
     author: PublicKey,
     content: String,
@@ -347,3 +357,24 @@ fn work_logger(work_receiver: mpsc::Receiver<u8>, powint: u8) {
         }
     }
 }
+
+/// Ask the overlord to publish scheduled posts whose time has come. It takes each
+/// off the schedule only once it has been handed off for posting.
+///
+/// These are signed when they are posted, so if the identity is locked they wait.
+pub(crate) fn send_due_scheduled_posts() -> Result<(), Error> {
+    if !GLOBALS.identity.is_unlocked() {
+        return Ok(());
+    }
+
+    let now = Unixtime::now();
+    for post in GLOBALS.storage.read_all_scheduled_posts()? {
+        if post.publish_at <= now {
+            GLOBALS
+                .to_overlord
+                .send(ToOverlordMessage::PostScheduled(post.id))?;
+        }
+    }
+
+    Ok(())
+}
@@ -7,6 +7,7 @@ use crate::misc::{Freshness, Private};
 use crate::people::{People, PersonList, PersonListMetadata};
 use crate::relationship::{RelationshipByAddr, RelationshipById};
 use crate::storage::{PersonTable, Table};
+use crate::zaps::ZapReceiptCheck;
 use heed::RwTxn;
 use nostr_types::{
     Event, EventKind, EventReference, Filter, Id, Metadata, NAddr, NostrBech32, PublicKey,
@@ -196,6 +197,11 @@ pub fn process_new_event(
         event.created_at
     );
 
+    // Announce it to anyone listening (e.g. the local API)
+    if GLOBALS.new_events.receiver_count() > 0 {
+        let _ = GLOBALS.new_events.send(event.clone());
+    }
+
     // If we were searching for this event, add it to the search results
     let is_a_search_result: bool = GLOBALS.events_being_searched_for.read().contains(&event.id);
     if is_a_search_result {
@@ -353,6 +359,8 @@ pub fn process_new_event(
         }
     } else if event.kind == EventKind::NostrConnect {
         crate::nip46::handle_command(event, seen_on.clone())?
+    } else if event.kind == EventKind::from(crate::nwc::NWC_RESPONSE_KIND) {
+        crate::nwc::handle_response(event)?
     }
 
     if event.kind.is_feed_displayable() {
@@ -608,6 +616,16 @@ pub(crate) fn process_relationships_of_event<'a>(
                             }
                         }
 
+                        // Earlier versions we kept go too
+                        if ea.author == event.pubkey {
+                            GLOBALS.storage.delete_revisions(
+                                ea.kind,
+                                ea.author,
+                                &ea.d,
+                                Some(txn),
+                            )?;
+                        }
+
                         // Store the delete (we either don't have the target to verify,
                         // or we just verified above. In the former case, it is okay because
                         // we verify on usage)
@@ -784,19 +802,35 @@ pub(crate) fn process_relationships_of_event<'a>(
             }
         }
 
-        // zaps
-        if let Ok(Some(zapdata)) = event.zaps() {
-            GLOBALS.storage.write_relationship_by_id(
-                zapdata.id,
-                event.id,
-                RelationshipById::Zaps {
-                    by: event.pubkey,
-                    amount: zapdata.amount,
-                },
-                Some(txn),
-            )?;
+        // zaps (only counted once the receipt checks out)
+        if event.kind == EventKind::Zap {
+            match crate::zaps::check_zap_receipt(event)? {
+                ZapReceiptCheck::Valid(zapped_id, amount) => {
+                    GLOBALS.storage.write_relationship_by_id(
+                        zapped_id,
+                        event.id,
+                        RelationshipById::Zaps {
+                            by: event.pubkey,
+                            amount,
+                        },
+                        Some(txn),
+                    )?;
 
-            invalidate.push(zapdata.id);
+                    invalidate.push(zapped_id);
+                }
+                ZapReceiptCheck::Invalid(reason) => {
+                    tracing::info!(
+                        "Discarding zap receipt {}: {}",
+                        event.id.as_hex_string(),
+                        reason
+                    );
+                }
+                ZapReceiptCheck::Unknown(recipient) => {
+                    GLOBALS
+                        .storage
+                        .write_unverified_zap(event.id, recipient, Some(txn))?;
+                }
+            }
         }
 
         // JobResult
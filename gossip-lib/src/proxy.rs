@@ -0,0 +1,93 @@
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use tokio::net::TcpStream;
+
+/// The configured SOCKS5 proxy as `host:port`, if any
+pub fn socks5_proxy() -> Option<String> {
+    let proxy = GLOBALS.storage.read_setting_socks5_proxy();
+    let proxy = proxy.trim();
+    if proxy.is_empty() {
+        None
+    } else {
+        Some(proxy.to_owned())
+    }
+}
+
+/// If the host is a Tor onion service (only reachable through the proxy)
+pub fn is_onion_host(host: &str) -> bool {
+    host.trim_end_matches('.')
+        .to_ascii_lowercase()
+        .ends_with(".onion")
+}
+
+/// If the URL is of a Tor onion service (only reachable through the proxy)
+pub fn is_onion_url(url: &str) -> bool {
+    match url.parse::<http::Uri>() {
+        Ok(uri) => uri.host().map(is_onion_host).unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+/// A reqwest client builder which goes through the SOCKS5 proxy if one is set.
+///
+/// Host names are resolved by the proxy (socks5h) so that DNS does not leak and
+/// so that .onion hosts work.
+pub(crate) fn http_client_builder() -> Result<reqwest::ClientBuilder, Error> {
+    let builder = reqwest::Client::builder();
+    match socks5_proxy() {
+        Some(proxy) => Ok(builder.proxy(reqwest::Proxy::all(format!("socks5h://{proxy}"))?)),
+        None => Ok(builder),
+    }
+}
+
+/// Open a TCP connection, through the SOCKS5 proxy if one is set.
+///
+/// If `requires_proxy` is set (or the host is an onion service) and no proxy is
+/// set, this fails rather than connecting directly.
+pub(crate) async fn connect_tcp(
+    host: &str,
+    port: u16,
+    requires_proxy: bool,
+) -> Result<TcpStream, Error> {
+    match socks5_proxy() {
+        Some(proxy) => {
+            let stream = tokio_socks::tcp::Socks5Stream::connect(proxy.as_str(), (host, port))
+                .await?
+                .into_inner();
+            Ok(stream)
+        }
+        None => {
+            if requires_proxy || is_onion_host(host) {
+                return Err(ErrorKind::ProxyRequired(host.to_owned()).into());
+            }
+            Ok(TcpStream::connect((host, port)).await?)
+        }
+    }
+}
+
+/// Open a blocking TCP connection, through the SOCKS5 proxy if one is set.
+pub(crate) fn connect_tcp_blocking(host: &str, port: u16) -> Result<std::net::TcpStream, Error> {
+    match socks5_proxy() {
+        Some(proxy) => Ok(socks::Socks5Stream::connect(proxy.as_str(), (host, port))?.into_inner()),
+        None => {
+            if is_onion_host(host) {
+                return Err(ErrorKind::ProxyRequired(host.to_owned()).into());
+            }
+            Ok(std::net::TcpStream::connect((host, port))?)
+        }
+    }
+}
+
+/// The port of a websocket URI, defaulting by scheme
+pub(crate) fn websocket_port(uri: &http::Uri) -> u16 {
+    match uri.port_u16() {
+        Some(port) => port,
+        None => {
+            if uri.scheme_str() == Some("ws") {
+                80
+            } else {
+                443
+            }
+        }
+    }
+}
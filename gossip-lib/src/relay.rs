@@ -14,7 +14,10 @@
 // future: get_all_pubkey_outboxes_for_batch_search(pubkey)?     // for seeker exhaustive search
 
 /// Relay type, aliased to the latest version
-pub type Relay = crate::storage::types::Relay3;
+pub type Relay = crate::storage::types::Relay6;
+
+/// How a relay has performed for us, aliased to the latest version
+pub type RelayMetrics = crate::storage::types::RelayMetrics1;
 
 use crate::error::{Error, ErrorKind};
 use crate::person_relay::PersonRelay;
@@ -0,0 +1,554 @@
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::people::PersonList;
+use nostr_types::{Event, EventKind, Id, PublicKey, Unixtime};
+use speedy::{Readable, Writable};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Whose events a retention rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Readable, Writable)]
+pub enum AuthorClass {
+    /// Our own events
+    Myself,
+
+    /// People we follow
+    Followed,
+
+    /// People on any of our other lists (but not the mute list)
+    ListMember,
+
+    /// Everybody else
+    Stranger,
+}
+
+impl fmt::Display for AuthorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthorClass::Myself => write!(f, "me"),
+            AuthorClass::Followed => write!(f, "people I follow"),
+            AuthorClass::ListMember => write!(f, "people on my lists"),
+            AuthorClass::Stranger => write!(f, "strangers"),
+        }
+    }
+}
+
+/// A rule saying how long to keep the events it matches. Every condition that
+/// is set must hold for the rule to match.
+#[derive(Debug, Clone, PartialEq, Readable, Writable)]
+pub struct RetentionRule {
+    pub name: String,
+
+    /// Whose events (any, if None)
+    pub authors: Option<AuthorClass>,
+
+    /// Which event kinds (any, if empty)
+    pub kinds: Vec<u32>,
+
+    /// Whether the event is on our bookmark or pin list (either, if None)
+    pub bookmarked: Option<bool>,
+
+    /// Whether the event refers to one of our events (a reply, reaction, zap...)
+    /// or one of our events refers to it (either, if None)
+    pub related_to_ours: Option<bool>,
+
+    /// Delete matching events older than this (kept forever, if None)
+    pub max_age_days: Option<u64>,
+
+    /// Keep no more than this many matching events, deleting the oldest first
+    pub max_events: Option<u64>,
+
+    /// Keep no more than this many bytes of matching events, deleting the
+    /// oldest first
+    pub max_bytes: Option<u64>,
+}
+
+impl RetentionRule {
+    /// A rule matching everything, keeping it forever
+    pub fn new(name: &str) -> RetentionRule {
+        RetentionRule {
+            name: name.to_owned(),
+            authors: None,
+            kinds: vec![],
+            bookmarked: None,
+            related_to_ours: None,
+            max_age_days: None,
+            max_events: None,
+            max_bytes: None,
+        }
+    }
+
+    /// Describe which events this rule matches
+    pub fn describe_matches(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        match self.authors {
+            Some(authors) => parts.push(format!("by {}", authors)),
+            None => parts.push("by anyone".to_owned()),
+        }
+        if !self.kinds.is_empty() {
+            let kinds: Vec<String> = self.kinds.iter().map(|k| k.to_string()).collect();
+            parts.push(format!("of kind {}", kinds.join(", ")));
+        }
+        match self.bookmarked {
+            Some(true) => parts.push("bookmarked or pinned".to_owned()),
+            Some(false) => parts.push("not bookmarked or pinned".to_owned()),
+            None => {}
+        }
+        match self.related_to_ours {
+            Some(true) => parts.push("related to my events".to_owned()),
+            Some(false) => parts.push("unrelated to my events".to_owned()),
+            None => {}
+        }
+        parts.join(", ")
+    }
+
+    fn matches(&self, event: &EventSummary, facts: &Facts) -> bool {
+        if let Some(authors) = self.authors {
+            if facts.author_class(&event.pubkey) != authors {
+                return false;
+            }
+        }
+        if !self.kinds.is_empty() && !self.kinds.contains(&u32::from(event.kind)) {
+            return false;
+        }
+        if let Some(bookmarked) = self.bookmarked {
+            if facts.bookmarked.contains(&event.id) != bookmarked {
+                return false;
+            }
+        }
+        if let Some(related) = self.related_to_ours {
+            if facts.is_related_to_ours(event) != related {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Rules for which events to keep when pruning the database. The first rule
+/// that matches an event decides; events no rule matches are kept for the
+/// `prune_period_days` setting.
+#[derive(Debug, Clone, PartialEq, Readable, Writable)]
+pub struct RetentionPolicy {
+    pub rules: Vec<RetentionRule>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> RetentionPolicy {
+        RetentionPolicy {
+            rules: vec![
+                RetentionRule {
+                    authors: Some(AuthorClass::Myself),
+                    ..RetentionRule::new("My events")
+                },
+                RetentionRule {
+                    bookmarked: Some(true),
+                    ..RetentionRule::new("Bookmarks and pins")
+                },
+                RetentionRule {
+                    kinds: vec![
+                        u32::from(EventKind::EncryptedDirectMessage),
+                        u32::from(EventKind::GiftWrap),
+                    ],
+                    ..RetentionRule::new("Direct messages")
+                },
+                RetentionRule {
+                    related_to_ours: Some(true),
+                    max_age_days: Some(365),
+                    ..RetentionRule::new("Replies, reactions and zaps")
+                },
+                RetentionRule {
+                    authors: Some(AuthorClass::Followed),
+                    max_age_days: Some(180),
+                    ..RetentionRule::new("People I follow")
+                },
+                RetentionRule {
+                    authors: Some(AuthorClass::ListMember),
+                    max_age_days: Some(180),
+                    ..RetentionRule::new("People on my lists")
+                },
+            ],
+        }
+    }
+}
+
+/// What pruning deleted, or would delete
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    /// Whether this was a dry run (nothing was deleted)
+    pub dry_run: bool,
+
+    /// Number of events looked at
+    pub examined: usize,
+
+    /// Number of events (to be) deleted, and their size
+    pub deleted: usize,
+    pub deleted_bytes: u64,
+
+    /// Per rule (by name, or "No rule"): events matched, and events (to be)
+    /// deleted and their size
+    pub by_rule: Vec<RuleReport>,
+}
+
+/// How one rule fared in pruning
+#[derive(Debug, Clone, Default)]
+pub struct RuleReport {
+    pub name: String,
+    pub matched: usize,
+    pub deleted: usize,
+    pub deleted_bytes: u64,
+}
+
+// What we need to know about an event to apply rules to it
+pub(crate) struct EventSummary {
+    pub id: Id,
+    pub pubkey: PublicKey,
+    pub kind: EventKind,
+    pub created_at: Unixtime,
+    pub size: usize,
+
+    // Ids this event refers to in 'e' tags
+    pub references: Vec<Id>,
+}
+
+impl EventSummary {
+    pub(crate) fn new(event: &Event, size: usize) -> EventSummary {
+        EventSummary {
+            id: event.id,
+            pubkey: event.pubkey,
+            kind: event.kind,
+            created_at: event.created_at,
+            size,
+            references: event
+                .tags
+                .iter()
+                .filter(|tag| tag.tagname() == "e")
+                .filter_map(|tag| tag.parse_event().ok().map(|(id, _, _)| id))
+                .collect(),
+        }
+    }
+}
+
+// Things about us that rules depend on
+struct Facts {
+    ours: Option<PublicKey>,
+    our_ids: HashSet<Id>,
+
+    // Ids our events refer to
+    referenced_by_ours: HashSet<Id>,
+
+    bookmarked: HashSet<Id>,
+    author_classes: HashMap<PublicKey, AuthorClass>,
+}
+
+impl Facts {
+    fn author_class(&self, pubkey: &PublicKey) -> AuthorClass {
+        if Some(*pubkey) == self.ours {
+            AuthorClass::Myself
+        } else {
+            self.author_classes
+                .get(pubkey)
+                .copied()
+                .unwrap_or(AuthorClass::Stranger)
+        }
+    }
+
+    fn is_related_to_ours(&self, event: &EventSummary) -> bool {
+        self.referenced_by_ours.contains(&event.id)
+            || event.references.iter().any(|id| self.our_ids.contains(id))
+    }
+}
+
+/// Work out which events the policy would delete
+pub(crate) fn plan(
+    policy: &RetentionPolicy,
+    events: Vec<EventSummary>,
+) -> Result<(HashSet<Id>, PruneReport), Error> {
+    let facts = gather_facts(&events)?;
+    let fallback_days = GLOBALS.storage.read_setting_prune_period_days();
+    Ok(apply_rules(
+        policy,
+        events,
+        &facts,
+        Unixtime::now(),
+        fallback_days,
+    ))
+}
+
+fn apply_rules(
+    policy: &RetentionPolicy,
+    events: Vec<EventSummary>,
+    facts: &Facts,
+    now: Unixtime,
+    fallback_days: u64,
+) -> (HashSet<Id>, PruneReport) {
+    let mut report = PruneReport {
+        examined: events.len(),
+        ..Default::default()
+    };
+    report.by_rule = policy
+        .rules
+        .iter()
+        .map(|rule| RuleReport {
+            name: rule.name.clone(),
+            ..Default::default()
+        })
+        .collect();
+    report.by_rule.push(RuleReport {
+        name: "No rule".to_owned(),
+        ..Default::default()
+    });
+
+    // Sort events to the first rule they match (or the fallback at the end)
+    let mut matched: Vec<Vec<EventSummary>> = (0..report.by_rule.len()).map(|_| vec![]).collect();
+    for event in events {
+        let index = policy
+            .rules
+            .iter()
+            .position(|rule| rule.matches(&event, facts))
+            .unwrap_or(policy.rules.len());
+        matched[index].push(event);
+    }
+
+    let mut doomed: HashSet<Id> = HashSet::new();
+    for (index, mut events) in matched.into_iter().enumerate() {
+        let (max_age_days, max_events, max_bytes) = match policy.rules.get(index) {
+            Some(rule) => (rule.max_age_days, rule.max_events, rule.max_bytes),
+            None => (Some(fallback_days), None, None),
+        };
+        let rule_report = &mut report.by_rule[index];
+        rule_report.matched = events.len();
+
+        // Newest first, so budgets keep the newest
+        events.sort_by(|a, b| b.created_at.0.cmp(&a.created_at.0));
+
+        // (saturating, as an absurd age in the policy just means "keep everything")
+        let cutoff = max_age_days.map(|days| {
+            let age = i64::try_from(days)
+                .unwrap_or(i64::MAX)
+                .saturating_mul(60 * 60 * 24);
+            now.0.saturating_sub(age)
+        });
+        let mut kept_events: u64 = 0;
+        let mut kept_bytes: u64 = 0;
+
+        // Once an event doesn't fit, nothing older is kept either
+        let mut over_budget = false;
+        for event in events {
+            let too_old = cutoff
+                .map(|cutoff| event.created_at.0 < cutoff)
+                .unwrap_or(false);
+            over_budget = over_budget
+                || max_events.map(|max| kept_events >= max).unwrap_or(false)
+                || max_bytes
+                    .map(|max| kept_bytes + event.size as u64 > max)
+                    .unwrap_or(false);
+            if too_old || over_budget {
+                rule_report.deleted += 1;
+                rule_report.deleted_bytes += event.size as u64;
+                doomed.insert(event.id);
+            } else {
+                kept_events += 1;
+                kept_bytes += event.size as u64;
+            }
+        }
+    }
+
+    report.deleted = doomed.len();
+    report.deleted_bytes = report.by_rule.iter().map(|r| r.deleted_bytes).sum();
+
+    (doomed, report)
+}
+
+fn gather_facts(events: &[EventSummary]) -> Result<Facts, Error> {
+    let ours = GLOBALS.identity.public_key();
+
+    let mut our_ids: HashSet<Id> = HashSet::new();
+    let mut referenced_by_ours: HashSet<Id> = HashSet::new();
+    if let Some(ours) = ours {
+        for event in events.iter().filter(|e| e.pubkey == ours) {
+            our_ids.insert(event.id);
+            referenced_by_ours.extend(event.references.iter().copied());
+        }
+    }
+
+    let mut bookmarked: HashSet<Id> = GLOBALS.current_bookmarks.read().iter().copied().collect();
+    if let Some(ours) = ours {
+        if let Some(pin_list) =
+            GLOBALS
+                .storage
+                .get_replaceable_event(EventKind::PinList, ours, "")?
+        {
+            for tag in &pin_list.tags {
+                if let Ok((id, _, _)) = tag.parse_event() {
+                    bookmarked.insert(id);
+                }
+            }
+        }
+    }
+
+    let mut author_classes: HashMap<PublicKey, AuthorClass> = HashMap::new();
+    for event in events {
+        if author_classes.contains_key(&event.pubkey) || Some(event.pubkey) == ours {
+            continue;
+        }
+        let lists = GLOBALS.storage.read_person_lists(&event.pubkey)?;
+        let class = if lists.contains_key(&PersonList::Followed) {
+            AuthorClass::Followed
+        } else if lists.keys().any(|list| *list != PersonList::Muted) {
+            AuthorClass::ListMember
+        } else {
+            AuthorClass::Stranger
+        };
+        author_classes.insert(event.pubkey, class);
+    }
+
+    Ok(Facts {
+        ours,
+        our_ids,
+        referenced_by_ours,
+        bookmarked,
+        author_classes,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nostr_types::PrivateKey;
+
+    const DAY: i64 = 60 * 60 * 24;
+
+    fn summary(
+        n: u8,
+        pubkey: PublicKey,
+        kind: EventKind,
+        age_days: i64,
+        size: usize,
+    ) -> EventSummary {
+        EventSummary {
+            id: Id([n; 32]),
+            pubkey,
+            kind,
+            created_at: Unixtime(1_700_000_000 - age_days * DAY),
+            size,
+            references: vec![],
+        }
+    }
+
+    fn facts(ours: PublicKey) -> Facts {
+        Facts {
+            ours: Some(ours),
+            our_ids: HashSet::new(),
+            referenced_by_ours: HashSet::new(),
+            bookmarked: HashSet::new(),
+            author_classes: HashMap::new(),
+        }
+    }
+
+    fn ids(ns: &[u8]) -> HashSet<Id> {
+        ns.iter().map(|n| Id([*n; 32])).collect()
+    }
+
+    #[test]
+    fn test_first_match_wins() {
+        let ours = PrivateKey::generate().public_key();
+        let theirs = PrivateKey::generate().public_key();
+        let policy = RetentionPolicy {
+            rules: vec![
+                RetentionRule {
+                    authors: Some(AuthorClass::Myself),
+                    ..RetentionRule::new("Mine")
+                },
+                RetentionRule {
+                    max_age_days: Some(1),
+                    ..RetentionRule::new("Everything")
+                },
+            ],
+        };
+        let events = vec![
+            summary(1, ours, EventKind::TextNote, 100, 10),
+            summary(2, theirs, EventKind::TextNote, 100, 10),
+        ];
+        let now = Unixtime(1_700_000_000);
+        let (doomed, report) = apply_rules(&policy, events, &facts(ours), now, 30);
+        assert_eq!(doomed, ids(&[2]));
+        assert_eq!(report.by_rule[0].matched, 1);
+        assert_eq!(report.by_rule[1].matched, 1);
+        assert_eq!(report.by_rule[2].matched, 0);
+    }
+
+    #[test]
+    fn test_fallback_age() {
+        let ours = PrivateKey::generate().public_key();
+        let theirs = PrivateKey::generate().public_key();
+        let policy = RetentionPolicy {
+            rules: vec![RetentionRule {
+                kinds: vec![u32::from(EventKind::Reaction)],
+                ..RetentionRule::new("Reactions")
+            }],
+        };
+        let events = vec![
+            summary(1, theirs, EventKind::TextNote, 10, 10),
+            summary(2, theirs, EventKind::TextNote, 40, 10),
+            summary(3, theirs, EventKind::Reaction, 40, 10),
+        ];
+        let now = Unixtime(1_700_000_000);
+        let (doomed, report) = apply_rules(&policy, events, &facts(ours), now, 30);
+        assert_eq!(doomed, ids(&[2]));
+        assert_eq!(report.by_rule[1].name, "No rule");
+        assert_eq!(report.by_rule[1].matched, 2);
+        assert_eq!(report.by_rule[1].deleted, 1);
+        assert_eq!(report.deleted_bytes, 10);
+
+        // An absurd age keeps everything rather than overflowing
+        let events = vec![summary(1, theirs, EventKind::TextNote, 40, 10)];
+        let (doomed, _) = apply_rules(&policy, events, &facts(ours), now, u64::MAX);
+        assert!(doomed.is_empty());
+    }
+
+    #[test]
+    fn test_max_events() {
+        let ours = PrivateKey::generate().public_key();
+        let theirs = PrivateKey::generate().public_key();
+        let policy = RetentionPolicy {
+            rules: vec![RetentionRule {
+                max_events: Some(2),
+                ..RetentionRule::new("Two")
+            }],
+        };
+        let events = vec![
+            summary(1, theirs, EventKind::TextNote, 3, 10),
+            summary(2, theirs, EventKind::TextNote, 1, 10),
+            summary(3, theirs, EventKind::TextNote, 4, 10),
+            summary(4, theirs, EventKind::TextNote, 2, 10),
+        ];
+        let now = Unixtime(1_700_000_000);
+        let (doomed, report) = apply_rules(&policy, events, &facts(ours), now, 30);
+        assert_eq!(doomed, ids(&[1, 3]));
+        assert_eq!(report.deleted, 2);
+    }
+
+    #[test]
+    fn test_max_bytes() {
+        let ours = PrivateKey::generate().public_key();
+        let theirs = PrivateKey::generate().public_key();
+        let policy = RetentionPolicy {
+            rules: vec![RetentionRule {
+                max_bytes: Some(100),
+                ..RetentionRule::new("Small")
+            }],
+        };
+        // The 80 byte event doesn't fit after the 50 byte one, and the small
+        // event older than it must not be kept in its place
+        let events = vec![
+            summary(1, theirs, EventKind::TextNote, 1, 50),
+            summary(2, theirs, EventKind::TextNote, 2, 80),
+            summary(3, theirs, EventKind::TextNote, 3, 10),
+        ];
+        let now = Unixtime(1_700_000_000);
+        let (doomed, report) = apply_rules(&policy, events, &facts(ours), now, 30);
+        assert_eq!(doomed, ids(&[2, 3]));
+        assert_eq!(report.by_rule[0].deleted_bytes, 90);
+    }
+}
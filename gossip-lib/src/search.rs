@@ -0,0 +1,400 @@
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::relay::Relay;
+use crate::storage::{PersonTable, Table};
+use nostr_types::{Event, EventKind, Filter, PublicKey, RelayUrl, Unixtime};
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+
+/// Terms shorter than this (in characters) are not indexed nor searched for
+pub const MIN_TERM_CHARS: usize = 2;
+
+/// How many note search results are loaded at a time
+pub const SEARCH_PAGE_SIZE: usize = 50;
+
+/// Terms longer than this (in bytes) are not indexed nor searched for. These are
+/// almost always base64 blobs, hashes or other junk.
+pub const MAX_TERM_BYTES: usize = 64;
+
+/// How many people to ask each relay for in a relay search
+const RELAY_SEARCH_PEOPLE_LIMIT: usize = 20;
+
+// Tags whose values are indexed along with the content
+const SEARCHABLE_TAGS: [&str; 5] = ["subject", "title", "summary", "t", "alt"];
+
+/// A parsed local search query.
+///
+/// Plain words must all match (AND). Double-quoted text must match as a phrase.
+/// The following qualifiers restrict results further:
+///
+/// * `author:<npub or hex>`
+/// * `kind:<number>`
+/// * `since:<YYYY-MM-DD or unixtime>`
+/// * `until:<YYYY-MM-DD or unixtime>`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    /// Individual terms, all of which must be present (includes the terms of phrases)
+    pub terms: Vec<String>,
+
+    /// Phrases, each a sequence of terms which must appear adjacent and in order
+    pub phrases: Vec<Vec<String>>,
+
+    /// If not empty, the author must be one of these
+    pub authors: Vec<PublicKey>,
+
+    /// If not empty, the kind must be one of these
+    pub kinds: Vec<EventKind>,
+
+    /// Only events created at or after this time
+    pub since: Option<Unixtime>,
+
+    /// Only events created at or before this time
+    pub until: Option<Unixtime>,
+}
+
+impl SearchQuery {
+    /// Parse a search query from user input
+    pub fn parse(text: &str) -> SearchQuery {
+        let mut query = SearchQuery::default();
+
+        // Split off quoted phrases first. Odd segments are inside quotes.
+        for (i, segment) in text.split('"').enumerate() {
+            if i % 2 == 1 {
+                let phrase = tokenize(segment);
+                match phrase.len() {
+                    0 => (),
+                    1 => query.add_term(&phrase[0]),
+                    _ => {
+                        for term in &phrase {
+                            query.add_term(term);
+                        }
+                        query.phrases.push(phrase);
+                    }
+                }
+                continue;
+            }
+
+            for word in segment.split_whitespace() {
+                if let Some((qualifier, value)) = word.split_once(':') {
+                    if query.add_qualifier(&qualifier.to_lowercase(), value) {
+                        continue;
+                    }
+                }
+                for term in tokenize(word) {
+                    query.add_term(&term);
+                }
+            }
+        }
+
+        query
+    }
+
+    /// True if the query has no terms and no qualifiers
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+            && self.authors.is_empty()
+            && self.kinds.is_empty()
+            && self.since.is_none()
+            && self.until.is_none()
+    }
+
+    /// Check the non-text restrictions of this query against an event
+    pub fn qualifiers_match(&self, event: &Event) -> bool {
+        if !self.authors.is_empty() && !self.authors.contains(&event.pubkey) {
+            return false;
+        }
+        if !self.kinds.is_empty() && !self.kinds.contains(&event.kind) {
+            return false;
+        }
+        if let Some(since) = self.since {
+            if event.created_at < since {
+                return false;
+            }
+        }
+        if let Some(until) = self.until {
+            if event.created_at > until {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Check that every phrase of this query appears in the event
+    pub fn phrases_match(&self, event: &Event) -> bool {
+        if self.phrases.is_empty() {
+            return true;
+        }
+        let tokens = searchable_tokens(event);
+        self.phrases
+            .iter()
+            .all(|phrase| tokens.windows(phrase.len()).any(|w| w == phrase.as_slice()))
+    }
+
+    /// The text to send to relays as a NIP-50 `search`. Qualifiers are not part of
+    /// it; they go into the other filter fields.
+    pub fn relay_search_text(&self) -> String {
+        let mut parts: Vec<String> = self
+            .terms
+            .iter()
+            .filter(|t| !self.phrases.iter().any(|p| p.contains(t)))
+            .cloned()
+            .collect();
+        for phrase in &self.phrases {
+            parts.push(format!("\"{}\"", phrase.join(" ")));
+        }
+        parts.join(" ")
+    }
+
+    /// Filters for asking relays to search (NIP-50). These are JSON because
+    /// `Filter` has no `search` field.
+    pub(crate) fn relay_filters(&self) -> Result<Vec<serde_json::Value>, Error> {
+        let text = self.relay_search_text();
+        let mut filters: Vec<Filter> = Vec::new();
+
+        let mut notes = Filter::new();
+        notes.kinds = if self.kinds.is_empty() {
+            crate::feed::feed_displayable_event_kinds(false)
+                .drain(..)
+                .filter(|k| is_searchable_kind(*k))
+                .collect()
+        } else {
+            self.kinds.clone()
+        };
+        for author in &self.authors {
+            notes.add_author(&(*author).into());
+        }
+        notes.since = self.since;
+        notes.until = self.until;
+        notes.limit = Some(SEARCH_PAGE_SIZE);
+        filters.push(notes);
+
+        // People only when nothing narrows the search to notes
+        if self.authors.is_empty() && self.kinds.is_empty() {
+            let mut people = Filter::new();
+            people.add_event_kind(EventKind::Metadata);
+            people.limit = Some(RELAY_SEARCH_PEOPLE_LIMIT);
+            filters.push(people);
+        }
+
+        let mut values: Vec<serde_json::Value> = Vec::new();
+        for filter in filters.drain(..) {
+            let mut value = serde_json::to_value(&filter)?;
+            if let Some(object) = value.as_object_mut() {
+                object.insert("search".to_owned(), serde_json::Value::String(text.clone()));
+            }
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    fn add_term(&mut self, term: &str) {
+        if !self.terms.iter().any(|t| t == term) {
+            self.terms.push(term.to_owned());
+        }
+    }
+
+    fn add_qualifier(&mut self, qualifier: &str, value: &str) -> bool {
+        match qualifier {
+            "author" | "by" => {
+                let pubkey = match PublicKey::try_from_bech32_string(value, true) {
+                    Ok(pk) => pk,
+                    Err(_) => match PublicKey::try_from_hex_string(value, true) {
+                        Ok(pk) => pk,
+                        Err(_) => return false,
+                    },
+                };
+                self.authors.push(pubkey);
+                true
+            }
+            "kind" => match value.parse::<u32>() {
+                Ok(k) => {
+                    self.kinds.push(k.into());
+                    true
+                }
+                Err(_) => false,
+            },
+            "since" => match parse_time(value) {
+                Some(t) => {
+                    self.since = Some(t);
+                    true
+                }
+                None => false,
+            },
+            "until" => match parse_time(value) {
+                // A bare date means the end of that day
+                Some(t) => {
+                    self.until = Some(if value.contains('-') {
+                        Unixtime(t.0 + 86399)
+                    } else {
+                        t
+                    });
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Break text into lowercase search terms
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|s| s.chars().count() >= MIN_TERM_CHARS && s.len() <= MAX_TERM_BYTES)
+        .map(|s| s.to_lowercase())
+        .collect()
+}
+
+/// Whether events of this kind are put into the search index.
+///
+/// Encrypted kinds are not indexed, as their content is ciphertext and we would
+/// not want their plaintext stored in an index anyways.
+pub fn is_searchable_kind(kind: EventKind) -> bool {
+    kind.is_feed_displayable()
+        && kind != EventKind::EncryptedDirectMessage
+        && kind != EventKind::GiftWrap
+        && kind != EventKind::DmChat
+}
+
+// All tokens of an event, in order, from the content and then the searchable tags
+fn searchable_tokens(event: &Event) -> Vec<String> {
+    let mut tokens = tokenize(&event.content);
+    for tag in &event.tags {
+        if SEARCHABLE_TAGS.contains(&tag.tagname()) {
+            tokens.extend(tokenize(tag.value()));
+        }
+    }
+    tokens
+}
+
+/// The terms of an event that go into the search index, with their frequency
+pub(crate) fn event_terms(event: &Event) -> HashMap<String, u16> {
+    let mut terms: HashMap<String, u16> = HashMap::new();
+    if !is_searchable_kind(event.kind) {
+        return terms;
+    }
+    for token in searchable_tokens(event) {
+        let count = terms.entry(token).or_insert(0);
+        *count = count.saturating_add(1);
+    }
+    terms
+}
+
+/// The relays to search (NIP-50): those the user has marked for search, and any
+/// other relay in use which says it supports searching
+pub(crate) fn search_relays() -> Result<Vec<RelayUrl>, Error> {
+    let relays = GLOBALS.storage.filter_relays(|relay| {
+        !relay.should_avoid()
+            && (relay.has_usage_bits(Relay::SEARCH)
+                || (relay.has_any_usage_bit() && relay.supports_nip(50)))
+    })?;
+    Ok(relays.iter().map(|relay| relay.url.clone()).collect())
+}
+
+/// If relay search results for this job are still wanted (a newer search
+/// replaces an older one)
+pub(crate) fn is_current_relay_search(job_id: u64) -> bool {
+    GLOBALS.relay_search_job.load(Ordering::Relaxed) == job_id
+}
+
+/// Add an event a relay returned for a search to the search results, unless we
+/// already have it there, and remember which relay it came from.
+///
+/// This must be called after the event is processed.
+pub(crate) fn add_relay_search_result(event: &Event, url: &RelayUrl) -> Result<(), Error> {
+    if event.kind == EventKind::Metadata {
+        let person = match PersonTable::read_record(event.pubkey, None)? {
+            Some(person) => person,
+            None => return Ok(()),
+        };
+        {
+            let mut results = GLOBALS.people_search_results.write();
+            if !results.iter().any(|p| p.pubkey == person.pubkey) {
+                results.push(person);
+            }
+        }
+        let mut relays = GLOBALS
+            .people_search_relays
+            .entry(event.pubkey)
+            .or_default();
+        if !relays.contains(url) {
+            relays.push(url.to_owned());
+        }
+    } else {
+        // It is not stored if the spam filter refused it
+        if !GLOBALS.storage.has_event(event.id)? {
+            return Ok(());
+        }
+        {
+            let mut results = GLOBALS.note_search_results.write();
+            if !results.iter().any(|e| e.id == event.id) {
+                results.push(event.to_owned());
+            }
+        }
+        let mut relays = GLOBALS.note_search_relays.entry(event.id).or_default();
+        if !relays.contains(url) {
+            relays.push(url.to_owned());
+        }
+    }
+    Ok(())
+}
+
+// Parse YYYY-MM-DD (UTC) or a raw unixtime
+fn parse_time(value: &str) -> Option<Unixtime> {
+    if let Ok(secs) = value.parse::<i64>() {
+        return Some(Unixtime(secs));
+    }
+
+    let mut parts = value.split('-');
+    let y = parts.next()?.parse::<i64>().ok()?;
+    let m = parts.next()?.parse::<i64>().ok()?;
+    let d = parts.next()?.parse::<i64>().ok()?;
+    if parts.next().is_some() || !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return None;
+    }
+
+    // Days from civil (Howard Hinnant's algorithm)
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    Some(Unixtime(days * 86400))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("Hello, #Nostr world! a https://x.io/p"),
+            vec!["hello", "nostr", "world", "https", "io"]
+        );
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let q = SearchQuery::parse(
+            r#"Gossip "Mike Dilger" kind:1 since:2024-01-01 until:1720000000 foo:bar"#,
+        );
+        assert_eq!(q.terms, vec!["gossip", "mike", "dilger", "foo", "bar"]);
+        assert_eq!(
+            q.phrases,
+            vec![vec!["mike".to_owned(), "dilger".to_owned()]]
+        );
+        assert_eq!(q.kinds, vec![EventKind::TextNote]);
+        assert_eq!(q.since, Some(Unixtime(1704067200)));
+        assert_eq!(q.until, Some(Unixtime(1720000000)));
+    }
+
+    #[test]
+    fn test_relay_search_text() {
+        let q = SearchQuery::parse(r#"gossip "mike dilger" kind:1 client"#);
+        assert_eq!(q.relay_search_text(), r#"gossip client "mike dilger""#);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::error::Error;
+use crate::storage::types::{Count1, CountKey1};
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::{RoTxn, RwTxn};
+use nostr_types::Unixtime;
+use std::sync::Mutex;
+
+// CountKey -> Count
+//   key: count_key.key()
+//   val: serde_json::to_vec(count) | serde_json::from_slice(bytes)
+
+static COUNTS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut COUNTS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_counts1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = COUNTS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = COUNTS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = COUNTS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("counts1")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                COUNTS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn get_counts1_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_counts1()?.len(&txn)?)
+    }
+
+    pub(crate) fn write_count1<'a>(
+        &'a self,
+        count: &Count1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let bytes = serde_json::to_vec(count)?;
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_counts1()?.put(txn, &count.key.key(), &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn read_count1<'a>(
+        &'a self,
+        key: &CountKey1,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Option<Count1>, Error> {
+        let f = |txn: &RoTxn<'a>| -> Result<Option<Count1>, Error> {
+            match self.db_counts1()?.get(txn, &key.key())? {
+                Some(bytes) => Ok(Some(serde_json::from_slice(bytes)?)),
+                None => Ok(None),
+            }
+        };
+
+        read_transact!(self, txn, f)
+    }
+
+    pub(crate) fn prune_counts1<'a>(
+        &'a self,
+        older_than: Unixtime,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<usize, Error> {
+        let f = |txn: &mut RwTxn<'a>| -> Result<usize, Error> {
+            let mut stale: Vec<Vec<u8>> = Vec::new();
+            for result in self.db_counts1()?.iter(txn)? {
+                let (key, val) = result?;
+                let count: Count1 = serde_json::from_slice(val)?;
+                if count.updated_at < older_than {
+                    stale.push(key.to_owned());
+                }
+            }
+            for key in stale.iter() {
+                let _ = self.db_counts1()?.delete(txn, key)?;
+            }
+            Ok(stale.len())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+}
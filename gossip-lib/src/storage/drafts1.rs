@@ -0,0 +1,131 @@
+use crate::error::Error;
+use crate::storage::types::{Draft1, DraftContext1};
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::{RoTxn, RwTxn};
+use std::sync::Mutex;
+
+// DraftContext -> Draft
+//   key: context.key()
+//   val: serde_json::to_vec(draft) | serde_json::from_slice(bytes)
+
+static DRAFTS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut DRAFTS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_drafts1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = DRAFTS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = DRAFTS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = DRAFTS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("drafts1")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                DRAFTS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn get_drafts1_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_drafts1()?.len(&txn)?)
+    }
+
+    pub(crate) fn write_draft1<'a>(
+        &'a self,
+        draft: &Draft1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = draft.context.key();
+        let bytes = serde_json::to_vec(draft)?;
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_drafts1()?.put(txn, &key, &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn read_draft1<'a>(
+        &'a self,
+        context: &DraftContext1,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Option<Draft1>, Error> {
+        let key = context.key();
+
+        let f = |txn: &RoTxn<'a>| -> Result<Option<Draft1>, Error> {
+            match self.db_drafts1()?.get(txn, &key)? {
+                Some(bytes) => Ok(Some(serde_json::from_slice(bytes)?)),
+                None => Ok(None),
+            }
+        };
+
+        read_transact!(self, txn, f)
+    }
+
+    pub(crate) fn read_all_drafts1(&self) -> Result<Vec<Draft1>, Error> {
+        let txn = self.env().read_txn()?;
+        let mut output: Vec<Draft1> = Vec::new();
+        for result in self.db_drafts1()?.iter(&txn)? {
+            let (_key, val) = result?;
+            output.push(serde_json::from_slice(val)?);
+        }
+        Ok(output)
+    }
+
+    pub(crate) fn delete_draft1<'a>(
+        &'a self,
+        context: &DraftContext1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = context.key();
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            let _ = self.db_drafts1()?.delete(txn, &key)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nostr_types::{Id, PrivateKey};
+
+    #[test]
+    fn test_draft_context_keys_are_distinct() {
+        let id = Id([7; 32]);
+        let pubkey = PrivateKey::generate().public_key();
+        let keys = [
+            DraftContext1::NewNote.key(),
+            DraftContext1::Reply(id).key(),
+            DraftContext1::DmChannel(vec![]).key(),
+            DraftContext1::DmChannel(vec![pubkey]).key(),
+        ];
+        for (i, a) in keys.iter().enumerate() {
+            for b in keys.iter().skip(i + 1) {
+                assert_ne!(a, b);
+            }
+        }
+    }
+}
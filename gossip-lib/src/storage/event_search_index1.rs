@@ -0,0 +1,165 @@
+use crate::error::{Error, ErrorKind};
+use crate::storage::{RawDatabase, Storage};
+use heed::{types::Bytes, DatabaseFlags, RwTxn};
+use nostr_types::{EventV3, Id, Unixtime};
+use std::sync::Mutex;
+
+// Term -> (Id, CreatedAt, Count)
+// (dup keys, so multiple entries per term)
+//   key: key!(term.as_bytes())
+//   val: SearchIndexEntry::as_slice() | SearchIndexEntry::from_bytes(val)
+
+static EVENT_SEARCH_INDEX1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut EVENT_SEARCH_INDEX1_DB: Option<RawDatabase> = None;
+
+/// An entry in the search index, fixed length so that we can use DUP_FIXED.
+/// Ids come first so duplicates sort by id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchIndexEntry([u8; 32 + 8 + 2]);
+
+impl SearchIndexEntry {
+    pub fn from_parts(id: Id, created_at: Unixtime, count: u16) -> SearchIndexEntry {
+        let mut entry = [0; 32 + 8 + 2];
+        entry[0..32].copy_from_slice(id.as_slice());
+        entry[32..40].copy_from_slice(&created_at.0.to_be_bytes());
+        entry[40..42].copy_from_slice(&count.to_be_bytes());
+        SearchIndexEntry(entry)
+    }
+
+    pub fn into_parts(self) -> (Id, Unixtime, u16) {
+        let id = Id(self.0[0..32].try_into().unwrap());
+        let created_at = Unixtime(i64::from_be_bytes(self.0[32..40].try_into().unwrap()));
+        let count = u16::from_be_bytes(self.0[40..42].try_into().unwrap());
+        (id, created_at, count)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<SearchIndexEntry, Error> {
+        let entry = bytes
+            .get(0..42)
+            .ok_or_else(|| ErrorKind::Internal("Search index entry too short".to_owned()))?;
+        Ok(SearchIndexEntry(entry.try_into()?))
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
+impl Storage {
+    pub(super) fn db_event_search_index1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = EVENT_SEARCH_INDEX1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = EVENT_SEARCH_INDEX1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = EVENT_SEARCH_INDEX1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    .flags(DatabaseFlags::DUP_SORT | DatabaseFlags::DUP_FIXED)
+                    .name("event_search_index")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                EVENT_SEARCH_INDEX1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_event3_search_index1<'a>(
+        &'a self,
+        event: &EventV3,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let terms = crate::search::event_terms(event);
+        if terms.is_empty() {
+            return Ok(());
+        }
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            for (term, count) in terms.iter() {
+                let entry = SearchIndexEntry::from_parts(event.id, event.created_at, *count);
+                self.db_event_search_index1()?
+                    .put(txn, key!(term.as_bytes()), entry.as_slice())?;
+            }
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn delete_event3_search_index1<'a>(
+        &'a self,
+        event: &EventV3,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        // The entries are fully determined by the event, so we can recompute them
+        // and delete them precisely.
+        let terms = crate::search::event_terms(event);
+        if terms.is_empty() {
+            return Ok(());
+        }
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            for (term, count) in terms.iter() {
+                let entry = SearchIndexEntry::from_parts(event.id, event.created_at, *count);
+                let _ = self.db_event_search_index1()?.delete_one_duplicate(
+                    txn,
+                    key!(term.as_bytes()),
+                    entry.as_slice(),
+                )?;
+            }
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    /// Get all search index entries for a term
+    pub(crate) fn get_event_search_index1_entries(
+        &self,
+        term: &str,
+    ) -> Result<Vec<SearchIndexEntry>, Error> {
+        let key = key!(term.as_bytes());
+        let txn = self.env().read_txn()?;
+        let mut output: Vec<SearchIndexEntry> = Vec::new();
+        let iter = match self.db_event_search_index1()?.get_duplicates(&txn, key)? {
+            Some(i) => i,
+            None => return Ok(vec![]),
+        };
+        for result in iter {
+            let (_key, val) = result?;
+            output.push(SearchIndexEntry::from_bytes(val)?);
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_search_index_entry() {
+        let id = Id::try_from_hex_string(
+            "77f7653c67147a125cc624f695029d0557e3ab402e714680eb23dd2499f439a0",
+        )
+        .unwrap();
+        let created_at = Unixtime(1700000000);
+        let entry = SearchIndexEntry::from_parts(id, created_at, 3);
+        let entry2 = SearchIndexEntry::from_bytes(entry.as_slice()).unwrap();
+        assert_eq!(entry2.into_parts(), (id, created_at, 3));
+
+        assert!(SearchIndexEntry::from_bytes(&entry.as_slice()[..20]).is_err());
+    }
+}
@@ -85,6 +85,8 @@ impl Storage {
                 } // upstream bug
                 self.add_hashtag(&hashtag, event.id, Some(txn))?;
             }
+
+            self.write_event3_search_index1(event, Some(txn))?;
             Ok(())
         };
 
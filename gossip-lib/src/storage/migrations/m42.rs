@@ -0,0 +1,56 @@
+use crate::error::Error;
+use crate::storage::types::{Relay5, RelayMetrics1};
+use crate::storage::Storage;
+use heed::RwTxn;
+
+impl Storage {
+    pub(super) fn m42_trigger(&self) -> Result<(), Error> {
+        let _ = self.db_relays4()?;
+        let _ = self.db_relays5()?;
+        Ok(())
+    }
+
+    pub(super) fn m42_migrate<'a>(
+        &'a self,
+        prefix: &str,
+        txn: &mut RwTxn<'a>,
+    ) -> Result<(), Error> {
+        // Info message
+        tracing::info!("{prefix}: Migrating relay records...");
+
+        // Migrate
+        self.m42_migrate_relay_records(txn)?;
+
+        Ok(())
+    }
+
+    fn m42_migrate_relay_records<'a>(&'a self, txn: &mut RwTxn<'a>) -> Result<(), Error> {
+        let mut old = self.filter_relays4(|_| true)?;
+        for relay4 in old.drain(..) {
+            let usage_bits = relay4.get_usage_bits();
+            let relay5 = Relay5 {
+                url: relay4.url,
+                success_count: relay4.success_count,
+                failure_count: relay4.failure_count,
+                last_connected_at: relay4.last_connected_at,
+                last_general_eose_at: relay4.last_general_eose_at,
+                rank: relay4.rank,
+                hidden: relay4.hidden,
+                usage_bits,
+                nip11: relay4.nip11,
+                last_attempt_nip11: relay4.last_attempt_nip11,
+                allow_connect: relay4.allow_connect,
+                allow_auth: relay4.allow_auth,
+                avoid_until: relay4.avoid_until,
+                proxy_only: relay4.proxy_only,
+                metrics: RelayMetrics1::default(),
+            };
+            self.write_relay5(&relay5, Some(txn))?;
+        }
+
+        // Clear the old database
+        self.db_relays4()?.clear(txn)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use crate::error::Error;
+use crate::storage::types::{Mute1, MuteEntry1};
+use crate::storage::Storage;
+use heed::RwTxn;
+use nostr_types::EventKind;
+
+impl Storage {
+    pub(super) fn m43_trigger(&self) -> Result<(), Error> {
+        let _ = self.db_mutes1()?;
+        Ok(())
+    }
+
+    pub(super) fn m43_migrate<'a>(
+        &'a self,
+        prefix: &str,
+        txn: &mut RwTxn<'a>,
+    ) -> Result<(), Error> {
+        // Info message
+        tracing::info!("{prefix}: Importing muted words, hashtags and threads...");
+
+        // Migrate
+        self.m43_import_mutes(txn)?;
+
+        Ok(())
+    }
+
+    // Until now we only preserved these tags when publishing our mute list. Now that
+    // we publish from local storage, import the public ones so they are not lost.
+    // The private ones can't be decrypted here, so we flag them to be imported once
+    // the key is unlocked (see mutes::import_private_mutes).
+    fn m43_import_mutes<'a>(&'a self, txn: &mut RwTxn<'a>) -> Result<(), Error> {
+        let pubkey = match self.read_setting_public_key() {
+            Some(pk) => pk,
+            None => return Ok(()),
+        };
+
+        let event = match self.get_replaceable_event(EventKind::MuteList, pubkey, "")? {
+            Some(event) => event,
+            None => return Ok(()),
+        };
+
+        for tag in event.tags.iter() {
+            let mute = match tag.tagname() {
+                "word" => Mute1::Word(tag.value().trim().to_lowercase()),
+                "t" => Mute1::Hashtag(tag.value().trim().trim_start_matches('#').to_lowercase()),
+                "e" => match tag.parse_event() {
+                    Ok((id, _, _)) => Mute1::Thread(id),
+                    Err(_) => continue,
+                },
+                _ => continue,
+            };
+            if matches!(&mute, Mute1::Word(s) | Mute1::Hashtag(s) if s.is_empty()) {
+                continue;
+            }
+            self.write_mute1(
+                &MuteEntry1 {
+                    mute,
+                    private: false,
+                },
+                Some(txn),
+            )?;
+        }
+
+        if !event.content.is_empty() {
+            self.set_flag_import_private_mutes_needed(true, Some(txn))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use crate::error::Error;
+use crate::storage::types::RelationshipById2;
+use crate::storage::Storage;
+use heed::RwTxn;
+use nostr_types::Id;
+use speedy::Readable;
+
+impl Storage {
+    pub(super) fn m44_trigger(&self) -> Result<(), Error> {
+        let _ = self.db_relationships_by_id2()?;
+        let _ = self.db_events3()?;
+        let _ = self.db_unverified_zaps1()?;
+        Ok(())
+    }
+
+    pub(super) fn m44_migrate<'a>(
+        &'a self,
+        prefix: &str,
+        txn: &mut RwTxn<'a>,
+    ) -> Result<(), Error> {
+        // Info message
+        tracing::info!("{prefix}: Setting aside unchecked zap receipts...");
+
+        // Migrate
+        self.m44_set_aside_zaps(txn)?;
+
+        Ok(())
+    }
+
+    // Zap receipts were counted without being checked. Stop counting them until they
+    // have been checked against the recipient's LNURL provider.
+    fn m44_set_aside_zaps<'a>(&'a self, txn: &mut RwTxn<'a>) -> Result<(), Error> {
+        let mut zaps: Vec<(Vec<u8>, Id)> = Vec::new();
+        {
+            let loop_txn = self.env().read_txn()?;
+            for result in self.db_relationships_by_id2()?.iter(&loop_txn)? {
+                let (key, val) = result?;
+                if let RelationshipById2::Zaps { .. } = RelationshipById2::read_from_buffer(val)? {
+                    let receipt_id = Id(key[32..64].try_into()?);
+                    zaps.push((key.to_owned(), receipt_id));
+                }
+            }
+        }
+
+        for (key, receipt_id) in zaps.drain(..) {
+            self.db_relationships_by_id2()?.delete(txn, &key)?;
+
+            let event = match self.read_event3(receipt_id)? {
+                Some(event) => event,
+                None => continue,
+            };
+            let recipient = event
+                .tags
+                .iter()
+                .find_map(|tag| tag.parse_pubkey().ok().map(|(pubkey, _, _)| pubkey));
+            if let Some(recipient) = recipient {
+                self.write_unverified_zap1(receipt_id, recipient, Some(txn))?;
+            }
+        }
+
+        Ok(())
+    }
+}
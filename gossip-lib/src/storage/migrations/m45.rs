@@ -0,0 +1,61 @@
+use crate::error::Error;
+use crate::storage::types::Relay6;
+use crate::storage::Storage;
+use heed::RwTxn;
+
+impl Storage {
+    pub(super) fn m45_trigger(&self) -> Result<(), Error> {
+        let _ = self.db_relays5()?;
+        let _ = self.db_relays6()?;
+        let _ = self.db_relay_metrics1()?;
+        Ok(())
+    }
+
+    pub(super) fn m45_migrate<'a>(
+        &'a self,
+        prefix: &str,
+        txn: &mut RwTxn<'a>,
+    ) -> Result<(), Error> {
+        // Info message
+        tracing::info!("{prefix}: Moving relay metrics into their own table...");
+
+        // Migrate
+        self.m45_migrate_relay_records(txn)?;
+
+        Ok(())
+    }
+
+    // Metrics were kept in the relay records, where every other change to a relay
+    // could write over them. They get their own table, keyed by url.
+    fn m45_migrate_relay_records<'a>(&'a self, txn: &mut RwTxn<'a>) -> Result<(), Error> {
+        let mut old = self.filter_relays5(|_| true)?;
+        for relay5 in old.drain(..) {
+            let usage_bits = relay5.get_usage_bits();
+            if relay5.metrics != Default::default() {
+                self.write_relay_metrics1(&relay5.url, &relay5.metrics, Some(txn))?;
+            }
+            let relay6 = Relay6 {
+                url: relay5.url,
+                success_count: relay5.success_count,
+                failure_count: relay5.failure_count,
+                last_connected_at: relay5.last_connected_at,
+                last_general_eose_at: relay5.last_general_eose_at,
+                rank: relay5.rank,
+                hidden: relay5.hidden,
+                usage_bits,
+                nip11: relay5.nip11,
+                last_attempt_nip11: relay5.last_attempt_nip11,
+                allow_connect: relay5.allow_connect,
+                allow_auth: relay5.allow_auth,
+                avoid_until: relay5.avoid_until,
+                proxy_only: relay5.proxy_only,
+            };
+            self.write_relay6(&relay6, Some(txn))?;
+        }
+
+        // Clear the old database
+        self.db_relays5()?.clear(txn)?;
+
+        Ok(())
+    }
+}
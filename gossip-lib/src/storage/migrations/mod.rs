@@ -23,6 +23,12 @@ mod m36;
 mod m37;
 mod m38;
 mod m39;
+mod m40;
+mod m41;
+mod m42;
+mod m43;
+mod m44;
+mod m45;
 
 use super::Storage;
 use crate::error::{Error, ErrorKind};
@@ -30,7 +36,7 @@ use heed::RwTxn;
 
 impl Storage {
     const MIN_MIGRATION_LEVEL: u32 = 23;
-    const MAX_MIGRATION_LEVEL: u32 = 39;
+    const MAX_MIGRATION_LEVEL: u32 = 45;
 
     /// Initialize the database from empty
     pub(super) fn init_from_empty(&self) -> Result<(), Error> {
@@ -122,6 +128,12 @@ impl Storage {
             37 => self.m37_trigger()?,
             38 => self.m38_trigger()?,
             39 => self.m39_trigger()?,
+            40 => self.m40_trigger()?,
+            41 => self.m41_trigger()?,
+            42 => self.m42_trigger()?,
+            43 => self.m43_trigger()?,
+            44 => self.m44_trigger()?,
+            45 => self.m45_trigger()?,
             _ => panic!("Unreachable migration level"),
         }
 
@@ -152,6 +164,12 @@ impl Storage {
             37 => self.m37_migrate(&prefix, txn)?,
             38 => self.m38_migrate(&prefix, txn)?,
             39 => self.m39_migrate(&prefix, txn)?,
+            40 => self.m40_migrate(&prefix, txn)?,
+            41 => self.m41_migrate(&prefix, txn)?,
+            42 => self.m42_migrate(&prefix, txn)?,
+            43 => self.m43_migrate(&prefix, txn)?,
+            44 => self.m44_migrate(&prefix, txn)?,
+            45 => self.m45_migrate(&prefix, txn)?,
             _ => panic!("Unreachable migration level"),
         };
 
@@ -17,6 +17,8 @@ pub use person3_table::Person3Table;
 pub type PersonTable = Person3Table;
 
 // database implementations
+mod counts1;
+mod drafts1;
 mod event_akci_index;
 use event_akci_index::AkciKey;
 mod event_kci_index;
@@ -24,6 +26,8 @@ use event_kci_index::KciKey;
 
 mod event_ek_c_index1;
 mod event_ek_pk_index1;
+mod event_search_index1;
+use event_search_index1::SearchIndexEntry;
 mod event_seen_on_relay1;
 mod event_tag_index1;
 mod event_viewed1;
@@ -31,8 +35,12 @@ mod events2;
 mod events3;
 mod general;
 mod hashtags1;
+mod mutes1;
 mod nip46servers1;
 mod nip46servers2;
+mod nwc_connections1;
+mod offline_queue1;
+mod outbox1;
 mod people2;
 mod person_lists2;
 mod person_lists_metadata1;
@@ -44,22 +52,39 @@ mod relationships_by_addr1;
 mod relationships_by_addr2;
 mod relationships_by_id1;
 mod relationships_by_id2;
+mod relay_metrics1;
 mod relays1;
 mod relays2;
 mod relays3;
+mod relays4;
+mod relays5;
+mod relays6;
+mod revisions1;
+mod scheduled_posts1;
 mod unindexed_giftwraps1;
+mod unverified_zaps1;
 mod versioned;
+mod zap_providers1;
 
+use crate::counts::{Count, CountKey};
 use crate::dm_channel::{DmChannel, DmChannelData};
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
+use crate::local_relay::{LocalRelayPolicy, LocalRelayReadPolicy};
 use crate::misc::Private;
+use crate::mutes::{Mute, MuteEntry};
 use crate::nip46::{Nip46Server, Nip46UnconnectedServer};
+use crate::nwc::NwcConnection;
+use crate::outbox::{OutboxEntry, QueuedEvent};
 use crate::people::{PersonList, PersonListMetadata};
 use crate::person_relay::PersonRelay;
+use crate::post::{Draft, DraftContext, ScheduledPost};
 use crate::profile::Profile;
 use crate::relationship::{RelationshipByAddr, RelationshipById};
-use crate::relay::Relay;
+use crate::relay::{Relay, RelayMetrics};
+use crate::retention::{EventSummary, PruneReport, RetentionPolicy};
+use crate::search::SearchQuery;
+use crate::zaps::ZapProvider;
 use dashmap::DashMap;
 use heed::types::{Bytes, Unit};
 use heed::{Database, Env, EnvFlags, EnvOpenOptions, RoTxn, RwTxn};
@@ -69,9 +94,10 @@ use nostr_types::{
 };
 use paste::paste;
 use speedy::{Readable, Writable};
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::ops::Bound;
 use std::sync::OnceLock;
+use std::time::Duration;
 
 use self::event_kci_index::INDEXED_KINDS;
 use self::event_tag_index1::INDEXED_TAGS;
@@ -120,7 +146,7 @@ impl Storage {
         }
 
         // builder.max_readers(126); // this is the default
-        builder.max_dbs(32);
+        builder.max_dbs(48);
 
         // This has to be big enough for all the data.
         // Note that it is the size of the map in VIRTUAL address space,
@@ -152,19 +178,31 @@ impl Storage {
         //
         // old-version databases will be handled by their migration code and only
         // triggered into existence if their migration is necessary.
+        let _ = self.db_counts()?;
+        let _ = self.db_drafts()?;
         let _ = self.db_event_akci_index()?;
         let _ = self.db_event_kci_index()?;
+        let _ = self.db_event_search_index()?;
         let _ = self.db_event_tag_index()?;
         let _ = self.db_events()?;
         let _ = self.db_event_seen_on_relay()?;
         let _ = self.db_event_viewed()?;
         let _ = self.db_hashtags()?;
+        let _ = self.db_mutes()?;
         let _ = self.db_nip46servers()?;
+        let _ = self.db_nwc_connections()?;
+        let _ = self.db_offline_queue()?;
+        let _ = self.db_outbox()?;
         let _ = self.db_person_relays()?;
         let _ = self.db_relationships_by_id()?;
         let _ = self.db_relationships_by_addr()?;
+        let _ = self.db_relay_metrics()?;
         let _ = self.db_relays()?;
+        let _ = self.db_revisions()?;
+        let _ = self.db_scheduled_posts()?;
         let _ = self.db_unindexed_giftwraps()?;
+        let _ = self.db_unverified_zaps()?;
+        let _ = self.db_zap_providers()?;
         let _ = self.db_person_lists()?;
         let _ = self.db_person_lists_metadata()?;
         let _ = PersonTable::db()?;
@@ -210,6 +248,21 @@ impl Storage {
 
     // Database getters ---------------------------------
 
+    #[inline]
+    pub(crate) fn db_counts(&self) -> Result<RawDatabase, Error> {
+        self.db_counts1()
+    }
+
+    #[inline]
+    pub(crate) fn db_drafts(&self) -> Result<RawDatabase, Error> {
+        self.db_drafts1()
+    }
+
+    #[inline]
+    pub(crate) fn db_event_search_index(&self) -> Result<RawDatabase, Error> {
+        self.db_event_search_index1()
+    }
+
     #[inline]
     pub(crate) fn db_event_tag_index(&self) -> Result<RawDatabase, Error> {
         self.db_event_tag_index1()
@@ -235,11 +288,31 @@ impl Storage {
         self.db_hashtags1()
     }
 
+    #[inline]
+    pub(crate) fn db_mutes(&self) -> Result<RawDatabase, Error> {
+        self.db_mutes1()
+    }
+
     #[inline]
     pub(crate) fn db_nip46servers(&self) -> Result<RawDatabase, Error> {
         self.db_nip46servers2()
     }
 
+    #[inline]
+    pub(crate) fn db_nwc_connections(&self) -> Result<RawDatabase, Error> {
+        self.db_nwc_connections1()
+    }
+
+    #[inline]
+    pub(crate) fn db_offline_queue(&self) -> Result<RawDatabase, Error> {
+        self.db_offline_queue1()
+    }
+
+    #[inline]
+    pub(crate) fn db_outbox(&self) -> Result<RawDatabase, Error> {
+        self.db_outbox1()
+    }
+
     #[inline]
     pub(crate) fn db_person_relays(&self) -> Result<RawDatabase, Error> {
         self.db_person_relays2()
@@ -255,9 +328,24 @@ impl Storage {
         self.db_relationships_by_id2()
     }
 
+    #[inline]
+    pub(crate) fn db_relay_metrics(&self) -> Result<RawDatabase, Error> {
+        self.db_relay_metrics1()
+    }
+
     #[inline]
     pub(crate) fn db_relays(&self) -> Result<RawDatabase, Error> {
-        self.db_relays3()
+        self.db_relays6()
+    }
+
+    #[inline]
+    pub(crate) fn db_revisions(&self) -> Result<RawDatabase, Error> {
+        self.db_revisions1()
+    }
+
+    #[inline]
+    pub(crate) fn db_scheduled_posts(&self) -> Result<RawDatabase, Error> {
+        self.db_scheduled_posts1()
     }
 
     #[inline]
@@ -265,6 +353,16 @@ impl Storage {
         self.db_unindexed_giftwraps1()
     }
 
+    #[inline]
+    pub(crate) fn db_unverified_zaps(&self) -> Result<RawDatabase, Error> {
+        self.db_unverified_zaps1()
+    }
+
+    #[inline]
+    pub(crate) fn db_zap_providers(&self) -> Result<RawDatabase, Error> {
+        self.db_zap_providers1()
+    }
+
     #[inline]
     pub(crate) fn db_person_lists(&self) -> Result<RawDatabase, Error> {
         self.db_person_lists2()
@@ -283,6 +381,24 @@ impl Storage {
         Ok(self.db_general()?.len(&txn)?)
     }
 
+    /// The number of records in the counts table
+    #[inline]
+    pub fn get_counts_len(&self) -> Result<u64, Error> {
+        self.get_counts1_len()
+    }
+
+    /// The number of records in the drafts table
+    #[inline]
+    pub fn get_drafts_len(&self) -> Result<u64, Error> {
+        self.get_drafts1_len()
+    }
+
+    /// The number of records in the mutes table
+    #[inline]
+    pub fn get_mutes_len(&self) -> Result<u64, Error> {
+        self.get_mutes1_len()
+    }
+
     /// The number of records in the event_seen_on table
     #[inline]
     pub fn get_event_seen_on_relay_len(&self) -> Result<u64, Error> {
@@ -307,10 +423,58 @@ impl Storage {
         Ok(self.db_nip46servers()?.len(&txn)?)
     }
 
+    /// The number of records in the nwc_connections table
+    pub fn get_nwc_connections_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_nwc_connections()?.len(&txn)?)
+    }
+
+    /// The number of events queued while offline
+    #[inline]
+    pub fn get_offline_queue_len(&self) -> Result<u64, Error> {
+        self.get_offline_queue1_len()
+    }
+
+    /// The number of records in the outbox table
+    #[inline]
+    pub fn get_outbox_len(&self) -> Result<u64, Error> {
+        self.get_outbox1_len()
+    }
+
+    /// The number of records in the relay_metrics table
+    #[inline]
+    pub fn get_relay_metrics_len(&self) -> Result<u64, Error> {
+        self.get_relay_metrics1_len()
+    }
+
     /// The number of records in the relays table
     #[inline]
     pub fn get_relays_len(&self) -> Result<u64, Error> {
-        self.get_relays3_len()
+        self.get_relays6_len()
+    }
+
+    /// The number of records in the revisions table
+    #[inline]
+    pub fn get_revisions_len(&self) -> Result<u64, Error> {
+        self.get_revisions1_len()
+    }
+
+    /// The number of records in the scheduled_posts table
+    #[inline]
+    pub fn get_scheduled_posts_len(&self) -> Result<u64, Error> {
+        self.get_scheduled_posts1_len()
+    }
+
+    /// The number of records in the unverified_zaps table
+    pub fn get_unverified_zaps_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_unverified_zaps()?.len(&txn)?)
+    }
+
+    /// The number of records in the zap_providers table
+    pub fn get_zap_providers_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_zap_providers()?.len(&txn)?)
     }
 
     /// The number of records in the event table
@@ -337,6 +501,12 @@ impl Storage {
         Ok(self.db_event_tag_index()?.len(&txn)?)
     }
 
+    /// The number of records in the event_search index table
+    pub fn get_event_search_index_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_event_search_index()?.len(&txn)?)
+    }
+
     /// The number of records in the relationships_by_addr table
     #[inline]
     pub fn get_relationships_by_addr_len(&self) -> Result<u64, Error> {
@@ -365,33 +535,43 @@ impl Storage {
 
     // Prune -------------------------------------------------------
 
-    /// Remove all events (and related data) with a created_at before `from`
-    /// and all related indexes.
-    pub fn prune(&self, from: Unixtime) -> Result<usize, Error> {
-        // Extract the Ids to delete.
+    /// Prune events according to a retention policy. On a dry run nothing is
+    /// deleted, but the report says what would be.
+    pub fn prune(&self, policy: &RetentionPolicy, dry_run: bool) -> Result<PruneReport, Error> {
+        // Summarize every event
         let txn = self.env().read_txn()?;
-        let mut ids: HashSet<Id> = HashSet::new();
+        let mut events: Vec<EventSummary> = Vec::new();
         for result in self.db_events()?.iter(&txn)? {
             let (_key, val) = result?;
-
-            if let Some(created_at) = Event::get_created_at_from_speedy_bytes(val) {
-                if created_at < from {
-                    if let Some(id) = Event::get_id_from_speedy_bytes(val) {
-                        ids.insert(id);
-                        // Too bad but we can't delete it now, other threads
-                        // might try to access it still. We have to delete it from
-                        // all the other maps first.
-                    }
-                }
+            match Event::read_from_buffer(val) {
+                Ok(event) => events.push(EventSummary::new(&event, val.len())),
+                Err(e) => tracing::warn!("PRUNE: skipping unreadable event: {}", e),
             }
         }
         drop(txn);
 
+        let (ids, mut report) = crate::retention::plan(policy, events)?;
+        report.dry_run = dry_run;
+
+        if !dry_run {
+            // Counts we have not refreshed in this long are pruned too
+            let from = Unixtime::now()
+                - Duration::new(self.read_setting_prune_period_days() * 60 * 60 * 24, 0);
+            self.delete_pruned_events(&ids, from)?;
+        }
+
+        Ok(report)
+    }
+
+    // Delete events and everything about them. We can't delete them while finding
+    // them, as other threads might try to access them still. We have to delete them
+    // from all the other maps first.
+    fn delete_pruned_events(&self, ids: &HashSet<Id>, from: Unixtime) -> Result<(), Error> {
         let mut txn = self.env().write_txn()?;
 
         // Delete from event_seen_on_relay
         let mut deletions: Vec<Vec<u8>> = Vec::new();
-        for id in &ids {
+        for id in ids {
             let start_key: &[u8] = id.as_slice();
             for result in self
                 .db_event_seen_on_relay()?
@@ -410,7 +590,7 @@ impl Storage {
         }
 
         // Delete from event_viewed
-        for id in &ids {
+        for id in ids {
             let _ = self.db_event_viewed()?.delete(&mut txn, id.as_slice());
         }
         tracing::info!("PRUNE: deleted {} records from event_viewed", ids.len());
@@ -431,6 +611,23 @@ impl Storage {
                 .delete_one_duplicate(&mut txn, &deletion.0, &deletion.1)?;
         }
 
+        // Delete from event_search_index
+        // (the entries are computed from the event, so we read each event back)
+        for id in ids {
+            let event = match self.db_events()?.get(&txn, id.as_slice())? {
+                Some(bytes) => Event::read_from_buffer(bytes)?,
+                None => continue,
+            };
+            self.delete_event_search_index(&event, Some(&mut txn))?;
+
+            // and revisions, which go with the latest version of an article
+            if event.kind == EventKind::LongFormContent {
+                let d = event.parameter().unwrap_or_default();
+                self.delete_revisions(event.kind, event.pubkey, &d, Some(&mut txn))?;
+            }
+        }
+        tracing::info!("PRUNE: deleted event_search_index and revisions records");
+
         // Delete from relationships
         // (unfortunately because of the 2nd Id in the tag, we have to scan the whole thing)
         let mut deletions: Vec<Vec<u8>> = Vec::new();
@@ -451,15 +648,25 @@ impl Storage {
             self.db_relationships_by_id()?.delete(&mut txn, &deletion)?;
         }
 
+        // Delete from outbox
+        for id in ids {
+            self.delete_outbox_entry(*id, Some(&mut txn))?;
+        }
+        tracing::info!("PRUNE: deleted outbox records");
+
+        // Delete counts we have not refreshed since
+        let n = self.prune_counts1(from, Some(&mut txn))?;
+        tracing::info!("PRUNE: deleted {} records from counts", n);
+
         // delete from events
-        for id in &ids {
+        for id in ids {
             let _ = self.db_events()?.delete(&mut txn, id.as_slice());
         }
         tracing::info!("PRUNE: deleted {} records from events", ids.len());
 
         txn.commit()?;
 
-        Ok(ids.len())
+        Ok(())
     }
 
     // General key-value functions --------------------------------------------------
@@ -578,6 +785,11 @@ impl Storage {
         b"reprocess_relay_lists_needed",
         true
     );
+    def_flag!(
+        import_private_mutes_needed,
+        b"import_private_mutes_needed",
+        false
+    );
 
     // Settings ----------------------------------------------------------
 
@@ -625,7 +837,9 @@ impl Storage {
     def_setting!(hide_mutes_entirely, b"hide_mutes_entirely", bool, true);
     def_setting!(reactions, b"reactions", bool, true);
     def_setting!(enable_zap_receipts, b"enable_zap_receipts", bool, true);
+    def_setting!(zap_default_sats, b"zap_default_sats", u64, 0);
     def_setting!(show_media, b"show_media", bool, true);
+    def_setting!(animate_media, b"animate_media", bool, true);
     def_setting!(
         approve_content_warning,
         b"approve_content_warning",
@@ -637,6 +851,8 @@ impl Storage {
     def_setting!(set_client_tag, b"set_client_tag", bool, false);
     def_setting!(set_user_agent, b"set_user_agent", bool, false);
     def_setting!(delegatee_tag, b"delegatee_tag", String, String::new());
+    def_setting!(media_server, b"media_server", String, String::new());
+    def_setting!(media_server_blossom, b"media_server_blossom", bool, false);
     def_setting!(max_fps, b"max_fps", u32, 12);
     def_setting!(
         recompute_feed_periodically,
@@ -748,6 +964,8 @@ impl Storage {
         u64,
         55
     );
+    def_setting!(use_negentropy, b"use_negentropy", bool, true);
+    def_setting!(socks5_proxy, b"socks5_proxy", String, "".to_owned());
     def_setting!(
         fetcher_connect_timeout_sec,
         b"fetcher_connect_timeout_sec",
@@ -786,13 +1004,44 @@ impl Storage {
         10
     );
     def_setting!(prune_period_days, b"prune_period_days", u64, 90);
+    def_setting!(
+        retention_policy,
+        b"retention_policy",
+        RetentionPolicy,
+        RetentionPolicy::default()
+    );
     def_setting!(cache_prune_period_days, b"cache_prune_period_days", u64, 90);
+    def_setting!(cache_quota_mb, b"cache_quota_mb", u64, 2048);
     def_setting!(
         avoid_spam_on_unsafe_relays,
         b"avoid_spam_on_unsafe_relays",
         bool,
         false
     );
+    def_setting!(fetch_counts, b"fetch_counts", bool, true);
+    def_setting!(api_enabled, b"api_enabled", bool, false);
+    def_setting!(api_port, b"api_port", u16, 4869);
+    def_setting!(api_token, b"api_token", String, "".to_owned());
+    def_setting!(local_relay_enabled, b"local_relay_enabled", bool, false);
+    def_setting!(local_relay_port, b"local_relay_port", u16, 4870);
+    def_setting!(
+        local_relay_policy,
+        b"local_relay_policy",
+        LocalRelayPolicy,
+        LocalRelayPolicy::AcceptOwn
+    );
+    def_setting!(
+        local_relay_read_policy,
+        b"local_relay_read_policy",
+        LocalRelayReadPolicy,
+        LocalRelayReadPolicy::ServeAll
+    );
+    def_setting!(
+        local_relay_allowed_origins,
+        b"local_relay_allowed_origins",
+        String,
+        "".to_owned()
+    );
 
     // -------------------------------------------------------------------
 
@@ -942,6 +1191,213 @@ impl Storage {
         self.get_event_ids_with_hashtag1(hashtag)
     }
 
+    /// Write the counts relays gave us for something
+    #[inline]
+    pub(crate) fn write_count<'a>(
+        &'a self,
+        count: &Count,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_count1(count, rw_txn)
+    }
+
+    /// Read the counts relays gave us for something
+    #[inline]
+    pub fn read_count<'a>(
+        &'a self,
+        key: &CountKey,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Option<Count>, Error> {
+        self.read_count1(key, txn)
+    }
+
+    /// Write a draft, replacing any other draft with the same context
+    #[inline]
+    pub fn write_draft<'a>(
+        &'a self,
+        draft: &Draft,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_draft1(draft, rw_txn)
+    }
+
+    /// Read the draft for a context
+    #[inline]
+    pub fn read_draft<'a>(
+        &'a self,
+        context: &DraftContext,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Option<Draft>, Error> {
+        self.read_draft1(context, txn)
+    }
+
+    /// Read all drafts, most recently saved first
+    pub fn read_all_drafts(&self) -> Result<Vec<Draft>, Error> {
+        let mut drafts = self.read_all_drafts1()?;
+        drafts.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(drafts)
+    }
+
+    /// Delete the draft for a context
+    #[inline]
+    pub fn delete_draft<'a>(
+        &'a self,
+        context: &DraftContext,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_draft1(context, rw_txn)
+    }
+
+    /// Write a word, hashtag or thread mute, replacing any same mute
+    #[inline]
+    pub(crate) fn write_mute<'a>(
+        &'a self,
+        entry: &MuteEntry,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_mute1(entry, rw_txn)
+    }
+
+    /// Delete a word, hashtag or thread mute
+    #[inline]
+    pub(crate) fn delete_mute<'a>(
+        &'a self,
+        mute: &Mute,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_mute1(mute, rw_txn)
+    }
+
+    /// Read all word, hashtag and thread mutes
+    #[inline]
+    pub fn read_all_mutes(&self) -> Result<Vec<MuteEntry>, Error> {
+        self.read_all_mutes1()
+    }
+
+    /// Delete all word, hashtag and thread mutes
+    #[inline]
+    pub(crate) fn clear_mutes<'a>(&'a self, rw_txn: Option<&mut RwTxn<'a>>) -> Result<(), Error> {
+        self.clear_mutes1(rw_txn)
+    }
+
+    /// Queue one of our events to be posted once we are online
+    #[inline]
+    pub(crate) fn queue_offline_event<'a>(
+        &'a self,
+        queued: &QueuedEvent,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.queue_offline_event1(queued, rw_txn)
+    }
+
+    /// Read the events queued while offline, oldest first
+    #[inline]
+    pub fn read_offline_queue<'a>(
+        &'a self,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Vec<QueuedEvent>, Error> {
+        self.read_offline_queue1(txn)
+    }
+
+    /// Empty the offline queue
+    #[inline]
+    pub(crate) fn clear_offline_queue<'a>(
+        &'a self,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.clear_offline_queue1(rw_txn)
+    }
+
+    /// Write an outbox entry (one of our events and its publishing state)
+    #[inline]
+    pub(crate) fn write_outbox_entry<'a>(
+        &'a self,
+        entry: &OutboxEntry,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_outbox_entry1(entry, rw_txn)
+    }
+
+    /// Read an outbox entry
+    #[inline]
+    pub fn read_outbox_entry<'a>(
+        &'a self,
+        id: Id,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Option<OutboxEntry>, Error> {
+        self.read_outbox_entry1(id, txn)
+    }
+
+    /// Modify an outbox entry. This does nothing if there is no such entry.
+    #[inline]
+    pub(crate) fn modify_outbox_entry<'a, M>(
+        &'a self,
+        id: Id,
+        modify: M,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error>
+    where
+        M: FnMut(&mut OutboxEntry),
+    {
+        self.modify_outbox_entry1(id, modify, rw_txn)
+    }
+
+    /// Delete an outbox entry
+    #[inline]
+    pub fn delete_outbox_entry<'a>(
+        &'a self,
+        id: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_outbox_entry1(id, rw_txn)
+    }
+
+    /// Read matching outbox entries
+    #[inline]
+    pub fn filter_outbox_entries<F>(&self, f: F) -> Result<Vec<OutboxEntry>, Error>
+    where
+        F: Fn(&OutboxEntry) -> bool,
+    {
+        self.filter_outbox_entries1(f)
+    }
+
+    /// Write a scheduled post. This overwrites any with the same id.
+    #[inline]
+    pub(crate) fn write_scheduled_post<'a>(
+        &'a self,
+        post: &ScheduledPost,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_scheduled_post1(post, rw_txn)
+    }
+
+    /// Read a scheduled post
+    #[inline]
+    pub fn read_scheduled_post<'a>(
+        &'a self,
+        id: u64,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Option<ScheduledPost>, Error> {
+        self.read_scheduled_post1(id, txn)
+    }
+
+    /// Read all scheduled posts, soonest first
+    pub fn read_all_scheduled_posts(&self) -> Result<Vec<ScheduledPost>, Error> {
+        let mut posts = self.read_all_scheduled_posts1()?;
+        posts.sort_by(|a, b| a.publish_at.cmp(&b.publish_at));
+        Ok(posts)
+    }
+
+    /// Delete a scheduled post
+    #[inline]
+    pub(crate) fn delete_scheduled_post<'a>(
+        &'a self,
+        id: u64,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_scheduled_post1(id, rw_txn)
+    }
+
     /// Write a relay record.
     ///
     /// NOTE: this overwrites. You may wish to read first, or you might prefer
@@ -952,7 +1408,7 @@ impl Storage {
         relay: &Relay,
         rw_txn: Option<&mut RwTxn<'a>>,
     ) -> Result<(), Error> {
-        self.write_relay3(relay, rw_txn)
+        self.write_relay6(relay, rw_txn)
     }
 
     /// Delete a relay record
@@ -963,7 +1419,7 @@ impl Storage {
         url: &RelayUrl,
         rw_txn: Option<&mut RwTxn<'a>>,
     ) -> Result<(), Error> {
-        self.delete_relay3(url, rw_txn)
+        self.delete_relay6(url, rw_txn)
     }
 
     /// Write a new relay record only if it is missing
@@ -1000,7 +1456,7 @@ impl Storage {
     where
         M: FnMut(&mut Relay),
     {
-        self.modify_relay3(url, modify, rw_txn)
+        self.modify_relay6(url, modify, rw_txn)
     }
 
     //// Modify all relay records
@@ -1013,7 +1469,7 @@ impl Storage {
     where
         M: FnMut(&mut Relay),
     {
-        self.modify_all_relays3(modify, rw_txn)
+        self.modify_all_relays6(modify, rw_txn)
     }
 
     /// Read a relay record
@@ -1023,7 +1479,32 @@ impl Storage {
         url: &RelayUrl,
         txn: Option<&RoTxn<'a>>,
     ) -> Result<Option<Relay>, Error> {
-        self.read_relay3(url, txn)
+        self.read_relay6(url, txn)
+    }
+
+    /// Read how a relay has performed for us
+    #[inline]
+    pub fn read_relay_metrics<'a>(
+        &'a self,
+        url: &RelayUrl,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Option<RelayMetrics>, Error> {
+        self.read_relay_metrics1(url, txn)
+    }
+
+    /// Modify how a relay has performed for us, starting from nothing if we have
+    /// no record yet
+    #[inline]
+    pub(crate) fn modify_relay_metrics<'a, M>(
+        &'a self,
+        url: &RelayUrl,
+        modify: M,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error>
+    where
+        M: FnMut(&mut RelayMetrics),
+    {
+        self.modify_relay_metrics1(url, modify, rw_txn)
     }
 
     /// Read or create relay
@@ -1058,7 +1539,7 @@ impl Storage {
     where
         F: Fn(&Relay) -> bool,
     {
-        self.filter_relays3(f)
+        self.filter_relays6(f)
     }
 
     pub fn load_effective_public_relay_list(&self) -> Result<RelayList, Error> {
@@ -1352,6 +1833,15 @@ impl Storage {
     /// Delete the event
     pub fn delete_event<'a>(&'a self, id: Id, rw_txn: Option<&mut RwTxn<'a>>) -> Result<(), Error> {
         let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            // Delete from the search index (this needs the event, so do it first)
+            let event = match self.db_events()?.get(txn, id.as_slice())? {
+                Some(bytes) => Some(Event::read_from_buffer(bytes)?),
+                None => None,
+            };
+            if let Some(event) = event {
+                self.delete_event_search_index(&event, Some(txn))?;
+            }
+
             // Delete from the events table
             self.delete_event3(id, Some(txn))?;
 
@@ -1421,21 +1911,41 @@ impl Storage {
             }
         })?;
 
+        // Our articles, and those the user has opened, keep their superseded
+        // versions as revisions
+        let keep_revisions = event.kind == EventKind::LongFormContent
+            && (Some(event.pubkey) == GLOBALS.identity.public_key()
+                || GLOBALS
+                    .opened_articles
+                    .read()
+                    .contains(&(event.pubkey, event.parameter().unwrap_or_default())));
+
         let mut found_newer = false;
+        let mut found_same = false;
         for old in existing {
             if old.created_at < event.created_at {
                 // here is some reborrow magic we needed to appease the borrow checker
                 if let Some(&mut ref mut v) = rw_txn {
+                    if keep_revisions {
+                        self.write_revision1(&old, Some(v))?;
+                    }
                     self.delete_event(old.id, Some(v))?;
                 } else {
+                    if keep_revisions {
+                        self.write_revision1(&old, None)?;
+                    }
                     self.delete_event(old.id, None)?;
                 }
             } else {
+                found_same |= old.id == event.id;
                 found_newer = true;
             }
         }
 
         if found_newer {
+            if keep_revisions && !found_same {
+                self.write_revision1(event, rw_txn)?;
+            }
             return Ok(false); // this event is not the latest one.
         }
 
@@ -1472,6 +1982,44 @@ impl Storage {
             .cloned())
     }
 
+    /// Get the earlier versions of a replaceable event that we kept, newest first.
+    /// These are only kept for long-form articles, either ours or ones the user
+    /// opened.
+    #[inline]
+    pub fn get_revisions(
+        &self,
+        kind: EventKind,
+        pubkey: PublicKey,
+        parameter: &str,
+    ) -> Result<Vec<Event>, Error> {
+        self.get_revisions1(kind, pubkey, parameter)
+    }
+
+    /// Delete the earlier versions we kept of a replaceable event
+    #[inline]
+    pub(crate) fn delete_revisions<'a>(
+        &'a self,
+        kind: EventKind,
+        pubkey: PublicKey,
+        parameter: &str,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_revisions1(kind, pubkey, parameter, rw_txn)
+    }
+
+    /// Whether [find_events_by_filter](Storage::find_events_by_filter) can answer
+    /// this filter from an index, rather than by scraping all of storage
+    pub fn filter_is_indexed(filter: &Filter) -> bool {
+        !filter.ids.is_empty()
+            || (!filter.tags.is_empty()
+                && filter
+                    .tags
+                    .iter()
+                    .all(|t| INDEXED_TAGS.contains(&&*t.0.to_string())))
+            || (!filter.authors.is_empty() && !filter.kinds.is_empty())
+            || (!filter.kinds.is_empty() && filter.kinds.iter().all(|k| INDEXED_KINDS.contains(k)))
+    }
+
     /// Find events by filter.
     ///
     /// This function may inefficiently scrape all of storage for some filters.
@@ -1690,52 +2238,191 @@ impl Storage {
         Ok(output.into_iter().rev().take(limit).collect())
     }
 
-    /// Search all events for the text, case insensitive. Both content and tags
-    /// are searched.
-    pub fn search_events(&self, text: &str) -> Result<Vec<Event>, Error> {
-        let event_kinds = crate::feed::feed_displayable_event_kinds(true);
+    /// Get the (created_at, id) of every local event matching the filter, for set
+    /// reconciliation with a relay (NIP-77). The limit of the filter is ignored.
+    ///
+    /// This only works from the event_akci_index or event_kci_index. If the filter
+    /// cannot be fully answered from those indexes, None is returned.
+    pub fn find_event_index_items(
+        &self,
+        filter: &Filter,
+    ) -> Result<Option<Vec<(Unixtime, Id)>>, Error> {
+        if !filter.ids.is_empty() || !filter.tags.is_empty() || filter.kinds.is_empty() {
+            return Ok(None);
+        }
 
-        let needle = regex::escape(text.to_lowercase().as_str());
-        let re = regex::RegexBuilder::new(needle.as_str())
-            .unicode(true)
-            .case_insensitive(true)
-            .build()?;
+        let use_akci = !filter.authors.is_empty();
+        if !use_akci && !filter.kinds.iter().all(|k| INDEXED_KINDS.contains(k)) {
+            return Ok(None);
+        }
 
         let txn = self.env().read_txn()?;
-        let iter = self.db_events()?.iter(&txn)?;
-        let mut events: Vec<Event> = Vec::new();
-        for result in iter {
-            let (_key, val) = result?;
+        let since = filter.since.unwrap_or(Unixtime(0));
+        let until = filter.until.unwrap_or(Unixtime(i64::MAX));
 
-            // event kind must match
-            if let Some(kind) = Event::get_kind_from_speedy_bytes(val) {
-                if !event_kinds.contains(&kind) {
-                    continue;
+        let mut output: Vec<(Unixtime, Id)> = Vec::new();
+
+        for kind in &filter.kinds {
+            if use_akci {
+                for pkh in &filter.authors {
+                    let author = PublicKey::try_from_hex_string(pkh.as_str(), true)?;
+                    let start_prefix = AkciKey::from_parts(author, *kind, until, Id([0; 32]));
+                    let end_prefix = AkciKey::from_parts(author, *kind, since, Id([255; 32]));
+                    let range = (
+                        Bound::Included(start_prefix.as_slice()),
+                        Bound::Included(end_prefix.as_slice()),
+                    );
+                    for result in self.db_event_akci_index()?.range(&txn, &range)? {
+                        let (keybytes, _) = result?;
+                        let (_, _, created_at, id) = AkciKey::from_bytes(keybytes)?.into_parts()?;
+                        // The index may contain entries for events since deleted
+                        if self.db_events()?.get(&txn, id.as_slice())?.is_some() {
+                            output.push((created_at, id));
+                        }
+                    }
                 }
             } else {
-                continue;
+                let start_prefix = KciKey::from_parts(*kind, until, Id([0; 32]));
+                let end_prefix = KciKey::from_parts(*kind, since, Id([255; 32]));
+                let range = (
+                    Bound::Included(start_prefix.as_slice()),
+                    Bound::Included(end_prefix.as_slice()),
+                );
+                for result in self.db_event_kci_index()?.range(&txn, &range)? {
+                    let (keybytes, _) = result?;
+                    let (_, created_at, id) = KciKey::from_bytes(keybytes)?.into_parts()?;
+                    if self.db_events()?.get(&txn, id.as_slice())?.is_some() {
+                        output.push((created_at, id));
+                    }
+                }
             }
+        }
 
-            if let Some(content) = Event::get_content_from_speedy_bytes(val) {
-                if re.is_match(content.as_ref()) {
-                    let event = Event::read_from_buffer(val)?;
-                    events.push(event);
-                    continue;
-                }
+        // Filters may overlap (e.g. duplicated authors)
+        output.sort();
+        output.dedup();
+
+        Ok(Some(output))
+    }
+
+    /// Search events using the full-text search index.
+    ///
+    /// All terms must match, and phrases must match in order. Results are ranked
+    /// by relevance and then by recency, and paginated with `offset` and `limit`.
+    /// The returned bool is true if there are more results after this page.
+    ///
+    /// A query with only qualifiers (no terms) cannot use the index, and returns
+    /// matching events in reverse time order.
+    pub fn search_events(
+        &self,
+        query: &SearchQuery,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<Event>, bool), Error> {
+        let event_kinds = crate::feed::feed_displayable_event_kinds(true);
+
+        if query.terms.is_empty() {
+            if query.is_empty() {
+                return Ok((vec![], false));
             }
 
-            if Event::tag_search_in_speedy_bytes(val, &re)? {
-                let event = Event::read_from_buffer(val)?;
-                events.push(event);
+            let mut filter = Filter::new();
+            filter.kinds = event_kinds
+                .iter()
+                .filter(|k| query.kinds.is_empty() || query.kinds.contains(*k))
+                .copied()
+                .collect();
+            if filter.kinds.is_empty() {
+                return Ok((vec![], false));
+            }
+            filter.authors = query.authors.iter().map(|pk| (*pk).into()).collect();
+            filter.since = query.since;
+            filter.until = query.until;
+            let events = self.find_events_by_filter(&filter, |_| true)?;
+            let more = events.len() > offset + limit;
+            return Ok((events.into_iter().skip(offset).take(limit).collect(), more));
+        }
+
+        // Fetch the postings for each term, rarest first so the intersection
+        // shrinks as quickly as possible
+        let mut postings: Vec<Vec<SearchIndexEntry>> = Vec::new();
+        for term in query.terms.iter() {
+            let posting = self.get_event_search_index1_entries(term)?;
+            if posting.is_empty() {
+                // AND semantics; nothing can match
+                return Ok((vec![], false));
+            }
+            postings.push(posting);
+        }
+        postings.sort_by_key(|p| p.len());
+
+        // Intersect, scoring by tf-idf
+        let total = (self.get_event_len()? as f64).max(1.0);
+        let mut scores: HashMap<Id, (f64, Unixtime)> = HashMap::new();
+        for (i, posting) in postings.iter().enumerate() {
+            let idf = (total / posting.len() as f64).ln().max(0.0) + 1.0;
+            let mut next: HashMap<Id, (f64, Unixtime)> = HashMap::new();
+            for entry in posting.iter() {
+                let (id, created_at, count) = entry.into_parts();
+                if let Some(since) = query.since {
+                    if created_at < since {
+                        continue;
+                    }
+                }
+                if let Some(until) = query.until {
+                    if created_at > until {
+                        continue;
+                    }
+                }
+                let weight = idf * (1.0 + (count.max(1) as f64).ln());
+                if i == 0 {
+                    next.insert(id, (weight, created_at));
+                } else if let Some((score, _)) = scores.get(&id) {
+                    next.insert(id, (score + weight, created_at));
+                }
+            }
+            scores = next;
+            if scores.is_empty() {
+                return Ok((vec![], false));
             }
         }
 
-        events.sort_by(|a, b| {
-            // ORDER created_at desc
-            b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id))
+        let mut ranked: Vec<(Id, f64, Unixtime)> = scores
+            .drain()
+            .map(|(id, (score, created_at))| (id, score, created_at))
+            .collect();
+        ranked.sort_by(|a, b| {
+            // ORDER score desc, created_at desc
+            b.1.total_cmp(&a.1).then(b.2.cmp(&a.2)).then(b.0.cmp(&a.0))
         });
 
-        Ok(events)
+        // Walk in rank order, verifying what the index cannot tell us, until
+        // we have filled the page (plus one to know if there are more)
+        let txn = self.env().read_txn()?;
+        let mut skipped: usize = 0;
+        let mut events: Vec<Event> = Vec::new();
+        for (id, _, _) in ranked.iter() {
+            let event = match self.db_events()?.get(&txn, id.as_slice())? {
+                Some(bytes) => Event::read_from_buffer(bytes)?,
+                None => continue,
+            };
+            if !event_kinds.contains(&event.kind)
+                || !query.qualifiers_match(&event)
+                || !query.phrases_match(&event)
+            {
+                continue;
+            }
+            if skipped < offset {
+                skipped += 1;
+                continue;
+            }
+            if events.len() == limit {
+                return Ok((events, true));
+            }
+            events.push(event);
+        }
+
+        Ok((events, false))
     }
 
     fn switch_to_rumor<'a>(
@@ -1797,6 +2484,25 @@ impl Storage {
         self.write_event3_tag_index1(event, rw_txn)
     }
 
+    // We don't call this externally. Whenever we write an event, we do this
+    #[inline]
+    fn write_event_search_index<'a>(
+        &'a self,
+        event: &Event,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_event3_search_index1(event, rw_txn)
+    }
+
+    #[inline]
+    fn delete_event_search_index<'a>(
+        &'a self,
+        event: &Event,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_event3_search_index1(event, rw_txn)
+    }
+
     #[inline]
     pub(crate) fn index_unindexed_giftwraps(&self) -> Result<(), Error> {
         self.index_unindexed_giftwraps1()
@@ -2305,6 +3011,7 @@ impl Storage {
             self.db_event_akci_index()?.clear(txn)?;
             self.db_event_kci_index()?.clear(txn)?;
             self.db_event_tag_index()?.clear(txn)?;
+            self.db_event_search_index()?.clear(txn)?;
             self.db_hashtags()?.clear(txn)?;
 
             let loop_txn = self.env().read_txn()?;
@@ -2340,6 +3047,7 @@ impl Storage {
                     } // upstream bug
                     self.add_hashtag(&hashtag, event.id, Some(txn))?;
                 }
+                self.write_event_search_index(&event, Some(txn))?;
             }
             self.set_flag_rebuild_indexes_needed(false, Some(txn))?;
             Ok(())
@@ -2405,7 +3113,21 @@ impl Storage {
 
     /// Hash a person list
     pub fn hash_person_list(&self, list: PersonList) -> Result<u64, Error> {
-        self.hash_person_list2(list)
+        let hash = self.hash_person_list2(list)?;
+        if list != PersonList::Muted {
+            return Ok(hash);
+        }
+
+        let force_private = match self.get_person_list_metadata(list)? {
+            Some(metadata) => *metadata.private,
+            None => false,
+        };
+        let mutes: BTreeMap<Vec<u8>, Private> = self
+            .read_all_mutes()?
+            .into_iter()
+            .map(|entry| (entry.mute.key(), Private(entry.private || force_private)))
+            .collect();
+        Ok(crate::mutes::hash_with_mutes(hash, &mutes))
     }
 
     pub fn get_people_in_all_followed_lists(&self) -> Result<Vec<PublicKey>, Error> {
@@ -2560,6 +3282,64 @@ impl Storage {
         self.delete_nip46server2(pubkey, rw_txn)
     }
 
+    pub fn write_nwc_connection<'a>(
+        &'a self,
+        connection: &NwcConnection,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_nwc_connection1(connection, rw_txn)
+    }
+
+    pub fn read_all_nwc_connections(&self) -> Result<Vec<NwcConnection>, Error> {
+        self.read_all_nwc_connections1()
+    }
+
+    pub fn delete_nwc_connection<'a>(
+        &'a self,
+        pubkey: PublicKey,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_nwc_connection1(pubkey, rw_txn)
+    }
+
+    /// Remember what a zap recipient's LNURL provider told us
+    pub(crate) fn write_zap_provider<'a>(
+        &'a self,
+        recipient: PublicKey,
+        provider: &ZapProvider,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_zap_provider1(recipient, provider, rw_txn)
+    }
+
+    /// What a zap recipient's LNURL provider told us, if we have asked
+    pub fn read_zap_provider(&self, recipient: PublicKey) -> Result<Option<ZapProvider>, Error> {
+        self.read_zap_provider1(recipient)
+    }
+
+    /// Hold a zap receipt until the recipient's LNURL provider can be checked
+    pub(crate) fn write_unverified_zap<'a>(
+        &'a self,
+        receipt_id: Id,
+        recipient: PublicKey,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_unverified_zap1(receipt_id, recipient, rw_txn)
+    }
+
+    /// Zap receipts (and their recipients) waiting to be checked
+    pub(crate) fn read_all_unverified_zaps(&self) -> Result<Vec<(Id, PublicKey)>, Error> {
+        self.read_all_unverified_zaps1()
+    }
+
+    pub(crate) fn delete_unverified_zap<'a>(
+        &'a self,
+        receipt_id: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_unverified_zap1(receipt_id, rw_txn)
+    }
+
     pub fn url_is_banned(url: &RelayUrl) -> bool {
         // Infinite number of subdomain relays being unmanageable
         url.as_str().contains("relay.nostr.band")
@@ -0,0 +1,102 @@
+use crate::error::Error;
+use crate::storage::types::{Mute1, MuteEntry1};
+use crate::storage::{RawDatabase, Storage, MAX_LMDB_KEY};
+use heed::types::Bytes;
+use heed::RwTxn;
+use std::sync::Mutex;
+
+// Mute -> MuteEntry
+//   key: mute.key()
+//   val: serde_json::to_vec(entry) | serde_json::from_slice(bytes)
+
+static MUTES1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut MUTES1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_mutes1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = MUTES1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = MUTES1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = MUTES1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("mutes1")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                MUTES1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn get_mutes1_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_mutes1()?.len(&txn)?)
+    }
+
+    pub(crate) fn write_mute1<'a>(
+        &'a self,
+        entry: &MuteEntry1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut key = entry.mute.key();
+        key.truncate(MAX_LMDB_KEY);
+        let bytes = serde_json::to_vec(entry)?;
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_mutes1()?.put(txn, &key, &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn delete_mute1<'a>(
+        &'a self,
+        mute: &Mute1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut key = mute.key();
+        key.truncate(MAX_LMDB_KEY);
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            let _ = self.db_mutes1()?.delete(txn, &key)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn read_all_mutes1(&self) -> Result<Vec<MuteEntry1>, Error> {
+        let txn = self.env().read_txn()?;
+        let mut output: Vec<MuteEntry1> = Vec::new();
+        for result in self.db_mutes1()?.iter(&txn)? {
+            let (_key, val) = result?;
+            output.push(serde_json::from_slice(val)?);
+        }
+        Ok(output)
+    }
+
+    pub(crate) fn clear_mutes1<'a>(&'a self, rw_txn: Option<&mut RwTxn<'a>>) -> Result<(), Error> {
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_mutes1()?.clear(txn)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+}
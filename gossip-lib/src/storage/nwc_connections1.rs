@@ -0,0 +1,89 @@
+use crate::error::Error;
+use crate::nwc::NwcConnection;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::PublicKey;
+use speedy::{Readable, Writable};
+use std::sync::Mutex;
+
+// PublicKey -> NwcConnection
+//   key: wallet_pubkey.as_bytes()
+//   val: connection.write_to_vec() | NwcConnection::read_from_buffer(val)
+
+static NWC_CONNECTIONS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut NWC_CONNECTIONS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_nwc_connections1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = NWC_CONNECTIONS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = NWC_CONNECTIONS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = NWC_CONNECTIONS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("nwc_connections1")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                NWC_CONNECTIONS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_nwc_connection1<'a>(
+        &'a self,
+        connection: &NwcConnection,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = connection.wallet_pubkey.as_bytes();
+        let bytes = connection.write_to_vec()?;
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_nwc_connections1()?.put(txn, key, &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn read_all_nwc_connections1(&self) -> Result<Vec<NwcConnection>, Error> {
+        let txn = self.env().read_txn()?;
+        let mut output: Vec<NwcConnection> = Vec::new();
+        for result in self.db_nwc_connections1()?.iter(&txn)? {
+            let (_key, val) = result?;
+            let connection = NwcConnection::read_from_buffer(val)?;
+            output.push(connection);
+        }
+        Ok(output)
+    }
+
+    pub(crate) fn delete_nwc_connection1<'a>(
+        &'a self,
+        pubkey: PublicKey,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = pubkey.as_bytes();
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            let _ = self.db_nwc_connections1()?.delete(txn, key);
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+}
@@ -0,0 +1,101 @@
+use crate::error::Error;
+use crate::storage::types::QueuedEvent1;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::{RoTxn, RwTxn};
+use std::sync::Mutex;
+
+// Sequence -> QueuedEvent
+//   key: sequence.to_be_bytes()
+//   val: serde_json::to_vec(queued) | serde_json::from_slice(bytes)
+
+static OFFLINE_QUEUE1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut OFFLINE_QUEUE1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_offline_queue1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = OFFLINE_QUEUE1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = OFFLINE_QUEUE1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = OFFLINE_QUEUE1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("offline_queue1")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                OFFLINE_QUEUE1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn get_offline_queue1_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_offline_queue1()?.len(&txn)?)
+    }
+
+    /// Append to the end of the queue
+    pub(crate) fn queue_offline_event1<'a>(
+        &'a self,
+        queued: &QueuedEvent1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let bytes = serde_json::to_vec(queued)?;
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            // Keys are big-endian so the last one is the highest
+            let sequence: u64 = match self.db_offline_queue1()?.last(txn)? {
+                Some((key, _val)) => u64::from_be_bytes(key[..8].try_into()?) + 1,
+                None => 0,
+            };
+            self.db_offline_queue1()?
+                .put(txn, &sequence.to_be_bytes(), &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    /// Read the whole queue, in order
+    pub(crate) fn read_offline_queue1<'a>(
+        &'a self,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Vec<QueuedEvent1>, Error> {
+        let f = |txn: &RoTxn<'a>| -> Result<Vec<QueuedEvent1>, Error> {
+            let mut output: Vec<QueuedEvent1> = Vec::new();
+            for result in self.db_offline_queue1()?.iter(txn)? {
+                let (_key, val) = result?;
+                output.push(serde_json::from_slice(val)?);
+            }
+            Ok(output)
+        };
+
+        read_transact!(self, txn, f)
+    }
+
+    pub(crate) fn clear_offline_queue1<'a>(
+        &'a self,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_offline_queue1()?.clear(txn)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error::Error;
+use crate::storage::types::OutboxEntry1;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::{RoTxn, RwTxn};
+use nostr_types::Id;
+use std::sync::Mutex;
+
+// Id -> OutboxEntry
+//   key: id.as_slice()
+//   val: serde_json::to_vec(entry) | serde_json::from_slice(bytes)
+
+static OUTBOX1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut OUTBOX1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_outbox1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = OUTBOX1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = OUTBOX1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = OUTBOX1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("outbox1")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                OUTBOX1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn get_outbox1_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_outbox1()?.len(&txn)?)
+    }
+
+    pub(crate) fn write_outbox_entry1<'a>(
+        &'a self,
+        entry: &OutboxEntry1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let bytes = serde_json::to_vec(entry)?;
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_outbox1()?
+                .put(txn, entry.event.id.as_slice(), &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn read_outbox_entry1<'a>(
+        &'a self,
+        id: Id,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Option<OutboxEntry1>, Error> {
+        let f = |txn: &RoTxn<'a>| -> Result<Option<OutboxEntry1>, Error> {
+            match self.db_outbox1()?.get(txn, id.as_slice())? {
+                Some(bytes) => Ok(Some(serde_json::from_slice(bytes)?)),
+                None => Ok(None),
+            }
+        };
+
+        read_transact!(self, txn, f)
+    }
+
+    pub(crate) fn modify_outbox_entry1<'a, M>(
+        &'a self,
+        id: Id,
+        mut modify: M,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error>
+    where
+        M: FnMut(&mut OutboxEntry1),
+    {
+        let mut f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            // Unlike relays, we do not create missing entries, as we could
+            // not know the event
+            let mut entry: OutboxEntry1 = match self.db_outbox1()?.get(txn, id.as_slice())? {
+                Some(bytes) => serde_json::from_slice(bytes)?,
+                None => return Ok(()),
+            };
+            modify(&mut entry);
+            let bytes = serde_json::to_vec(&entry)?;
+            self.db_outbox1()?.put(txn, id.as_slice(), &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn delete_outbox_entry1<'a>(
+        &'a self,
+        id: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            let _ = self.db_outbox1()?.delete(txn, id.as_slice())?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn filter_outbox_entries1<F>(&self, f: F) -> Result<Vec<OutboxEntry1>, Error>
+    where
+        F: Fn(&OutboxEntry1) -> bool,
+    {
+        let txn = self.env().read_txn()?;
+        let mut output: Vec<OutboxEntry1> = Vec::new();
+        for result in self.db_outbox1()?.iter(&txn)? {
+            let (_key, val) = result?;
+            let entry: OutboxEntry1 = serde_json::from_slice(val)?;
+            if f(&entry) {
+                output.push(entry);
+            }
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error::{Error, ErrorKind};
+use crate::storage::types::RelayMetrics1;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::{RoTxn, RwTxn};
+use nostr_types::RelayUrl;
+use std::sync::Mutex;
+
+// Url -> RelayMetrics
+//   key: key!(url.0.as_bytes())
+//   val: serde_json::to_vec(metrics) | serde_json::from_slice(bytes)
+
+static RELAY_METRICS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut RELAY_METRICS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_relay_metrics1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = RELAY_METRICS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = RELAY_METRICS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = RELAY_METRICS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("relay_metrics1")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                RELAY_METRICS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn get_relay_metrics1_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_relay_metrics1()?.len(&txn)?)
+    }
+
+    pub(crate) fn write_relay_metrics1<'a>(
+        &'a self,
+        url: &RelayUrl,
+        metrics: &RelayMetrics1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = key!(url.as_str().as_bytes());
+        if key.is_empty() {
+            return Err(ErrorKind::Empty("relay url".to_owned()).into());
+        }
+        let bytes = serde_json::to_vec(metrics)?;
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_relay_metrics1()?.put(txn, key, &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn delete_relay_metrics1<'a>(
+        &'a self,
+        url: &RelayUrl,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = key!(url.as_str().as_bytes());
+        if key.is_empty() {
+            return Err(ErrorKind::Empty("relay url".to_owned()).into());
+        }
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            let _ = self.db_relay_metrics1()?.delete(txn, key);
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn modify_relay_metrics1<'a, M>(
+        &'a self,
+        url: &RelayUrl,
+        mut modify: M,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error>
+    where
+        M: FnMut(&mut RelayMetrics1),
+    {
+        let key = key!(url.as_str().as_bytes());
+        if key.is_empty() {
+            return Err(ErrorKind::Empty("relay url".to_owned()).into());
+        }
+
+        let mut f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            let mut metrics = match self.db_relay_metrics1()?.get(txn, key)? {
+                Some(bytes) => serde_json::from_slice(bytes)?,
+                None => RelayMetrics1::default(),
+            };
+            modify(&mut metrics);
+            let bytes = serde_json::to_vec(&metrics)?;
+            self.db_relay_metrics1()?.put(txn, key, &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn read_relay_metrics1<'a>(
+        &'a self,
+        url: &RelayUrl,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Option<RelayMetrics1>, Error> {
+        let f = |txn: &RoTxn<'a>| -> Result<Option<RelayMetrics1>, Error> {
+            let key = key!(url.as_str().as_bytes());
+            if key.is_empty() {
+                return Err(ErrorKind::Empty("relay url".to_owned()).into());
+            }
+            match self.db_relay_metrics1()?.get(txn, key)? {
+                Some(bytes) => Ok(Some(serde_json::from_slice(bytes)?)),
+                None => Ok(None),
+            }
+        };
+
+        read_transact!(self, txn, f)
+    }
+}
@@ -0,0 +1,127 @@
+use crate::error::Error;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::{Event, EventKind, PublicKey};
+use speedy::{Readable, Writable};
+use std::sync::Mutex;
+
+// Superseded versions of replaceable events we keep revisions of
+// Kind:PublicKey:d:Unixtime:Id -> Event
+//   key: addr_prefix(kind, pubkey, d), created_at.0.to_be_bytes(), id.as_slice()
+//   val: event.write_to_vec() | Event::read_from_buffer(val)
+
+// Long `d` tags are cut so the key fits in LMDB
+const MAX_D_LEN: usize = 400;
+
+static REVISIONS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut REVISIONS1_DB: Option<RawDatabase> = None;
+
+// The key prefix shared by every revision at one address
+fn addr_prefix(kind: EventKind, pubkey: PublicKey, d: &str) -> Vec<u8> {
+    let d = &d.as_bytes()[..d.len().min(MAX_D_LEN)];
+    let mut key: Vec<u8> = Vec::new();
+    key.extend(u32::from(kind).to_be_bytes());
+    key.extend(pubkey.as_bytes());
+    // The length keeps one `d` from being the prefix of another
+    key.extend((d.len() as u16).to_be_bytes());
+    key.extend(d);
+    key
+}
+
+impl Storage {
+    pub(super) fn db_revisions1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = REVISIONS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = REVISIONS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = REVISIONS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("revisions1")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                REVISIONS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn get_revisions1_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_revisions1()?.len(&txn)?)
+    }
+
+    pub(crate) fn write_revision1<'a>(
+        &'a self,
+        event: &Event,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let d = event.parameter().unwrap_or_default();
+        let mut key = addr_prefix(event.kind, event.pubkey, &d);
+        key.extend(event.created_at.0.to_be_bytes());
+        key.extend(event.id.as_slice());
+        let bytes = event.write_to_vec()?;
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_revisions1()?.put(txn, &key, &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn delete_revisions1<'a>(
+        &'a self,
+        kind: EventKind,
+        pubkey: PublicKey,
+        d: &str,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let prefix = addr_prefix(kind, pubkey, d);
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            let mut iter = self.db_revisions1()?.prefix_iter_mut(txn, &prefix)?;
+            while let Some(result) = iter.next() {
+                let _ = result?;
+                unsafe {
+                    iter.del_current()?;
+                }
+            }
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn get_revisions1(
+        &self,
+        kind: EventKind,
+        pubkey: PublicKey,
+        d: &str,
+    ) -> Result<Vec<Event>, Error> {
+        let prefix = addr_prefix(kind, pubkey, d);
+        let txn = self.env().read_txn()?;
+        let mut output: Vec<Event> = Vec::new();
+        for result in self.db_revisions1()?.prefix_iter(&txn, &prefix)? {
+            let (_key, val) = result?;
+            output.push(Event::read_from_buffer(val)?);
+        }
+        // Keys sort oldest first
+        output.reverse();
+        Ok(output)
+    }
+}
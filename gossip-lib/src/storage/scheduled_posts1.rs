@@ -0,0 +1,104 @@
+use crate::error::Error;
+use crate::storage::types::ScheduledPost1;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::{RoTxn, RwTxn};
+use std::sync::Mutex;
+
+// ScheduledPostId -> ScheduledPost
+//   key: id.to_be_bytes()
+//   val: serde_json::to_vec(post) | serde_json::from_slice(bytes)
+
+static SCHEDULED_POSTS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut SCHEDULED_POSTS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_scheduled_posts1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = SCHEDULED_POSTS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = SCHEDULED_POSTS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = SCHEDULED_POSTS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("scheduled_posts1")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                SCHEDULED_POSTS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn get_scheduled_posts1_len(&self) -> Result<u64, Error> {
+        let txn = self.env().read_txn()?;
+        Ok(self.db_scheduled_posts1()?.len(&txn)?)
+    }
+
+    pub(crate) fn write_scheduled_post1<'a>(
+        &'a self,
+        post: &ScheduledPost1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let bytes = serde_json::to_vec(post)?;
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_scheduled_posts1()?
+                .put(txn, &post.id.to_be_bytes(), &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn read_scheduled_post1<'a>(
+        &'a self,
+        id: u64,
+        txn: Option<&RoTxn<'a>>,
+    ) -> Result<Option<ScheduledPost1>, Error> {
+        let f = |txn: &RoTxn<'a>| -> Result<Option<ScheduledPost1>, Error> {
+            match self.db_scheduled_posts1()?.get(txn, &id.to_be_bytes())? {
+                Some(bytes) => Ok(Some(serde_json::from_slice(bytes)?)),
+                None => Ok(None),
+            }
+        };
+
+        read_transact!(self, txn, f)
+    }
+
+    pub(crate) fn read_all_scheduled_posts1(&self) -> Result<Vec<ScheduledPost1>, Error> {
+        let txn = self.env().read_txn()?;
+        let mut output: Vec<ScheduledPost1> = Vec::new();
+        for result in self.db_scheduled_posts1()?.iter(&txn)? {
+            let (_key, val) = result?;
+            output.push(serde_json::from_slice(val)?);
+        }
+        Ok(output)
+    }
+
+    pub(crate) fn delete_scheduled_post1<'a>(
+        &'a self,
+        id: u64,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            let _ = self.db_scheduled_posts1()?.delete(txn, &id.to_be_bytes())?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+}
@@ -0,0 +1,73 @@
+use nostr_types::{Id, PublicKey, RelayUrl, Unixtime};
+use serde::{Deserialize, Serialize};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// Something we ask relays to count (NIP-45)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum CountKey1 {
+    /// How many contact lists include this person
+    Followers(PublicKey),
+
+    /// How many notes reply to this event
+    Replies(Id),
+
+    /// How many reactions this event has
+    Reactions(Id),
+}
+
+impl CountKey1 {
+    /// The database key
+    pub fn key(&self) -> Vec<u8> {
+        match self {
+            CountKey1::Followers(pubkey) => {
+                let mut key = b"f:".to_vec();
+                key.extend(pubkey.as_bytes());
+                key
+            }
+            CountKey1::Replies(id) => {
+                let mut key = b"r:".to_vec();
+                key.extend(id.as_slice());
+                key
+            }
+            CountKey1::Reactions(id) => {
+                let mut key = b"x:".to_vec();
+                key.extend(id.as_slice());
+                key
+            }
+        }
+    }
+}
+
+/// The counts relays have given us for something
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Count1 {
+    /// What was counted
+    pub key: CountKey1,
+
+    /// The latest count from each relay that answered
+    pub relays: Vec<(RelayUrl, u64)>,
+
+    /// If any relay said its count is approximate
+    pub approximate: bool,
+
+    /// When a relay last answered
+    pub updated_at: Unixtime,
+}
+
+impl Count1 {
+    pub fn new(key: CountKey1) -> Count1 {
+        Count1 {
+            key,
+            relays: Vec::new(),
+            approximate: false,
+            updated_at: Unixtime(0),
+        }
+    }
+
+    /// Our best estimate. Relays only see part of the network and overlap with each
+    /// other, so this is the largest count any relay gave.
+    pub fn count(&self) -> u64 {
+        self.relays.iter().map(|(_, c)| *c).max().unwrap_or(0)
+    }
+}
@@ -0,0 +1,60 @@
+use nostr_types::{Id, PublicKey, Unixtime};
+use serde::{Deserialize, Serialize};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// What a draft is for. There is at most one draft per context.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum DraftContext1 {
+    /// A new note, not in reply to anything
+    NewNote,
+
+    /// A reply to (or annotation of) this event
+    Reply(Id),
+
+    /// A DM to this channel (the other members, sorted)
+    DmChannel(Vec<PublicKey>),
+}
+
+impl DraftContext1 {
+    /// The database key for this context
+    pub fn key(&self) -> Vec<u8> {
+        match self {
+            DraftContext1::NewNote => b"new".to_vec(),
+            DraftContext1::Reply(id) => {
+                let mut key = b"re:".to_vec();
+                key.extend(id.as_slice());
+                key
+            }
+            DraftContext1::DmChannel(pubkeys) => {
+                let mut key = b"dm:".to_vec();
+                for pubkey in pubkeys {
+                    key.extend(pubkey.as_bytes());
+                }
+                key
+            }
+        }
+    }
+}
+
+/// An unfinished post from the composer
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Draft1 {
+    /// What it is for
+    pub context: DraftContext1,
+
+    /// The text typed so far
+    pub content: String,
+
+    /// The subject, if one was added
+    pub subject: Option<String>,
+
+    /// The content warning, if one was added
+    pub content_warning: Option<String>,
+
+    /// If this is an annotation
+    pub annotation: bool,
+
+    /// When this was last saved
+    pub updated_at: Unixtime,
+}
@@ -1,3 +1,15 @@
+mod count1;
+pub use count1::{Count1, CountKey1};
+
+mod draft1;
+pub use draft1::{Draft1, DraftContext1};
+
+mod mute1;
+pub use mute1::{Mute1, MuteEntry1};
+
+mod outbox_entry1;
+pub use outbox_entry1::{OutboxEntry1, OutboxRelay1, OutboxStatus1};
+
 mod person2;
 pub use person2::Person2;
 
@@ -22,6 +34,9 @@ pub use person_relay1::PersonRelay1;
 mod person_relay2;
 pub use person_relay2::PersonRelay2;
 
+mod queued_event1;
+pub use queued_event1::QueuedEvent1;
+
 mod relationship1;
 pub use relationship1::Relationship1;
 
@@ -46,6 +61,21 @@ pub use relay2::Relay2;
 mod relay3;
 pub use relay3::Relay3;
 
+mod relay4;
+pub use relay4::Relay4;
+
+mod relay5;
+pub use relay5::Relay5;
+
+mod relay6;
+pub use relay6::Relay6;
+
+mod relay_metrics1;
+pub use relay_metrics1::{RelayMetrics1, RelaySession1, RELAY_HISTORY_LEN};
+
+mod scheduled_post1;
+pub use scheduled_post1::ScheduledPost1;
+
 use crate::error::Error;
 use nostr_types::{Id, PublicKey};
 
@@ -0,0 +1,47 @@
+use nostr_types::Id;
+use serde::{Deserialize, Serialize};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// Something other than a person on the mute list (NIP-51)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Mute1 {
+    /// Notes containing this word (lowercase)
+    Word(String),
+
+    /// Notes with this hashtag (lowercase, without the '#')
+    Hashtag(String),
+
+    /// This note and every reply in its thread
+    Thread(Id),
+}
+
+impl Mute1 {
+    /// The database key
+    pub fn key(&self) -> Vec<u8> {
+        match self {
+            Mute1::Word(word) => {
+                let mut key = b"w:".to_vec();
+                key.extend(word.as_bytes());
+                key
+            }
+            Mute1::Hashtag(hashtag) => {
+                let mut key = b"t:".to_vec();
+                key.extend(hashtag.as_bytes());
+                key
+            }
+            Mute1::Thread(id) => {
+                let mut key = b"e:".to_vec();
+                key.extend(id.as_slice());
+                key
+            }
+        }
+    }
+}
+
+/// A mute list entry, and whether it is kept in the encrypted part of the list
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MuteEntry1 {
+    pub mute: Mute1,
+    pub private: bool,
+}
@@ -0,0 +1,90 @@
+use nostr_types::{Event, RelayUrl, Unixtime};
+use serde::{Deserialize, Serialize};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// The publishing state of one of our events on one relay
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OutboxStatus1 {
+    /// Sent (or about to be sent), awaiting an OK
+    Pending,
+
+    /// The relay answered OK=true
+    Accepted,
+
+    /// The relay answered OK=false, or did not answer in time
+    Failed,
+}
+
+/// The publishing record of one of our events on one relay
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OutboxRelay1 {
+    /// The relay
+    pub url: RelayUrl,
+
+    /// Where we stand with this relay
+    pub status: OutboxStatus1,
+
+    /// How many times we have tried to post to this relay
+    pub attempts: u32,
+
+    /// When we last tried to post to this relay
+    pub last_attempt: Option<Unixtime>,
+
+    /// When we should next retry, if the post has failed
+    pub next_attempt: Option<Unixtime>,
+
+    /// The message the relay gave with its OK, or why we gave up waiting
+    pub message: Option<String>,
+}
+
+/// One of our events and its publishing state on each relay we posted it to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OutboxEntry1 {
+    /// The event
+    pub event: Event,
+
+    /// The relays we are posting it to
+    pub relays: Vec<OutboxRelay1>,
+
+    /// When we first posted it
+    pub created_at: Unixtime,
+}
+
+impl OutboxEntry1 {
+    pub fn new(event: Event) -> OutboxEntry1 {
+        OutboxEntry1 {
+            event,
+            relays: Vec::new(),
+            created_at: Unixtime::now(),
+        }
+    }
+
+    /// The record for this relay, if we posted there
+    pub fn relay(&self, url: &RelayUrl) -> Option<&OutboxRelay1> {
+        self.relays.iter().find(|r| r.url == *url)
+    }
+
+    /// The record for this relay, created as pending if missing
+    pub fn relay_mut(&mut self, url: &RelayUrl) -> &mut OutboxRelay1 {
+        match self.relays.iter().position(|r| r.url == *url) {
+            Some(i) => &mut self.relays[i],
+            None => {
+                self.relays.push(OutboxRelay1 {
+                    url: url.to_owned(),
+                    status: OutboxStatus1::Pending,
+                    attempts: 0,
+                    last_attempt: None,
+                    next_attempt: None,
+                    message: None,
+                });
+                self.relays.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// How many relays have this status
+    pub fn count(&self, status: OutboxStatus1) -> usize {
+        self.relays.iter().filter(|r| r.status == status).count()
+    }
+}
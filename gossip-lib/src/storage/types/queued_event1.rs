@@ -0,0 +1,27 @@
+use nostr_types::{Event, RelayUrl, Unixtime};
+use serde::{Deserialize, Serialize};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// One of our events, signed while offline and waiting to be posted
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QueuedEvent1 {
+    /// The event
+    pub event: Event,
+
+    /// The relays it is to be posted to
+    pub relay_urls: Vec<RelayUrl>,
+
+    /// When it was queued
+    pub queued_at: Unixtime,
+}
+
+impl QueuedEvent1 {
+    pub fn new(event: Event, relay_urls: Vec<RelayUrl>) -> QueuedEvent1 {
+        QueuedEvent1 {
+            event,
+            relay_urls,
+            queued_at: Unixtime::now(),
+        }
+    }
+}
@@ -1,5 +1,3 @@
-use crate::error::Error;
-use crate::globals::GLOBALS;
 use nostr_types::{RelayInformationDocument, RelayUrl, Unixtime};
 use serde::{Deserialize, Serialize};
 
@@ -163,96 +161,4 @@ impl Relay3 {
         } // unknown, so we put it in the middle
         self.success_count as f32 / attempts as f32
     }
-
-    pub fn should_avoid(&self) -> bool {
-        #[allow(clippy::if_same_then_else)]
-        if self.rank == 0 {
-            true
-        } else if GLOBALS
-            .storage
-            .read_setting_relay_connection_requires_approval()
-            && self.allow_connect == Some(false)
-        {
-            true
-        } else if crate::storage::Storage::url_is_banned(&self.url) {
-            true
-        } else if let Some(when) = self.avoid_until {
-            when >= Unixtime::now()
-        } else {
-            false
-        }
-    }
-
-    pub fn is_good_for_advertise(&self) -> bool {
-        if self.should_avoid() {
-            return false;
-        }
-
-        self.has_usage_bits(Self::INBOX)
-            || self.has_usage_bits(Self::OUTBOX)
-            || self.has_usage_bits(Self::DISCOVER)
-            || (self.rank > 0 && self.success_rate() > 0.50 && self.success_count > 15)
-    }
-
-    /// This gives a pure score for the relay outside of context
-    ///
-    /// Output ranges from 0.0 (worst) to 1.0 (best)
-    ///
-    /// Typical good relays still only score about 0.3, simply because rank goes so high.
-    ///
-    /// If `None` is returned, do not use this relay.
-    pub fn score(&self) -> f32 {
-        if self.should_avoid() {
-            return 0.0;
-        }
-
-        let mut score: f32 = 1.0;
-
-        // Adjust by rank:
-        //   1 = 0.11111
-        //   3 = 0.33333
-        //   5 = 0.55555
-        //   9 = 1.0
-        score *= self.rank as f32 / 9.0;
-
-        // Adjust by success rate (max penalty of cutting in half)
-        score *= 0.5 + 0.5 * self.success_rate();
-
-        // We don't penalize low-attempt relays even as they are less reliable
-        // because we want to let new relays establish.
-
-        score
-    }
-
-    /// This also checks if we are already connected to a relay and those scores
-    /// are doubled (and normalized to 0.0 to 1.0)
-    pub fn score_plus_connected(&self) -> f32 {
-        let score = self.score();
-        if GLOBALS.connected_relays.contains_key(&self.url) {
-            score
-        } else {
-            score * 0.5
-        }
-    }
-
-    pub fn choose_relays<F>(bits: u64, f: F) -> Result<Vec<Relay3>, Error>
-    where
-        F: Fn(&Relay3) -> bool,
-    {
-        GLOBALS
-            .storage
-            .filter_relays(|r| r.has_usage_bits(bits) && !r.should_avoid() && f(r))
-    }
-
-    pub fn choose_relay_urls<F>(bits: u64, f: F) -> Result<Vec<RelayUrl>, Error>
-    where
-        F: Fn(&Relay3) -> bool,
-    {
-        Ok(GLOBALS
-            .storage
-            .filter_relays(|r| r.has_usage_bits(bits) && !r.should_avoid() && f(r))?
-            .iter()
-            .map(|r| r.url.clone())
-            .collect())
-    }
 }
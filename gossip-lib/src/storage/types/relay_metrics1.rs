@@ -0,0 +1,134 @@
+use crate::misc::reason_prefix;
+use nostr_types::Unixtime;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// How many recent connections are kept for charting
+pub const RELAY_HISTORY_LEN: usize = 48;
+
+// Weight of the newest connection in the rolling averages
+const AVERAGE_WEIGHT: f32 = 0.25;
+
+/// What we measured during one connection to a relay
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RelaySession1 {
+    /// When the connection was made
+    pub at: Unixtime,
+
+    /// How long it took to connect, in milliseconds
+    pub connect_ms: u32,
+
+    /// How long after connecting the first EOSE arrived, in milliseconds
+    pub first_eose_ms: Option<u32>,
+
+    /// How long the connection lasted, in seconds
+    pub seconds: u32,
+
+    /// How many events the relay sent
+    pub events: u64,
+
+    /// How many bytes the relay sent
+    pub bytes: u64,
+}
+
+impl RelaySession1 {
+    /// Events per second over the connection
+    pub fn events_per_sec(&self) -> f32 {
+        self.events as f32 / (self.seconds.max(1) as f32)
+    }
+}
+
+/// Rolling measurements of how a relay performs for us
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RelayMetrics1 {
+    /// Average time to connect, in milliseconds
+    pub connect_ms: Option<f32>,
+
+    /// Average time from connecting to the first EOSE, in milliseconds
+    pub first_eose_ms: Option<f32>,
+
+    /// Average events per second while connected
+    pub events_per_sec: Option<f32>,
+
+    /// Total bytes received
+    pub bytes: u64,
+
+    /// How many times AUTH succeeded
+    pub auth_successes: u64,
+
+    /// How many times AUTH was refused
+    pub auth_failures: u64,
+
+    /// How many times subscriptions were CLOSED, by the machine-readable prefix
+    /// of the message (or "other")
+    pub closed: BTreeMap<String, u64>,
+
+    /// How many NOTICEs were received, by prefix (or "other")
+    pub notices: BTreeMap<String, u64>,
+
+    /// Recent connections, oldest first
+    pub history: Vec<RelaySession1>,
+}
+
+impl RelayMetrics1 {
+    /// Fold a finished connection into the averages and the history
+    pub fn add_session(&mut self, session: RelaySession1) {
+        self.connect_ms = Some(average(self.connect_ms, session.connect_ms as f32));
+        if let Some(first_eose_ms) = session.first_eose_ms {
+            self.first_eose_ms = Some(average(self.first_eose_ms, first_eose_ms as f32));
+        }
+        self.events_per_sec = Some(average(self.events_per_sec, session.events_per_sec()));
+        self.bytes += session.bytes;
+
+        self.history.push(session);
+        if self.history.len() > RELAY_HISTORY_LEN {
+            let excess = self.history.len() - RELAY_HISTORY_LEN;
+            self.history.drain(..excess);
+        }
+    }
+
+    /// Count a CLOSED message by its prefix
+    pub fn count_closed(&mut self, message: &str) {
+        *self
+            .closed
+            .entry(reason_prefix(message).unwrap_or("other").to_owned())
+            .or_insert(0) += 1;
+    }
+
+    /// Count a NOTICE message by its prefix
+    pub fn count_notice(&mut self, message: &str) {
+        *self
+            .notices
+            .entry(reason_prefix(message).unwrap_or("other").to_owned())
+            .or_insert(0) += 1;
+    }
+
+    /// How quick the relay is, from 0.0 (slow) to 1.0 (instant), or 0.5 if we
+    /// have not measured it yet
+    pub fn speed(&self) -> f32 {
+        let ms = match (self.connect_ms, self.first_eose_ms) {
+            (None, None) => return 0.5,
+            (c, e) => c.unwrap_or(0.0) + e.unwrap_or(0.0),
+        };
+        // One second overall scores 0.5
+        1000.0 / (1000.0 + ms)
+    }
+
+    /// The fraction of AUTH attempts which succeeded, or 1.0 if none were made
+    pub fn auth_success_rate(&self) -> f32 {
+        let attempts = self.auth_successes + self.auth_failures;
+        if attempts == 0 {
+            return 1.0;
+        }
+        self.auth_successes as f32 / attempts as f32
+    }
+}
+
+fn average(old: Option<f32>, new: f32) -> f32 {
+    match old {
+        Some(old) => old * (1.0 - AVERAGE_WEIGHT) + new * AVERAGE_WEIGHT,
+        None => new,
+    }
+}
@@ -0,0 +1,35 @@
+use nostr_types::{Id, PublicKey, Tag, Unixtime};
+use serde::{Deserialize, Serialize};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// A post to be published at a later time.
+///
+/// This is kept unsigned so that it can still be edited. It is prepared and signed
+/// when it is published, exactly as if it were posted then.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScheduledPost1 {
+    /// A unique id for this scheduled post (not the event id, which is not known yet)
+    pub id: u64,
+
+    /// When to publish
+    pub publish_at: Unixtime,
+
+    /// The content of the post
+    pub content: String,
+
+    /// Additional tags
+    pub tags: Vec<Tag>,
+
+    /// The event this replies to
+    pub in_reply_to: Option<Id>,
+
+    /// If this is an annotation
+    pub annotation: bool,
+
+    /// If this is a DM, the other members of the DM channel
+    pub dm_recipients: Option<Vec<PublicKey>>,
+
+    /// When this was scheduled (or last edited)
+    pub scheduled_at: Unixtime,
+}
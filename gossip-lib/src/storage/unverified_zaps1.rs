@@ -0,0 +1,90 @@
+use crate::error::Error;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::{Id, PublicKey};
+use std::sync::Mutex;
+
+// Id -> PublicKey
+//   key: receipt_id.as_slice()
+//   val: recipient.as_bytes() | PublicKey::from_bytes(val, false)
+//
+// Zap receipts we have not yet been able to check against the recipient's
+// LNURL provider. They don't count towards zap totals until they are checked.
+
+static UNVERIFIED_ZAPS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut UNVERIFIED_ZAPS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_unverified_zaps1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = UNVERIFIED_ZAPS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = UNVERIFIED_ZAPS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = UNVERIFIED_ZAPS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("unverified_zaps1")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                UNVERIFIED_ZAPS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_unverified_zap1<'a>(
+        &'a self,
+        receipt_id: Id,
+        recipient: PublicKey,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_unverified_zaps1()?
+                .put(txn, receipt_id.as_slice(), recipient.as_bytes())?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn read_all_unverified_zaps1(&self) -> Result<Vec<(Id, PublicKey)>, Error> {
+        let txn = self.env().read_txn()?;
+        let mut output: Vec<(Id, PublicKey)> = Vec::new();
+        for result in self.db_unverified_zaps1()?.iter(&txn)? {
+            let (key, val) = result?;
+            let id = Id(key[0..32].try_into()?);
+            let recipient = PublicKey::from_bytes(val, false)?;
+            output.push((id, recipient));
+        }
+        Ok(output)
+    }
+
+    pub(crate) fn delete_unverified_zap1<'a>(
+        &'a self,
+        receipt_id: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            let _ = self
+                .db_unverified_zaps1()?
+                .delete(txn, receipt_id.as_slice());
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+}
@@ -0,0 +1,76 @@
+use crate::error::Error;
+use crate::storage::{RawDatabase, Storage};
+use crate::zaps::ZapProvider;
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::PublicKey;
+use speedy::{Readable, Writable};
+use std::sync::Mutex;
+
+// PublicKey -> ZapProvider
+//   key: recipient.as_bytes()
+//   val: provider.write_to_vec() | ZapProvider::read_from_buffer(val)
+
+static ZAP_PROVIDERS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut ZAP_PROVIDERS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_zap_providers1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = ZAP_PROVIDERS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = ZAP_PROVIDERS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = ZAP_PROVIDERS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.env().write_txn()?;
+                let db = self
+                    .env()
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("zap_providers1")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                ZAP_PROVIDERS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_zap_provider1<'a>(
+        &'a self,
+        recipient: PublicKey,
+        provider: &ZapProvider,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = recipient.as_bytes();
+        let bytes = provider.write_to_vec()?;
+
+        let f = |txn: &mut RwTxn<'a>| -> Result<(), Error> {
+            self.db_zap_providers1()?.put(txn, key, &bytes)?;
+            Ok(())
+        };
+
+        write_transact!(self, rw_txn, f)
+    }
+
+    pub(crate) fn read_zap_provider1(
+        &self,
+        recipient: PublicKey,
+    ) -> Result<Option<ZapProvider>, Error> {
+        let key = recipient.as_bytes();
+        let txn = self.env().read_txn()?;
+        Ok(match self.db_zap_providers1()?.get(&txn, key)? {
+            None => None,
+            Some(bytes) => Some(ZapProvider::read_from_buffer(bytes)?),
+        })
+    }
+}
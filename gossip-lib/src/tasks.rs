@@ -70,10 +70,34 @@ async fn do_online_tasks(tick: usize) {
         }
     }
 
+    // Retry failed posts (every 10 seconds)
+    if tick % 10 == 0 {
+        if let Err(e) = crate::outbox::retry_due() {
+            tracing::error!("{:?}", e);
+        }
+    }
+
+    // Check zap receipts that were waiting on LNURL providers (every 15 seconds)
+    // (in its own task, as LNURL providers may be slow to answer)
+    if tick % 15 == 0 {
+        std::mem::drop(tokio::spawn(async move {
+            if let Err(e) = crate::zaps::check_unverified_zaps().await {
+                tracing::error!("{:?}", e);
+            }
+        }));
+    }
+
     // Update people metadata every 2 seconds
     if tick % 2 == 0 {
         GLOBALS.people.maybe_fetch_metadata().await;
     }
+
+    // Ask relays for the counts being shown (every 2 seconds)
+    if tick % 2 == 0 {
+        if let Err(e) = GLOBALS.counts.maybe_fetch_counts() {
+            tracing::error!("{:?}", e);
+        }
+    }
 }
 
 async fn do_general_tasks(tick: usize) {
@@ -85,4 +109,17 @@ async fn do_general_tasks(tick: usize) {
             GLOBALS.unread_dms.store(unread, Ordering::Relaxed);
         }
     }
+
+    // Apply a changed cache quota (every 3 seconds)
+    if tick % 3 == 0 {
+        GLOBALS.fetcher.enforce_changed_quota().await;
+    }
+
+    // Publish scheduled posts that are due (every 5 seconds)
+    // (while offline these go into the offline queue)
+    if tick % 5 == 0 {
+        if let Err(e) = crate::post::send_due_scheduled_posts() {
+            tracing::error!("{:?}", e);
+        }
+    }
 }
@@ -0,0 +1,451 @@
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use base64::Engine;
+use image::{DynamicImage, ImageFormat};
+use nostr_types::{EventKind, PreEvent, Tag, Unixtime};
+use parking_lot::RwLock;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+// HTTP Auth (NIP-98), which NIP-96 servers use
+const HTTP_AUTH_KIND: u32 = 27235;
+
+// Blossom authorization (BUD-01)
+const BLOSSOM_AUTH_KIND: u32 = 24242;
+
+// How long a Blossom authorization is good for
+const BLOSSOM_AUTH_SECS: i64 = 300;
+
+/// A file we uploaded, and what a NIP-92 `imeta` tag says about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadedMedia {
+    pub url: String,
+    pub mime: String,
+
+    /// SHA-256 of the file as the server serves it, in hex
+    pub sha256: String,
+
+    /// Width and height, for images
+    pub dimensions: Option<(u32, u32)>,
+
+    /// A placeholder to show while the image loads
+    pub blurhash: Option<String>,
+}
+
+impl UploadedMedia {
+    /// The NIP-92 `imeta` tag describing this media
+    pub fn imeta_tag(&self) -> Tag {
+        let mut fields: Vec<String> = vec![
+            "imeta".to_owned(),
+            format!("url {}", self.url),
+            format!("m {}", self.mime),
+            format!("x {}", self.sha256),
+        ];
+        if let Some((width, height)) = self.dimensions {
+            fields.push(format!("dim {}x{}", width, height));
+        }
+        if let Some(blurhash) = &self.blurhash {
+            fields.push(format!("blurhash {}", blurhash));
+        }
+        let fields: Vec<&str> = fields.iter().map(|s| s.as_str()).collect();
+        Tag::new(&fields)
+    }
+}
+
+/// Uploads in progress, and finished ones waiting for the composer to pick up
+#[derive(Debug, Default)]
+pub struct Uploads {
+    in_progress: AtomicUsize,
+    finished: RwLock<Vec<UploadedMedia>>,
+}
+
+impl Uploads {
+    /// How many uploads are in progress
+    pub fn in_progress(&self) -> usize {
+        self.in_progress.load(Ordering::Relaxed)
+    }
+
+    /// Take the uploads that have finished since last asked
+    pub fn take_finished(&self) -> Vec<UploadedMedia> {
+        std::mem::take(&mut *self.finished.write())
+    }
+}
+
+/// Upload a file to the configured media server (NIP-96 or Blossom). When it
+/// finishes it can be taken from `GLOBALS.uploads`.
+pub(crate) async fn upload_file(path: PathBuf) -> Result<(), Error> {
+    GLOBALS.uploads.in_progress.fetch_add(1, Ordering::Relaxed);
+    let result = upload_file_inner(&path).await;
+    GLOBALS.uploads.in_progress.fetch_sub(1, Ordering::Relaxed);
+    GLOBALS.uploads.finished.write().push(result?);
+    Ok(())
+}
+
+async fn upload_file_inner(path: &Path) -> Result<UploadedMedia, Error> {
+    let server = GLOBALS.storage.read_setting_media_server();
+    let server = server.trim().trim_end_matches('/');
+    if server.is_empty() {
+        return Err(ErrorKind::NoMediaServer.into());
+    }
+
+    let bytes = tokio::fs::read(path).await?;
+    let filename = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "upload".to_owned());
+
+    let media = tokio::task::spawn_blocking(move || prepare_media(bytes, &filename)).await??;
+
+    let client = crate::proxy::http_client_builder()?
+        .timeout(Duration::new(120, 0))
+        .build()?;
+
+    let served = if GLOBALS.storage.read_setting_media_server_blossom() {
+        blossom_upload(&client, server, &media).await?
+    } else {
+        nip96_upload(&client, server, &media).await?
+    };
+
+    // Servers may transform what we send them (e.g. re-compress images), in which
+    // case what they say about the file they serve is what readers will check
+    Ok(UploadedMedia {
+        url: served.url,
+        mime: served.mime.unwrap_or(media.mime),
+        sha256: served.sha256.unwrap_or(media.sha256),
+        dimensions: served.dimensions.or(media.dimensions),
+        blurhash: media.blurhash,
+    })
+}
+
+// What the server says about the file it now serves
+struct ServedMedia {
+    url: String,
+    mime: Option<String>,
+    sha256: Option<String>,
+    dimensions: Option<(u32, u32)>,
+}
+
+impl ServedMedia {
+    // From NIP-94 tags (as NIP-96 servers describe uploads)
+    fn from_nip94_tags(tags: &[Value]) -> Option<ServedMedia> {
+        let get = |tagname: &str| -> Option<String> {
+            tags.iter()
+                .find_map(|tag| match tag.as_array()?.as_slice() {
+                    [name, value, ..] if name.as_str() == Some(tagname) => {
+                        value.as_str().map(|s| s.to_owned())
+                    }
+                    _ => None,
+                })
+        };
+        Some(ServedMedia {
+            url: get("url")?,
+            mime: get("m"),
+            sha256: get("x").filter(|x| is_sha256_hex(x)),
+            dimensions: get("dim").and_then(|dim| parse_dimensions(&dim)),
+        })
+    }
+}
+
+fn is_sha256_hex(s: &str) -> bool {
+    s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Like "1920x1080"
+fn parse_dimensions(dim: &str) -> Option<(u32, u32)> {
+    let (width, height) = dim.split_once('x')?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
+
+// A file ready to upload
+struct PreparedMedia {
+    filename: String,
+    bytes: Vec<u8>,
+    mime: String,
+    sha256: String,
+    dimensions: Option<(u32, u32)>,
+    blurhash: Option<String>,
+}
+
+// Strip EXIF metadata (which may say where a photo was taken) from images, and
+// measure them. Files with EXIF that we cannot strip are refused.
+fn prepare_media(bytes: Vec<u8>, filename: &str) -> Result<PreparedMedia, Error> {
+    let format = image::guess_format(&bytes).ok();
+    let mut mime = match format {
+        Some(format) => format.to_mime_type().to_owned(),
+        None => mime_from_filename(filename).to_owned(),
+    };
+    let mut filename = filename.to_owned();
+    let image = format.and_then(|format| image::load_from_memory_with_format(&bytes, format).ok());
+
+    let (bytes, image) = match (format, image) {
+        (_, image) if !has_exif(&bytes) => (bytes, image),
+        (Some(format), Some(image)) => {
+            // Re-encoding drops the EXIF, so keep its orientation in the pixels
+            let image = crate::media::adjust_orientation(&bytes, image);
+
+            // Formats we cannot write go up as PNG
+            let format = if format.writing_enabled() {
+                format
+            } else {
+                mime = ImageFormat::Png.to_mime_type().to_owned();
+                let stem = filename
+                    .rsplit_once('.')
+                    .map(|(stem, _)| stem.to_owned())
+                    .unwrap_or_else(|| filename.clone());
+                filename = format!("{}.png", stem);
+                ImageFormat::Png
+            };
+
+            (encode_image(&image, format)?, Some(image))
+        }
+        _ => {
+            return Err(ErrorKind::MediaUploadFailed(
+                "this file has EXIF metadata (which may say where it was taken) that \
+                 gossip cannot remove"
+                    .to_owned(),
+            )
+            .into())
+        }
+    };
+
+    Ok(PreparedMedia {
+        filename,
+        sha256: hex::encode(Sha256::digest(&bytes)),
+        mime,
+        dimensions: image.as_ref().map(|i| (i.width(), i.height())),
+        blurhash: image
+            .as_ref()
+            .map(|i| crate::blurhash::encode(&i.thumbnail(64, 64), 4, 3)),
+        bytes,
+    })
+}
+
+fn has_exif(bytes: &[u8]) -> bool {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .is_ok()
+}
+
+fn encode_image(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, Error> {
+    let mut output: Vec<u8> = Vec::new();
+    if format == ImageFormat::Jpeg {
+        // JPEG has no alpha channel
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut output, 90);
+        DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)?;
+    } else {
+        image.write_to(&mut Cursor::new(&mut output), format)?;
+    }
+    Ok(output)
+}
+
+fn mime_from_filename(filename: &str) -> &'static str {
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+// An `Authorization` header value carrying a signed event
+fn auth_header(kind: u32, content: String, tags: Vec<Tag>) -> Result<String, Error> {
+    let pubkey = match GLOBALS.identity.public_key() {
+        Some(pk) => pk,
+        None => return Err(ErrorKind::NoPublicKey.into()),
+    };
+    let pre_event = PreEvent {
+        pubkey,
+        created_at: Unixtime::now(),
+        kind: EventKind::from(kind),
+        tags,
+        content,
+    };
+    let event = GLOBALS.identity.sign_event(pre_event)?;
+    let json = serde_json::to_string(&event)?;
+    Ok(format!(
+        "Nostr {}",
+        base64::engine::general_purpose::STANDARD.encode(json)
+    ))
+}
+
+async fn nip96_upload(
+    client: &reqwest::Client,
+    server: &str,
+    media: &PreparedMedia,
+) -> Result<ServedMedia, Error> {
+    // Find where to upload to
+    let info: Value = client
+        .get(format!("{}/.well-known/nostr/nip96.json", server))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let api_url = match info.get("api_url").and_then(|v| v.as_str()) {
+        Some(api_url) if !api_url.is_empty() => api_url.to_owned(),
+        _ => {
+            return Err(
+                ErrorKind::MediaUploadFailed("server is not a NIP-96 server".to_owned()).into(),
+            )
+        }
+    };
+
+    let auth = auth_header(
+        HTTP_AUTH_KIND,
+        "".to_owned(),
+        vec![
+            Tag::new(&["u", &api_url]),
+            Tag::new(&["method", "POST"]),
+            Tag::new(&["payload", &media.sha256]),
+        ],
+    )?;
+
+    let part = reqwest::multipart::Part::bytes(media.bytes.clone())
+        .file_name(media.filename.clone())
+        .mime_str(&media.mime)?;
+    let form = reqwest::multipart::Form::new()
+        .part("file", part)
+        .text("size", media.bytes.len().to_string())
+        .text("content_type", media.mime.clone());
+
+    let response: Value = client
+        .post(&api_url)
+        .header("Authorization", auth)
+        .multipart(form)
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    if response.get("status").and_then(|v| v.as_str()) != Some("success") {
+        let message = response
+            .get("message")
+            .and_then(|v| v.as_str())
+            .unwrap_or("the server did not accept the file");
+        return Err(ErrorKind::MediaUploadFailed(message.to_owned()).into());
+    }
+
+    // The URL (and maybe more) is in the NIP-94 tags of the response
+    let served = response
+        .get("nip94_event")
+        .and_then(|e| e.get("tags"))
+        .and_then(|t| t.as_array())
+        .and_then(|tags| ServedMedia::from_nip94_tags(tags));
+    match served {
+        Some(served) => Ok(served),
+        None => Err(ErrorKind::MediaUploadFailed(
+            "the server did not say where the file is".to_owned(),
+        )
+        .into()),
+    }
+}
+
+async fn blossom_upload(
+    client: &reqwest::Client,
+    server: &str,
+    media: &PreparedMedia,
+) -> Result<ServedMedia, Error> {
+    let expiration = Unixtime::now().0 + BLOSSOM_AUTH_SECS;
+    let auth = auth_header(
+        BLOSSOM_AUTH_KIND,
+        format!("Upload {}", media.filename),
+        vec![
+            Tag::new(&["t", "upload"]),
+            Tag::new(&["x", &media.sha256]),
+            Tag::new(&["expiration", &expiration.to_string()]),
+        ],
+    )?;
+
+    let response = client
+        .put(format!("{}/upload", server))
+        .header("Authorization", auth)
+        .header("Content-Type", media.mime.clone())
+        .body(media.bytes.clone())
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let reason = response
+            .headers()
+            .get("X-Reason")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_owned())
+            .unwrap_or_else(|| response.status().to_string());
+        return Err(ErrorKind::MediaUploadFailed(reason).into());
+    }
+
+    // The blob descriptor (BUD-02), which may carry NIP-94 tags too
+    let descriptor: Value = response.json().await?;
+    let nip94 = descriptor
+        .get("nip94")
+        .and_then(|t| t.as_array())
+        .and_then(|tags| ServedMedia::from_nip94_tags(tags));
+    let str_field = |name: &str| {
+        descriptor
+            .get(name)
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_owned())
+    };
+    match str_field("url") {
+        Some(url) => Ok(ServedMedia {
+            url,
+            mime: str_field("type").or_else(|| nip94.as_ref().and_then(|n| n.mime.clone())),
+            sha256: str_field("sha256")
+                .filter(|x| is_sha256_hex(x))
+                .or_else(|| nip94.as_ref().and_then(|n| n.sha256.clone())),
+            dimensions: nip94.and_then(|n| n.dimensions),
+        }),
+        None => Err(ErrorKind::MediaUploadFailed(
+            "the server did not say where the file is".to_owned(),
+        )
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_imeta_tag() {
+        let media = UploadedMedia {
+            url: "https://example.com/a.png".to_owned(),
+            mime: "image/png".to_owned(),
+            sha256: "00".repeat(32),
+            dimensions: Some((16, 16)),
+            blurhash: None,
+        };
+        let tag = media.imeta_tag();
+        assert_eq!(tag.tagname(), "imeta");
+        assert_eq!(tag.value(), "url https://example.com/a.png");
+        assert_eq!(tag.get_index(4), "dim 16x16");
+    }
+
+    #[test]
+    fn test_served_media() {
+        let tags: Vec<Value> = serde_json::from_str(&format!(
+            r#"[["url", "https://example.com/b.webp"], ["ox", "{}"], ["x", "{}"],
+                ["m", "image/webp"], ["dim", "800x600"]]"#,
+            "00".repeat(32),
+            "11".repeat(32)
+        ))
+        .unwrap();
+        let served = ServedMedia::from_nip94_tags(&tags).unwrap();
+        assert_eq!(served.url, "https://example.com/b.webp");
+        assert_eq!(served.sha256, Some("11".repeat(32)));
+        assert_eq!(served.mime.as_deref(), Some("image/webp"));
+        assert_eq!(served.dimensions, Some((800, 600)));
+    }
+}
@@ -0,0 +1,315 @@
+use crate::bolt11::Bolt11;
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::relationship::RelationshipById;
+use crate::storage::{PersonTable, Table};
+use nostr_types::{Event, EventKind, Id, MilliSatoshi, PublicKey, UncheckedUrl, Unixtime};
+use sha2::{Digest, Sha256};
+use speedy::{Readable, Writable};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Whether check_unverified_zaps() is running
+static CHECKING: AtomicBool = AtomicBool::new(false);
+
+// How long we trust what a LNURL provider told us
+const PROVIDER_MAX_AGE_SECS: i64 = 60 * 60 * 24 * 7;
+
+// How long to wait before asking a LNURL provider again after it did not answer
+const PROVIDER_RETRY_SECS: i64 = 60 * 15;
+
+/// What a zap recipient's LNURL provider told us (NIP-57)
+#[derive(Debug, Clone, Readable, Writable)]
+pub struct ZapProvider {
+    /// The LNURL (from the recipient's lud06 or lud16) we asked
+    pub lnurl: String,
+
+    /// Whether the provider answered. If not, we ask again later.
+    pub answered: bool,
+
+    /// The key the provider signs zap receipts with, if it supports nostr zaps
+    pub nostr_pubkey: Option<PublicKey>,
+
+    /// When we asked
+    pub fetched_at: Unixtime,
+}
+
+/// The outcome of checking a zap receipt (NIP-57 Appendix F)
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZapReceiptCheck {
+    /// The note (Id) was zapped this amount
+    Valid(Id, MilliSatoshi),
+
+    /// The receipt is forged or malformed, for this reason
+    Invalid(String),
+
+    /// We need to hear from the LNURL provider of this recipient to decide
+    Unknown(PublicKey),
+}
+
+/// Check a zap receipt against its zap request, its invoice, and the LNURL
+/// provider of the person zapped
+pub fn check_zap_receipt(receipt: &Event) -> Result<ZapReceiptCheck, Error> {
+    let (zapped_id, recipient, amount) = match check_receipt_contents(receipt) {
+        Ok(parts) => parts,
+        Err(reason) => return Ok(ZapReceiptCheck::Invalid(reason)),
+    };
+
+    let lnurl = match recipient_lnurl(recipient)? {
+        Ok(lnurl) => lnurl,
+        Err(reason) => return Ok(ZapReceiptCheck::Invalid(reason)),
+    };
+
+    let provider = match GLOBALS.storage.read_zap_provider(recipient)? {
+        Some(provider) => provider,
+        None => return Ok(ZapReceiptCheck::Unknown(recipient)),
+    };
+    if !provider_is_current(&provider, lnurl.as_deref()) || !provider.answered {
+        return Ok(ZapReceiptCheck::Unknown(recipient));
+    }
+
+    match provider.nostr_pubkey {
+        Some(pubkey) if pubkey == receipt.pubkey => Ok(ZapReceiptCheck::Valid(zapped_id, amount)),
+        Some(_) => Ok(ZapReceiptCheck::Invalid(
+            "not signed by the recipient's LNURL provider".to_owned(),
+        )),
+        None => Ok(ZapReceiptCheck::Invalid(
+            "the recipient's LNURL provider does not support nostr zaps".to_owned(),
+        )),
+    }
+}
+
+// The checks that don't need the LNURL provider. Returns the zapped note, the
+// recipient and the amount.
+fn check_receipt_contents(receipt: &Event) -> Result<(Id, PublicKey, MilliSatoshi), String> {
+    if receipt.kind != EventKind::Zap {
+        return Err("not a zap receipt".to_owned());
+    }
+
+    let mut zapped_id: Option<Id> = None;
+    let mut recipient: Option<PublicKey> = None;
+    let mut description: Option<&str> = None;
+    let mut bolt11: Option<&str> = None;
+    for tag in receipt.tags.iter() {
+        match tag.tagname() {
+            "e" if zapped_id.is_none() => zapped_id = tag.parse_event().ok().map(|(id, _, _)| id),
+            "p" if recipient.is_none() => {
+                recipient = tag.parse_pubkey().ok().map(|(pubkey, _, _)| pubkey)
+            }
+            "description" => description = Some(tag.value()),
+            "bolt11" => bolt11 = Some(tag.value()),
+            _ => {}
+        }
+    }
+    let zapped_id = zapped_id.ok_or("no zapped note")?;
+    let recipient = recipient.ok_or("no recipient")?;
+    let description = description.ok_or("no zap request")?;
+    let bolt11 = bolt11.ok_or("no invoice")?;
+
+    // The zap request must be valid and match the receipt
+    let request: Event =
+        serde_json::from_str(description).map_err(|_| "zap request is not an event")?;
+    if request.kind != EventKind::ZapRequest {
+        return Err("zap request has the wrong kind".to_owned());
+    }
+    if request.verify(None).is_err() {
+        return Err("zap request signature is invalid".to_owned());
+    }
+    let mut request_recipients = 0;
+    let mut request_amount: Option<u64> = None;
+    for tag in request.tags.iter() {
+        match tag.tagname() {
+            "p" => {
+                request_recipients += 1;
+                match tag.parse_pubkey() {
+                    Ok((pubkey, _, _)) if pubkey == recipient => {}
+                    _ => return Err("zap request is for someone else".to_owned()),
+                }
+            }
+            "e" => match tag.parse_event() {
+                Ok((id, _, _)) if id == zapped_id => {}
+                _ => return Err("zap request is for another note".to_owned()),
+            },
+            "amount" => {
+                request_amount = Some(
+                    tag.value()
+                        .parse::<u64>()
+                        .map_err(|_| "zap request amount is invalid")?,
+                )
+            }
+            _ => {}
+        }
+    }
+    if request_recipients != 1 {
+        return Err("zap request must have exactly one recipient".to_owned());
+    }
+
+    // The invoice must be for the zap request and for its amount
+    let invoice = Bolt11::parse(bolt11).ok_or("invoice is invalid")?;
+    let amount = invoice.amount.ok_or("invoice has no amount")?;
+    if let Some(request_amount) = request_amount {
+        if request_amount != amount.0 {
+            return Err("invoice amount does not match the zap request".to_owned());
+        }
+    }
+    let hash: [u8; 32] = Sha256::digest(description.as_bytes()).into();
+    if invoice.description_hash != Some(hash) {
+        return Err("invoice is not for the zap request".to_owned());
+    }
+
+    Ok((zapped_id, recipient, amount))
+}
+
+// The recipient's LNURL, None if we don't have their metadata yet, or an error
+// if they have no way to be zapped
+fn recipient_lnurl(recipient: PublicKey) -> Result<Result<Option<String>, String>, Error> {
+    let person = match PersonTable::read_record(recipient, None)? {
+        Some(person) => person,
+        None => return Ok(Ok(None)),
+    };
+    match person.metadata() {
+        None => Ok(Ok(None)),
+        Some(metadata) => match metadata.lnurl() {
+            Some(lnurl) => Ok(Ok(Some(lnurl))),
+            None => Ok(Err("the recipient has no lightning address".to_owned())),
+        },
+    }
+}
+
+// Whether what the provider told us still applies. If we don't know the
+// recipient's LNURL yet, we go with the one we asked.
+fn provider_is_current(provider: &ZapProvider, lnurl: Option<&str>) -> bool {
+    if let Some(lnurl) = lnurl {
+        if provider.lnurl != lnurl {
+            return false;
+        }
+    }
+    let max_age = if provider.answered {
+        PROVIDER_MAX_AGE_SECS
+    } else {
+        PROVIDER_RETRY_SECS
+    };
+    Unixtime::now().0 - provider.fetched_at.0 < max_age
+}
+
+/// Check the zap receipts that were waiting on LNURL providers, asking the
+/// providers first if needed
+pub(crate) async fn check_unverified_zaps() -> Result<(), Error> {
+    if CHECKING.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let result = check_unverified_zaps_inner().await;
+    CHECKING.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn check_unverified_zaps_inner() -> Result<(), Error> {
+    let unverified = GLOBALS.storage.read_all_unverified_zaps()?;
+    if unverified.is_empty() {
+        return Ok(());
+    }
+
+    let recipients: HashSet<PublicKey> = unverified.iter().map(|(_, pk)| *pk).collect();
+    for recipient in recipients {
+        if let Err(e) = update_provider(recipient).await {
+            tracing::warn!("{}", e);
+        }
+    }
+
+    for (receipt_id, _) in unverified {
+        let receipt = match GLOBALS.storage.read_event(receipt_id)? {
+            Some(receipt) => receipt,
+            None => {
+                GLOBALS.storage.delete_unverified_zap(receipt_id, None)?;
+                continue;
+            }
+        };
+        match check_zap_receipt(&receipt)? {
+            ZapReceiptCheck::Valid(zapped_id, amount) => {
+                let mut txn = GLOBALS.storage.get_write_txn()?;
+                GLOBALS.storage.write_relationship_by_id(
+                    zapped_id,
+                    receipt_id,
+                    RelationshipById::Zaps {
+                        by: receipt.pubkey,
+                        amount,
+                    },
+                    Some(&mut txn),
+                )?;
+                GLOBALS
+                    .storage
+                    .delete_unverified_zap(receipt_id, Some(&mut txn))?;
+                txn.commit()?;
+                GLOBALS.ui_notes_to_invalidate.write().push(zapped_id);
+            }
+            ZapReceiptCheck::Invalid(reason) => {
+                tracing::info!(
+                    "Discarding zap receipt {}: {}",
+                    receipt_id.as_hex_string(),
+                    reason
+                );
+                GLOBALS.storage.delete_unverified_zap(receipt_id, None)?;
+            }
+            ZapReceiptCheck::Unknown(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+// Ask the recipient's LNURL provider for its nostr pubkey, unless we already
+// know it (or asked recently)
+async fn update_provider(recipient: PublicKey) -> Result<(), Error> {
+    let lnurl = match recipient_lnurl(recipient)? {
+        Ok(Some(lnurl)) => lnurl,
+        _ => return Ok(()),
+    };
+
+    if let Some(provider) = GLOBALS.storage.read_zap_provider(recipient)? {
+        if provider_is_current(&provider, Some(&lnurl)) {
+            return Ok(());
+        }
+    }
+
+    let mut provider = ZapProvider {
+        lnurl: lnurl.clone(),
+        answered: false,
+        nostr_pubkey: None,
+        fetched_at: Unixtime::now(),
+    };
+
+    match fetch_nostr_pubkey(&lnurl).await {
+        Ok(nostr_pubkey) => {
+            provider.answered = true;
+            provider.nostr_pubkey = nostr_pubkey;
+        }
+        Err(e) => tracing::info!("LNURL provider {} did not answer: {}", lnurl, e),
+    }
+
+    GLOBALS
+        .storage
+        .write_zap_provider(recipient, &provider, None)?;
+
+    Ok(())
+}
+
+async fn fetch_nostr_pubkey(lnurl: &str) -> Result<Option<PublicKey>, Error> {
+    let client = crate::proxy::http_client_builder()?
+        .timeout(std::time::Duration::new(15, 0))
+        .gzip(true)
+        .brotli(true)
+        .deflate(true)
+        .build()?;
+
+    let url = nostr_types::Url::try_from_unchecked_url(&UncheckedUrl(lnurl.to_owned()))?;
+    let response = client.get(url.as_str()).send().await?;
+    let value: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+
+    if value.get("allowsNostr").and_then(|v| v.as_bool()) != Some(true) {
+        return Ok(None);
+    }
+    match value.get("nostrPubkey").and_then(|v| v.as_str()) {
+        Some(hex) => Ok(PublicKey::try_from_hex_string(hex, true).ok()),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::mock_relay::{Behavior, MockRelay};
+use common::{add_relay, sign, wait_for, Gossip};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{Relay, GLOBALS};
+use nostr_types::EventKind;
+
+// A relay that requires NIP-42 AUTH gets it, and then serves our subscription
+#[tokio::test]
+async fn authenticate_then_fetch() {
+    let relay = MockRelay::start(Behavior {
+        auth_required: true,
+        ..Default::default()
+    })
+    .await;
+
+    let mut gossip = Gossip::init().await;
+    add_relay(&relay.url(), Relay::READ);
+
+    let event = sign(EventKind::TextNote, vec![], "Members only");
+    relay.add_event(event.clone());
+
+    gossip.run().await;
+
+    let _ = GLOBALS
+        .to_overlord
+        .send(ToOverlordMessage::FetchEvent(event.id, vec![relay.url()]));
+
+    wait_for("the event to be stored", || {
+        GLOBALS.storage.has_event(event.id).unwrap_or(false)
+    })
+    .await;
+
+    let pubkey = GLOBALS.identity.public_key().unwrap();
+    assert_eq!(relay.authenticated(), vec![pubkey]);
+
+    gossip.shutdown().await;
+}
@@ -0,0 +1,460 @@
+//! An in-process NIP-01 relay for integration tests.
+//!
+//! It keeps events in memory, answers REQ (with NIP-50 search), CLOSE, EVENT, AUTH
+//! and COUNT, serves a NIP-11 document to plain HTTP requests, and can be scripted
+//! (see [`Behavior`]) to misbehave the way real relays do.
+
+use futures_util::{SinkExt, StreamExt};
+use nostr_types::{Event, EventKind, Filter, PublicKey, RelayUrl};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tungstenite::protocol::Message;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// What a mock relay does beyond plain NIP-01
+#[derive(Debug, Clone, Default)]
+pub struct Behavior {
+    /// How long after sending the stored events to send EOSE
+    pub eose_delay: Duration,
+
+    /// Send an AUTH challenge on connect, and refuse REQs and EVENTs until the
+    /// client has authenticated (NIP-42)
+    pub auth_required: bool,
+
+    /// Answer every REQ with CLOSED and this message
+    pub closed: Option<String>,
+
+    /// Send this NOTICE on connect
+    pub notice: Option<String>,
+
+    /// Refuse every EVENT with OK false and this message
+    pub reject_events: Option<String>,
+
+    /// Refuse EVENTs with less proof-of-work than this (NIP-13) with OK false "pow:"
+    pub min_pow: Option<u8>,
+
+    /// Advertise this `max_filters` (NIP-11), and answer REQs with more filters with
+    /// CLOSED "invalid:"
+    pub max_filters: Option<usize>,
+
+    /// Answer REQs beyond this many (per connection) with CLOSED "rate-limited:"
+    pub rate_limit: Option<usize>,
+
+    /// Drop the connection (without a close handshake) upon receiving this many
+    /// messages from the client
+    pub disconnect_after: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    events: Vec<Event>,
+    received: Vec<String>,
+    connections: usize,
+    authenticated: Vec<PublicKey>,
+}
+
+struct Shared {
+    behavior: Behavior,
+    state: Mutex<State>,
+    new_events: broadcast::Sender<Event>,
+}
+
+/// A running mock relay. It stops when dropped.
+pub struct MockRelay {
+    url: RelayUrl,
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+impl MockRelay {
+    /// Start a relay on a free local port
+    pub async fn start(behavior: Behavior) -> MockRelay {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let url = RelayUrl::try_from_str(&format!("ws://127.0.0.1:{port}")).unwrap();
+
+        let (new_events, _) = broadcast::channel(256);
+        let shared = Arc::new(Shared {
+            behavior,
+            state: Mutex::new(State::default()),
+            new_events,
+        });
+
+        let task_shared = shared.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let shared = task_shared.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, shared).await;
+                });
+            }
+        });
+
+        MockRelay { url, shared, task }
+    }
+
+    /// The websocket URL of this relay
+    pub fn url(&self) -> RelayUrl {
+        self.url.clone()
+    }
+
+    /// Store an event, as if someone had posted it
+    pub fn add_event(&self, event: Event) {
+        self.shared.state.lock().events.push(event.clone());
+        let _ = self.shared.new_events.send(event);
+    }
+
+    /// The events this relay holds
+    pub fn events(&self) -> Vec<Event> {
+        self.shared.state.lock().events.clone()
+    }
+
+    /// If this relay holds the event
+    pub fn has_event(&self, event: &Event) -> bool {
+        self.shared
+            .state
+            .lock()
+            .events
+            .iter()
+            .any(|e| e.id == event.id)
+    }
+
+    /// Every message clients have sent, in order
+    pub fn received(&self) -> Vec<String> {
+        self.shared.state.lock().received.clone()
+    }
+
+    /// How many websocket connections have been made
+    pub fn connections(&self) -> usize {
+        self.shared.state.lock().connections
+    }
+
+    /// The keys of clients which have authenticated
+    pub fn authenticated(&self) -> Vec<PublicKey> {
+        self.shared.state.lock().authenticated.clone()
+    }
+}
+
+impl Drop for MockRelay {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(stream: TcpStream, shared: Arc<Shared>) -> Result<(), BoxError> {
+    // Look at the request without consuming it, to tell websocket upgrades
+    // apart from NIP-11 requests
+    let mut buf = [0u8; 4096];
+    let head = loop {
+        let n = stream.peek(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        if n == buf.len() || buf[..n].windows(4).any(|w| w == b"\r\n\r\n") {
+            break String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    };
+
+    if !head.contains("upgrade: websocket") {
+        return serve_nip11(stream, &shared.behavior).await;
+    }
+
+    let ws = tokio_tungstenite::accept_async(stream).await?;
+    shared.state.lock().connections += 1;
+    Connection {
+        shared,
+        challenge: format!("{:016x}", rand::random::<u64>()),
+        authenticated: None,
+        subscriptions: HashMap::new(),
+        reqs: 0,
+    }
+    .run(ws)
+    .await
+}
+
+async fn serve_nip11(mut stream: TcpStream, behavior: &Behavior) -> Result<(), BoxError> {
+    let body = json!({
+        "name": "mock relay",
+        "software": "gossip integration tests",
+        "supported_nips": [1, 11, 42, 45, 50],
+        "limitation": {
+            "auth_required": behavior.auth_required,
+            "max_filters": behavior.max_filters,
+        },
+    })
+    .to_string();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/nostr+json\r\nAccess-Control-Allow-Origin: *\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+type WebSocket = tokio_tungstenite::WebSocketStream<TcpStream>;
+
+// A REQ filter, with the NIP-50 search text which `Filter` does not carry. This
+// relay's search is just a case-insensitive substring match on the content.
+struct SearchFilter {
+    filter: Filter,
+    search: Option<String>,
+}
+
+impl SearchFilter {
+    fn matches(&self, event: &Event) -> bool {
+        self.filter.event_matches(event)
+            && self
+                .search
+                .as_ref()
+                .map(|s| event.content.to_lowercase().contains(s))
+                .unwrap_or(true)
+    }
+}
+
+struct Connection {
+    shared: Arc<Shared>,
+    challenge: String,
+    authenticated: Option<PublicKey>,
+    subscriptions: HashMap<String, Vec<SearchFilter>>,
+    reqs: usize,
+}
+
+impl Connection {
+    async fn run(mut self, mut ws: WebSocket) -> Result<(), BoxError> {
+        let behavior = self.shared.behavior.clone();
+        let mut new_events = self.shared.new_events.subscribe();
+
+        if behavior.auth_required {
+            send(&mut ws, json!(["AUTH", self.challenge])).await?;
+        }
+        if let Some(notice) = &behavior.notice {
+            send(&mut ws, json!(["NOTICE", notice])).await?;
+        }
+
+        let mut count: usize = 0;
+        loop {
+            tokio::select! {
+                message = ws.next() => {
+                    let text = match message {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                        Some(Ok(_)) => continue,
+                    };
+                    self.shared.state.lock().received.push(text.clone());
+                    count += 1;
+                    if behavior.disconnect_after.map(|n| count >= n).unwrap_or(false) {
+                        // Drop the socket out from under the client
+                        return Ok(());
+                    }
+                    self.handle(&mut ws, &text).await?;
+                },
+                event = new_events.recv() => {
+                    if let Ok(event) = event {
+                        for (subid, filters) in self.subscriptions.iter() {
+                            if filters.iter().any(|f| f.matches(&event)) {
+                                send(&mut ws, json!(["EVENT", subid, event])).await?;
+                            }
+                        }
+                    }
+                },
+            }
+        }
+
+        let _ = ws.close(None).await;
+        Ok(())
+    }
+
+    async fn handle(&mut self, ws: &mut WebSocket, text: &str) -> Result<(), BoxError> {
+        let parts: Vec<Value> = match serde_json::from_str(text) {
+            Ok(parts) => parts,
+            Err(_) => return send(ws, json!(["NOTICE", "invalid: not a JSON array"])).await,
+        };
+        let arg = parts.get(1).cloned().unwrap_or(Value::Null);
+        let subid = arg.as_str().unwrap_or("").to_owned();
+
+        match parts.first().and_then(|v| v.as_str()) {
+            Some("EVENT") => {
+                let event: Event = serde_json::from_value(arg.clone())?;
+                let (ok, message) = self.accept_event(&event);
+                send(ws, json!(["OK", event.id, ok, message])).await
+            }
+            Some("REQ") => {
+                let mut filters: Vec<SearchFilter> = Vec::new();
+                for value in parts.iter().skip(2) {
+                    let mut value = value.clone();
+                    let search = value
+                        .as_object_mut()
+                        .and_then(|o| o.remove("search"))
+                        .and_then(|s| s.as_str().map(|s| s.to_lowercase()));
+                    filters.push(SearchFilter {
+                        filter: serde_json::from_value(value)?,
+                        search,
+                    });
+                }
+                self.req(ws, subid, filters).await
+            }
+            Some("COUNT") => {
+                let filters: Vec<Filter> = parts
+                    .iter()
+                    .skip(2)
+                    .map(|v| serde_json::from_value(v.clone()))
+                    .collect::<Result<_, _>>()?;
+                let count = self
+                    .shared
+                    .state
+                    .lock()
+                    .events
+                    .iter()
+                    .filter(|e| filters.iter().any(|f| f.event_matches(e)))
+                    .count();
+                send(ws, json!(["COUNT", subid, { "count": count }])).await
+            }
+            Some("CLOSE") => {
+                self.subscriptions.remove(&subid);
+                Ok(())
+            }
+            Some("AUTH") => {
+                let event: Event = serde_json::from_value(arg.clone())?;
+                let ok = event.kind == EventKind::Auth
+                    && event.verify(None).is_ok()
+                    && event
+                        .tags
+                        .iter()
+                        .any(|t| t.tagname() == "challenge" && t.value() == self.challenge);
+                if ok {
+                    self.authenticated = Some(event.pubkey);
+                    self.shared.state.lock().authenticated.push(event.pubkey);
+                    send(ws, json!(["OK", event.id, true, ""])).await
+                } else {
+                    send(
+                        ws,
+                        json!(["OK", event.id, false, "invalid: bad AUTH event"]),
+                    )
+                    .await
+                }
+            }
+            _ => send(ws, json!(["NOTICE", "unsupported: unknown message"])).await,
+        }
+    }
+
+    fn accept_event(&self, event: &Event) -> (bool, String) {
+        let behavior = &self.shared.behavior;
+        if event.verify(None).is_err() {
+            return (false, "invalid: bad signature".to_owned());
+        }
+        if behavior.auth_required && self.authenticated.is_none() {
+            return (false, "auth-required: please AUTH first".to_owned());
+        }
+        if let Some(message) = &behavior.reject_events {
+            return (false, message.clone());
+        }
+        if let Some(min_pow) = behavior.min_pow {
+            let pow = pow(event);
+            if pow < min_pow {
+                return (
+                    false,
+                    format!("pow: difficulty {} is less than {}", pow, min_pow),
+                );
+            }
+        }
+
+        {
+            let mut state = self.shared.state.lock();
+            if state.events.iter().any(|e| e.id == event.id) {
+                return (true, "duplicate: already have it".to_owned());
+            }
+            state.events.push(event.clone());
+        }
+        let _ = self.shared.new_events.send(event.clone());
+        (true, "".to_owned())
+    }
+
+    async fn req(
+        &mut self,
+        ws: &mut WebSocket,
+        subid: String,
+        filters: Vec<SearchFilter>,
+    ) -> Result<(), BoxError> {
+        let behavior = self.shared.behavior.clone();
+        self.reqs += 1;
+
+        let refusal = if let Some(message) = behavior.closed {
+            Some(message)
+        } else if behavior.auth_required && self.authenticated.is_none() {
+            Some("auth-required: please AUTH first".to_owned())
+        } else if behavior
+            .max_filters
+            .map(|n| filters.len() > n)
+            .unwrap_or(false)
+        {
+            Some("invalid: too many filters".to_owned())
+        } else if behavior.rate_limit.map(|n| self.reqs > n).unwrap_or(false) {
+            Some("rate-limited: slow down".to_owned())
+        } else {
+            None
+        };
+        if let Some(message) = refusal {
+            return send(ws, json!(["CLOSED", subid, message])).await;
+        }
+
+        let mut matching: Vec<Event> = Vec::new();
+        {
+            let state = self.shared.state.lock();
+            for filter in filters.iter() {
+                let mut events: Vec<&Event> =
+                    state.events.iter().filter(|e| filter.matches(e)).collect();
+                events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+                if let Some(limit) = filter.filter.limit {
+                    events.truncate(limit);
+                }
+                for event in events {
+                    if !matching.iter().any(|e| e.id == event.id) {
+                        matching.push(event.clone());
+                    }
+                }
+            }
+        }
+
+        for event in matching.iter() {
+            send(ws, json!(["EVENT", subid, event])).await?;
+        }
+        if !behavior.eose_delay.is_zero() {
+            tokio::time::sleep(behavior.eose_delay).await;
+        }
+        send(ws, json!(["EOSE", subid])).await?;
+
+        self.subscriptions.insert(subid, filters);
+        Ok(())
+    }
+}
+
+async fn send(ws: &mut WebSocket, message: Value) -> Result<(), BoxError> {
+    ws.send(Message::Text(message.to_string())).await?;
+    Ok(())
+}
+
+// The proof-of-work of an event: the leading zero bits of its id
+fn pow(event: &Event) -> u8 {
+    let mut bits = 0;
+    for c in event.id.as_hex_string().chars() {
+        let nibble = c.to_digit(16).unwrap_or(0);
+        if nibble == 0 {
+            bits += 4;
+        } else {
+            bits += nibble.leading_zeros() as u8 - 28;
+            break;
+        }
+    }
+    bits
+}
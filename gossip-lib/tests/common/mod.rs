@@ -0,0 +1,109 @@
+//! Shared setup for the integration tests.
+//!
+//! Gossip keeps its state in process-wide globals, so each test file boots a
+//! single instance (in a fresh data directory) and runs a single test.
+
+#![allow(dead_code)]
+
+pub mod mock_relay;
+
+use gossip_lib::{Overlord, Relay, RunState, GLOBALS};
+use nostr_types::{Event, EventKind, PreEvent, RelayUrl, Tag, Unixtime};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tempdir::TempDir;
+
+/// How long to wait for something to happen before failing the test
+pub const WAIT: Duration = Duration::from_secs(30);
+
+/// A running gossip, with its data in a temporary directory
+pub struct Gossip {
+    _dir: TempDir,
+    run: Option<JoinHandle<()>>,
+}
+
+impl Gossip {
+    /// Set up storage and an identity, but do not run yet. This gives the test
+    /// a chance to add relays and settings first.
+    pub async fn init() -> Gossip {
+        let dir = TempDir::new("gossip-test").unwrap();
+        std::env::set_var("GOSSIP_DIR", dir.path());
+
+        gossip_lib::init(false).unwrap();
+
+        // Connect and authenticate without asking, and make keys quickly
+        GLOBALS.storage.write_setting_offline(&false, None).unwrap();
+        GLOBALS
+            .storage
+            .write_setting_relay_connection_requires_approval(&false, None)
+            .unwrap();
+        GLOBALS
+            .storage
+            .write_setting_relay_auth_requires_approval(&false, None)
+            .unwrap();
+        GLOBALS.storage.write_setting_log_n(&8, None).unwrap();
+
+        Overlord::generate_private_key("testing".to_owned())
+            .await
+            .unwrap();
+
+        Gossip {
+            _dir: dir,
+            run: None,
+        }
+    }
+
+    /// Start the overlord (on its own runtime, as the binary does) and wait until
+    /// it is online
+    pub async fn run(&mut self) {
+        self.run = Some(std::thread::spawn(|| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(gossip_lib::run());
+        }));
+        wait_for("gossip to go online", || {
+            *GLOBALS.read_runstate.borrow() == RunState::Online
+        })
+        .await;
+    }
+
+    /// Shut down, waiting for the overlord and its minions to finish
+    pub async fn shutdown(mut self) {
+        let _ = GLOBALS.write_runstate.send(RunState::ShuttingDown);
+        if let Some(run) = self.run.take() {
+            wait_for("gossip to shut down", || run.is_finished()).await;
+        }
+    }
+}
+
+/// Add a relay with the given usage (e.g. `Relay::READ | Relay::WRITE`)
+pub fn add_relay(url: &RelayUrl, usage_bits: u64) {
+    let mut relay = Relay::new(url.clone());
+    relay.set_usage_bits(usage_bits);
+    GLOBALS.storage.write_relay(&relay, None).unwrap();
+}
+
+/// Make an event signed by our identity
+pub fn sign(kind: EventKind, tags: Vec<Tag>, content: &str) -> Event {
+    let pre_event = PreEvent {
+        pubkey: GLOBALS.identity.public_key().unwrap(),
+        created_at: Unixtime::now(),
+        kind,
+        tags,
+        content: content.to_owned(),
+    };
+    GLOBALS.identity.sign_event(pre_event).unwrap()
+}
+
+/// Wait until the condition holds, panicking if it does not in time
+pub async fn wait_for<F>(what: &str, condition: F)
+where
+    F: Fn() -> bool,
+{
+    let start = std::time::Instant::now();
+    while !condition() {
+        if start.elapsed() > WAIT {
+            panic!("Timed out waiting for {what}");
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
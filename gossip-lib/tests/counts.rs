@@ -0,0 +1,54 @@
+mod common;
+
+use common::mock_relay::{Behavior, MockRelay};
+use common::{sign, wait_for, Gossip};
+use gossip_lib::{CountKey, Relay, GLOBALS};
+use nostr_types::{EventKind, RelayInformationDocument, Tag};
+
+// Reactions are counted by a relay that supports NIP-45 (and that we are already
+// connected to), without fetching them
+#[tokio::test]
+async fn count_reactions() {
+    let relay = MockRelay::start(Behavior::default()).await;
+
+    let mut gossip = Gossip::init().await;
+
+    // We only ask relays that say they can count
+    let mut dbrelay = Relay::new(relay.url());
+    dbrelay.set_usage_bits(Relay::READ);
+    let nip11: RelayInformationDocument =
+        serde_json::from_value(serde_json::json!({ "supported_nips": [1, 11, 45] })).unwrap();
+    dbrelay.nip11 = Some(nip11);
+    GLOBALS.storage.write_relay(&dbrelay, None).unwrap();
+
+    let note = sign(EventKind::TextNote, vec![], "React to me");
+    relay.add_event(note.clone());
+    for content in ["+", "🤙", "-"] {
+        relay.add_event(sign(
+            EventKind::Reaction,
+            vec![Tag::new_event(note.id, None, None)],
+            content,
+        ));
+    }
+
+    gossip.run().await;
+
+    wait_for("the relay to be connected", || {
+        GLOBALS.connected_relays.contains_key(&relay.url())
+    })
+    .await;
+
+    let key = CountKey::Reactions(note.id);
+    assert!(GLOBALS.counts.get(key).is_none());
+
+    wait_for("the relay to count", || GLOBALS.counts.get(key).is_some()).await;
+
+    let count = GLOBALS.counts.get(key).unwrap();
+    assert_eq!(count.count(), 3);
+    assert!(!count.approximate);
+    assert_eq!(count.relays, vec![(relay.url(), 3)]);
+
+    assert!(relay.received().iter().any(|m| m.starts_with("[\"COUNT\"")));
+
+    gossip.shutdown().await;
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::mock_relay::{Behavior, MockRelay};
+use common::{add_relay, sign, wait_for, Gossip};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{Relay, GLOBALS};
+use nostr_types::EventKind;
+use std::time::Duration;
+
+// Fetching an event asks every given relay, and relays that refuse the REQ do
+// not keep us from getting it from the others
+#[tokio::test]
+async fn fetch_event_from_relays() {
+    let holding = MockRelay::start(Behavior {
+        eose_delay: Duration::from_millis(500),
+        ..Default::default()
+    })
+    .await;
+    let refusing = MockRelay::start(Behavior {
+        closed: Some("restricted: members only".to_owned()),
+        ..Default::default()
+    })
+    .await;
+
+    let mut gossip = Gossip::init().await;
+    add_relay(&holding.url(), Relay::READ);
+    add_relay(&refusing.url(), Relay::READ);
+
+    // Only the relay has it
+    let event = sign(EventKind::TextNote, vec![], "Fetch me");
+    holding.add_event(event.clone());
+
+    gossip.run().await;
+    assert!(!GLOBALS.storage.has_event(event.id).unwrap());
+
+    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::FetchEvent(
+        event.id,
+        vec![refusing.url(), holding.url()],
+    ));
+
+    wait_for("the event to be stored", || {
+        GLOBALS.storage.has_event(event.id).unwrap_or(false)
+    })
+    .await;
+
+    assert_eq!(
+        GLOBALS.storage.read_event(event.id).unwrap(),
+        Some(event.clone())
+    );
+    assert!(refusing
+        .received()
+        .iter()
+        .any(|m| m.starts_with("[\"REQ\"")));
+    assert!(GLOBALS
+        .storage
+        .get_event_seen_on_relay(event.id)
+        .unwrap()
+        .iter()
+        .any(|(url, _)| *url == holding.url()));
+
+    gossip.shutdown().await;
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::mock_relay::{Behavior, MockRelay};
+use common::{add_relay, wait_for, Gossip};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{OutboxStatus, Relay, GLOBALS};
+
+// A relay that drops our connection is counted as a failure, and does not get
+// in the way of posting to the relays that behave
+#[tokio::test]
+async fn dropped_connection() {
+    let dropping = MockRelay::start(Behavior {
+        disconnect_after: Some(1),
+        ..Default::default()
+    })
+    .await;
+    let steady = MockRelay::start(Behavior {
+        notice: Some("welcome to the test relay".to_owned()),
+        ..Default::default()
+    })
+    .await;
+
+    let mut gossip = Gossip::init().await;
+    add_relay(&dropping.url(), Relay::WRITE);
+    add_relay(&steady.url(), Relay::WRITE);
+    gossip.run().await;
+
+    // The config subscription is the first thing we send it
+    wait_for("the dropped connection to count as a failure", || {
+        GLOBALS
+            .storage
+            .read_relay(&dropping.url(), None)
+            .ok()
+            .flatten()
+            .map(|r| r.failure_count > 0)
+            .unwrap_or(false)
+    })
+    .await;
+    assert!(dropping.connections() >= 1);
+
+    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Post {
+        content: "Still here".to_owned(),
+        tags: vec![],
+        in_reply_to: None,
+        annotation: false,
+        dm_channel: None,
+    });
+
+    wait_for("the steady relay to accept the post", || {
+        GLOBALS
+            .storage
+            .filter_outbox_entries(|e| {
+                e.relay(&steady.url())
+                    .map(|r| r.status == OutboxStatus::Accepted)
+                    .unwrap_or(false)
+            })
+            .map(|entries| !entries.is_empty())
+            .unwrap_or(false)
+    })
+    .await;
+
+    let entry = GLOBALS
+        .storage
+        .filter_outbox_entries(|_| true)
+        .unwrap()
+        .pop()
+        .unwrap();
+    assert!(steady.has_event(&entry.event));
+    assert!(!dropping.has_event(&entry.event));
+
+    gossip.shutdown().await;
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::mock_relay::{Behavior, MockRelay};
+use common::{add_relay, wait_for, Gossip};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{OutboxStatus, Relay, GLOBALS};
+
+// Relays that refuse our post for want of proof-of-work get it again, mined at
+// the difficulty they asked for. The post is mined once, so they all get the
+// same copy, and the outbox keeps tracking it as the post we made.
+#[tokio::test]
+async fn remine_for_relay() {
+    let relay = MockRelay::start(Behavior {
+        min_pow: Some(8),
+        ..Default::default()
+    })
+    .await;
+    let other_relay = MockRelay::start(Behavior {
+        min_pow: Some(8),
+        ..Default::default()
+    })
+    .await;
+
+    let mut gossip = Gossip::init().await;
+    add_relay(&relay.url(), Relay::WRITE);
+    add_relay(&other_relay.url(), Relay::WRITE);
+    gossip.run().await;
+
+    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Post {
+        content: "Worth the work".to_owned(),
+        tags: vec![],
+        in_reply_to: None,
+        annotation: false,
+        dm_channel: None,
+    });
+
+    wait_for("the relays to accept the post", || {
+        GLOBALS
+            .storage
+            .filter_outbox_entries(|e| e.count(OutboxStatus::Accepted) == 2)
+            .map(|entries| !entries.is_empty())
+            .unwrap_or(false)
+    })
+    .await;
+
+    let events = relay.events();
+    assert_eq!(events.len(), 1);
+    let mined = &events[0];
+    assert_eq!(mined.content, "Worth the work");
+    assert!(mined.id.as_hex_string().starts_with("00"));
+    assert_eq!(other_relay.events(), vec![mined.clone()]);
+
+    // The outbox knows it by the post we made
+    let entries = GLOBALS.storage.filter_outbox_entries(|_| true).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_ne!(entries[0].event.id, mined.id);
+    assert!(GLOBALS
+        .storage
+        .read_event(entries[0].event.id)
+        .unwrap()
+        .is_some());
+
+    gossip.shutdown().await;
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::mock_relay::{Behavior, MockRelay};
+use common::{add_relay, wait_for, Gossip};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{OutboxStatus, Relay, GLOBALS};
+
+// A post goes to all of our write relays, and the outbox records which of them
+// accepted it
+#[tokio::test]
+async fn post_to_write_relays() {
+    let accepting = MockRelay::start(Behavior::default()).await;
+    let refusing = MockRelay::start(Behavior {
+        reject_events: Some("blocked: not on the allow list".to_owned()),
+        ..Default::default()
+    })
+    .await;
+
+    let mut gossip = Gossip::init().await;
+    add_relay(&accepting.url(), Relay::READ | Relay::WRITE);
+    add_relay(&refusing.url(), Relay::READ | Relay::WRITE);
+    gossip.run().await;
+
+    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Post {
+        content: "Hello from the integration tests".to_owned(),
+        tags: vec![],
+        in_reply_to: None,
+        annotation: false,
+        dm_channel: None,
+    });
+
+    wait_for("both relays to answer", || {
+        GLOBALS
+            .storage
+            .filter_outbox_entries(|e| e.count(OutboxStatus::Pending) == 0 && e.relays.len() == 2)
+            .map(|entries| !entries.is_empty())
+            .unwrap_or(false)
+    })
+    .await;
+
+    let entry = GLOBALS
+        .storage
+        .filter_outbox_entries(|_| true)
+        .unwrap()
+        .pop()
+        .unwrap();
+    let id = entry.event.id;
+    assert_eq!(
+        entry.relay(&accepting.url()).unwrap().status,
+        OutboxStatus::Accepted
+    );
+    let refused = entry.relay(&refusing.url()).unwrap();
+    assert_eq!(refused.status, OutboxStatus::Failed);
+    assert_eq!(
+        refused.message.as_deref(),
+        Some("blocked: not on the allow list")
+    );
+
+    assert!(accepting.has_event(&entry.event));
+    assert!(!refusing.has_event(&entry.event));
+
+    // Refusing one event is not refusing us, so we don't avoid the relay
+    let refusing_relay = GLOBALS
+        .storage
+        .read_relay(&refusing.url(), None)
+        .unwrap()
+        .unwrap();
+    assert!(refusing_relay.avoid_until.is_none());
+
+    // We have it ourselves, seen on the relay that took it
+    assert!(GLOBALS.storage.read_event(id).unwrap().is_some());
+    let seen_on: Vec<_> = GLOBALS
+        .storage
+        .get_event_seen_on_relay(id)
+        .unwrap()
+        .into_iter()
+        .map(|(url, _)| url)
+        .collect();
+    assert!(seen_on.contains(&accepting.url()));
+    assert!(!seen_on.contains(&refusing.url()));
+
+    gossip.shutdown().await;
+}
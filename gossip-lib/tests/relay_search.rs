@@ -0,0 +1,56 @@
+mod common;
+
+use common::mock_relay::{Behavior, MockRelay};
+use common::{sign, wait_for, Gossip};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{Relay, GLOBALS};
+use nostr_types::{EventKind, RelayInformationDocument};
+
+// Searching asks relays that support NIP-50, and their results join the local
+// ones without duplicates
+#[tokio::test]
+async fn relay_search() {
+    let relay = MockRelay::start(Behavior::default()).await;
+
+    let mut gossip = Gossip::init().await;
+
+    let mut dbrelay = Relay::new(relay.url());
+    dbrelay.set_usage_bits(Relay::READ);
+    let nip11: RelayInformationDocument =
+        serde_json::from_value(serde_json::json!({ "supported_nips": [1, 11, 50] })).unwrap();
+    dbrelay.nip11 = Some(nip11);
+    GLOBALS.storage.write_relay(&dbrelay, None).unwrap();
+
+    // One note we have and the relay has too, one only the relay has
+    let local = sign(EventKind::TextNote, vec![], "Gossip is a nostr client");
+    gossip_lib::process::process_new_event(&local, None, None, false, false).unwrap();
+    relay.add_event(local.clone());
+    let remote = sign(EventKind::TextNote, vec![], "Another gossip note");
+    relay.add_event(remote.clone());
+
+    gossip.run().await;
+
+    let _ = GLOBALS
+        .to_overlord
+        .send(ToOverlordMessage::Search("gossip".to_owned()));
+
+    wait_for("the relay's result", || {
+        GLOBALS.note_search_relays.contains_key(&remote.id)
+            && GLOBALS.note_search_relays.contains_key(&local.id)
+    })
+    .await;
+
+    let results = GLOBALS.note_search_results.read().clone();
+    assert_eq!(results.iter().filter(|e| e.id == local.id).count(), 1);
+    assert_eq!(results.iter().filter(|e| e.id == remote.id).count(), 1);
+    assert_eq!(
+        GLOBALS.note_search_relays.get(&remote.id).unwrap().clone(),
+        vec![relay.url()]
+    );
+    assert!(relay
+        .received()
+        .iter()
+        .any(|m| m.starts_with("[\"REQ\"") && m.contains("\"search\":\"gossip\"")));
+
+    gossip.shutdown().await;
+}
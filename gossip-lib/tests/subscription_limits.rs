@@ -0,0 +1,47 @@
+mod common;
+
+use common::mock_relay::{Behavior, MockRelay};
+use common::{add_relay, sign, wait_for, Gossip};
+use gossip_lib::{Relay, GLOBALS};
+use nostr_types::EventKind;
+use serde_json::Value;
+
+// A relay that allows only one filter per REQ gets our config subscription split
+// over several REQs, and still serves it
+#[tokio::test]
+async fn split_for_max_filters() {
+    let relay = MockRelay::start(Behavior {
+        max_filters: Some(1),
+        ..Default::default()
+    })
+    .await;
+
+    let mut gossip = Gossip::init().await;
+    add_relay(&relay.url(), Relay::WRITE);
+
+    relay.add_event(sign(EventKind::Metadata, vec![], r#"{"name":"split"}"#));
+
+    gossip.run().await;
+
+    wait_for("our metadata to be stored", || {
+        let pubkey = GLOBALS.identity.public_key().unwrap();
+        GLOBALS
+            .storage
+            .get_replaceable_event(EventKind::Metadata, pubkey, "")
+            .ok()
+            .flatten()
+            .is_some()
+    })
+    .await;
+
+    let reqs: Vec<Vec<Value>> = relay
+        .received()
+        .iter()
+        .filter(|m| m.starts_with("[\"REQ\""))
+        .map(|m| serde_json::from_str(m).unwrap())
+        .collect();
+    assert!(reqs.len() > 1);
+    assert!(reqs.iter().all(|req| req.len() == 3));
+
+    gossip.shutdown().await;
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::mock_relay::{Behavior, MockRelay};
+use common::{add_relay, sign, wait_for, Gossip};
+use gossip_lib::{Relay, GLOBALS};
+use nostr_types::EventKind;
+use std::time::Duration;
+
+fn our_metadata_content() -> Option<String> {
+    let pubkey = GLOBALS.identity.public_key()?;
+    GLOBALS
+        .storage
+        .get_replaceable_event(EventKind::Metadata, pubkey, "")
+        .ok()?
+        .map(|e| e.content)
+}
+
+// On start we subscribe to our own configuration on our write relays, and keep
+// that subscription open for changes made elsewhere
+#[tokio::test]
+async fn config_subscription() {
+    let relay = MockRelay::start(Behavior {
+        eose_delay: Duration::from_secs(1),
+        ..Default::default()
+    })
+    .await;
+
+    let mut gossip = Gossip::init().await;
+    add_relay(&relay.url(), Relay::WRITE);
+
+    relay.add_event(sign(EventKind::Metadata, vec![], r#"{"name":"first"}"#));
+
+    gossip.run().await;
+
+    wait_for("our metadata to be stored", || {
+        our_metadata_content().as_deref() == Some(r#"{"name":"first"}"#)
+    })
+    .await;
+
+    // Replaceable events need a newer timestamp to replace
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    relay.add_event(sign(EventKind::Metadata, vec![], r#"{"name":"second"}"#));
+
+    wait_for("our new metadata to arrive", || {
+        our_metadata_content().as_deref() == Some(r#"{"name":"second"}"#)
+    })
+    .await;
+
+    assert_eq!(relay.connections(), 1);
+
+    gossip.shutdown().await;
+}